@@ -0,0 +1,989 @@
+//! Typed parameters for the effect **AudioUnit**s provided by Apple.
+//!
+//! Each effect subtype in [**EffectType**](../types/enum.EffectType) has an associated parameter
+//! enum describing the identifier, unit and documented range of each of its parameters, along
+//! with a thin wrapper around an **AudioUnit** of that subtype, e.g.
+//! [**LowPassFilter**](./struct.LowPassFilter).
+//!
+//! Values are validated against the documented ranges before being passed to the audio unit.
+//! Ranges with an upper bound of half the sample rate are resolved using the sample rate of the
+//! audio unit.
+//!
+//! Original documentation [here](https://developer.apple.com/documentation/audiotoolbox/audio_unit_parameters).
+
+use objc2_audio_toolbox::{
+    kAULowShelfParam_CutoffFrequency, kAULowShelfParam_Gain, kAUNBandEQParam_Bandwidth,
+    kAUNBandEQParam_BypassBand, kAUNBandEQParam_FilterType, kAUNBandEQParam_Frequency,
+    kAUNBandEQParam_Gain, kAUNBandEQParam_GlobalGain, kBandpassParam_Bandwidth,
+    kBandpassParam_CenterFrequency, kDelayParam_DelayTime, kDelayParam_Feedback,
+    kDelayParam_LopassCutoff, kDelayParam_WetDryMix, kDistortionParam_CubicTerm,
+    kDistortionParam_Decay, kDistortionParam_Decimation, kDistortionParam_DecimationMix,
+    kDistortionParam_Delay, kDistortionParam_DelayMix, kDistortionParam_FinalMix,
+    kDistortionParam_LinearTerm, kDistortionParam_PolynomialMix, kDistortionParam_RingModBalance,
+    kDistortionParam_RingModFreq1, kDistortionParam_RingModFreq2, kDistortionParam_RingModMix,
+    kDistortionParam_Rounding, kDistortionParam_SoftClipGain, kDistortionParam_SquaredTerm,
+    kDynamicsProcessorParam_AttackTime, kDynamicsProcessorParam_ExpansionRatio,
+    kDynamicsProcessorParam_HeadRoom, kDynamicsProcessorParam_OverallGain,
+    kDynamicsProcessorParam_ReleaseTime, kDynamicsProcessorParam_Threshold,
+    kHighShelfParam_CutOffFrequency, kHighShelfParam_Gain, kHipassParam_CutoffFrequency,
+    kHipassParam_Resonance, kLimiterParam_AttackTime, kLimiterParam_DecayTime,
+    kLimiterParam_PreGain, kLowPassParam_CutoffFrequency, kLowPassParam_Resonance,
+    kParametricEQParam_CenterFreq, kParametricEQParam_Gain, kParametricEQParam_Q,
+    kSampleDelayParam_DelayFrames,
+};
+
+use super::parameter::ParameterUnit;
+use super::{AudioUnit, EffectType, Element, Scope};
+use crate::error::{AudioUnitError, Error};
+
+/// The range of values accepted by a parameter.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ParameterRange {
+    /// The minimum value.
+    pub min: f32,
+    /// The maximum value.
+    pub max: f32,
+    /// The default value.
+    pub default: f32,
+}
+
+impl ParameterRange {
+    /// Whether or not the given value lies within the range.
+    pub fn contains(&self, value: f32) -> bool {
+        value >= self.min && value <= self.max
+    }
+
+    /// Returns the given value if it lies within the range, or an `Error` otherwise.
+    pub fn check(&self, value: f32) -> Result<f32, Error> {
+        if self.contains(value) {
+            Ok(value)
+        } else {
+            Err(Error::ParameterValueOutOfRange)
+        }
+    }
+}
+
+/// A parameter of one of Apple's built-in effect **AudioUnit**s.
+///
+/// All of these parameters live within the `Global` scope.
+pub trait EffectParameter: Copy {
+    /// The effect subtype to which this parameter belongs.
+    const EFFECT_TYPE: EffectType;
+
+    /// The parameter identifier passed to the audio unit.
+    fn id(&self) -> u32;
+
+    /// The unit in which the parameter is expressed.
+    fn unit(&self) -> ParameterUnit;
+
+    /// The documented range of the parameter for an audio unit running at `sample_rate`.
+    fn range(&self, sample_rate: f64) -> ParameterRange;
+
+    /// Returns an `Error` if the parameter does not address a parameter of the audio unit.
+    fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Shorthand for constructing a `ParameterRange`.
+fn range(min: f32, max: f32, default: f32) -> ParameterRange {
+    ParameterRange { min, max, default }
+}
+
+/// The Nyquist frequency for the given sample rate.
+fn nyquist(sample_rate: f64) -> f32 {
+    (sample_rate / 2.0) as f32
+}
+
+/// Shorthand for constructing a `ParameterRange` bounded above by the Nyquist frequency.
+///
+/// At sample rates low enough for the Nyquist frequency to fall below `min`, the range collapses
+/// onto `min` rather than becoming empty. The default is clamped into the range.
+fn frequency_range(min: f32, sample_rate: f64, default: f32) -> ParameterRange {
+    let max = nyquist(sample_rate).max(min);
+    range(min, max, default.min(max))
+}
+
+/// Parameters of the [**EffectType::PeakLimiter**](../types/enum.EffectType) audio unit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PeakLimiterParam {
+    /// Seconds, 0.001 -> 0.03, 0.012.
+    AttackTime,
+    /// Seconds, 0.001 -> 0.06, 0.024.
+    DecayTime,
+    /// Decibels, -40 -> 40, 0.
+    PreGain,
+}
+
+impl EffectParameter for PeakLimiterParam {
+    const EFFECT_TYPE: EffectType = EffectType::PeakLimiter;
+
+    fn id(&self) -> u32 {
+        match *self {
+            PeakLimiterParam::AttackTime => kLimiterParam_AttackTime,
+            PeakLimiterParam::DecayTime => kLimiterParam_DecayTime,
+            PeakLimiterParam::PreGain => kLimiterParam_PreGain,
+        }
+    }
+
+    fn unit(&self) -> ParameterUnit {
+        match *self {
+            PeakLimiterParam::AttackTime | PeakLimiterParam::DecayTime => ParameterUnit::Seconds,
+            PeakLimiterParam::PreGain => ParameterUnit::Decibels,
+        }
+    }
+
+    fn range(&self, _sample_rate: f64) -> ParameterRange {
+        match *self {
+            PeakLimiterParam::AttackTime => range(0.001, 0.03, 0.012),
+            PeakLimiterParam::DecayTime => range(0.001, 0.06, 0.024),
+            PeakLimiterParam::PreGain => range(-40.0, 40.0, 0.0),
+        }
+    }
+}
+
+/// Parameters of the [**EffectType::DynamicsProcessor**](../types/enum.EffectType) audio unit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DynamicsProcessorParam {
+    /// Decibels, -40 -> 20, -20.
+    Threshold,
+    /// Decibels, 0.1 -> 40, 5.
+    HeadRoom,
+    /// Rate, 1 -> 50, 2.
+    ExpansionRatio,
+    /// Seconds, 0.0001 -> 0.2, 0.001.
+    AttackTime,
+    /// Seconds, 0.01 -> 3, 0.05.
+    ReleaseTime,
+    /// Decibels, -40 -> 40, 0.
+    OverallGain,
+}
+
+impl EffectParameter for DynamicsProcessorParam {
+    const EFFECT_TYPE: EffectType = EffectType::DynamicsProcessor;
+
+    fn id(&self) -> u32 {
+        match *self {
+            DynamicsProcessorParam::Threshold => kDynamicsProcessorParam_Threshold,
+            DynamicsProcessorParam::HeadRoom => kDynamicsProcessorParam_HeadRoom,
+            DynamicsProcessorParam::ExpansionRatio => kDynamicsProcessorParam_ExpansionRatio,
+            DynamicsProcessorParam::AttackTime => kDynamicsProcessorParam_AttackTime,
+            DynamicsProcessorParam::ReleaseTime => kDynamicsProcessorParam_ReleaseTime,
+            DynamicsProcessorParam::OverallGain => kDynamicsProcessorParam_OverallGain,
+        }
+    }
+
+    fn unit(&self) -> ParameterUnit {
+        match *self {
+            DynamicsProcessorParam::Threshold
+            | DynamicsProcessorParam::HeadRoom
+            | DynamicsProcessorParam::OverallGain => ParameterUnit::Decibels,
+            DynamicsProcessorParam::ExpansionRatio => ParameterUnit::Rate,
+            DynamicsProcessorParam::AttackTime | DynamicsProcessorParam::ReleaseTime => {
+                ParameterUnit::Seconds
+            }
+        }
+    }
+
+    fn range(&self, _sample_rate: f64) -> ParameterRange {
+        match *self {
+            DynamicsProcessorParam::Threshold => range(-40.0, 20.0, -20.0),
+            DynamicsProcessorParam::HeadRoom => range(0.1, 40.0, 5.0),
+            DynamicsProcessorParam::ExpansionRatio => range(1.0, 50.0, 2.0),
+            DynamicsProcessorParam::AttackTime => range(0.0001, 0.2, 0.001),
+            DynamicsProcessorParam::ReleaseTime => range(0.01, 3.0, 0.05),
+            DynamicsProcessorParam::OverallGain => range(-40.0, 40.0, 0.0),
+        }
+    }
+}
+
+/// Parameters of the [**EffectType::LowPassFilter**](../types/enum.EffectType) audio unit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LowPassFilterParam {
+    /// Hertz, 10 -> (sample rate / 2), 6900.
+    CutoffFrequency,
+    /// Decibels, -20 -> 40, 0.
+    Resonance,
+}
+
+impl EffectParameter for LowPassFilterParam {
+    const EFFECT_TYPE: EffectType = EffectType::LowPassFilter;
+
+    fn id(&self) -> u32 {
+        match *self {
+            LowPassFilterParam::CutoffFrequency => kLowPassParam_CutoffFrequency,
+            LowPassFilterParam::Resonance => kLowPassParam_Resonance,
+        }
+    }
+
+    fn unit(&self) -> ParameterUnit {
+        match *self {
+            LowPassFilterParam::CutoffFrequency => ParameterUnit::Hertz,
+            LowPassFilterParam::Resonance => ParameterUnit::Decibels,
+        }
+    }
+
+    fn range(&self, sample_rate: f64) -> ParameterRange {
+        match *self {
+            LowPassFilterParam::CutoffFrequency => frequency_range(10.0, sample_rate, 6900.0),
+            LowPassFilterParam::Resonance => range(-20.0, 40.0, 0.0),
+        }
+    }
+}
+
+/// Parameters of the [**EffectType::HighPassFilter**](../types/enum.EffectType) audio unit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum HighPassFilterParam {
+    /// Hertz, 10 -> (sample rate / 2), 6900.
+    CutoffFrequency,
+    /// Decibels, -20 -> 40, 0.
+    Resonance,
+}
+
+impl EffectParameter for HighPassFilterParam {
+    const EFFECT_TYPE: EffectType = EffectType::HighPassFilter;
+
+    fn id(&self) -> u32 {
+        match *self {
+            HighPassFilterParam::CutoffFrequency => kHipassParam_CutoffFrequency,
+            HighPassFilterParam::Resonance => kHipassParam_Resonance,
+        }
+    }
+
+    fn unit(&self) -> ParameterUnit {
+        match *self {
+            HighPassFilterParam::CutoffFrequency => ParameterUnit::Hertz,
+            HighPassFilterParam::Resonance => ParameterUnit::Decibels,
+        }
+    }
+
+    fn range(&self, sample_rate: f64) -> ParameterRange {
+        match *self {
+            HighPassFilterParam::CutoffFrequency => frequency_range(10.0, sample_rate, 6900.0),
+            HighPassFilterParam::Resonance => range(-20.0, 40.0, 0.0),
+        }
+    }
+}
+
+/// Parameters of the [**EffectType::BandPassFilter**](../types/enum.EffectType) audio unit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BandPassFilterParam {
+    /// Hertz, 20 -> (sample rate / 2), 5000.
+    CenterFrequency,
+    /// Cents, 100 -> 12000, 600.
+    Bandwidth,
+}
+
+impl EffectParameter for BandPassFilterParam {
+    const EFFECT_TYPE: EffectType = EffectType::BandPassFilter;
+
+    fn id(&self) -> u32 {
+        match *self {
+            BandPassFilterParam::CenterFrequency => kBandpassParam_CenterFrequency,
+            BandPassFilterParam::Bandwidth => kBandpassParam_Bandwidth,
+        }
+    }
+
+    fn unit(&self) -> ParameterUnit {
+        match *self {
+            BandPassFilterParam::CenterFrequency => ParameterUnit::Hertz,
+            BandPassFilterParam::Bandwidth => ParameterUnit::Cents,
+        }
+    }
+
+    fn range(&self, sample_rate: f64) -> ParameterRange {
+        match *self {
+            BandPassFilterParam::CenterFrequency => frequency_range(20.0, sample_rate, 5000.0),
+            BandPassFilterParam::Bandwidth => range(100.0, 12000.0, 600.0),
+        }
+    }
+}
+
+/// Parameters of the [**EffectType::HighShelfFilter**](../types/enum.EffectType) audio unit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum HighShelfFilterParam {
+    /// Hertz, 10000 -> (sample rate / 2), 10000.
+    CutoffFrequency,
+    /// Decibels, -40 -> 40, 0.
+    Gain,
+}
+
+impl EffectParameter for HighShelfFilterParam {
+    const EFFECT_TYPE: EffectType = EffectType::HighShelfFilter;
+
+    fn id(&self) -> u32 {
+        match *self {
+            HighShelfFilterParam::CutoffFrequency => kHighShelfParam_CutOffFrequency,
+            HighShelfFilterParam::Gain => kHighShelfParam_Gain,
+        }
+    }
+
+    fn unit(&self) -> ParameterUnit {
+        match *self {
+            HighShelfFilterParam::CutoffFrequency => ParameterUnit::Hertz,
+            HighShelfFilterParam::Gain => ParameterUnit::Decibels,
+        }
+    }
+
+    fn range(&self, sample_rate: f64) -> ParameterRange {
+        match *self {
+            HighShelfFilterParam::CutoffFrequency => frequency_range(10000.0, sample_rate, 10000.0),
+            HighShelfFilterParam::Gain => range(-40.0, 40.0, 0.0),
+        }
+    }
+}
+
+/// Parameters of the [**EffectType::LowShelfFilter**](../types/enum.EffectType) audio unit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LowShelfFilterParam {
+    /// Hertz, 10 -> 200, 80.
+    CutoffFrequency,
+    /// Decibels, -40 -> 40, 0.
+    Gain,
+}
+
+impl EffectParameter for LowShelfFilterParam {
+    const EFFECT_TYPE: EffectType = EffectType::LowShelfFilter;
+
+    fn id(&self) -> u32 {
+        match *self {
+            LowShelfFilterParam::CutoffFrequency => kAULowShelfParam_CutoffFrequency,
+            LowShelfFilterParam::Gain => kAULowShelfParam_Gain,
+        }
+    }
+
+    fn unit(&self) -> ParameterUnit {
+        match *self {
+            LowShelfFilterParam::CutoffFrequency => ParameterUnit::Hertz,
+            LowShelfFilterParam::Gain => ParameterUnit::Decibels,
+        }
+    }
+
+    fn range(&self, _sample_rate: f64) -> ParameterRange {
+        match *self {
+            LowShelfFilterParam::CutoffFrequency => range(10.0, 200.0, 80.0),
+            LowShelfFilterParam::Gain => range(-40.0, 40.0, 0.0),
+        }
+    }
+}
+
+/// Parameters of the [**EffectType::ParametricEQ**](../types/enum.EffectType) audio unit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ParametricEqParam {
+    /// Hertz, 20 -> (sample rate / 2), 2000.
+    CenterFrequency,
+    /// Hertz, 0.1 -> 20, 1.
+    Q,
+    /// Decibels, -20 -> 20, 0.
+    Gain,
+}
+
+impl EffectParameter for ParametricEqParam {
+    const EFFECT_TYPE: EffectType = EffectType::ParametricEQ;
+
+    fn id(&self) -> u32 {
+        match *self {
+            ParametricEqParam::CenterFrequency => kParametricEQParam_CenterFreq,
+            ParametricEqParam::Q => kParametricEQParam_Q,
+            ParametricEqParam::Gain => kParametricEQParam_Gain,
+        }
+    }
+
+    fn unit(&self) -> ParameterUnit {
+        match *self {
+            ParametricEqParam::CenterFrequency | ParametricEqParam::Q => ParameterUnit::Hertz,
+            ParametricEqParam::Gain => ParameterUnit::Decibels,
+        }
+    }
+
+    fn range(&self, sample_rate: f64) -> ParameterRange {
+        match *self {
+            ParametricEqParam::CenterFrequency => frequency_range(20.0, sample_rate, 2000.0),
+            ParametricEqParam::Q => range(0.1, 20.0, 1.0),
+            ParametricEqParam::Gain => range(-20.0, 20.0, 0.0),
+        }
+    }
+}
+
+/// Parameters of the [**EffectType::Distortion**](../types/enum.EffectType) audio unit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DistortionParam {
+    /// Milliseconds, 0.1 -> 500, 0.1.
+    Delay,
+    /// Rate, 0.1 -> 50, 1.
+    Decay,
+    /// Percent, 0 -> 100, 50.
+    DelayMix,
+    /// Percent, 0 -> 100, 50.
+    Decimation,
+    /// Percent, 0 -> 100, 0.
+    Rounding,
+    /// Percent, 0 -> 100, 50.
+    DecimationMix,
+    /// Linear gain, 0 -> 1, 1.
+    LinearTerm,
+    /// Linear gain, 0 -> 20, 0.
+    SquaredTerm,
+    /// Linear gain, 0 -> 20, 0.
+    CubicTerm,
+    /// Percent, 0 -> 100, 50.
+    PolynomialMix,
+    /// Hertz, 0.5 -> 8000, 100.
+    RingModFreq1,
+    /// Hertz, 0.5 -> 8000, 100.
+    RingModFreq2,
+    /// Percent, 0 -> 100, 50.
+    RingModBalance,
+    /// Percent, 0 -> 100, 0.
+    RingModMix,
+    /// Decibels, -80 -> 20, -6.
+    SoftClipGain,
+    /// Percent, 0 -> 100, 50.
+    FinalMix,
+}
+
+impl EffectParameter for DistortionParam {
+    const EFFECT_TYPE: EffectType = EffectType::Distortion;
+
+    fn id(&self) -> u32 {
+        match *self {
+            DistortionParam::Delay => kDistortionParam_Delay,
+            DistortionParam::Decay => kDistortionParam_Decay,
+            DistortionParam::DelayMix => kDistortionParam_DelayMix,
+            DistortionParam::Decimation => kDistortionParam_Decimation,
+            DistortionParam::Rounding => kDistortionParam_Rounding,
+            DistortionParam::DecimationMix => kDistortionParam_DecimationMix,
+            DistortionParam::LinearTerm => kDistortionParam_LinearTerm,
+            DistortionParam::SquaredTerm => kDistortionParam_SquaredTerm,
+            DistortionParam::CubicTerm => kDistortionParam_CubicTerm,
+            DistortionParam::PolynomialMix => kDistortionParam_PolynomialMix,
+            DistortionParam::RingModFreq1 => kDistortionParam_RingModFreq1,
+            DistortionParam::RingModFreq2 => kDistortionParam_RingModFreq2,
+            DistortionParam::RingModBalance => kDistortionParam_RingModBalance,
+            DistortionParam::RingModMix => kDistortionParam_RingModMix,
+            DistortionParam::SoftClipGain => kDistortionParam_SoftClipGain,
+            DistortionParam::FinalMix => kDistortionParam_FinalMix,
+        }
+    }
+
+    fn unit(&self) -> ParameterUnit {
+        match *self {
+            DistortionParam::Delay => ParameterUnit::Milliseconds,
+            DistortionParam::Decay => ParameterUnit::Rate,
+            DistortionParam::LinearTerm
+            | DistortionParam::SquaredTerm
+            | DistortionParam::CubicTerm => ParameterUnit::LinearGain,
+            DistortionParam::RingModFreq1 | DistortionParam::RingModFreq2 => ParameterUnit::Hertz,
+            DistortionParam::SoftClipGain => ParameterUnit::Decibels,
+            DistortionParam::DelayMix
+            | DistortionParam::Decimation
+            | DistortionParam::Rounding
+            | DistortionParam::DecimationMix
+            | DistortionParam::PolynomialMix
+            | DistortionParam::RingModBalance
+            | DistortionParam::RingModMix
+            | DistortionParam::FinalMix => ParameterUnit::Percent,
+        }
+    }
+
+    fn range(&self, _sample_rate: f64) -> ParameterRange {
+        match *self {
+            DistortionParam::Delay => range(0.1, 500.0, 0.1),
+            DistortionParam::Decay => range(0.1, 50.0, 1.0),
+            DistortionParam::DelayMix => range(0.0, 100.0, 50.0),
+            DistortionParam::Decimation => range(0.0, 100.0, 50.0),
+            DistortionParam::Rounding => range(0.0, 100.0, 0.0),
+            DistortionParam::DecimationMix => range(0.0, 100.0, 50.0),
+            DistortionParam::LinearTerm => range(0.0, 1.0, 1.0),
+            DistortionParam::SquaredTerm => range(0.0, 20.0, 0.0),
+            DistortionParam::CubicTerm => range(0.0, 20.0, 0.0),
+            DistortionParam::PolynomialMix => range(0.0, 100.0, 50.0),
+            DistortionParam::RingModFreq1 => range(0.5, 8000.0, 100.0),
+            DistortionParam::RingModFreq2 => range(0.5, 8000.0, 100.0),
+            DistortionParam::RingModBalance => range(0.0, 100.0, 50.0),
+            DistortionParam::RingModMix => range(0.0, 100.0, 0.0),
+            DistortionParam::SoftClipGain => range(-80.0, 20.0, -6.0),
+            DistortionParam::FinalMix => range(0.0, 100.0, 50.0),
+        }
+    }
+}
+
+/// Parameters of the [**EffectType::Delay**](../types/enum.EffectType) audio unit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DelayParam {
+    /// Equal power crossfade, 0 -> 100, 50.
+    WetDryMix,
+    /// Seconds, 0 -> 2, 1.
+    DelayTime,
+    /// Percent, -100 -> 100, 50.
+    Feedback,
+    /// Hertz, 10 -> (sample rate / 2), 15000.
+    LopassCutoff,
+}
+
+impl EffectParameter for DelayParam {
+    const EFFECT_TYPE: EffectType = EffectType::Delay;
+
+    fn id(&self) -> u32 {
+        match *self {
+            DelayParam::WetDryMix => kDelayParam_WetDryMix,
+            DelayParam::DelayTime => kDelayParam_DelayTime,
+            DelayParam::Feedback => kDelayParam_Feedback,
+            DelayParam::LopassCutoff => kDelayParam_LopassCutoff,
+        }
+    }
+
+    fn unit(&self) -> ParameterUnit {
+        match *self {
+            DelayParam::WetDryMix => ParameterUnit::EqualPowerCrossfade,
+            DelayParam::DelayTime => ParameterUnit::Seconds,
+            DelayParam::Feedback => ParameterUnit::Percent,
+            DelayParam::LopassCutoff => ParameterUnit::Hertz,
+        }
+    }
+
+    fn range(&self, sample_rate: f64) -> ParameterRange {
+        match *self {
+            DelayParam::WetDryMix => range(0.0, 100.0, 50.0),
+            DelayParam::DelayTime => range(0.0, 2.0, 1.0),
+            DelayParam::Feedback => range(-100.0, 100.0, 50.0),
+            DelayParam::LopassCutoff => frequency_range(10.0, sample_rate, 15000.0),
+        }
+    }
+}
+
+/// Parameters of the [**EffectType::SampleDelay**](../types/enum.EffectType) audio unit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SampleDelayParam {
+    /// Sample frames, 0 -> sample rate, 0.
+    DelayFrames,
+}
+
+impl EffectParameter for SampleDelayParam {
+    const EFFECT_TYPE: EffectType = EffectType::SampleDelay;
+
+    fn id(&self) -> u32 {
+        match *self {
+            SampleDelayParam::DelayFrames => kSampleDelayParam_DelayFrames,
+        }
+    }
+
+    fn unit(&self) -> ParameterUnit {
+        match *self {
+            SampleDelayParam::DelayFrames => ParameterUnit::SampleFrames,
+        }
+    }
+
+    fn range(&self, sample_rate: f64) -> ParameterRange {
+        match *self {
+            SampleDelayParam::DelayFrames => range(0.0, sample_rate as f32, 0.0),
+        }
+    }
+}
+
+/// The number of filter types supported by each band of the **NBandEQ** audio unit.
+pub const NBAND_EQ_FILTER_TYPE_COUNT: u32 = 11;
+
+/// The maximum number of bands of the AUNBandEQ, and so the number of bands that an
+/// [**NBandEqParam**](./enum.NBandEqParam) may address.
+pub const NBAND_EQ_MAX_BANDS: u32 = 16;
+
+/// Parameters of the [**EffectType::NBandEQ**](../types/enum.EffectType) audio unit.
+///
+/// All parameters other than `GlobalGain` apply to a single band, given by its zero-based index
+/// below [**NBAND_EQ_MAX_BANDS**](./constant.NBAND_EQ_MAX_BANDS).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NBandEqParam {
+    /// Decibels, -96 -> 24, 0.
+    GlobalGain,
+    /// Boolean, 0 or 1, 0.
+    BypassBand(u32),
+    /// Indexed, 0 -> 10, 0. See `kAUNBandEQFilterType_*` for the meaning of each index.
+    FilterType(u32),
+    /// Hertz, 20 -> (sample rate / 2), 1000.
+    Frequency(u32),
+    /// Decibels, -96 -> 24, 0.
+    Gain(u32),
+    /// Octaves, 0.05 -> 5, 0.5.
+    Bandwidth(u32),
+}
+
+impl EffectParameter for NBandEqParam {
+    const EFFECT_TYPE: EffectType = EffectType::NBandEQ;
+
+    fn id(&self) -> u32 {
+        match *self {
+            NBandEqParam::GlobalGain => kAUNBandEQParam_GlobalGain,
+            NBandEqParam::BypassBand(band) => kAUNBandEQParam_BypassBand + band,
+            NBandEqParam::FilterType(band) => kAUNBandEQParam_FilterType + band,
+            NBandEqParam::Frequency(band) => kAUNBandEQParam_Frequency + band,
+            NBandEqParam::Gain(band) => kAUNBandEQParam_Gain + band,
+            NBandEqParam::Bandwidth(band) => kAUNBandEQParam_Bandwidth + band,
+        }
+    }
+
+    fn unit(&self) -> ParameterUnit {
+        match *self {
+            NBandEqParam::GlobalGain | NBandEqParam::Gain(_) => ParameterUnit::Decibels,
+            NBandEqParam::BypassBand(_) => ParameterUnit::Boolean,
+            NBandEqParam::FilterType(_) => ParameterUnit::Indexed,
+            NBandEqParam::Frequency(_) => ParameterUnit::Hertz,
+            NBandEqParam::Bandwidth(_) => ParameterUnit::Octaves,
+        }
+    }
+
+    fn range(&self, sample_rate: f64) -> ParameterRange {
+        match *self {
+            NBandEqParam::GlobalGain | NBandEqParam::Gain(_) => range(-96.0, 24.0, 0.0),
+            NBandEqParam::BypassBand(_) => range(0.0, 1.0, 0.0),
+            NBandEqParam::FilterType(_) => range(0.0, (NBAND_EQ_FILTER_TYPE_COUNT - 1) as f32, 0.0),
+            NBandEqParam::Frequency(_) => frequency_range(20.0, sample_rate, 1000.0),
+            NBandEqParam::Bandwidth(_) => range(0.05, 5.0, 0.5),
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        match *self {
+            NBandEqParam::GlobalGain => Ok(()),
+            NBandEqParam::BypassBand(band)
+            | NBandEqParam::FilterType(band)
+            | NBandEqParam::Frequency(band)
+            | NBandEqParam::Gain(band)
+            | NBandEqParam::Bandwidth(band) => {
+                if band < NBAND_EQ_MAX_BANDS {
+                    Ok(())
+                } else {
+                    Err(Error::AudioUnit(AudioUnitError::InvalidParameter))
+                }
+            }
+        }
+    }
+}
+
+impl AudioUnit {
+    /// Gets the current value of a typed effect parameter.
+    pub fn effect_parameter<P>(&self, param: P) -> Result<f32, Error>
+    where
+        P: EffectParameter,
+    {
+        param.validate()?;
+        self.parameter(param.id(), Scope::Global, Element::Output)
+    }
+
+    /// Sets the value of a typed effect parameter.
+    ///
+    /// Returns an `Error` if the value lies outside of the parameter's documented range for the
+    /// audio unit's current sample rate.
+    pub fn set_effect_parameter<P>(&mut self, param: P, value: f32) -> Result<(), Error>
+    where
+        P: EffectParameter,
+    {
+        param.validate()?;
+        let sample_rate = self.sample_rate()?;
+        let value = param.range(sample_rate).check(value)?;
+        self.set_parameter(param.id(), Scope::Global, Element::Output, value)
+    }
+}
+
+macro_rules! effect_unit {
+    ($(#[$attr:meta])* $Name:ident, $Param:ident) => {
+        $(#[$attr])*
+        pub struct $Name {
+            audio_unit: AudioUnit,
+        }
+
+        impl $Name {
+            /// Construct and initialize a new instance of the effect.
+            pub fn new() -> Result<Self, Error> {
+                let audio_unit = AudioUnit::new(<$Param as EffectParameter>::EFFECT_TYPE)?;
+                Ok($Name { audio_unit })
+            }
+
+            /// Gets the current value of the given parameter.
+            pub fn get(&self, param: $Param) -> Result<f32, Error> {
+                self.audio_unit.effect_parameter(param)
+            }
+
+            /// Sets the value of the given parameter, validating it against the parameter's
+            /// documented range.
+            pub fn set(&mut self, param: $Param, value: f32) -> Result<(), Error> {
+                self.audio_unit.set_effect_parameter(param, value)
+            }
+
+            /// Consume the effect, returning the inner **AudioUnit**.
+            pub fn into_audio_unit(self) -> AudioUnit {
+                self.audio_unit
+            }
+        }
+
+        impl AsRef<AudioUnit> for $Name {
+            fn as_ref(&self) -> &AudioUnit {
+                &self.audio_unit
+            }
+        }
+
+        impl AsMut<AudioUnit> for $Name {
+            fn as_mut(&mut self) -> &mut AudioUnit {
+                &mut self.audio_unit
+            }
+        }
+    };
+}
+
+effect_unit!(
+    /// A wrapper around an [**EffectType::PeakLimiter**](../types/enum.EffectType) audio unit.
+    PeakLimiter,
+    PeakLimiterParam
+);
+effect_unit!(
+    /// A wrapper around an [**EffectType::DynamicsProcessor**](../types/enum.EffectType) audio
+    /// unit.
+    DynamicsProcessor,
+    DynamicsProcessorParam
+);
+effect_unit!(
+    /// A wrapper around an [**EffectType::LowPassFilter**](../types/enum.EffectType) audio unit.
+    LowPassFilter,
+    LowPassFilterParam
+);
+effect_unit!(
+    /// A wrapper around an [**EffectType::HighPassFilter**](../types/enum.EffectType) audio unit.
+    HighPassFilter,
+    HighPassFilterParam
+);
+effect_unit!(
+    /// A wrapper around an [**EffectType::BandPassFilter**](../types/enum.EffectType) audio unit.
+    BandPassFilter,
+    BandPassFilterParam
+);
+effect_unit!(
+    /// A wrapper around an [**EffectType::HighShelfFilter**](../types/enum.EffectType) audio
+    /// unit.
+    HighShelfFilter,
+    HighShelfFilterParam
+);
+effect_unit!(
+    /// A wrapper around an [**EffectType::LowShelfFilter**](../types/enum.EffectType) audio unit.
+    LowShelfFilter,
+    LowShelfFilterParam
+);
+effect_unit!(
+    /// A wrapper around an [**EffectType::ParametricEQ**](../types/enum.EffectType) audio unit.
+    ParametricEq,
+    ParametricEqParam
+);
+effect_unit!(
+    /// A wrapper around an [**EffectType::Distortion**](../types/enum.EffectType) audio unit.
+    Distortion,
+    DistortionParam
+);
+effect_unit!(
+    /// A wrapper around an [**EffectType::Delay**](../types/enum.EffectType) audio unit.
+    Delay,
+    DelayParam
+);
+effect_unit!(
+    /// A wrapper around an [**EffectType::SampleDelay**](../types/enum.EffectType) audio unit.
+    SampleDelay,
+    SampleDelayParam
+);
+effect_unit!(
+    /// A wrapper around an [**EffectType::NBandEQ**](../types/enum.EffectType) audio unit.
+    NBandEq,
+    NBandEqParam
+);
+
+impl PeakLimiter {
+    /// Set the gain applied before limiting in decibels.
+    pub fn set_pre_gain(&mut self, db: f32) -> Result<(), Error> {
+        self.set(PeakLimiterParam::PreGain, db)
+    }
+}
+
+impl LowPassFilter {
+    /// Set the cutoff frequency in hertz.
+    pub fn set_cutoff(&mut self, hz: f32) -> Result<(), Error> {
+        self.set(LowPassFilterParam::CutoffFrequency, hz)
+    }
+
+    /// The cutoff frequency in hertz.
+    pub fn cutoff(&self) -> Result<f32, Error> {
+        self.get(LowPassFilterParam::CutoffFrequency)
+    }
+
+    /// Set the resonance in decibels.
+    pub fn set_resonance(&mut self, db: f32) -> Result<(), Error> {
+        self.set(LowPassFilterParam::Resonance, db)
+    }
+}
+
+impl HighPassFilter {
+    /// Set the cutoff frequency in hertz.
+    pub fn set_cutoff(&mut self, hz: f32) -> Result<(), Error> {
+        self.set(HighPassFilterParam::CutoffFrequency, hz)
+    }
+
+    /// The cutoff frequency in hertz.
+    pub fn cutoff(&self) -> Result<f32, Error> {
+        self.get(HighPassFilterParam::CutoffFrequency)
+    }
+
+    /// Set the resonance in decibels.
+    pub fn set_resonance(&mut self, db: f32) -> Result<(), Error> {
+        self.set(HighPassFilterParam::Resonance, db)
+    }
+}
+
+impl Delay {
+    /// Set the delay time in seconds.
+    pub fn set_delay_time(&mut self, seconds: f32) -> Result<(), Error> {
+        self.set(DelayParam::DelayTime, seconds)
+    }
+
+    /// Set the feedback as a percentage.
+    pub fn set_feedback(&mut self, percent: f32) -> Result<(), Error> {
+        self.set(DelayParam::Feedback, percent)
+    }
+
+    /// Set the balance between the wet and dry signal, from 0 (dry) to 100 (wet).
+    pub fn set_wet_dry_mix(&mut self, mix: f32) -> Result<(), Error> {
+        self.set(DelayParam::WetDryMix, mix)
+    }
+}
+
+#[cfg(test)]
+fn check_params<P>(params: &[P])
+where
+    P: EffectParameter + ::std::fmt::Debug,
+{
+    for &sample_rate in &[16_000.0, 22_050.0, 44_100.0, 48_000.0, 96_000.0] {
+        for param in params {
+            let r = param.range(sample_rate);
+            assert!(r.min <= r.max, "{:?}: empty range {:?}", param, r);
+            assert!(
+                r.contains(r.default),
+                "{:?}: default outside {:?}",
+                param,
+                r
+            );
+            assert!(r.check(r.min).is_ok());
+            assert!(r.check(r.max).is_ok());
+            assert!(r.check(r.min - 1.0).is_err());
+            assert!(r.check(r.max + 1.0).is_err());
+        }
+    }
+}
+
+#[test]
+fn test_effect_parameter_ranges() {
+    use DistortionParam as D;
+    use DynamicsProcessorParam as Dp;
+    check_params(&[
+        PeakLimiterParam::AttackTime,
+        PeakLimiterParam::DecayTime,
+        PeakLimiterParam::PreGain,
+    ]);
+    check_params(&[
+        Dp::Threshold,
+        Dp::HeadRoom,
+        Dp::ExpansionRatio,
+        Dp::AttackTime,
+        Dp::ReleaseTime,
+        Dp::OverallGain,
+    ]);
+    check_params(&[
+        LowPassFilterParam::CutoffFrequency,
+        LowPassFilterParam::Resonance,
+    ]);
+    check_params(&[
+        HighPassFilterParam::CutoffFrequency,
+        HighPassFilterParam::Resonance,
+    ]);
+    check_params(&[
+        BandPassFilterParam::CenterFrequency,
+        BandPassFilterParam::Bandwidth,
+    ]);
+    check_params(&[
+        HighShelfFilterParam::CutoffFrequency,
+        HighShelfFilterParam::Gain,
+    ]);
+    check_params(&[
+        LowShelfFilterParam::CutoffFrequency,
+        LowShelfFilterParam::Gain,
+    ]);
+    check_params(&[
+        ParametricEqParam::CenterFrequency,
+        ParametricEqParam::Q,
+        ParametricEqParam::Gain,
+    ]);
+    check_params(&[
+        D::Delay,
+        D::Decay,
+        D::DelayMix,
+        D::Decimation,
+        D::Rounding,
+        D::DecimationMix,
+        D::LinearTerm,
+        D::SquaredTerm,
+        D::CubicTerm,
+        D::PolynomialMix,
+        D::RingModFreq1,
+        D::RingModFreq2,
+        D::RingModBalance,
+        D::RingModMix,
+        D::SoftClipGain,
+        D::FinalMix,
+    ]);
+    check_params(&[
+        DelayParam::WetDryMix,
+        DelayParam::DelayTime,
+        DelayParam::Feedback,
+        DelayParam::LopassCutoff,
+    ]);
+    check_params(&[SampleDelayParam::DelayFrames]);
+    check_params(&[
+        NBandEqParam::GlobalGain,
+        NBandEqParam::BypassBand(3),
+        NBandEqParam::FilterType(3),
+        NBandEqParam::Frequency(3),
+        NBandEqParam::Gain(3),
+        NBandEqParam::Bandwidth(3),
+    ]);
+}
+
+#[test]
+fn test_nyquist_bounded_ranges() {
+    let cutoff = LowPassFilterParam::CutoffFrequency;
+    assert_eq!(cutoff.range(44_100.0).max, 22_050.0);
+    assert_eq!(cutoff.range(96_000.0).max, 48_000.0);
+    assert!(cutoff.range(44_100.0).check(22_050.0).is_ok());
+    assert!(cutoff.range(44_100.0).check(30_000.0).is_err());
+    assert!(cutoff.range(96_000.0).check(30_000.0).is_ok());
+    let shelf = HighShelfFilterParam::CutoffFrequency.range(16_000.0);
+    assert_eq!(shelf, range(10_000.0, 10_000.0, 10_000.0));
+    assert!(shelf.check(10_000.0).is_ok());
+    assert_eq!(DelayParam::LopassCutoff.range(22_050.0).default, 11_025.0);
+    assert_eq!(cutoff.unit(), ParameterUnit::Hertz);
+    assert_eq!(
+        LowPassFilterParam::Resonance.unit(),
+        ParameterUnit::Decibels
+    );
+}
+
+#[test]
+fn test_nband_eq_param_ids() {
+    assert_eq!(NBandEqParam::GlobalGain.id(), 0);
+    assert_eq!(NBandEqParam::BypassBand(0).id(), 1000);
+    assert_eq!(NBandEqParam::FilterType(2).id(), 2002);
+    assert_eq!(NBandEqParam::Frequency(15).id(), 3015);
+    assert_eq!(NBandEqParam::Gain(1).id(), 4001);
+    assert_eq!(NBandEqParam::Bandwidth(7).id(), 5007);
+    assert!(NBandEqParam::GlobalGain.validate().is_ok());
+    assert!(NBandEqParam::Gain(NBAND_EQ_MAX_BANDS - 1)
+        .validate()
+        .is_ok());
+    assert!(matches!(
+        NBandEqParam::BypassBand(NBAND_EQ_MAX_BANDS).validate(),
+        Err(Error::AudioUnit(AudioUnitError::InvalidParameter))
+    ));
+}
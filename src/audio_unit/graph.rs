@@ -0,0 +1,551 @@
+//! Wiring multiple **AudioUnit**s together into a processing graph.
+//!
+//! A [**Graph**](./struct.Graph) owns a set of audio units and connects the output buses of
+//! some to the input buses of others using `kAudioUnitProperty_MakeConnection`. One of the units,
+//! usually an I/O unit, is nominated as the *head* of the graph. Starting the graph starts the
+//! head, which in turn pulls audio through every unit connected upstream of it.
+//!
+//! The bookkeeping of which buses are connected, cycle detection, processing order and format
+//! propagation is handled by the [**Topology**](./struct.Topology) type, which is independent
+//! of Core Audio.
+
+use objc2_audio_toolbox::{
+    kAudioUnitProperty_MakeConnection, kAudioUnitProperty_StreamFormat, AudioUnitConnection,
+};
+
+use super::audio_format::LinearPcmFlags;
+use super::{set_property, AudioUnit, Element, Scope, StreamFormat};
+use crate::error::{Error, GraphError};
+use std::mem;
+use std::ptr;
+
+/// Identifies a node within a **Graph** or **Topology**.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// The index of the node in the order in which it was added.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// A connection from the output bus of one node to the input bus of another.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Connection {
+    pub source: NodeId,
+    pub source_bus: u32,
+    pub destination: NodeId,
+    pub destination_bus: u32,
+}
+
+/// The connections between the nodes of a graph.
+///
+/// Each input bus may be fed by at most one output bus, and connections may not form cycles.
+#[derive(Clone, Debug, Default)]
+pub struct Topology {
+    node_count: usize,
+    connections: Vec<Connection>,
+}
+
+impl Topology {
+    /// Construct an empty topology.
+    pub fn new() -> Self {
+        Topology::default()
+    }
+
+    /// Add a new node without any connections.
+    pub fn add_node(&mut self) -> NodeId {
+        let id = NodeId(self.node_count);
+        self.node_count += 1;
+        id
+    }
+
+    /// The number of nodes within the topology.
+    pub fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    /// Whether or not the node belongs to the topology.
+    pub fn contains(&self, node: NodeId) -> bool {
+        node.0 < self.node_count
+    }
+
+    /// All connections in the order in which they were made.
+    pub fn connections(&self) -> &[Connection] {
+        &self.connections
+    }
+
+    /// The connection feeding the given input bus, if any.
+    pub fn input(&self, destination: NodeId, destination_bus: u32) -> Option<&Connection> {
+        self.connections
+            .iter()
+            .find(|c| c.destination == destination && c.destination_bus == destination_bus)
+    }
+
+    /// Whether or not audio may flow from `from` to `to` through zero or more connections.
+    pub fn is_reachable(&self, from: NodeId, to: NodeId) -> bool {
+        let mut visited = vec![false; self.node_count];
+        let mut stack = vec![from];
+        while let Some(node) = stack.pop() {
+            if node == to {
+                return true;
+            }
+            if mem::replace(&mut visited[node.0], true) {
+                continue;
+            }
+            stack.extend(
+                self.connections
+                    .iter()
+                    .filter(|c| c.source == node)
+                    .map(|c| c.destination),
+            );
+        }
+        false
+    }
+
+    /// Check whether or not the connection may be made without modifying the topology.
+    pub fn check_connection(&self, connection: &Connection) -> Result<(), GraphError> {
+        if !self.contains(connection.source) || !self.contains(connection.destination) {
+            return Err(GraphError::InvalidNode);
+        }
+        if self
+            .input(connection.destination, connection.destination_bus)
+            .is_some()
+        {
+            return Err(GraphError::InputAlreadyConnected);
+        }
+        if self.is_reachable(connection.destination, connection.source) {
+            return Err(GraphError::Cycle);
+        }
+        Ok(())
+    }
+
+    /// Add the connection if it is valid.
+    pub fn connect(&mut self, connection: Connection) -> Result<(), GraphError> {
+        self.check_connection(&connection)?;
+        self.connections.push(connection);
+        Ok(())
+    }
+
+    /// Remove the connection feeding the given input bus, returning it.
+    pub fn disconnect(
+        &mut self,
+        destination: NodeId,
+        destination_bus: u32,
+    ) -> Result<Connection, GraphError> {
+        let position = self
+            .connections
+            .iter()
+            .position(|c| c.destination == destination && c.destination_bus == destination_bus)
+            .ok_or(GraphError::NotConnected)?;
+        Ok(self.connections.remove(position))
+    }
+
+    /// All nodes ordered such that every node appears after the nodes that feed it.
+    ///
+    /// Nodes that are otherwise unordered appear in the order in which they were added.
+    pub fn processing_order(&self) -> Vec<NodeId> {
+        let mut in_degree = vec![0usize; self.node_count];
+        for c in &self.connections {
+            in_degree[c.destination.0] += 1;
+        }
+        let mut order = Vec::with_capacity(self.node_count);
+        let mut done = vec![false; self.node_count];
+        while order.len() < self.node_count {
+            // As cycles are rejected upon connection, there is always at least one ready node.
+            let next = (0..self.node_count)
+                .find(|&i| !done[i] && in_degree[i] == 0)
+                .expect("topology contains a cycle");
+            done[next] = true;
+            order.push(NodeId(next));
+            for c in self.connections.iter().filter(|c| c.source.0 == next) {
+                in_degree[c.destination.0] -= 1;
+            }
+        }
+        order
+    }
+
+    /// Determine the stream format of every node, given the formats of some of them.
+    ///
+    /// Nodes listed in `fixed` keep the given format. Every other node takes on the format of
+    /// the node feeding its lowest-numbered connected input bus, as is the case for effect units
+    /// whose output format follows their input format. The result is indexed by node, with
+    /// `None` for nodes whose format could not be determined.
+    pub fn propagate_formats(&self, fixed: &[(NodeId, StreamFormat)]) -> Vec<Option<StreamFormat>> {
+        let mut formats: Vec<Option<StreamFormat>> = vec![None; self.node_count];
+        for node in self.processing_order() {
+            formats[node.0] = match fixed.iter().find(|&&(n, _)| n == node) {
+                Some(&(_, format)) => Some(format),
+                None => self
+                    .connections
+                    .iter()
+                    .filter(|c| c.destination == node)
+                    .min_by_key(|c| c.destination_bus)
+                    .and_then(|c| formats[c.source.0]),
+            };
+        }
+        formats
+    }
+
+    /// All connections whose source and destination formats are known but do not match.
+    pub fn mismatched_connections(&self, formats: &[Option<StreamFormat>]) -> Vec<Connection> {
+        self.connections
+            .iter()
+            .filter(|c| match (formats[c.source.0], formats[c.destination.0]) {
+                (Some(a), Some(b)) => !formats_match(&a, &b),
+                _ => false,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Whether or not audio in format `a` may be passed directly to a bus expecting format `b`.
+///
+/// The `IS_PACKED` flag is ignored, as all formats supported by **StreamFormat** are packed.
+pub fn formats_match(a: &StreamFormat, b: &StreamFormat) -> bool {
+    let flags = |f: &StreamFormat| (f.flags | LinearPcmFlags::IS_PACKED).bits();
+    a.sample_rate == b.sample_rate
+        && a.sample_format == b.sample_format
+        && a.channels == b.channels
+        && flags(a) == flags(b)
+}
+
+/// A set of connected **AudioUnit**s.
+///
+/// Units are dropped in reverse processing order, so that no unit is disposed of while another
+/// unit is still connected to it.
+pub struct Graph {
+    units: Vec<AudioUnit>,
+    topology: Topology,
+    head: Option<NodeId>,
+}
+
+impl Graph {
+    /// Construct an empty graph.
+    pub fn new() -> Self {
+        Graph {
+            units: Vec::new(),
+            topology: Topology::new(),
+            head: None,
+        }
+    }
+
+    /// Add an **AudioUnit** to the graph.
+    ///
+    /// Units whose input format should be set by
+    /// [**Graph::connect_propagating_format**](#method.connect_propagating_format) should be added
+    /// uninitialized (see [**AudioUnit::new_uninitialized**](../struct.AudioUnit#method.new_uninitialized))
+    /// and initialized via [**Graph::initialize**](#method.initialize).
+    pub fn add_node(&mut self, unit: AudioUnit) -> NodeId {
+        self.units.push(unit);
+        self.topology.add_node()
+    }
+
+    /// Nominate the node that is started and stopped along with the graph.
+    pub fn set_head(&mut self, node: NodeId) -> Result<(), Error> {
+        if !self.topology.contains(node) {
+            return Err(Error::Graph(GraphError::InvalidNode));
+        }
+        self.head = Some(node);
+        Ok(())
+    }
+
+    /// The head node, if one has been set.
+    pub fn head(&self) -> Option<NodeId> {
+        self.head
+    }
+
+    /// The **AudioUnit** of the given node.
+    pub fn node(&self, node: NodeId) -> Option<&AudioUnit> {
+        self.units.get(node.0)
+    }
+
+    /// The **AudioUnit** of the given node.
+    pub fn node_mut(&mut self, node: NodeId) -> Option<&mut AudioUnit> {
+        self.units.get_mut(node.0)
+    }
+
+    /// The connections between the nodes of the graph.
+    pub fn topology(&self) -> &Topology {
+        &self.topology
+    }
+
+    /// Connect an output bus of the `source` node to an input bus of the `destination` node.
+    ///
+    /// An error is returned if the stream formats of the two buses differ, if the input bus is
+    /// already connected or if the connection would introduce a cycle. The destination unit is
+    /// left unchanged upon error. See
+    /// [**Graph::connect_propagating_format**](#method.connect_propagating_format) in order to
+    /// apply the source format to the destination instead.
+    pub fn connect(
+        &mut self,
+        source: NodeId,
+        source_bus: u32,
+        destination: NodeId,
+        destination_bus: u32,
+    ) -> Result<(), Error> {
+        self.connect_buses(source, source_bus, destination, destination_bus, false)
+    }
+
+    /// The same as [**Graph::connect**](#method.connect), except that if the stream formats of
+    /// the two buses differ, the format of the source output bus is first applied to the
+    /// destination input bus.
+    ///
+    /// The destination unit must then be uninitialized. Any error from applying the format is
+    /// returned as is.
+    pub fn connect_propagating_format(
+        &mut self,
+        source: NodeId,
+        source_bus: u32,
+        destination: NodeId,
+        destination_bus: u32,
+    ) -> Result<(), Error> {
+        self.connect_buses(source, source_bus, destination, destination_bus, true)
+    }
+
+    fn connect_buses(
+        &mut self,
+        source: NodeId,
+        source_bus: u32,
+        destination: NodeId,
+        destination_bus: u32,
+        propagate_format: bool,
+    ) -> Result<(), Error> {
+        let connection = Connection {
+            source,
+            source_bus,
+            destination,
+            destination_bus,
+        };
+        self.topology
+            .check_connection(&connection)
+            .map_err(Error::Graph)?;
+
+        let source_unit = &self.units[source.0];
+        let destination_unit = &self.units[destination.0];
+        unsafe {
+            let source_format = source_unit.stream_format(Scope::Output, Element(source_bus))?;
+            let destination_format =
+                destination_unit.stream_format(Scope::Input, Element(destination_bus))?;
+            if !formats_match(&source_format, &destination_format) {
+                if !propagate_format {
+                    return Err(Error::Graph(GraphError::FormatMismatch));
+                }
+                let asbd = source_format.to_asbd();
+                set_property(
+                    destination_unit.instance,
+                    kAudioUnitProperty_StreamFormat,
+                    Scope::Input,
+                    Element(destination_bus),
+                    Some(&asbd),
+                )?;
+            }
+
+            let raw = AudioUnitConnection {
+                sourceAudioUnit: source_unit.instance,
+                sourceOutputNumber: source_bus,
+                destInputNumber: destination_bus,
+            };
+            set_property(
+                destination_unit.instance,
+                kAudioUnitProperty_MakeConnection,
+                Scope::Input,
+                Element(destination_bus),
+                Some(&raw),
+            )?;
+        }
+
+        self.topology.connect(connection).map_err(Error::Graph)
+    }
+
+    /// Remove the connection feeding the given input bus of the `destination` node.
+    pub fn disconnect(&mut self, destination: NodeId, destination_bus: u32) -> Result<(), Error> {
+        if self.topology.input(destination, destination_bus).is_none() {
+            return Err(Error::Graph(GraphError::NotConnected));
+        }
+        let raw = AudioUnitConnection {
+            sourceAudioUnit: ptr::null_mut(),
+            sourceOutputNumber: 0,
+            destInputNumber: destination_bus,
+        };
+        self.units[destination.0].set_property(
+            kAudioUnitProperty_MakeConnection,
+            Scope::Input,
+            Element(destination_bus),
+            Some(&raw),
+        )?;
+        self.topology
+            .disconnect(destination, destination_bus)
+            .map(|_| ())
+            .map_err(Error::Graph)
+    }
+
+    /// Initialize every unit in processing order, so that each unit is initialized after the
+    /// units feeding it.
+    pub fn initialize(&mut self) -> Result<(), Error> {
+        for node in self.topology.processing_order() {
+            self.units[node.0].initialize()?;
+        }
+        Ok(())
+    }
+
+    /// Initialize the graph and start the head node.
+    pub fn start(&mut self) -> Result<(), Error> {
+        let head = self.head.ok_or(Error::Graph(GraphError::NoHead))?;
+        self.initialize()?;
+        self.units[head.0].start()
+    }
+
+    /// Stop the head node.
+    pub fn stop(&mut self) -> Result<(), Error> {
+        let head = self.head.ok_or(Error::Graph(GraphError::NoHead))?;
+        self.units[head.0].stop()
+    }
+}
+
+impl Default for Graph {
+    fn default() -> Self {
+        Graph::new()
+    }
+}
+
+impl Drop for Graph {
+    fn drop(&mut self) {
+        if let Some(head) = self.head {
+            self.units[head.0].stop().ok();
+        }
+        let order = self.topology.processing_order();
+        let mut units: Vec<Option<AudioUnit>> = self.units.drain(..).map(Some).collect();
+        for node in order.into_iter().rev() {
+            units[node.0].take();
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_format(sample_rate: f64, channels: u32) -> StreamFormat {
+    StreamFormat {
+        sample_rate,
+        sample_format: super::SampleFormat::F32,
+        flags: LinearPcmFlags::IS_FLOAT | LinearPcmFlags::IS_NON_INTERLEAVED,
+        channels,
+    }
+}
+
+#[test]
+fn test_topology_connect() {
+    let mut t = Topology::new();
+    let a = t.add_node();
+    let b = t.add_node();
+    let c = t.add_node();
+    let conn = |source, source_bus, destination, destination_bus| Connection {
+        source,
+        source_bus,
+        destination,
+        destination_bus,
+    };
+    assert_eq!(t.connect(conn(a, 0, b, 0)), Ok(()));
+    assert_eq!(
+        t.connect(conn(c, 0, b, 0)),
+        Err(GraphError::InputAlreadyConnected)
+    );
+    assert_eq!(t.connect(conn(c, 0, b, 1)), Ok(()));
+    assert_eq!(
+        t.connect(conn(a, 0, NodeId(7), 0)),
+        Err(GraphError::InvalidNode)
+    );
+    assert_eq!(t.input(b, 1).map(|c| c.source), Some(c));
+    assert!(t.input(a, 0).is_none());
+    assert_eq!(t.disconnect(b, 1).map(|c| c.source), Ok(c));
+    assert_eq!(t.disconnect(b, 1), Err(GraphError::NotConnected));
+    assert_eq!(t.connections().len(), 1);
+}
+
+#[test]
+fn test_topology_cycles() {
+    let mut t = Topology::new();
+    let a = t.add_node();
+    let b = t.add_node();
+    let c = t.add_node();
+    let conn = |source, destination, destination_bus| Connection {
+        source,
+        source_bus: 0,
+        destination,
+        destination_bus,
+    };
+    assert_eq!(t.connect(conn(a, a, 0)), Err(GraphError::Cycle));
+    t.connect(conn(a, b, 0)).unwrap();
+    t.connect(conn(b, c, 0)).unwrap();
+    assert!(t.is_reachable(a, c));
+    assert!(!t.is_reachable(c, a));
+    assert_eq!(t.connect(conn(c, a, 0)), Err(GraphError::Cycle));
+    assert_eq!(t.connect(conn(c, b, 1)), Err(GraphError::Cycle));
+    // A diamond is not a cycle.
+    assert_eq!(t.connect(conn(a, c, 1)), Ok(()));
+}
+
+#[test]
+fn test_topology_processing_order() {
+    let mut t = Topology::new();
+    let output = t.add_node();
+    let mixer = t.add_node();
+    let fx = t.add_node();
+    let source = t.add_node();
+    let conn = |source, destination, destination_bus| Connection {
+        source,
+        source_bus: 0,
+        destination,
+        destination_bus,
+    };
+    t.connect(conn(mixer, output, 0)).unwrap();
+    t.connect(conn(fx, mixer, 0)).unwrap();
+    t.connect(conn(source, fx, 0)).unwrap();
+    t.connect(conn(source, mixer, 1)).unwrap();
+    assert_eq!(t.processing_order(), vec![source, fx, mixer, output]);
+
+    let mut t = Topology::new();
+    let nodes: Vec<_> = (0..3).map(|_| t.add_node()).collect();
+    assert_eq!(t.processing_order(), nodes);
+}
+
+#[test]
+fn test_topology_propagate_formats() {
+    let mut t = Topology::new();
+    let source = t.add_node();
+    let fx = t.add_node();
+    let output = t.add_node();
+    let unconnected = t.add_node();
+    let conn = |source, destination| Connection {
+        source,
+        source_bus: 0,
+        destination,
+        destination_bus: 0,
+    };
+    t.connect(conn(source, fx)).unwrap();
+    t.connect(conn(fx, output)).unwrap();
+
+    let stereo = test_format(44_100.0, 2);
+    let formats = t.propagate_formats(&[(source, stereo)]);
+    assert!(formats[..3]
+        .iter()
+        .all(|f| f.is_some_and(|f| formats_match(&f, &stereo))));
+    assert!(formats[unconnected.index()].is_none());
+    assert!(t.mismatched_connections(&formats).is_empty());
+
+    let formats = t.propagate_formats(&[(source, stereo), (output, test_format(48_000.0, 2))]);
+    assert_eq!(t.mismatched_connections(&formats), vec![conn(fx, output)]);
+}
+
+#[test]
+fn test_formats_match() {
+    let a = test_format(44_100.0, 2);
+    let mut b = a;
+    b.flags |= LinearPcmFlags::IS_PACKED;
+    assert!(formats_match(&a, &b));
+    assert!(!formats_match(&a, &test_format(48_000.0, 2)));
+    assert!(!formats_match(&a, &test_format(44_100.0, 1)));
+    b.flags.remove(LinearPcmFlags::IS_NON_INTERLEAVED);
+    assert!(!formats_match(&a, &b));
+}
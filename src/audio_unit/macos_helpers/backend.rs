@@ -0,0 +1,395 @@
+//! Access to the properties of Core Audio HAL objects through a swappable backend.
+//!
+//! The helpers within [macos_helpers](../index.html) read and write device properties via the
+//! [**AudioObjectBackend**](./trait.AudioObjectBackend) trait rather than calling
+//! `AudioObjectGetPropertyData` and friends directly. [**CoreAudioBackend**](./struct.CoreAudioBackend)
+//! forwards to the HAL, while [**FakeBackend**](../fake/struct.FakeBackend) holds scripted
+//! properties in memory so that device selection logic may be tested without hardware.
+
+use objc2_core_audio::{
+    kAudioObjectPropertyElementMaster, kAudioObjectPropertyScopeGlobal, kAudioObjectSystemObject,
+    AudioObjectAddPropertyListener, AudioObjectGetPropertyData, AudioObjectGetPropertyDataSize,
+    AudioObjectHasProperty, AudioObjectID, AudioObjectIsPropertySettable,
+    AudioObjectPropertyAddress, AudioObjectPropertyScope, AudioObjectPropertySelector,
+    AudioObjectRemovePropertyListener, AudioObjectSetPropertyData,
+};
+use objc2_core_foundation::{CFRetained, CFString};
+
+use crate::error::{DeviceError, Error};
+use crate::shared_callback::SharedCallback;
+use crate::OSStatus;
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr::{self, NonNull};
+use std::slice;
+
+/// The identifier of the system object, which holds the list of devices and the defaults.
+pub const SYSTEM_OBJECT: AudioObjectID = kAudioObjectSystemObject as AudioObjectID;
+
+/// The closure type called upon changes to the properties of an audio object.
+///
+/// The closure is passed the object along with the addresses of the properties that changed.
+pub type ObjectPropertyListenerFn =
+    dyn FnMut(AudioObjectID, &[AudioObjectPropertyAddress]) + Send + 'static;
+
+/// Reads and writes the properties of Core Audio HAL objects.
+///
+/// Every method addresses a property by object and `AudioObjectPropertyAddress`, exactly as the
+/// `AudioObject*` functions of the HAL do. The optional qualifier is passed through as raw bytes.
+pub trait AudioObjectBackend: Send + Sync {
+    /// Whether or not the object has the given property.
+    fn has_property(&self, object: AudioObjectID, address: &AudioObjectPropertyAddress) -> bool;
+
+    /// Whether or not the value of the given property may be changed.
+    fn is_property_settable(
+        &self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+    ) -> Result<bool, Error>;
+
+    /// The size in bytes of the value of the given property.
+    fn property_data_size(
+        &self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        qualifier: &[u8],
+    ) -> Result<u32, Error>;
+
+    /// Read the value of the given property into `data`, returning the number of bytes written.
+    ///
+    /// Some properties, such as translations, also read their input from `data`.
+    fn get_property_data(
+        &self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        qualifier: &[u8],
+        data: &mut [u8],
+    ) -> Result<usize, Error>;
+
+    /// Change the value of the given property to `data`.
+    fn set_property_data(
+        &self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        qualifier: &[u8],
+        data: &[u8],
+    ) -> Result<(), Error>;
+
+    /// Register a closure to be called whenever the given property changes.
+    ///
+    /// The listener remains registered until the returned
+    /// [**ObjectPropertyListener**](./struct.ObjectPropertyListener) is dropped.
+    fn add_property_listener(
+        &self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        listener: Box<ObjectPropertyListenerFn>,
+    ) -> Result<ObjectPropertyListener, Error>;
+}
+
+/// A property listener registered with an [**AudioObjectBackend**](./trait.AudioObjectBackend).
+///
+/// The listener is removed and its closure freed when the **ObjectPropertyListener** is dropped.
+pub struct ObjectPropertyListener {
+    object: AudioObjectID,
+    address: AudioObjectPropertyAddress,
+    remove: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl ObjectPropertyListener {
+    /// Create a handle for a registered listener.
+    ///
+    /// The `remove` closure is called once upon drop and should unregister the listener. This is
+    /// only required by implementors of **AudioObjectBackend**.
+    pub fn new<F>(object: AudioObjectID, address: AudioObjectPropertyAddress, remove: F) -> Self
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        ObjectPropertyListener {
+            object,
+            address,
+            remove: Some(Box::new(remove)),
+        }
+    }
+
+    /// The object being listened to.
+    pub fn object(&self) -> AudioObjectID {
+        self.object
+    }
+
+    /// The address of the property being listened to.
+    pub fn address(&self) -> AudioObjectPropertyAddress {
+        self.address
+    }
+}
+
+impl Drop for ObjectPropertyListener {
+    fn drop(&mut self) {
+        if let Some(remove) = self.remove.take() {
+            remove();
+        }
+    }
+}
+
+/// The [**AudioObjectBackend**](./trait.AudioObjectBackend) that forwards to the Core Audio HAL.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CoreAudioBackend;
+
+/// The qualifier pointer expected by the HAL, which is null when there is no qualifier.
+fn qualifier_ptr(qualifier: &[u8]) -> *const c_void {
+    if qualifier.is_empty() {
+        ptr::null()
+    } else {
+        qualifier.as_ptr() as *const c_void
+    }
+}
+
+impl AudioObjectBackend for CoreAudioBackend {
+    fn has_property(&self, object: AudioObjectID, address: &AudioObjectPropertyAddress) -> bool {
+        unsafe { AudioObjectHasProperty(object, NonNull::from(address)) }
+    }
+
+    fn is_property_settable(
+        &self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+    ) -> Result<bool, Error> {
+        let mut settable = 0u8;
+        let status = unsafe {
+            AudioObjectIsPropertySettable(
+                object,
+                NonNull::from(address),
+                NonNull::from(&mut settable),
+            )
+        };
+        Error::from_os_status(status)?;
+        Ok(settable != 0)
+    }
+
+    fn property_data_size(
+        &self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        qualifier: &[u8],
+    ) -> Result<u32, Error> {
+        let mut data_size = 0u32;
+        let status = unsafe {
+            AudioObjectGetPropertyDataSize(
+                object,
+                NonNull::from(address),
+                qualifier.len() as u32,
+                qualifier_ptr(qualifier),
+                NonNull::from(&mut data_size),
+            )
+        };
+        Error::from_os_status(status)?;
+        Ok(data_size)
+    }
+
+    fn get_property_data(
+        &self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        qualifier: &[u8],
+        data: &mut [u8],
+    ) -> Result<usize, Error> {
+        let mut data_size = data.len() as u32;
+        let status = unsafe {
+            AudioObjectGetPropertyData(
+                object,
+                NonNull::from(address),
+                qualifier.len() as u32,
+                qualifier_ptr(qualifier),
+                NonNull::from(&mut data_size),
+                NonNull::new_unchecked(data.as_mut_ptr()).cast(),
+            )
+        };
+        Error::from_os_status(status)?;
+        Ok(data_size as usize)
+    }
+
+    fn set_property_data(
+        &self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        qualifier: &[u8],
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let status = unsafe {
+            AudioObjectSetPropertyData(
+                object,
+                NonNull::from(address),
+                qualifier.len() as u32,
+                qualifier_ptr(qualifier),
+                data.len() as u32,
+                NonNull::new_unchecked(data.as_ptr() as *mut u8).cast(),
+            )
+        };
+        Error::from_os_status(status)
+    }
+
+    fn add_property_listener(
+        &self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        listener: Box<ObjectPropertyListenerFn>,
+    ) -> Result<ObjectPropertyListener, Error> {
+        let callback = SharedCallback::new(listener);
+        let user_data = callback.user_data();
+        let status = unsafe {
+            AudioObjectAddPropertyListener(
+                object,
+                NonNull::from(address),
+                Some(object_listener_proc),
+                user_data,
+            )
+        };
+        if let Err(err) = Error::from_os_status(status) {
+            unsafe { SharedCallback::<ObjectPropertyListenerFn>::release(user_data) };
+            return Err(err);
+        }
+
+        // The pointer is carried as an address so that the removal closure is `Send`.
+        let address = *address;
+        let user_data = user_data as usize;
+        Ok(ObjectPropertyListener::new(
+            object,
+            address,
+            move || unsafe {
+                // We don't want to panic in `drop`, so we'll ignore returned errors.
+                Error::from_os_status(AudioObjectRemovePropertyListener(
+                    object,
+                    NonNull::from(&address),
+                    Some(object_listener_proc),
+                    user_data as *mut c_void,
+                ))
+                .ok();
+                // The closure is freed here, or by the last call still in progress on another
+                // thread.
+                drop(callback);
+            },
+        ))
+    }
+}
+
+/// Callback procedure that will be called each time a listened-to object property changes.
+unsafe extern "C-unwind" fn object_listener_proc(
+    object: AudioObjectID,
+    n_addresses: u32,
+    addresses: NonNull<AudioObjectPropertyAddress>,
+    client_data: *mut c_void,
+) -> OSStatus {
+    let addresses = slice::from_raw_parts(addresses.as_ptr(), n_addresses as usize);
+    SharedCallback::<ObjectPropertyListenerFn>::with(client_data, true, |callback| {
+        callback(object, addresses)
+    });
+    0
+}
+
+/// The address of a property on the main element of the given scope.
+pub fn property_address(
+    selector: AudioObjectPropertySelector,
+    scope: AudioObjectPropertyScope,
+) -> AudioObjectPropertyAddress {
+    AudioObjectPropertyAddress {
+        mSelector: selector,
+        mScope: scope,
+        mElement: kAudioObjectPropertyElementMaster,
+    }
+}
+
+/// The address of a property on the main element of the global scope.
+pub fn global_address(selector: AudioObjectPropertySelector) -> AudioObjectPropertyAddress {
+    property_address(selector, kAudioObjectPropertyScopeGlobal)
+}
+
+/// Reads the value of a fixed size property.
+///
+/// # Safety
+///
+/// The property must hold a value of type `T`.
+pub unsafe fn get_property<T>(
+    backend: &dyn AudioObjectBackend,
+    object: AudioObjectID,
+    address: &AudioObjectPropertyAddress,
+) -> Result<T, Error> {
+    let mut value = mem::MaybeUninit::<T>::zeroed();
+    let bytes = slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, mem::size_of::<T>());
+    backend.get_property_data(object, address, &[], bytes)?;
+    Ok(value.assume_init())
+}
+
+/// Reads the value of a fixed size property that takes a qualifier, such as a translation.
+///
+/// # Safety
+///
+/// The property must hold a value of type `T` and take a qualifier of type `Q`.
+pub unsafe fn get_qualified_property<T, Q>(
+    backend: &dyn AudioObjectBackend,
+    object: AudioObjectID,
+    address: &AudioObjectPropertyAddress,
+    qualifier: &Q,
+) -> Result<T, Error> {
+    let qualifier = slice::from_raw_parts(qualifier as *const Q as *const u8, mem::size_of::<Q>());
+    let mut value = mem::MaybeUninit::<T>::zeroed();
+    let bytes = slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, mem::size_of::<T>());
+    backend.get_property_data(object, address, qualifier, bytes)?;
+    Ok(value.assume_init())
+}
+
+/// Reads the value of a variable length property holding an array of `T`.
+///
+/// # Safety
+///
+/// The property must hold an array of values of type `T`.
+pub unsafe fn get_property_vec<T>(
+    backend: &dyn AudioObjectBackend,
+    object: AudioObjectID,
+    address: &AudioObjectPropertyAddress,
+) -> Result<Vec<T>, Error> {
+    let size = backend.property_data_size(object, address, &[])? as usize;
+    let len = size / mem::size_of::<T>();
+    let mut data: Vec<T> = Vec::with_capacity(len);
+    if len == 0 {
+        return Ok(data);
+    }
+    let bytes = slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, len * mem::size_of::<T>());
+    ptr::write_bytes(bytes.as_mut_ptr(), 0, bytes.len());
+    // The object may write less data than it initially reported.
+    let written = backend.get_property_data(object, address, &[], bytes)?;
+    data.set_len((written / mem::size_of::<T>()).min(len));
+    Ok(data)
+}
+
+/// Reads the value of a `CFStringRef` property.
+///
+/// # Safety
+///
+/// The property must hold a `CFStringRef` owned by the caller, as is the case for the `CFString`
+/// properties of the HAL.
+pub unsafe fn get_string_property(
+    backend: &dyn AudioObjectBackend,
+    object: AudioObjectID,
+    address: &AudioObjectPropertyAddress,
+) -> Result<String, Error> {
+    let string: *const CFString = get_property(backend, object, address)?;
+    let string = NonNull::new(string as *mut CFString)
+        .ok_or(Error::Device(DeviceError::MissingValue(address.mSelector)))?;
+    let string = CFRetained::from_raw(string);
+    Ok(string.to_string())
+}
+
+/// Changes the value of a property to `value`.
+///
+/// # Safety
+///
+/// The property must hold a value of type `T`.
+pub unsafe fn set_property<T>(
+    backend: &dyn AudioObjectBackend,
+    object: AudioObjectID,
+    address: &AudioObjectPropertyAddress,
+    value: &T,
+) -> Result<(), Error> {
+    let bytes = slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>());
+    backend.set_property_data(object, address, &[], bytes)
+}
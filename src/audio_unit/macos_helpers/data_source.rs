@@ -0,0 +1,200 @@
+//! Selectable data sources of audio devices.
+//!
+//! Many devices route a scope through one of several data sources, such as the internal
+//! speakers or the headphones of a built-in output, or the line and microphone inputs of an
+//! interface. The HAL may also switch the data source by itself upon jack sense, for example when
+//! headphones are plugged in.
+
+use objc2_core_audio::{
+    kAudioDevicePropertyDataSource, kAudioDevicePropertyDataSourceNameForIDCFString,
+    kAudioDevicePropertyDataSources, kAudioDevicePropertyJackIsConnected,
+};
+use objc2_core_audio_types::AudioValueTranslation;
+use objc2_core_foundation::{CFRetained, CFString};
+
+use super::backend::{property_address, ObjectPropertyListener};
+use super::device::Device;
+use super::object_scope;
+use crate::audio_unit::Scope;
+use crate::error::{DeviceError, Error};
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr::{self, NonNull};
+
+/// A data source of a device, identified by a code that is unique within its scope.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DataSource {
+    /// The `UInt32` identifier of the data source, often a four character code such as `'ispk'`
+    /// for the internal speakers.
+    pub id: u32,
+    /// The name of the data source, as presented to the user.
+    pub name: String,
+}
+
+impl Device {
+    /// The data sources available within the given scope, along with their names.
+    ///
+    /// Devices without selectable data sources return an empty list.
+    pub fn data_sources(&self, scope: Scope) -> Result<Vec<DataSource>, Error> {
+        let address = property_address(kAudioDevicePropertyDataSources, object_scope(scope));
+        if !self.object.has(&address) {
+            return Ok(Vec::new());
+        }
+        let ids: Vec<u32> = unsafe {
+            self.object
+                .get_vec(kAudioDevicePropertyDataSources, object_scope(scope))?
+        };
+        ids.into_iter()
+            .map(|id| {
+                let name = self.data_source_name(scope, id)?;
+                Ok(DataSource { id, name })
+            })
+            .collect()
+    }
+
+    /// The currently selected data source within the given scope.
+    pub fn data_source(&self, scope: Scope) -> Result<DataSource, Error> {
+        let id: u32 = unsafe {
+            self.object
+                .get(kAudioDevicePropertyDataSource, object_scope(scope))?
+        };
+        let name = self.data_source_name(scope, id)?;
+        Ok(DataSource { id, name })
+    }
+
+    /// Select the data source with the given identifier within the given scope.
+    pub fn set_data_source(&self, scope: Scope, id: u32) -> Result<(), Error> {
+        unsafe {
+            self.object
+                .set(kAudioDevicePropertyDataSource, object_scope(scope), &id)
+        }
+    }
+
+    /// Whether or not the data source of the given scope may be changed.
+    pub fn is_data_source_settable(&self, scope: Scope) -> Result<bool, Error> {
+        let address = property_address(kAudioDevicePropertyDataSource, object_scope(scope));
+        self.object.is_settable(&address)
+    }
+
+    /// The name of the data source with the given identifier within the given scope.
+    pub fn data_source_name(&self, scope: Scope, id: u32) -> Result<String, Error> {
+        let address = property_address(
+            kAudioDevicePropertyDataSourceNameForIDCFString,
+            object_scope(scope),
+        );
+        let mut id = id;
+        let mut name: *const CFString = ptr::null();
+        let translation = AudioValueTranslation {
+            mInputData: NonNull::from(&mut id).cast::<c_void>(),
+            mInputDataSize: mem::size_of::<u32>() as u32,
+            mOutputData: NonNull::from(&mut name).cast::<c_void>(),
+            mOutputDataSize: mem::size_of::<*const CFString>() as u32,
+        };
+        // SAFETY: The translation reads a `UInt32` and writes a `CFStringRef` owned by the
+        // caller.
+        unsafe { self.object.convert(&address, translation)? };
+        let name = NonNull::new(name as *mut CFString)
+            .ok_or(Error::Device(DeviceError::MissingValue(address.mSelector)))?;
+        let name = unsafe { CFRetained::from_raw(name) };
+        Ok(name.to_string())
+    }
+
+    /// Call `f` whenever the data source of the given scope changes, including when the HAL
+    /// switches it upon jack sense.
+    ///
+    /// The listener remains registered until the returned
+    /// [**ObjectPropertyListener**](../backend/struct.ObjectPropertyListener) is dropped.
+    pub fn add_data_source_listener<F>(
+        &self,
+        scope: Scope,
+        f: F,
+    ) -> Result<ObjectPropertyListener, Error>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let address = property_address(kAudioDevicePropertyDataSource, object_scope(scope));
+        self.object.add_listener(&address, f)
+    }
+
+    /// Whether or not something is plugged into the jack of the given scope, for devices with
+    /// jack sense.
+    pub fn is_jack_connected(&self, scope: Scope) -> Result<bool, Error> {
+        let connected: u32 = unsafe {
+            self.object
+                .get(kAudioDevicePropertyJackIsConnected, object_scope(scope))?
+        };
+        Ok(connected != 0)
+    }
+
+    /// Call `f` whenever something is plugged into or unplugged from the jack of the given
+    /// scope.
+    pub fn add_jack_listener<F>(&self, scope: Scope, f: F) -> Result<ObjectPropertyListener, Error>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let address = property_address(kAudioDevicePropertyJackIsConnected, object_scope(scope));
+        self.object.add_listener(&address, f)
+    }
+}
+
+#[test]
+fn test_data_sources() {
+    use super::AudioObjectBackend;
+    use objc2_core_audio::kAudioObjectPropertyScopeOutput;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let speakers = u32::from_be_bytes(*b"ispk");
+    let headphones = u32::from_be_bytes(*b"hdpn");
+    let fake = super::fake::fake_system();
+    let backend: Arc<dyn AudioObjectBackend> = Arc::new(fake.clone());
+    let device = Device::with_backend(10, backend);
+
+    let sources = device.data_sources(Scope::Output).unwrap();
+    let names: Vec<&str> = sources.iter().map(|source| source.name.as_str()).collect();
+    assert_eq!(names, ["Internal Speakers", "Headphones"]);
+    assert!(device.data_sources(Scope::Input).unwrap().is_empty());
+    assert_eq!(device.data_source(Scope::Output).unwrap().id, speakers);
+    assert!(device.is_data_source_settable(Scope::Output).unwrap());
+    assert!(device.data_source_name(Scope::Output, 0).is_err());
+
+    let changes = Arc::new(AtomicUsize::new(0));
+    let counter = changes.clone();
+    let _listener = device
+        .add_data_source_listener(Scope::Output, move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+    device.set_data_source(Scope::Output, headphones).unwrap();
+    assert_eq!(
+        device.data_source(Scope::Output).unwrap().name,
+        "Headphones"
+    );
+    assert_eq!(changes.load(Ordering::SeqCst), 1);
+
+    // Jack sense: the HAL reports the jack and switches the data source by itself.
+    let jacks = Arc::new(AtomicUsize::new(0));
+    let counter = jacks.clone();
+    let _jack_listener = device
+        .add_jack_listener(Scope::Output, move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+    assert!(!device.is_jack_connected(Scope::Output).unwrap());
+    let jack = property_address(
+        kAudioDevicePropertyJackIsConnected,
+        kAudioObjectPropertyScopeOutput,
+    );
+    fake.insert(10, jack, &1u32);
+    fake.notify(10, jack);
+    let source = property_address(
+        kAudioDevicePropertyDataSource,
+        kAudioObjectPropertyScopeOutput,
+    );
+    fake.insert(10, source, &speakers);
+    fake.notify(10, source);
+    assert!(device.is_jack_connected(Scope::Output).unwrap());
+    assert_eq!(jacks.load(Ordering::SeqCst), 1);
+    assert_eq!(changes.load(Ordering::SeqCst), 2);
+    assert_eq!(device.data_source(Scope::Output).unwrap().id, speakers);
+}
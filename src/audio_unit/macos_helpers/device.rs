@@ -0,0 +1,423 @@
+//! An object model for the audio devices known to the Core Audio HAL.
+//!
+//! A [**Device**](./struct.Device) pairs an `AudioDeviceID` with the
+//! [**AudioObjectBackend**](../backend/trait.AudioObjectBackend) used to query it, and exposes
+//! the properties of the device as methods. Failures are reported as
+//! [**DeviceError**](../../../error/device/enum.Error)s naming the property involved.
+
+use objc2_core_audio::{
+    kAudioDevicePropertyAvailableNominalSampleRates, kAudioDevicePropertyDeviceIsAlive,
+    kAudioDevicePropertyDeviceNameCFString, kAudioDevicePropertyDeviceUID,
+    kAudioDevicePropertyLatency, kAudioDevicePropertyModelUID,
+    kAudioDevicePropertyNominalSampleRate, kAudioDevicePropertyStreamConfiguration,
+    kAudioDevicePropertyStreams, kAudioDevicePropertyTransportType,
+    kAudioHardwarePropertyDefaultInputDevice, kAudioHardwarePropertyDefaultOutputDevice,
+    kAudioHardwarePropertyDevices, kAudioHardwarePropertyTranslateUIDToDevice,
+    kAudioObjectPropertyManufacturer, kAudioObjectPropertyScopeGlobal, kAudioObjectUnknown,
+    AudioDeviceID, AudioObjectPropertySelector, AudioStreamID,
+};
+use objc2_core_audio_types::AudioValueRange;
+use objc2_core_foundation::CFString;
+
+use super::backend::{
+    get_qualified_property, global_address, AudioObjectBackend, CoreAudioBackend, SYSTEM_OBJECT,
+};
+use super::object::Object;
+use super::object_scope;
+use super::stream::Stream;
+use super::transport_type::TransportType;
+use super::SampleRateOptions;
+use crate::audio_unit::Scope;
+use crate::error::{DeviceError, Error};
+use std::fmt;
+use std::sync::Arc;
+
+/// An audio device, such as a built-in speaker, a USB interface or an aggregate device.
+///
+/// A **Device** is a cheap handle: cloning it shares the backend, and every method queries the
+/// backend afresh so that the values returned are always current.
+#[derive(Clone)]
+pub struct Device {
+    pub(super) object: Object,
+}
+
+impl Device {
+    /// The device with the given id, queried through the Core Audio HAL.
+    pub fn new(id: AudioDeviceID) -> Self {
+        Self::with_backend(id, Arc::new(CoreAudioBackend))
+    }
+
+    /// The device with the given id, queried through the given backend.
+    pub fn with_backend(id: AudioDeviceID, backend: Arc<dyn AudioObjectBackend>) -> Self {
+        Device {
+            object: Object::new(id, backend),
+        }
+    }
+
+    /// The system's default output device.
+    pub fn default_output() -> Result<Self, Error> {
+        Self::default_output_with_backend(Arc::new(CoreAudioBackend))
+    }
+
+    /// The system's default input device.
+    pub fn default_input() -> Result<Self, Error> {
+        Self::default_input_with_backend(Arc::new(CoreAudioBackend))
+    }
+
+    /// All audio devices on the system.
+    pub fn all() -> Result<Vec<Self>, Error> {
+        Self::all_with_backend(Arc::new(CoreAudioBackend))
+    }
+
+    /// The default output device known to the given backend.
+    pub fn default_output_with_backend(
+        backend: Arc<dyn AudioObjectBackend>,
+    ) -> Result<Self, Error> {
+        Self::default_device(backend, kAudioHardwarePropertyDefaultOutputDevice)
+    }
+
+    /// The default input device known to the given backend.
+    pub fn default_input_with_backend(backend: Arc<dyn AudioObjectBackend>) -> Result<Self, Error> {
+        Self::default_device(backend, kAudioHardwarePropertyDefaultInputDevice)
+    }
+
+    /// All audio devices known to the given backend.
+    pub fn all_with_backend(backend: Arc<dyn AudioObjectBackend>) -> Result<Vec<Self>, Error> {
+        let system = Object::new(SYSTEM_OBJECT, backend);
+        let ids: Vec<AudioDeviceID> = unsafe {
+            system.get_vec(
+                kAudioHardwarePropertyDevices,
+                kAudioObjectPropertyScopeGlobal,
+            )?
+        };
+        let backend = system.backend;
+        Ok(ids
+            .into_iter()
+            .map(|id| Device::with_backend(id, backend.clone()))
+            .collect())
+    }
+
+    fn default_device(
+        backend: Arc<dyn AudioObjectBackend>,
+        selector: AudioObjectPropertySelector,
+    ) -> Result<Self, Error> {
+        let system = Object::new(SYSTEM_OBJECT, backend);
+        let id: AudioDeviceID = unsafe { system.get(selector, kAudioObjectPropertyScopeGlobal) }
+            .map_err(|err| match err {
+                Error::Device(DeviceError::UnknownProperty(_)) => {
+                    Error::Device(DeviceError::NoDefaultDevice)
+                }
+                err => err,
+            })?;
+        if id == kAudioObjectUnknown {
+            return Err(Error::Device(DeviceError::NoDefaultDevice));
+        }
+        Ok(Device::with_backend(id, system.backend))
+    }
+
+    /// The device with the given persistent UID, as returned by
+    /// [**Device::uid**](#method.uid).
+    ///
+    /// Unlike device names, UIDs are unique and remain the same across reboots, so they are
+    /// suitable for storing a choice of device in a configuration.
+    pub fn from_uid(uid: &str) -> Result<Self, Error> {
+        Self::from_uid_with_backend(uid, Arc::new(CoreAudioBackend))
+    }
+
+    /// The device with the given UID, or the default input or output device if no device has
+    /// the UID, e.g. because it is disconnected.
+    pub fn from_uid_or_default(uid: &str, input: bool) -> Result<Self, Error> {
+        Self::from_uid_or_default_with_backend(uid, input, Arc::new(CoreAudioBackend))
+    }
+
+    /// The device with the given UID known to the given backend.
+    ///
+    /// The UID is translated by the system object. Should the system object not support the
+    /// translation, the UIDs of all devices are compared instead.
+    pub fn from_uid_with_backend(
+        uid: &str,
+        backend: Arc<dyn AudioObjectBackend>,
+    ) -> Result<Self, Error> {
+        let system = Object::new(SYSTEM_OBJECT, backend);
+        let selector = kAudioHardwarePropertyTranslateUIDToDevice;
+        let address = global_address(selector);
+        let uid_string = CFString::from_str(uid);
+        let qualifier: *const CFString = &*uid_string;
+        // SAFETY: The translation takes a `CFStringRef` qualifier and yields an `AudioObjectID`.
+        let translated: Result<AudioDeviceID, Error> = unsafe {
+            get_qualified_property(&*system.backend, SYSTEM_OBJECT, &address, &qualifier)
+        }
+        .map_err(|err| system.property_error(selector, err));
+        let id = match translated {
+            Err(Error::Device(DeviceError::UnknownProperty(_))) => {
+                let devices = Self::all_with_backend(system.backend.clone())?;
+                let found = devices
+                    .iter()
+                    .find(|d| d.uid().ok().as_deref() == Some(uid));
+                found.map_or(kAudioObjectUnknown, Device::id)
+            }
+            translated => translated?,
+        };
+        if id == kAudioObjectUnknown {
+            return Err(Error::Device(DeviceError::UidNotFound));
+        }
+        Ok(Device::with_backend(id, system.backend))
+    }
+
+    /// The device with the given UID known to the given backend, or its default input or
+    /// output device if no device has the UID.
+    pub fn from_uid_or_default_with_backend(
+        uid: &str,
+        input: bool,
+        backend: Arc<dyn AudioObjectBackend>,
+    ) -> Result<Self, Error> {
+        match Self::from_uid_with_backend(uid, backend.clone()) {
+            Err(Error::Device(DeviceError::UidNotFound)) if input => {
+                Self::default_input_with_backend(backend)
+            }
+            Err(Error::Device(DeviceError::UidNotFound)) => {
+                Self::default_output_with_backend(backend)
+            }
+            result => result,
+        }
+    }
+
+    /// The `AudioDeviceID` of the device.
+    pub fn id(&self) -> AudioDeviceID {
+        self.object.id
+    }
+
+    /// The backend through which the device is queried.
+    pub fn backend(&self) -> &Arc<dyn AudioObjectBackend> {
+        &self.object.backend
+    }
+
+    /// The human readable name of the device.
+    pub fn name(&self) -> Result<String, Error> {
+        self.object
+            .get_string(kAudioDevicePropertyDeviceNameCFString)
+    }
+
+    /// The human readable name of the manufacturer of the device.
+    pub fn manufacturer(&self) -> Result<String, Error> {
+        self.object.get_string(kAudioObjectPropertyManufacturer)
+    }
+
+    /// The persistent unique identifier of the device.
+    ///
+    /// Unlike the `AudioDeviceID`, the UID is the same across reboots and reconnections.
+    pub fn uid(&self) -> Result<String, Error> {
+        self.object.get_string(kAudioDevicePropertyDeviceUID)
+    }
+
+    /// An identifier shared by all devices of the same model.
+    pub fn model_uid(&self) -> Result<String, Error> {
+        self.object.get_string(kAudioDevicePropertyModelUID)
+    }
+
+    /// How the device is connected, e.g. via USB or Bluetooth.
+    pub fn transport_type(&self) -> Result<TransportType, Error> {
+        let code: u32 = unsafe {
+            self.object.get(
+                kAudioDevicePropertyTransportType,
+                kAudioObjectPropertyScopeGlobal,
+            )?
+        };
+        Ok(TransportType::from_u32(code))
+    }
+
+    /// The sample rate at which the device currently runs.
+    pub fn nominal_sample_rate(&self) -> Result<f64, Error> {
+        unsafe {
+            self.object.get(
+                kAudioDevicePropertyNominalSampleRate,
+                kAudioObjectPropertyScopeGlobal,
+            )
+        }
+    }
+
+    /// Change the nominal sample rate of the device, returning the rate that the device runs at.
+    ///
+    /// See [**set_device_sample_rate_with_options**](../fn.set_device_sample_rate_with_options)
+    /// for how the rate is matched against the available sample rates.
+    pub fn set_nominal_sample_rate(
+        &self,
+        rate: f64,
+        options: SampleRateOptions,
+    ) -> Result<f64, Error> {
+        super::set_sample_rate(&*self.object.backend, self.object.id, rate, options)
+    }
+
+    /// The nominal sample rates supported by the device.
+    ///
+    /// For discrete rates `mMinimum` and `mMaximum` are equal, while continuous ranges differ.
+    pub fn available_sample_rates(&self) -> Result<Vec<AudioValueRange>, Error> {
+        unsafe {
+            self.object.get_vec(
+                kAudioDevicePropertyAvailableNominalSampleRates,
+                kAudioObjectPropertyScopeGlobal,
+            )
+        }
+    }
+
+    /// The streams of the device within the given scope.
+    ///
+    /// The streams of both directions are listed for **Scope::Global**.
+    pub fn streams(&self, scope: Scope) -> Result<Vec<Stream>, Error> {
+        let ids: Vec<AudioStreamID> = unsafe {
+            self.object
+                .get_vec(kAudioDevicePropertyStreams, object_scope(scope))?
+        };
+        let backend = &self.object.backend;
+        Ok(ids
+            .into_iter()
+            .map(|id| Stream::with_backend(id, backend.clone()))
+            .collect())
+    }
+
+    /// The total number of channels of the device's streams within the given scope.
+    pub fn channel_count(&self, scope: Scope) -> Result<u32, Error> {
+        let object = &self.object;
+        let channels =
+            super::stream_configuration(&*object.backend, object.id, object_scope(scope)).map_err(
+                |err| object.property_error(kAudioDevicePropertyStreamConfiguration, err),
+            )?;
+        Ok(channels.iter().sum())
+    }
+
+    /// The latency of the device within the given scope, in frames.
+    ///
+    /// This excludes the latency of the device's streams and its safety offset.
+    pub fn latency(&self, scope: Scope) -> Result<u32, Error> {
+        unsafe {
+            self.object
+                .get(kAudioDevicePropertyLatency, object_scope(scope))
+        }
+    }
+
+    /// Whether or not the device is still present and usable.
+    pub fn is_alive(&self) -> Result<bool, Error> {
+        let alive: u32 = unsafe {
+            self.object.get(
+                kAudioDevicePropertyDeviceIsAlive,
+                kAudioObjectPropertyScopeGlobal,
+            )?
+        };
+        Ok(alive != 0)
+    }
+}
+
+impl fmt::Debug for Device {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Device")
+            .field("id", &self.object.id)
+            .finish()
+    }
+}
+
+#[test]
+fn test_device_properties() {
+    let backend: Arc<dyn AudioObjectBackend> = Arc::new(super::fake::fake_system());
+    let device = Device::with_backend(11, backend);
+    assert_eq!(device.id(), 11);
+    assert_eq!(device.name().unwrap(), "Interface");
+    assert_eq!(device.manufacturer().unwrap(), "Acme");
+    assert_eq!(device.uid().unwrap(), "fake-device-11");
+    assert_eq!(device.model_uid().unwrap(), "fake-model-Interface");
+    assert_eq!(device.transport_type().unwrap(), TransportType::Usb);
+    assert_eq!(device.nominal_sample_rate().unwrap(), 44_100.0);
+    let options = SampleRateOptions {
+        nearest: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        device.set_nominal_sample_rate(50_000.0, options).unwrap(),
+        48_000.0
+    );
+    assert_eq!(device.nominal_sample_rate().unwrap(), 48_000.0);
+    assert_eq!(device.available_sample_rates().unwrap().len(), 2);
+    let stream_ids = |scope| -> Vec<AudioStreamID> {
+        let streams = device.streams(scope).unwrap();
+        streams.iter().map(Stream::id).collect()
+    };
+    assert_eq!(stream_ids(Scope::Input), [1101, 1102]);
+    assert_eq!(stream_ids(Scope::Output), [1103]);
+    assert_eq!(device.streams(Scope::Global).unwrap().len(), 3);
+    assert_eq!(device.channel_count(Scope::Input).unwrap(), 4);
+    assert_eq!(device.channel_count(Scope::Output).unwrap(), 8);
+    assert_eq!(device.latency(Scope::Output).unwrap(), 64);
+    assert!(device.is_alive().unwrap());
+}
+
+#[test]
+fn test_device_lookup() {
+    let fake = super::fake::fake_system();
+    let backend: Arc<dyn AudioObjectBackend> = Arc::new(fake.clone());
+    let ids: Vec<_> = Device::all_with_backend(backend.clone())
+        .unwrap()
+        .iter()
+        .map(Device::id)
+        .collect();
+    assert_eq!(ids, [10, 11, 12]);
+    let output = Device::default_output_with_backend(backend.clone()).unwrap();
+    assert_eq!(output.name().unwrap(), "Speakers");
+    let input = Device::default_input_with_backend(backend.clone()).unwrap();
+    assert_eq!(input.id(), 12);
+    fake.set_default_device(true, kAudioObjectUnknown);
+    assert!(matches!(
+        Device::default_input_with_backend(backend),
+        Err(Error::Device(DeviceError::NoDefaultDevice))
+    ));
+}
+
+#[test]
+fn test_device_errors() {
+    let fake = super::fake::fake_system();
+    let backend: Arc<dyn AudioObjectBackend> = Arc::new(fake.clone());
+    let missing = Device::with_backend(99, backend.clone());
+    assert!(matches!(
+        missing.name(),
+        Err(Error::Device(DeviceError::NotFound(99)))
+    ));
+    // The fake holds the latency of both directions in a single property.
+    let latency = objc2_core_audio::AudioObjectPropertyAddress {
+        mSelector: kAudioDevicePropertyLatency,
+        mScope: objc2_core_audio::kAudioObjectPropertyScopeWildcard,
+        mElement: objc2_core_audio::kAudioObjectPropertyElementWildcard,
+    };
+    fake.remove(10, latency);
+    let speakers = Device::with_backend(10, backend);
+    assert!(matches!(
+        speakers.latency(Scope::Output),
+        Err(Error::Device(DeviceError::UnknownProperty(selector)))
+            if selector == kAudioDevicePropertyLatency
+    ));
+}
+
+#[test]
+fn test_device_from_uid() {
+    use objc2_core_audio::kAudioHardwarePropertyTranslateUIDToDevice;
+
+    let fake = super::fake::fake_system();
+    let backend: Arc<dyn AudioObjectBackend> = Arc::new(fake.clone());
+    let device = Device::from_uid_with_backend("fake-device-11", backend.clone()).unwrap();
+    assert_eq!(device.id(), 11);
+    assert!(matches!(
+        Device::from_uid_with_backend("missing", backend.clone()),
+        Err(Error::Device(DeviceError::UidNotFound))
+    ));
+    let fallback = Device::from_uid_or_default_with_backend("missing", false, backend.clone());
+    assert_eq!(fallback.unwrap().id(), 10);
+    fake.set_default_device(true, kAudioObjectUnknown);
+    assert!(matches!(
+        Device::from_uid_or_default_with_backend("missing", true, backend.clone()),
+        Err(Error::Device(DeviceError::NoDefaultDevice))
+    ));
+
+    // Without the translation, the UIDs of the devices are compared.
+    let translate = global_address(kAudioHardwarePropertyTranslateUIDToDevice);
+    fake.remove(SYSTEM_OBJECT, translate);
+    let device = Device::from_uid_with_backend("fake-device-10", backend.clone()).unwrap();
+    assert_eq!(device.id(), 10);
+    assert!(!backend.has_property(SYSTEM_OBJECT, &translate));
+}
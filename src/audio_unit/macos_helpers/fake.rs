@@ -0,0 +1,1024 @@
+//! An in-memory [**AudioObjectBackend**](../backend/trait.AudioObjectBackend) for tests.
+//!
+//! [**FakeBackend**](./struct.FakeBackend) holds scripted property values in place of the HAL.
+//! Properties may be inserted one at a time, or a whole device described by a
+//! [**FakeDevice**](./struct.FakeDevice) may be added at once. Property change notifications are
+//! delivered synchronously, both when a client sets a settable property and when a test calls
+//! [**FakeBackend::notify**](./struct.FakeBackend#method.notify).
+//!
+//! Once a device has been added, the system object also translates device UIDs via
+//! `kAudioHardwarePropertyTranslateUIDToDevice`.
+//!
+//! The volume controls of a device map their scalar linearly onto
+//! [**FAKE_VOLUME_DECIBELS**](./constant.FAKE_VOLUME_DECIBELS), and setting the volume in decibels
+//! changes the scalar and notifies the listeners of both.
+
+use objc2_core_audio::{
+    kAudioDevicePropertyAvailableNominalSampleRates, kAudioDevicePropertyDataSource,
+    kAudioDevicePropertyDataSourceNameForIDCFString, kAudioDevicePropertyDataSources,
+    kAudioDevicePropertyDeviceIsAlive, kAudioDevicePropertyDeviceNameCFString,
+    kAudioDevicePropertyDeviceUID, kAudioDevicePropertyHogMode,
+    kAudioDevicePropertyJackIsConnected, kAudioDevicePropertyLatency, kAudioDevicePropertyModelUID,
+    kAudioDevicePropertyMute, kAudioDevicePropertyNominalSampleRate, kAudioDevicePropertyStereoPan,
+    kAudioDevicePropertyStereoPanChannels, kAudioDevicePropertyStreamConfiguration,
+    kAudioDevicePropertyStreams, kAudioDevicePropertyTransportType,
+    kAudioDevicePropertyVolumeDecibels, kAudioDevicePropertyVolumeDecibelsToScalar,
+    kAudioDevicePropertyVolumeRangeDecibels, kAudioDevicePropertyVolumeScalar,
+    kAudioDevicePropertyVolumeScalarToDecibels, kAudioHardwareBadObjectError,
+    kAudioHardwareBadPropertySizeError, kAudioHardwareIllegalOperationError,
+    kAudioHardwarePropertyDefaultInputDevice, kAudioHardwarePropertyDefaultOutputDevice,
+    kAudioHardwarePropertyDevices, kAudioHardwarePropertyTranslateUIDToDevice,
+    kAudioHardwareUnknownPropertyError, kAudioObjectPropertyElementMaster,
+    kAudioObjectPropertyElementWildcard, kAudioObjectPropertyManufacturer,
+    kAudioObjectPropertyScopeGlobal, kAudioObjectPropertyScopeInput,
+    kAudioObjectPropertyScopeOutput, kAudioObjectPropertyScopeWildcard,
+    kAudioObjectPropertySelectorWildcard, kAudioObjectUnknown,
+    kAudioStreamPropertyAvailablePhysicalFormats, kAudioStreamPropertyAvailableVirtualFormats,
+    kAudioStreamPropertyDirection, kAudioStreamPropertyLatency, kAudioStreamPropertyPhysicalFormat,
+    kAudioStreamPropertyStartingChannel, kAudioStreamPropertyTerminalType,
+    kAudioStreamPropertyVirtualFormat, kAudioStreamTerminalTypeMicrophone,
+    kAudioStreamTerminalTypeSpeaker, AudioDeviceID, AudioObjectID, AudioObjectPropertyAddress,
+    AudioObjectPropertyScope, AudioStreamID, AudioStreamRangedDescription,
+};
+use objc2_core_audio_types::{
+    AudioBuffer, AudioBufferList, AudioValueRange, AudioValueTranslation,
+};
+use objc2_core_foundation::{CFRetained, CFString};
+
+use super::backend::{
+    global_address, property_address, AudioObjectBackend, ObjectPropertyListener,
+    ObjectPropertyListenerFn, SYSTEM_OBJECT,
+};
+use super::transport_type::TransportType;
+use crate::error::Error;
+use crate::format::audio_format::LinearPcmFlags;
+use crate::format::{SampleFormat, StreamFormat};
+use crate::OSStatus;
+use std::mem;
+use std::ptr::{self, NonNull};
+use std::slice;
+use std::sync::{Arc, Mutex, Weak};
+
+/// An [**AudioObjectBackend**](../backend/trait.AudioObjectBackend) holding scripted properties
+/// in memory.
+///
+/// Cloning a **FakeBackend** produces a handle to the same set of properties, so that a test may
+/// keep scripting changes after handing the backend to the code under test.
+#[derive(Clone, Default)]
+pub struct FakeBackend {
+    state: Arc<Mutex<State>>,
+}
+
+/// A description of a device to add to a [**FakeBackend**](./struct.FakeBackend).
+#[derive(Clone, Debug)]
+pub struct FakeDevice {
+    /// The identifier of the device.
+    pub id: AudioDeviceID,
+    /// The name of the device.
+    pub name: String,
+    /// The name of the manufacturer of the device.
+    pub manufacturer: String,
+    /// The persistent unique identifier of the device.
+    pub uid: String,
+    /// The identifier of the model of the device.
+    pub model_uid: String,
+    /// How the device is connected.
+    pub transport_type: TransportType,
+    /// The number of channels of each input stream.
+    ///
+    /// See [**FakeDevice::stream_id**](./struct.FakeDevice#method.stream_id) for the ids given
+    /// to the streams.
+    pub input_channels: Vec<u32>,
+    /// The number of channels of each output stream.
+    pub output_channels: Vec<u32>,
+    /// The latency of the device in frames, in both directions.
+    pub latency: u32,
+    /// The current nominal sample rate.
+    pub nominal_sample_rate: f64,
+    /// The available nominal sample rate ranges.
+    pub available_sample_rates: Vec<AudioValueRange>,
+    /// The available physical formats of the device and of each of its streams. The first, if
+    /// any, is the current physical format.
+    ///
+    /// When empty, each stream offers a single packed 32-bit float format of its own number of
+    /// channels at the nominal sample rate. Streams offer the same virtual formats as physical
+    /// formats.
+    pub physical_formats: Vec<AudioStreamRangedDescription>,
+    /// The output volume scalar of the main element, followed by that of each channel with a
+    /// volume control of its own.
+    ///
+    /// Each element listed has settable volume and mute controls, starting unmuted.
+    pub output_volumes: Vec<f32>,
+    /// The input volume scalar of the main element, followed by that of each channel with a
+    /// volume control of its own.
+    pub input_volumes: Vec<f32>,
+    /// The output stereo pan of the main element, if the device has a settable stereo pan
+    /// between channels `1` and `2`.
+    pub stereo_pan: Option<f32>,
+    /// The identifiers and names of the output data sources. The first, if any, is selected.
+    pub output_data_sources: Vec<(u32, String)>,
+    /// The identifiers and names of the input data sources. The first, if any, is selected.
+    pub input_data_sources: Vec<(u32, String)>,
+    /// Whether or not something is plugged into the output jack, for a device with jack sense.
+    pub jack_connected: Option<bool>,
+    /// Whether or not the device is alive.
+    pub alive: bool,
+}
+
+/// The range in decibels onto which the volume controls of fake devices map their scalar.
+pub const FAKE_VOLUME_DECIBELS: AudioValueRange = AudioValueRange {
+    mMinimum: -64.0,
+    mMaximum: 0.0,
+};
+
+#[derive(Default)]
+struct State {
+    properties: Vec<Property>,
+    listeners: Vec<Listener>,
+    next_listener_id: u64,
+}
+
+struct Property {
+    object: AudioObjectID,
+    address: AudioObjectPropertyAddress,
+    value: Value,
+    settable: bool,
+}
+
+enum Value {
+    Bytes(Vec<u8>),
+    String(String),
+    /// The `kAudioHardwarePropertyTranslateUIDToDevice` translation of the system object.
+    UidTranslation,
+    /// The `kAudioDevicePropertyVolumeDecibels` of the volume scalar at the same address.
+    Decibels,
+    /// The `kAudioDevicePropertyVolumeDecibelsToScalar` conversion.
+    DecibelsToScalar,
+    /// The `kAudioDevicePropertyVolumeScalarToDecibels` conversion.
+    ScalarToDecibels,
+    /// The `kAudioDevicePropertyDataSourceNameForIDCFString` translation of the given data
+    /// sources.
+    DataSourceNames(Vec<(u32, String)>),
+}
+
+struct Listener {
+    id: u64,
+    object: AudioObjectID,
+    address: AudioObjectPropertyAddress,
+    callback: Arc<Mutex<Box<ObjectPropertyListenerFn>>>,
+}
+
+impl FakeDevice {
+    /// A stereo output device with the given identifier and name, running at 44.1 kHz with
+    /// 44.1 kHz and 48 kHz available, and with a volume control on its main output element.
+    pub fn new(id: AudioDeviceID, name: &str) -> Self {
+        let rate = |rate| AudioValueRange {
+            mMinimum: rate,
+            mMaximum: rate,
+        };
+        FakeDevice {
+            id,
+            name: name.to_string(),
+            manufacturer: "coreaudio-rs".to_string(),
+            uid: format!("fake-device-{id}"),
+            model_uid: format!("fake-model-{name}"),
+            transport_type: TransportType::BuiltIn,
+            input_channels: vec![],
+            output_channels: vec![2],
+            latency: 32,
+            nominal_sample_rate: 44_100.0,
+            available_sample_rates: vec![rate(44_100.0), rate(48_000.0)],
+            physical_formats: vec![],
+            output_volumes: vec![1.0],
+            input_volumes: vec![],
+            stereo_pan: None,
+            output_data_sources: vec![],
+            input_data_sources: vec![],
+            jack_connected: None,
+            alive: true,
+        }
+    }
+
+    /// The id of the stream at `index` within the input or output streams of the device.
+    ///
+    /// Streams are numbered from `id * 100 + 1`, with the output streams following the input
+    /// streams.
+    pub fn stream_id(&self, input: bool, index: usize) -> AudioStreamID {
+        let offset = if input { 0 } else { self.input_channels.len() };
+        self.id * 100 + 1 + (offset + index) as AudioStreamID
+    }
+}
+
+/// Whether a property address given by a client refers to a property held by the fake.
+fn addresses_match(a: &AudioObjectPropertyAddress, b: &AudioObjectPropertyAddress) -> bool {
+    let matches = |a, b, wildcard| a == b || a == wildcard || b == wildcard;
+    matches(
+        a.mSelector,
+        b.mSelector,
+        kAudioObjectPropertySelectorWildcard,
+    ) && matches(a.mScope, b.mScope, kAudioObjectPropertyScopeWildcard)
+        && matches(a.mElement, b.mElement, kAudioObjectPropertyElementWildcard)
+}
+
+fn status_error(status: OSStatus) -> Error {
+    Error::from_os_status(status)
+        .err()
+        .unwrap_or(Error::Unknown(status))
+}
+
+fn scalar_to_decibels(scalar: f32) -> f32 {
+    let range = FAKE_VOLUME_DECIBELS;
+    let scalar = f64::from(scalar.clamp(0.0, 1.0));
+    (range.mMinimum + scalar * (range.mMaximum - range.mMinimum)) as f32
+}
+
+fn decibels_to_scalar(decibels: f32) -> f32 {
+    let range = FAKE_VOLUME_DECIBELS;
+    let scalar = (f64::from(decibels) - range.mMinimum) / (range.mMaximum - range.mMinimum);
+    scalar.clamp(0.0, 1.0) as f32
+}
+
+/// The `f32` held by the bytes of a property value or client buffer.
+fn read_f32(bytes: &[u8]) -> Result<f32, Error> {
+    let bytes = bytes
+        .get(..mem::size_of::<f32>())
+        .ok_or_else(|| status_error(kAudioHardwareBadPropertySizeError))?;
+    Ok(f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// The raw bytes of a slice of plain values.
+fn bytes_of<T: Copy>(values: &[T]) -> Vec<u8> {
+    let len = mem::size_of_val(values);
+    unsafe { slice::from_raw_parts(values.as_ptr() as *const u8, len).to_vec() }
+}
+
+/// The bytes of an `AudioBufferList` describing streams with the given numbers of channels.
+fn buffer_list_bytes(channels: &[u32]) -> Vec<u8> {
+    let header = mem::size_of::<AudioBufferList>() - mem::size_of::<AudioBuffer>();
+    let mut bytes = (channels.len() as u32).to_ne_bytes().to_vec();
+    bytes.resize(header, 0);
+    for &channels in channels {
+        bytes.extend(bytes_of(&[AudioBuffer {
+            mNumberChannels: channels,
+            mDataByteSize: 0,
+            mData: ptr::null_mut(),
+        }]));
+    }
+    bytes.resize(bytes.len().max(mem::size_of::<AudioBufferList>()), 0);
+    bytes
+}
+
+impl State {
+    fn find(
+        &mut self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+    ) -> Result<&mut Property, Error> {
+        if !self.properties.iter().any(|p| p.object == object) {
+            return Err(status_error(kAudioHardwareBadObjectError));
+        }
+        let index = self
+            .properties
+            .iter()
+            .position(|p| p.object == object && p.address == *address)
+            .or_else(|| {
+                self.properties
+                    .iter()
+                    .position(|p| p.object == object && addresses_match(&p.address, address))
+            })
+            .ok_or_else(|| status_error(kAudioHardwareUnknownPropertyError))?;
+        Ok(&mut self.properties[index])
+    }
+
+    /// The device having the UID given by a `CFStringRef` qualifier, or `kAudioObjectUnknown`.
+    fn translate_uid(&self, qualifier: &[u8]) -> Result<AudioObjectID, Error> {
+        if qualifier.len() != mem::size_of::<*const CFString>() {
+            return Err(status_error(kAudioHardwareBadPropertySizeError));
+        }
+        let uid = unsafe { ptr::read_unaligned(qualifier.as_ptr() as *const *const CFString) };
+        let uid = unsafe { uid.as_ref() }
+            .ok_or_else(|| status_error(kAudioHardwareIllegalOperationError))?
+            .to_string();
+        let device = self.properties.iter().find(|p| {
+            p.address.mSelector == kAudioDevicePropertyDeviceUID
+                && matches!(p.value, Value::String(ref value) if *value == uid)
+        });
+        Ok(device.map_or(kAudioObjectUnknown, |p| p.object))
+    }
+
+    /// The volume scalar controlled by the `kAudioDevicePropertyVolumeDecibels` property at the
+    /// given address.
+    fn volume_scalar(
+        &mut self,
+        object: AudioObjectID,
+        decibels: &AudioObjectPropertyAddress,
+    ) -> Result<&mut Property, Error> {
+        let address = AudioObjectPropertyAddress {
+            mSelector: kAudioDevicePropertyVolumeScalar,
+            ..*decibels
+        };
+        self.find(object, &address)
+    }
+
+    fn insert(&mut self, object: AudioObjectID, address: AudioObjectPropertyAddress, value: Value) {
+        match self
+            .properties
+            .iter_mut()
+            .find(|p| p.object == object && p.address == address)
+        {
+            Some(property) => property.value = value,
+            None => self.properties.push(Property {
+                object,
+                address,
+                value,
+                settable: false,
+            }),
+        }
+    }
+}
+
+impl FakeBackend {
+    /// A backend without any objects or properties.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Set the value of a fixed size property, adding it if necessary.
+    ///
+    /// Properties are added as read-only. Listeners are not notified.
+    pub fn insert<T: Copy>(
+        &self,
+        object: AudioObjectID,
+        address: AudioObjectPropertyAddress,
+        value: &T,
+    ) {
+        self.insert_vec(object, address, slice::from_ref(value));
+    }
+
+    /// Set the value of a variable length property holding an array, adding it if necessary.
+    ///
+    /// Properties are added as read-only. Listeners are not notified.
+    pub fn insert_vec<T: Copy>(
+        &self,
+        object: AudioObjectID,
+        address: AudioObjectPropertyAddress,
+        values: &[T],
+    ) {
+        self.state()
+            .insert(object, address, Value::Bytes(bytes_of(values)));
+    }
+
+    /// Set the value of a `CFStringRef` property, adding it if necessary.
+    ///
+    /// Properties are added as read-only. Listeners are not notified.
+    pub fn insert_string(
+        &self,
+        object: AudioObjectID,
+        address: AudioObjectPropertyAddress,
+        value: &str,
+    ) {
+        self.state()
+            .insert(object, address, Value::String(value.to_string()));
+    }
+
+    /// Change whether or not clients may set the value of a property.
+    pub fn set_settable(
+        &self,
+        object: AudioObjectID,
+        address: AudioObjectPropertyAddress,
+        settable: bool,
+    ) {
+        if let Ok(property) = self.state().find(object, &address) {
+            property.settable = settable;
+        }
+    }
+
+    /// Remove a property.
+    pub fn remove(&self, object: AudioObjectID, address: AudioObjectPropertyAddress) {
+        self.state()
+            .properties
+            .retain(|p| p.object != object || p.address != address);
+    }
+
+    /// The current value of a fixed size property, if present and of the right size.
+    pub fn get<T: Copy>(
+        &self,
+        object: AudioObjectID,
+        address: AudioObjectPropertyAddress,
+    ) -> Option<T> {
+        let mut state = self.state();
+        match state.find(object, &address) {
+            Ok(Property {
+                value: Value::Bytes(bytes),
+                ..
+            }) if bytes.len() == mem::size_of::<T>() => {
+                Some(unsafe { ptr::read_unaligned(bytes.as_ptr() as *const T) })
+            }
+            _ => None,
+        }
+    }
+
+    /// Call the listeners registered for the given property, as the HAL does upon a change.
+    pub fn notify(&self, object: AudioObjectID, address: AudioObjectPropertyAddress) {
+        // Collect the listeners first so that they may access the backend.
+        let callbacks: Vec<_> = self
+            .state()
+            .listeners
+            .iter()
+            .filter(|l| l.object == object && addresses_match(&l.address, &address))
+            .map(|l| l.callback.clone())
+            .collect();
+        for callback in callbacks {
+            let mut callback = callback.lock().unwrap_or_else(|e| e.into_inner());
+            (*callback)(object, &[address]);
+        }
+    }
+
+    /// Add the properties describing the given device, and list it within the system object's
+    /// devices.
+    pub fn add_device(&self, device: &FakeDevice) {
+        let id = device.id;
+        let any_scope = |selector| AudioObjectPropertyAddress {
+            mSelector: selector,
+            mScope: kAudioObjectPropertyScopeWildcard,
+            mElement: kAudioObjectPropertyElementWildcard,
+        };
+        let stream_configuration = |scope: AudioObjectPropertyScope| AudioObjectPropertyAddress {
+            mSelector: kAudioDevicePropertyStreamConfiguration,
+            mScope: scope,
+            mElement: kAudioObjectPropertyElementWildcard,
+        };
+
+        {
+            let mut state = self.state();
+            let input = buffer_list_bytes(&device.input_channels);
+            let output = buffer_list_bytes(&device.output_channels);
+            state.insert(
+                id,
+                stream_configuration(kAudioObjectPropertyScopeInput),
+                Value::Bytes(input),
+            );
+            state.insert(
+                id,
+                stream_configuration(kAudioObjectPropertyScopeOutput),
+                Value::Bytes(output),
+            );
+            state.insert(
+                id,
+                any_scope(kAudioDevicePropertyDeviceNameCFString),
+                Value::String(device.name.clone()),
+            );
+            for (selector, value) in &[
+                (kAudioObjectPropertyManufacturer, &device.manufacturer),
+                (kAudioDevicePropertyDeviceUID, &device.uid),
+                (kAudioDevicePropertyModelUID, &device.model_uid),
+            ] {
+                state.insert(
+                    id,
+                    global_address(*selector),
+                    Value::String(value.to_string()),
+                );
+            }
+        }
+        let inputs = (0..device.input_channels.len()).map(|i| device.stream_id(true, i));
+        let outputs = (0..device.output_channels.len()).map(|i| device.stream_id(false, i));
+        let inputs: Vec<AudioStreamID> = inputs.collect();
+        let outputs: Vec<AudioStreamID> = outputs.collect();
+        let all: Vec<AudioStreamID> = inputs.iter().chain(&outputs).copied().collect();
+        for (scope, streams) in &[
+            (kAudioObjectPropertyScopeInput, inputs),
+            (kAudioObjectPropertyScopeOutput, outputs),
+            (kAudioObjectPropertyScopeGlobal, all),
+        ] {
+            let address = AudioObjectPropertyAddress {
+                mSelector: kAudioDevicePropertyStreams,
+                mScope: *scope,
+                mElement: kAudioObjectPropertyElementWildcard,
+            };
+            self.insert_vec(id, address, streams);
+        }
+        self.insert(id, any_scope(kAudioDevicePropertyLatency), &device.latency);
+        self.insert(
+            id,
+            global_address(kAudioDevicePropertyTransportType),
+            &device.transport_type.as_u32(),
+        );
+        self.insert(
+            id,
+            global_address(kAudioDevicePropertyDeviceIsAlive),
+            &(device.alive as u32),
+        );
+        self.insert(
+            id,
+            global_address(kAudioDevicePropertyNominalSampleRate),
+            &device.nominal_sample_rate,
+        );
+        self.insert_vec(
+            id,
+            global_address(kAudioDevicePropertyAvailableNominalSampleRates),
+            &device.available_sample_rates,
+        );
+        self.insert(id, global_address(kAudioDevicePropertyHogMode), &-1i32);
+        self.insert_vec(
+            id,
+            global_address(kAudioStreamPropertyAvailablePhysicalFormats),
+            &device.physical_formats,
+        );
+        if let Some(format) = device.physical_formats.first() {
+            let address = global_address(kAudioStreamPropertyPhysicalFormat);
+            self.insert(id, address, &format.mFormat);
+            self.set_settable(id, address, true);
+        }
+        self.set_settable(
+            id,
+            global_address(kAudioDevicePropertyNominalSampleRate),
+            true,
+        );
+        self.set_settable(id, global_address(kAudioDevicePropertyHogMode), true);
+
+        for (scope, volumes) in &[
+            (kAudioObjectPropertyScopeInput, &device.input_volumes),
+            (kAudioObjectPropertyScopeOutput, &device.output_volumes),
+        ] {
+            for (element, &volume) in volumes.iter().enumerate() {
+                self.add_volume_controls(id, *scope, element as u32, volume);
+            }
+        }
+        if let Some(pan) = device.stereo_pan {
+            let address = |selector| AudioObjectPropertyAddress {
+                mSelector: selector,
+                mScope: kAudioObjectPropertyScopeOutput,
+                mElement: kAudioObjectPropertyElementMaster,
+            };
+            self.insert(id, address(kAudioDevicePropertyStereoPan), &pan);
+            self.set_settable(id, address(kAudioDevicePropertyStereoPan), true);
+            self.insert(
+                id,
+                address(kAudioDevicePropertyStereoPanChannels),
+                &[1u32, 2],
+            );
+        }
+        for (scope, sources) in &[
+            (kAudioObjectPropertyScopeInput, &device.input_data_sources),
+            (kAudioObjectPropertyScopeOutput, &device.output_data_sources),
+        ] {
+            if let Some(&(selected, _)) = sources.first() {
+                let address = |selector| property_address(selector, *scope);
+                let ids: Vec<u32> = sources.iter().map(|&(id, _)| id).collect();
+                self.insert_vec(id, address(kAudioDevicePropertyDataSources), &ids);
+                self.insert(id, address(kAudioDevicePropertyDataSource), &selected);
+                self.set_settable(id, address(kAudioDevicePropertyDataSource), true);
+                self.state().insert(
+                    id,
+                    address(kAudioDevicePropertyDataSourceNameForIDCFString),
+                    Value::DataSourceNames(sources.to_vec()),
+                );
+            }
+        }
+        if let Some(connected) = device.jack_connected {
+            let address = property_address(
+                kAudioDevicePropertyJackIsConnected,
+                kAudioObjectPropertyScopeOutput,
+            );
+            self.insert(id, address, &(connected as u32));
+        }
+
+        for (input, channels) in &[
+            (true, &device.input_channels),
+            (false, &device.output_channels),
+        ] {
+            let mut starting_channel = 1;
+            for (index, &channels) in channels.iter().enumerate() {
+                self.add_stream(device, *input, index, starting_channel, channels);
+                starting_channel += channels;
+            }
+        }
+
+        let devices_address = any_scope(kAudioHardwarePropertyDevices);
+        let mut devices: Vec<AudioDeviceID> =
+            unsafe { super::backend::get_property_vec(self, SYSTEM_OBJECT, &devices_address) }
+                .unwrap_or_default();
+        devices.retain(|&d| d != id);
+        devices.push(id);
+        let mut state = self.state();
+        state.insert(
+            SYSTEM_OBJECT,
+            devices_address,
+            Value::Bytes(bytes_of(&devices)),
+        );
+        state.insert(
+            SYSTEM_OBJECT,
+            global_address(kAudioHardwarePropertyTranslateUIDToDevice),
+            Value::UidTranslation,
+        );
+    }
+
+    /// Add the volume, mute and decibel conversion properties of an element of a device.
+    fn add_volume_controls(
+        &self,
+        device: AudioDeviceID,
+        scope: AudioObjectPropertyScope,
+        element: u32,
+        volume: f32,
+    ) {
+        let address = |selector| AudioObjectPropertyAddress {
+            mSelector: selector,
+            mScope: scope,
+            mElement: element,
+        };
+        self.insert(device, address(kAudioDevicePropertyVolumeScalar), &volume);
+        self.insert(device, address(kAudioDevicePropertyMute), &0u32);
+        self.insert(
+            device,
+            address(kAudioDevicePropertyVolumeRangeDecibels),
+            &FAKE_VOLUME_DECIBELS,
+        );
+        {
+            let mut state = self.state();
+            let decibels = address(kAudioDevicePropertyVolumeDecibels);
+            state.insert(device, decibels, Value::Decibels);
+            let to_scalar = address(kAudioDevicePropertyVolumeDecibelsToScalar);
+            state.insert(device, to_scalar, Value::DecibelsToScalar);
+            let to_decibels = address(kAudioDevicePropertyVolumeScalarToDecibels);
+            state.insert(device, to_decibels, Value::ScalarToDecibels);
+        }
+        for &selector in &[
+            kAudioDevicePropertyVolumeScalar,
+            kAudioDevicePropertyVolumeDecibels,
+            kAudioDevicePropertyMute,
+        ] {
+            self.set_settable(device, address(selector), true);
+        }
+    }
+
+    /// Add the properties of a stream of the given device.
+    fn add_stream(
+        &self,
+        device: &FakeDevice,
+        input: bool,
+        index: usize,
+        starting_channel: u32,
+        channels: u32,
+    ) {
+        let id = device.stream_id(input, index);
+        let (direction, terminal_type) = if input {
+            (1u32, kAudioStreamTerminalTypeMicrophone)
+        } else {
+            (0u32, kAudioStreamTerminalTypeSpeaker)
+        };
+        self.insert(
+            id,
+            global_address(kAudioStreamPropertyDirection),
+            &direction,
+        );
+        self.insert(
+            id,
+            global_address(kAudioStreamPropertyStartingChannel),
+            &starting_channel,
+        );
+        self.insert(
+            id,
+            global_address(kAudioStreamPropertyTerminalType),
+            &terminal_type,
+        );
+        self.insert(id, global_address(kAudioStreamPropertyLatency), &0u32);
+
+        let formats = if device.physical_formats.is_empty() {
+            let format = StreamFormat {
+                sample_rate: device.nominal_sample_rate,
+                sample_format: SampleFormat::F32,
+                flags: LinearPcmFlags::IS_FLOAT | LinearPcmFlags::IS_PACKED,
+                channels,
+            };
+            vec![AudioStreamRangedDescription {
+                mFormat: format.to_asbd(),
+                mSampleRateRange: AudioValueRange {
+                    mMinimum: device.nominal_sample_rate,
+                    mMaximum: device.nominal_sample_rate,
+                },
+            }]
+        } else {
+            device.physical_formats.clone()
+        };
+        for &(current, available) in &[
+            (
+                kAudioStreamPropertyPhysicalFormat,
+                kAudioStreamPropertyAvailablePhysicalFormats,
+            ),
+            (
+                kAudioStreamPropertyVirtualFormat,
+                kAudioStreamPropertyAvailableVirtualFormats,
+            ),
+        ] {
+            self.insert_vec(id, global_address(available), &formats);
+            self.insert(id, global_address(current), &formats[0].mFormat);
+            self.set_settable(id, global_address(current), true);
+        }
+    }
+
+    /// Make the given device the default input or output device of the system object.
+    pub fn set_default_device(&self, input: bool, device: AudioDeviceID) {
+        let selector = if input {
+            kAudioHardwarePropertyDefaultInputDevice
+        } else {
+            kAudioHardwarePropertyDefaultOutputDevice
+        };
+        self.insert(SYSTEM_OBJECT, global_address(selector), &device);
+    }
+}
+
+impl AudioObjectBackend for FakeBackend {
+    fn has_property(&self, object: AudioObjectID, address: &AudioObjectPropertyAddress) -> bool {
+        self.state().find(object, address).is_ok()
+    }
+
+    fn is_property_settable(
+        &self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+    ) -> Result<bool, Error> {
+        Ok(self.state().find(object, address)?.settable)
+    }
+
+    fn property_data_size(
+        &self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        _qualifier: &[u8],
+    ) -> Result<u32, Error> {
+        let size = match self.state().find(object, address)?.value {
+            Value::Bytes(ref bytes) => bytes.len(),
+            Value::String(_) => mem::size_of::<*const CFString>(),
+            Value::UidTranslation => mem::size_of::<AudioObjectID>(),
+            Value::DataSourceNames(_) => mem::size_of::<AudioValueTranslation>(),
+            Value::Decibels | Value::DecibelsToScalar | Value::ScalarToDecibels => {
+                mem::size_of::<f32>()
+            }
+        };
+        Ok(size as u32)
+    }
+
+    fn get_property_data(
+        &self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        qualifier: &[u8],
+        data: &mut [u8],
+    ) -> Result<usize, Error> {
+        let mut state = self.state();
+        let property = state.find(object, address)?;
+        // The volume properties return a single `f32`, possibly converted from `data`.
+        let volume = match property.value {
+            Value::Bytes(ref bytes) => {
+                let len = bytes.len().min(data.len());
+                data[..len].copy_from_slice(&bytes[..len]);
+                return Ok(len);
+            }
+            Value::String(ref string) => {
+                let len = mem::size_of::<*const CFString>();
+                if data.len() < len {
+                    return Err(status_error(kAudioHardwareBadPropertySizeError));
+                }
+                // As with the HAL, the caller takes ownership of the returned string.
+                let string = CFRetained::into_raw(CFString::from_str(string));
+                data[..len].copy_from_slice(&(string.as_ptr() as usize).to_ne_bytes());
+                return Ok(len);
+            }
+            Value::Decibels => {
+                let address = property.address;
+                match state.volume_scalar(object, &address)?.value {
+                    Value::Bytes(ref bytes) => scalar_to_decibels(read_f32(bytes)?),
+                    _ => return Err(status_error(kAudioHardwareUnknownPropertyError)),
+                }
+            }
+            Value::DecibelsToScalar => decibels_to_scalar(read_f32(data)?),
+            Value::ScalarToDecibels => scalar_to_decibels(read_f32(data)?),
+            Value::DataSourceNames(ref sources) => {
+                if data.len() < mem::size_of::<AudioValueTranslation>() {
+                    return Err(status_error(kAudioHardwareBadPropertySizeError));
+                }
+                let translation: AudioValueTranslation =
+                    unsafe { ptr::read_unaligned(data.as_ptr() as *const AudioValueTranslation) };
+                if translation.mInputDataSize as usize != mem::size_of::<u32>()
+                    || translation.mOutputDataSize as usize != mem::size_of::<*const CFString>()
+                {
+                    return Err(status_error(kAudioHardwareBadPropertySizeError));
+                }
+                let source = unsafe { *(translation.mInputData.as_ptr() as *const u32) };
+                let (_, name) = sources
+                    .iter()
+                    .find(|&&(id, _)| id == source)
+                    .ok_or_else(|| status_error(kAudioHardwareIllegalOperationError))?;
+                // As with the HAL, the caller takes ownership of the returned string.
+                let name = CFRetained::into_raw(CFString::from_str(name));
+                unsafe {
+                    *(translation.mOutputData.as_ptr() as *mut *const CFString) = name.as_ptr();
+                }
+                return Ok(mem::size_of::<AudioValueTranslation>());
+            }
+            Value::UidTranslation => {
+                let device = state.translate_uid(qualifier)?.to_ne_bytes();
+                if data.len() < device.len() {
+                    return Err(status_error(kAudioHardwareBadPropertySizeError));
+                }
+                data[..device.len()].copy_from_slice(&device);
+                return Ok(device.len());
+            }
+        };
+        let volume = volume.to_ne_bytes();
+        if data.len() < volume.len() {
+            return Err(status_error(kAudioHardwareBadPropertySizeError));
+        }
+        data[..volume.len()].copy_from_slice(&volume);
+        Ok(volume.len())
+    }
+
+    fn set_property_data(
+        &self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        _qualifier: &[u8],
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let changed = {
+            let mut state = self.state();
+            let property = state.find(object, address)?;
+            if !property.settable {
+                return Err(status_error(kAudioHardwareIllegalOperationError));
+            }
+            if let Value::Decibels = property.value {
+                // Setting the volume in decibels changes the scalar it is derived from.
+                let decibels = property.address;
+                let scalar = decibels_to_scalar(read_f32(data)?);
+                let property = state.volume_scalar(object, &decibels)?;
+                property.value = Value::Bytes(scalar.to_ne_bytes().to_vec());
+                vec![property.address, decibels]
+            } else {
+                property.value = match property.value {
+                    Value::Bytes(_) => Value::Bytes(data.to_vec()),
+                    Value::String(_) => {
+                        let len = mem::size_of::<*const CFString>();
+                        if data.len() != len {
+                            return Err(status_error(kAudioHardwareBadPropertySizeError));
+                        }
+                        let string = unsafe { ptr::read_unaligned(data.as_ptr() as *const usize) };
+                        let string = NonNull::new(string as *mut CFString)
+                            .ok_or_else(|| status_error(kAudioHardwareIllegalOperationError))?;
+                        let string = unsafe { CFRetained::retain(string) };
+                        Value::String(string.to_string())
+                    }
+                    Value::UidTranslation
+                    | Value::DataSourceNames(_)
+                    | Value::Decibels
+                    | Value::DecibelsToScalar
+                    | Value::ScalarToDecibels => {
+                        return Err(status_error(kAudioHardwareIllegalOperationError));
+                    }
+                };
+                vec![property.address]
+            }
+        };
+        for address in changed {
+            self.notify(object, address);
+        }
+        Ok(())
+    }
+
+    fn add_property_listener(
+        &self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        listener: Box<ObjectPropertyListenerFn>,
+    ) -> Result<ObjectPropertyListener, Error> {
+        let id = {
+            let mut state = self.state();
+            let id = state.next_listener_id;
+            state.next_listener_id += 1;
+            state.listeners.push(Listener {
+                id,
+                object,
+                address: *address,
+                callback: Arc::new(Mutex::new(listener)),
+            });
+            id
+        };
+        let state: Weak<Mutex<State>> = Arc::downgrade(&self.state);
+        Ok(ObjectPropertyListener::new(object, *address, move || {
+            if let Some(state) = state.upgrade() {
+                let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                state.listeners.retain(|l| l.id != id);
+            }
+        }))
+    }
+}
+
+/// A system of fake devices shared by the tests of the device helpers.
+///
+/// - `10`, "Speakers", is the default output device. Its output data sources are the internal
+///   speakers, `'ispk'`, and headphones, `'hdpn'`, with nothing plugged into its jack.
+/// - `11`, "Interface", is a USB device by "Acme" with two stereo input streams and an 8 channel
+///   output stream, running with a latency of 64 frames. It offers 8 channel 16-bit integer and
+///   stereo 32-bit float physical formats at 48 kHz, output volume controls on its main element
+///   and channels `1` and `2`, an input volume control and a stereo pan.
+/// - `12`, also named "Interface", is a mono microphone and the default input device.
+#[cfg(test)]
+pub(super) fn fake_system() -> FakeBackend {
+    use crate::format::negotiation::ranged;
+
+    let backend = FakeBackend::new();
+    let mut speakers = FakeDevice::new(10, "Speakers");
+    speakers.output_data_sources = vec![
+        (
+            u32::from_be_bytes(*b"ispk"),
+            "Internal Speakers".to_string(),
+        ),
+        (u32::from_be_bytes(*b"hdpn"), "Headphones".to_string()),
+    ];
+    speakers.jack_connected = Some(false);
+    backend.add_device(&speakers);
+    let mut interface = FakeDevice::new(11, "Interface");
+    interface.manufacturer = "Acme".to_string();
+    interface.transport_type = TransportType::Usb;
+    interface.input_channels = vec![2, 2];
+    interface.output_channels = vec![8];
+    interface.latency = 64;
+    let int = LinearPcmFlags::IS_SIGNED_INTEGER | LinearPcmFlags::IS_PACKED;
+    let float = LinearPcmFlags::IS_FLOAT | LinearPcmFlags::IS_PACKED;
+    interface.physical_formats = vec![
+        ranged(SampleFormat::I16, int, 8, (48_000.0, 48_000.0)),
+        ranged(SampleFormat::F32, float, 2, (48_000.0, 48_000.0)),
+    ];
+    interface.output_volumes = vec![0.5, 1.0, 0.25];
+    interface.input_volumes = vec![0.75];
+    interface.stereo_pan = Some(0.5);
+    backend.add_device(&interface);
+    let mut microphone = FakeDevice::new(12, "Interface");
+    microphone.input_channels = vec![1];
+    microphone.output_channels = vec![];
+    backend.add_device(&microphone);
+    backend.set_default_device(false, 10);
+    backend.set_default_device(true, 12);
+    backend
+}
+
+#[test]
+fn test_fake_properties() {
+    use super::backend::{get_property, get_string_property, property_address, set_property};
+
+    let backend = FakeBackend::new();
+    backend.add_device(&FakeDevice::new(7, "Speakers"));
+    let name = property_address(
+        kAudioDevicePropertyDeviceNameCFString,
+        kAudioObjectPropertyScopeOutput,
+    );
+    let rate = global_address(kAudioDevicePropertyNominalSampleRate);
+    let transport = global_address(kAudioDevicePropertyTransportType);
+
+    assert_eq!(
+        unsafe { get_string_property(&backend, 7, &name) }.unwrap(),
+        "Speakers"
+    );
+    assert!(backend.has_property(7, &rate));
+    assert!(!backend.has_property(8, &rate));
+    assert!(backend.is_property_settable(7, &rate).unwrap());
+    assert!(!backend.is_property_settable(7, &transport).unwrap());
+    assert!(unsafe { get_property::<f64>(&backend, 8, &rate) }.is_err());
+    assert!(unsafe { set_property(&backend, 7, &transport, &0u32) }.is_err());
+    unsafe { set_property(&backend, 7, &rate, &48_000.0f64) }.unwrap();
+    assert_eq!(backend.get::<f64>(7, rate), Some(48_000.0));
+}
+
+#[test]
+fn test_fake_listeners() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let backend = FakeBackend::new();
+    backend.add_device(&FakeDevice::new(7, "Speakers"));
+    let alive = global_address(kAudioDevicePropertyDeviceIsAlive);
+    let count = Arc::new(AtomicUsize::new(0));
+    let listener = {
+        let count = count.clone();
+        backend
+            .add_property_listener(
+                7,
+                &alive,
+                Box::new(move |object, addresses| {
+                    assert_eq!(object, 7);
+                    assert_eq!(addresses[0].mSelector, kAudioDevicePropertyDeviceIsAlive);
+                    count.fetch_add(1, Ordering::SeqCst);
+                }),
+            )
+            .unwrap()
+    };
+    backend.insert(7, alive, &0u32);
+    backend.notify(7, alive);
+    backend.notify(7, global_address(kAudioDevicePropertyNominalSampleRate));
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+    drop(listener);
+    backend.notify(7, alive);
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+}
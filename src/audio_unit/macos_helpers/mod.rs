@@ -0,0 +1,988 @@
+#![allow(deprecated)]
+//! This is a collection of helper functions for performing common tasks on macOS.
+//! These functions are only implemented for macOS, not iOS.
+//!
+//! Device properties are read and written through an
+//! [**AudioObjectBackend**](./backend/trait.AudioObjectBackend). The public functions use the
+//! [**CoreAudioBackend**](./backend/struct.CoreAudioBackend), while tests may substitute a
+//! [**FakeBackend**](./fake/struct.FakeBackend) describing scripted devices.
+//!
+//! The [**Device**](./device/struct.Device) type wraps an `AudioDeviceID` together with its
+//! backend, and is the preferred way of querying devices. The free functions taking a bare
+//! `AudioDeviceID` remain for compatibility.
+use crate::error::{DeviceError, Error};
+use std::collections::VecDeque;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{mem, slice};
+
+use libc::pid_t;
+use objc2_audio_toolbox::{
+    kAudioOutputUnitProperty_CurrentDevice, kAudioOutputUnitProperty_EnableIO,
+};
+use objc2_core_audio::{
+    kAudioDevicePropertyAvailableNominalSampleRates, kAudioDevicePropertyDeviceIsAlive,
+    kAudioDevicePropertyDeviceNameCFString, kAudioDevicePropertyHogMode,
+    kAudioDevicePropertyNominalSampleRate, kAudioDevicePropertyScopeOutput,
+    kAudioDevicePropertyStreamConfiguration, kAudioDevicePropertyTransportType,
+    kAudioHardwarePropertyDefaultInputDevice, kAudioHardwarePropertyDefaultOutputDevice,
+    kAudioHardwarePropertyDevices, kAudioObjectPropertyElementWildcard,
+    kAudioObjectPropertyScopeGlobal, kAudioObjectPropertyScopeInput,
+    kAudioObjectPropertyScopeOutput, kAudioStreamPropertyAvailablePhysicalFormats,
+    kAudioStreamPropertyPhysicalFormat, AudioDeviceID, AudioObjectPropertyAddress,
+    AudioObjectPropertyScope, AudioStreamRangedDescription,
+};
+use objc2_core_audio_types::{
+    AudioBuffer, AudioBufferList, AudioStreamBasicDescription, AudioValueRange,
+};
+
+use self::backend::{
+    get_property, get_property_vec, get_string_property, global_address, property_address,
+    set_property, SYSTEM_OBJECT,
+};
+use crate::audio_unit::stream_format::StreamFormat;
+use crate::audio_unit::{AudioUnit, Element, IOType, Scope};
+use crate::format::FormatRequest;
+
+pub use self::backend::{AudioObjectBackend, CoreAudioBackend, ObjectPropertyListener};
+pub use self::data_source::DataSource;
+pub use self::device::Device;
+pub use self::fake::{FakeBackend, FakeDevice};
+pub use self::stream::Stream;
+pub use self::transport_type::{TransportType, UnknownTransportType};
+pub use self::volume::VolumeControl;
+
+#[cfg(test)]
+use self::fake::fake_system;
+
+pub mod backend;
+pub mod data_source;
+pub mod device;
+pub mod fake;
+mod object;
+pub mod stream;
+pub mod transport_type;
+pub mod volume;
+
+/// The HAL property scope corresponding to the given **Scope**.
+fn object_scope(scope: Scope) -> AudioObjectPropertyScope {
+    match scope {
+        Scope::Input => kAudioObjectPropertyScopeInput,
+        Scope::Output => kAudioObjectPropertyScopeOutput,
+        _ => kAudioObjectPropertyScopeGlobal,
+    }
+}
+
+/// Helper function to get the device id of the default input or output device.
+pub fn get_default_device_id(input: bool) -> Option<AudioDeviceID> {
+    default_device_id(&CoreAudioBackend, input)
+}
+
+fn default_device_id(backend: &dyn AudioObjectBackend, input: bool) -> Option<AudioDeviceID> {
+    let selector = if input {
+        kAudioHardwarePropertyDefaultInputDevice
+    } else {
+        kAudioHardwarePropertyDefaultOutputDevice
+    };
+    let property_address = global_address(selector);
+    unsafe { get_property(backend, SYSTEM_OBJECT, &property_address) }.ok()
+}
+
+/// Find the device id for a device name.
+/// Set `input` to `true` to find a playback device, or `false` for a capture device.
+///
+/// Device names are localized and need not be unique. Prefer storing the UID of a device, see
+/// [**get_device_uid**](./fn.get_device_uid) and
+/// [**get_device_id_from_uid**](./fn.get_device_id_from_uid).
+pub fn get_device_id_from_name(name: &str, input: bool) -> Option<AudioDeviceID> {
+    device_id_from_name(&CoreAudioBackend, name, input)
+}
+
+/// Get the persistent unique identifier of a device.
+///
+/// The UID is the same across reboots and reconnections, unlike the device id.
+pub fn get_device_uid(device_id: AudioDeviceID) -> Result<String, Error> {
+    Device::new(device_id).uid()
+}
+
+/// Find the device id for a device UID, if the device is present.
+pub fn get_device_id_from_uid(uid: &str) -> Option<AudioDeviceID> {
+    Device::from_uid(uid).ok().map(|device| device.id())
+}
+
+fn device_id_from_name(
+    backend: &dyn AudioObjectBackend,
+    name: &str,
+    input: bool,
+) -> Option<AudioDeviceID> {
+    let scope = match input {
+        false => Scope::Output,
+        true => Scope::Input,
+    };
+    if let Ok(all_ids) = audio_device_ids(backend, Scope::Global) {
+        return all_ids
+            .iter()
+            .find(|id| {
+                device_name(backend, **id).unwrap_or_default() == name
+                    && device_supports_scope(backend, **id, scope).unwrap_or_default()
+            })
+            .copied();
+    }
+    None
+}
+
+/// Create an AudioUnit instance from a device id.
+/// Set `input` to `true` to create a capture device, or `false` for a playback device.
+pub fn audio_unit_from_device_id(
+    device_id: AudioDeviceID,
+    input: bool,
+) -> Result<AudioUnit, Error> {
+    let mut audio_unit = audio_unit_from_device_id_uninitialized(device_id, input)?;
+    audio_unit.initialize()?;
+    Ok(audio_unit)
+}
+
+/// Create an AudioUnit instance from a device id without initializing it.
+/// Set `input` to `true` to create a capture device, or `false` for a playback device.
+pub fn audio_unit_from_device_id_uninitialized(
+    device_id: AudioDeviceID,
+    input: bool,
+) -> Result<AudioUnit, Error> {
+    let output_type = if !input && get_default_device_id(false).is_some_and(|d| d == device_id) {
+        IOType::DefaultOutput
+    } else {
+        IOType::HalOutput
+    };
+    let mut audio_unit = AudioUnit::new_uninitialized(output_type)?;
+
+    if input {
+        // Enable input processing.
+        let enable_input = 1u32;
+        audio_unit.set_property(
+            kAudioOutputUnitProperty_EnableIO,
+            Scope::Input,
+            Element::Input,
+            Some(&enable_input),
+        )?;
+
+        // Disable output processing.
+        let disable_output = 0u32;
+        audio_unit.set_property(
+            kAudioOutputUnitProperty_EnableIO,
+            Scope::Output,
+            Element::Output,
+            Some(&disable_output),
+        )?;
+    }
+
+    audio_unit.set_property(
+        kAudioOutputUnitProperty_CurrentDevice,
+        Scope::Global,
+        Element::Output,
+        Some(&device_id),
+    )?;
+
+    Ok(audio_unit)
+}
+
+/// List all audio device ids on the system.
+pub fn get_audio_device_ids_for_scope(scope: Scope) -> Result<Vec<AudioDeviceID>, Error> {
+    audio_device_ids(&CoreAudioBackend, scope)
+}
+
+fn audio_device_ids(
+    backend: &dyn AudioObjectBackend,
+    scope: Scope,
+) -> Result<Vec<AudioDeviceID>, Error> {
+    let property_address = property_address(kAudioHardwarePropertyDevices, object_scope(scope));
+    unsafe { get_property_vec(backend, SYSTEM_OBJECT, &property_address) }
+}
+
+pub fn get_audio_device_ids() -> Result<Vec<AudioDeviceID>, Error> {
+    get_audio_device_ids_for_scope(Scope::Global)
+}
+
+#[test]
+fn test_get_audio_device_ids() {
+    let _ = get_audio_device_ids().expect("Failed to get audio device ids");
+}
+
+#[test]
+fn test_get_audio_device_ids_for_scope() {
+    for scope in &[
+        Scope::Global,
+        Scope::Input,
+        Scope::Output,
+        Scope::Group,
+        Scope::Part,
+        Scope::Note,
+        Scope::Layer,
+        Scope::LayerItem,
+    ] {
+        let _ = get_audio_device_ids_for_scope(*scope).expect("Failed to get audio device ids");
+    }
+}
+
+/// does this device support input / ouptut?
+pub fn get_audio_device_supports_scope(devid: AudioDeviceID, scope: Scope) -> Result<bool, Error> {
+    device_supports_scope(&CoreAudioBackend, devid, scope)
+}
+
+fn device_supports_scope(
+    backend: &dyn AudioObjectBackend,
+    devid: AudioDeviceID,
+    scope: Scope,
+) -> Result<bool, Error> {
+    let channels = stream_configuration(backend, devid, object_scope(scope))?;
+    Ok(channels.iter().any(|&channels| channels > 0))
+}
+
+/// The number of channels of each of the device's streams within the given scope.
+fn stream_configuration(
+    backend: &dyn AudioObjectBackend,
+    devid: AudioDeviceID,
+    scope: AudioObjectPropertyScope,
+) -> Result<Vec<u32>, Error> {
+    let property_address = AudioObjectPropertyAddress {
+        mSelector: kAudioDevicePropertyStreamConfiguration,
+        mScope: scope,
+        mElement: kAudioObjectPropertyElementWildcard,
+    };
+    let data_size = backend.property_data_size(devid, &property_address, &[])? as usize;
+
+    // Back the variable length list with `AudioBuffer`s so that it is suitably aligned.
+    let empty = AudioBuffer {
+        mNumberChannels: 0,
+        mDataByteSize: 0,
+        mData: ptr::null_mut(),
+    };
+    let header = mem::size_of::<AudioBufferList>() - mem::size_of::<AudioBuffer>();
+    let mut storage = vec![empty; data_size / mem::size_of::<AudioBuffer>() + 1];
+    let written = unsafe {
+        let bytes = slice::from_raw_parts_mut(storage.as_mut_ptr() as *mut u8, data_size);
+        backend.get_property_data(devid, &property_address, &[], bytes)?
+    };
+    if written < header {
+        return Ok(vec![]);
+    }
+
+    unsafe {
+        let list = storage.as_ptr() as *const AudioBufferList;
+        let buffers = ptr::addr_of!((*list).mBuffers) as *const AudioBuffer;
+        let available = (written - header) / mem::size_of::<AudioBuffer>();
+        let count = ((*list).mNumberBuffers as usize).min(available);
+        Ok((0..count)
+            .map(|i| (*buffers.add(i)).mNumberChannels)
+            .collect())
+    }
+}
+
+/// Get the device name for a device id.
+pub fn get_device_name(device_id: AudioDeviceID) -> Result<String, Error> {
+    device_name(&CoreAudioBackend, device_id)
+}
+
+fn device_name(
+    backend: &dyn AudioObjectBackend,
+    device_id: AudioDeviceID,
+) -> Result<String, Error> {
+    let property_address = property_address(
+        kAudioDevicePropertyDeviceNameCFString,
+        kAudioDevicePropertyScopeOutput,
+    );
+    unsafe { get_string_property(backend, device_id, &property_address) }
+}
+
+/// The largest difference between two sample rates that are considered equal, absorbing the
+/// rounding of rates such as 44100 reported by devices.
+const SAMPLE_RATE_TOLERANCE: f64 = 0.01;
+
+/// How long to wait by default for a device to report a changed sample rate or format.
+const DEFAULT_CHANGE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Options controlling how [**set_device_sample_rate_with_options**] changes the nominal sample
+/// rate of a device.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SampleRateOptions {
+    /// Whether or not to fall back to the supported rate nearest to the requested one, rather
+    /// than failing with **Error::UnsupportedSampleRate**.
+    pub nearest: bool,
+    /// How long to wait for the device to report the new rate.
+    pub timeout: Duration,
+}
+
+impl Default for SampleRateOptions {
+    fn default() -> Self {
+        SampleRateOptions {
+            nearest: false,
+            timeout: DEFAULT_CHANGE_TIMEOUT,
+        }
+    }
+}
+
+/// Change the sample rate of a device.
+/// Adapted from CPAL.
+///
+/// This is [**set_device_sample_rate_with_options**] with the default **SampleRateOptions**.
+pub fn set_device_sample_rate(device_id: AudioDeviceID, new_rate: f64) -> Result<(), Error> {
+    let options = SampleRateOptions::default();
+    set_sample_rate(&CoreAudioBackend, device_id, new_rate, options).map(|_| ())
+}
+
+/// Change the sample rate of a device, returning the rate that the device runs at.
+///
+/// The rate is supported if it lies within one of the discrete rates or continuous ranges
+/// reported by [**get_available_sample_rates**]. An unsupported rate fails with
+/// **Error::UnsupportedSampleRate**, unless `options.nearest` is set, in which case the nearest
+/// supported rate is used instead. The higher rate is chosen when two are equally near.
+///
+/// If the device does not report the new rate within `options.timeout`, a
+/// **DeviceError::ChangeTimedOut** is returned.
+pub fn set_device_sample_rate_with_options(
+    device_id: AudioDeviceID,
+    new_rate: f64,
+    options: SampleRateOptions,
+) -> Result<f64, Error> {
+    set_sample_rate(&CoreAudioBackend, device_id, new_rate, options)
+}
+
+/// The rate to request from a device supporting the given ranges in order to run at `rate`.
+fn supported_sample_rate(ranges: &[AudioValueRange], rate: f64, nearest: bool) -> Option<f64> {
+    let distance = |supported: f64| (supported - rate).abs();
+    let closest = ranges
+        .iter()
+        .map(|range| rate.max(range.mMinimum).min(range.mMaximum))
+        .min_by(|a, b| distance(*a).total_cmp(&distance(*b)).then(b.total_cmp(a)))?;
+    if nearest || distance(closest) <= SAMPLE_RATE_TOLERANCE {
+        Some(closest)
+    } else {
+        None
+    }
+}
+
+fn set_sample_rate(
+    backend: &dyn AudioObjectBackend,
+    device_id: AudioDeviceID,
+    new_rate: f64,
+    options: SampleRateOptions,
+) -> Result<f64, Error> {
+    // Check whether or not we need to change the device sample rate to suit the one specified for the stream.
+    let property_address = global_address(kAudioDevicePropertyNominalSampleRate);
+    let sample_rate: f64 = unsafe { get_property(backend, device_id, &property_address)? };
+    if (sample_rate - new_rate).abs() <= SAMPLE_RATE_TOLERANCE {
+        return Ok(sample_rate);
+    }
+
+    // Pick the rate within the available ranges matching the desired rate.
+    let ranges = available_sample_rates(backend, device_id)?;
+    let rate = supported_sample_rate(&ranges, new_rate, options.nearest)
+        .ok_or(Error::UnsupportedSampleRate)?;
+    if (sample_rate - rate).abs() <= SAMPLE_RATE_TOLERANCE {
+        return Ok(sample_rate);
+    }
+
+    // Add a listener to know when the sample rate changes.
+    // Since the listener implements Drop, we don't need to manually unregister this later.
+    let (sender, receiver) = channel();
+    let _listener = backend.add_property_listener(
+        device_id,
+        &property_address,
+        Box::new(move |_, _| {
+            let _ = sender.send(());
+        }),
+    )?;
+
+    // Finally, set the sample rate.
+    unsafe { set_property(backend, device_id, &property_address, &rate)? };
+
+    // Wait for the reported rate to change. This sometimes takes up to half a second.
+    let timer = ::std::time::Instant::now();
+    loop {
+        let remaining = options.timeout.saturating_sub(timer.elapsed());
+        let poll = remaining.min(Duration::from_millis(100));
+        if receiver.recv_timeout(poll).is_ok() {
+            let reported_rate: f64 =
+                unsafe { get_property(backend, device_id, &property_address)? };
+            if (reported_rate - rate).abs() <= SAMPLE_RATE_TOLERANCE {
+                return Ok(reported_rate);
+            }
+        }
+        if timer.elapsed() >= options.timeout {
+            let err = DeviceError::ChangeTimedOut(kAudioDevicePropertyNominalSampleRate);
+            return Err(Error::Device(err));
+        }
+    }
+}
+
+/// Find the closest match of the physical formats to the provided `StreamFormat`.
+///
+/// The formats supporting the sample rate, the sample format and at least the number of channels
+/// of the `StreamFormat` are ranked as by
+/// [**FormatRequest::from_stream_format**](../../format/negotiation/struct.FormatRequest#method.from_stream_format),
+/// preferring the exact number of channels, the interleaving given by the flags and mixable
+/// formats. Use [**FormatRequest::negotiate**](../../format/negotiation/struct.FormatRequest#method.negotiate)
+/// directly to choose between the candidates or to find out why formats were rejected.
+pub fn find_matching_physical_format(
+    device_id: AudioDeviceID,
+    stream_format: StreamFormat,
+) -> Option<AudioStreamBasicDescription> {
+    matching_physical_format(&CoreAudioBackend, device_id, stream_format)
+}
+
+fn matching_physical_format(
+    backend: &dyn AudioObjectBackend,
+    device_id: AudioDeviceID,
+    stream_format: StreamFormat,
+) -> Option<AudioStreamBasicDescription> {
+    let all_formats = supported_physical_stream_formats(backend, device_id).ok()?;
+    let negotiation = FormatRequest::from_stream_format(stream_format).negotiate(&all_formats);
+    negotiation.best().map(|candidate| candidate.format)
+}
+
+/// Change the physical stream format (sample rate and format) of a device.
+///
+/// This is [**set_device_physical_stream_format_with_timeout**] waiting for up to two seconds.
+pub fn set_device_physical_stream_format(
+    device_id: AudioDeviceID,
+    new_asbd: AudioStreamBasicDescription,
+) -> Result<(), Error> {
+    set_physical_stream_format(
+        &CoreAudioBackend,
+        device_id,
+        new_asbd,
+        DEFAULT_CHANGE_TIMEOUT,
+    )
+}
+
+/// Change the physical stream format (sample rate and format) of a device.
+///
+/// If the device does not report the new format within `timeout`, a
+/// **DeviceError::ChangeTimedOut** is returned.
+pub fn set_device_physical_stream_format_with_timeout(
+    device_id: AudioDeviceID,
+    new_asbd: AudioStreamBasicDescription,
+    timeout: Duration,
+) -> Result<(), Error> {
+    set_physical_stream_format(&CoreAudioBackend, device_id, new_asbd, timeout)
+}
+
+fn set_physical_stream_format(
+    backend: &dyn AudioObjectBackend,
+    device_id: AudioDeviceID,
+    new_asbd: AudioStreamBasicDescription,
+    timeout: Duration,
+) -> Result<(), Error> {
+    // Get the current format.
+    let property_address = global_address(kAudioStreamPropertyPhysicalFormat);
+    let asbd: AudioStreamBasicDescription =
+        unsafe { get_property(backend, device_id, &property_address)? };
+    if asbds_are_equal(&asbd, &new_asbd) {
+        return Ok(());
+    }
+
+    let (sender, receiver) = channel();
+    let _listener = backend.add_property_listener(
+        device_id,
+        &property_address,
+        Box::new(move |_, _| {
+            let _ = sender.send(());
+        }),
+    )?;
+    unsafe { set_property(backend, device_id, &property_address, &new_asbd)? };
+
+    // Wait for the reported format to change. This can take up to half a second. The format is
+    // also read between notifications, in case the device does not send one.
+    let timer = ::std::time::Instant::now();
+    loop {
+        let reported_asbd: AudioStreamBasicDescription =
+            unsafe { get_property(backend, device_id, &property_address)? };
+        if asbds_are_equal(&reported_asbd, &new_asbd) {
+            return Ok(());
+        }
+        if timer.elapsed() >= timeout {
+            let err = DeviceError::ChangeTimedOut(kAudioStreamPropertyPhysicalFormat);
+            return Err(Error::Device(err));
+        }
+        let remaining = timeout.saturating_sub(timer.elapsed());
+        let _ = receiver.recv_timeout(remaining.min(Duration::from_millis(100)));
+    }
+}
+
+/// Helper to check if two ASBDs are equal, within the tolerance of their sample rates.
+fn asbds_are_equal(
+    left: &AudioStreamBasicDescription,
+    right: &AudioStreamBasicDescription,
+) -> bool {
+    (left.mSampleRate - right.mSampleRate).abs() <= SAMPLE_RATE_TOLERANCE
+        && left.mFormatID == right.mFormatID
+        && left.mFormatFlags == right.mFormatFlags
+        && left.mBytesPerPacket == right.mBytesPerPacket
+        && left.mFramesPerPacket == right.mFramesPerPacket
+        && left.mBytesPerFrame == right.mBytesPerFrame
+        && left.mChannelsPerFrame == right.mChannelsPerFrame
+        && left.mBitsPerChannel == right.mBitsPerChannel
+}
+
+/// Get a vector with all supported physical formats as AudioBasicRangedDescriptions.
+pub fn get_supported_physical_stream_formats(
+    device_id: AudioDeviceID,
+) -> Result<Vec<AudioStreamRangedDescription>, Error> {
+    supported_physical_stream_formats(&CoreAudioBackend, device_id)
+}
+
+fn supported_physical_stream_formats(
+    backend: &dyn AudioObjectBackend,
+    device_id: AudioDeviceID,
+) -> Result<Vec<AudioStreamRangedDescription>, Error> {
+    let property_address = global_address(kAudioStreamPropertyAvailablePhysicalFormats);
+    unsafe { get_property_vec(backend, device_id, &property_address) }
+}
+
+/// Get the available nominal sample rate ranges for a device.
+///
+/// Returns a vector of [`AudioValueRange`] where each entry describes a supported range.
+/// For devices that support discrete rates, `mMinimum` and `mMaximum` will be equal.
+/// For devices that support continuous ranges, they will differ.
+pub fn get_available_sample_rates(device_id: AudioDeviceID) -> Result<Vec<AudioValueRange>, Error> {
+    available_sample_rates(&CoreAudioBackend, device_id)
+}
+
+fn available_sample_rates(
+    backend: &dyn AudioObjectBackend,
+    device_id: AudioDeviceID,
+) -> Result<Vec<AudioValueRange>, Error> {
+    let property_address = global_address(kAudioDevicePropertyAvailableNominalSampleRates);
+    // SAFETY: The property holds an array of `AudioValueRange`, a plain C struct with no drop
+    // semantics.
+    unsafe { get_property_vec(backend, device_id, &property_address) }
+}
+
+/// Get the transport type of a device, such as USB or Bluetooth.
+///
+/// The raw `kAudioDeviceTransportType*` code is available via
+/// [**TransportType::as_u32**](./transport_type/enum.TransportType#method.as_u32).
+pub fn get_device_transport_type(device_id: AudioDeviceID) -> Result<TransportType, Error> {
+    device_transport_type(&CoreAudioBackend, device_id)
+}
+
+fn device_transport_type(
+    backend: &dyn AudioObjectBackend,
+    device_id: AudioDeviceID,
+) -> Result<TransportType, Error> {
+    let property_address = global_address(kAudioDevicePropertyTransportType);
+    // SAFETY: The transport type is a `u32` FourCC.
+    let code: u32 = unsafe { get_property(backend, device_id, &property_address)? };
+    Ok(TransportType::from_u32(code))
+}
+
+/// Changing the sample rate is an asynchronous process.
+/// A RateListener can be used to get notified when the rate is changed.
+pub struct RateListener {
+    pub queue: Arc<Mutex<VecDeque<f64>>>,
+    sync_channel: Option<Sender<f64>>,
+    device_id: AudioDeviceID,
+    backend: Arc<dyn AudioObjectBackend>,
+    listener: Option<ObjectPropertyListener>,
+}
+
+impl RateListener {
+    /// Create a new RateListener for the given AudioDeviceID.
+    /// If an `std::sync::mpsc::Sender` is provided, then events will be pushed to that channel.
+    /// If not, they will instead be stored in an internal queue that will need to be polled.
+    /// The listener must be registered by calling `register()` in order to start receiving notifications.
+    pub fn new(device_id: AudioDeviceID, sync_channel: Option<Sender<f64>>) -> RateListener {
+        Self::with_backend(device_id, sync_channel, Arc::new(CoreAudioBackend))
+    }
+
+    /// Create a new RateListener for the given AudioDeviceID, registering through the given
+    /// backend.
+    pub fn with_backend(
+        device_id: AudioDeviceID,
+        sync_channel: Option<Sender<f64>>,
+        backend: Arc<dyn AudioObjectBackend>,
+    ) -> RateListener {
+        RateListener {
+            queue: Arc::new(Mutex::new(VecDeque::new())),
+            sync_channel,
+            device_id,
+            backend,
+            listener: None,
+        }
+    }
+
+    /// Register this listener to receive notifications.
+    pub fn register(&mut self) -> Result<(), Error> {
+        if self.listener.is_some() {
+            return Ok(());
+        }
+        let property_address = global_address(kAudioDevicePropertyNominalSampleRate);
+        let backend = self.backend.clone();
+        let sync_channel = self.sync_channel.clone();
+        let queue = self.queue.clone();
+        let listener = self.backend.add_property_listener(
+            self.device_id,
+            &property_address,
+            Box::new(move |device_id, _| {
+                // SAFETY: The nominal sample rate is an `f64`.
+                let rate: f64 =
+                    match unsafe { get_property(&*backend, device_id, &property_address) } {
+                        Ok(rate) => rate,
+                        Err(_) => return,
+                    };
+                if let Some(sender) = &sync_channel {
+                    let _ = sender.send(rate);
+                } else {
+                    queue.lock().unwrap().push_back(rate);
+                }
+            }),
+        )?;
+        self.listener = Some(listener);
+        Ok(())
+    }
+
+    /// Unregister this listener to stop receiving notifications.
+    pub fn unregister(&mut self) -> Result<(), Error> {
+        self.listener = None;
+        Ok(())
+    }
+
+    /// Get the number of sample rate values received (equals the number of change events).
+    /// Not used if the RateListener was created with a `std::sync::mpsc::Sender`.
+    pub fn get_nbr_values(&self) -> usize {
+        self.queue.lock().unwrap().len()
+    }
+
+    /// Copy all received values to a Vec. The latest value is the last element.
+    /// The internal buffer is preserved.
+    /// Not used if the RateListener was created with a `std::sync::mpsc::Sender`.
+    pub fn copy_values(&self) -> Vec<f64> {
+        self.queue
+            .lock()
+            .unwrap()
+            .iter()
+            .copied()
+            .collect::<Vec<f64>>()
+    }
+
+    /// Get all received values as a Vec. The latest value is the last element.
+    /// This clears the internal buffer.
+    /// Not used if the RateListener was created with a `std::sync::mpsc::Sender`.
+    pub fn drain_values(&mut self) -> Vec<f64> {
+        self.queue.lock().unwrap().drain(..).collect::<Vec<f64>>()
+    }
+}
+
+/// An AliveListener is used to get notified when a device is disconnected.
+pub struct AliveListener {
+    alive: Arc<AtomicBool>,
+    device_id: AudioDeviceID,
+    backend: Arc<dyn AudioObjectBackend>,
+    listener: Option<ObjectPropertyListener>,
+}
+
+impl AliveListener {
+    /// Create a new AliveListener for the given AudioDeviceID.
+    /// The listener must be registered by calling `register()` in order to start receiving notifications.
+    pub fn new(device_id: AudioDeviceID) -> AliveListener {
+        Self::with_backend(device_id, Arc::new(CoreAudioBackend))
+    }
+
+    /// Create a new AliveListener for the given AudioDeviceID, registering through the given
+    /// backend.
+    pub fn with_backend(
+        device_id: AudioDeviceID,
+        backend: Arc<dyn AudioObjectBackend>,
+    ) -> AliveListener {
+        AliveListener {
+            alive: Arc::new(AtomicBool::new(true)),
+            device_id,
+            backend,
+            listener: None,
+        }
+    }
+
+    /// Register this listener to receive notifications.
+    pub fn register(&mut self) -> Result<(), Error> {
+        if self.listener.is_some() {
+            return Ok(());
+        }
+        let property_address = global_address(kAudioDevicePropertyDeviceIsAlive);
+        let backend = self.backend.clone();
+        let alive = self.alive.clone();
+        let listener = self.backend.add_property_listener(
+            self.device_id,
+            &property_address,
+            Box::new(move |device_id, _| {
+                // SAFETY: Whether the device is alive is reported as a `u32`.
+                let is_alive: u32 =
+                    match unsafe { get_property(&*backend, device_id, &property_address) } {
+                        Ok(is_alive) => is_alive,
+                        Err(_) => return,
+                    };
+                alive.store(is_alive > 0, Ordering::SeqCst);
+            }),
+        )?;
+        self.listener = Some(listener);
+        Ok(())
+    }
+
+    /// Unregister this listener to stop receiving notifications
+    pub fn unregister(&mut self) -> Result<(), Error> {
+        self.listener = None;
+        Ok(())
+    }
+
+    /// Check if the device is still alive.
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::SeqCst)
+    }
+}
+
+/// Helper for hog mode (exclusive access).
+/// Get the pid of the process that currently owns exclusive access to a device.
+/// A pid value of -1 means no process owns exclusive access.
+pub fn get_hogging_pid(device_id: AudioDeviceID) -> Result<pid_t, Error> {
+    hogging_pid(&CoreAudioBackend, device_id)
+}
+
+fn hogging_pid(backend: &dyn AudioObjectBackend, device_id: AudioDeviceID) -> Result<pid_t, Error> {
+    let property_address = global_address(kAudioDevicePropertyHogMode);
+    unsafe { get_property(backend, device_id, &property_address) }
+}
+
+/// Helper for hog mode (exclusive access).
+/// Toggle hog mode for a device.
+/// If no process owns exclusive access, then the calling process takes ownership.
+/// If the calling process already has ownership, this is released.
+/// If another process owns access, then nothing will happen.
+/// Returns the pid of the new owning process.
+/// A pid value of -1 means no process owns exclusive access.
+pub fn toggle_hog_mode(device_id: AudioDeviceID) -> Result<pid_t, Error> {
+    toggle_hog(&CoreAudioBackend, device_id)
+}
+
+fn toggle_hog(backend: &dyn AudioObjectBackend, device_id: AudioDeviceID) -> Result<pid_t, Error> {
+    let property_address = global_address(kAudioDevicePropertyHogMode);
+    unsafe {
+        set_property(backend, device_id, &property_address, &(-1 as pid_t))?;
+        get_property(backend, device_id, &property_address)
+    }
+}
+
+#[test]
+fn test_find_devices() {
+    let backend = fake_system();
+    assert_eq!(default_device_id(&backend, false), Some(10));
+    assert_eq!(default_device_id(&backend, true), Some(12));
+    assert_eq!(
+        audio_device_ids(&backend, Scope::Global).unwrap(),
+        [10, 11, 12]
+    );
+    assert_eq!(device_name(&backend, 11).unwrap(), "Interface");
+    assert!(device_supports_scope(&backend, 11, Scope::Input).unwrap());
+    assert!(!device_supports_scope(&backend, 12, Scope::Output).unwrap());
+    assert_eq!(
+        stream_configuration(&backend, 11, kAudioObjectPropertyScopeInput).unwrap(),
+        [2, 2]
+    );
+
+    // Devices sharing a name are told apart by the requested scope.
+    assert_eq!(device_id_from_name(&backend, "Interface", false), Some(11));
+    assert_eq!(device_id_from_name(&backend, "Interface", true), Some(11));
+    assert_eq!(device_id_from_name(&backend, "Speakers", true), None);
+    assert_eq!(device_id_from_name(&backend, "Missing", false), None);
+}
+
+#[test]
+fn test_get_available_sample_rates() {
+    let backend = fake_system();
+    let rates = available_sample_rates(&backend, 10).expect("Failed to get sample rates");
+    assert_eq!(rates.len(), 2);
+    assert_eq!(rates[1].mMinimum, 48_000.0);
+    assert!(available_sample_rates(&backend, 99).is_err());
+}
+
+#[test]
+fn test_get_device_transport_type() {
+    let backend = fake_system();
+    assert_eq!(
+        device_transport_type(&backend, 10).unwrap(),
+        TransportType::BuiltIn
+    );
+    assert_eq!(
+        device_transport_type(&backend, 11).unwrap(),
+        TransportType::Usb
+    );
+}
+
+#[test]
+fn test_set_sample_rate() {
+    let backend = fake_system();
+    let address = global_address(kAudioDevicePropertyNominalSampleRate);
+    let options = SampleRateOptions::default();
+    assert_eq!(
+        set_sample_rate(&backend, 10, 48_000.0, options).unwrap(),
+        48_000.0
+    );
+    assert_eq!(backend.get::<f64>(10, address), Some(48_000.0));
+    assert!(matches!(
+        set_sample_rate(&backend, 10, 96_000.0, options),
+        Err(Error::UnsupportedSampleRate)
+    ));
+
+    let nearest = SampleRateOptions {
+        nearest: true,
+        ..options
+    };
+    assert_eq!(
+        set_sample_rate(&backend, 10, 96_000.0, nearest).unwrap(),
+        48_000.0
+    );
+    assert_eq!(
+        set_sample_rate(&backend, 10, 44_100.004, nearest).unwrap(),
+        44_100.0
+    );
+    assert_eq!(backend.get::<f64>(10, address), Some(44_100.0));
+
+    // Rates within a continuous range are supported as they are.
+    backend.insert_vec(
+        10,
+        global_address(kAudioDevicePropertyAvailableNominalSampleRates),
+        &[AudioValueRange {
+            mMinimum: 8_000.0,
+            mMaximum: 192_000.0,
+        }],
+    );
+    assert_eq!(
+        set_sample_rate(&backend, 10, 88_200.0, options).unwrap(),
+        88_200.0
+    );
+    assert_eq!(backend.get::<f64>(10, address), Some(88_200.0));
+}
+
+#[test]
+fn test_supported_sample_rate() {
+    let range = |minimum, maximum| AudioValueRange {
+        mMinimum: minimum,
+        mMaximum: maximum,
+    };
+    let discrete = [range(44_100.0, 44_100.0), range(48_000.0, 48_000.0)];
+    assert_eq!(
+        supported_sample_rate(&discrete, 48_000.0, false),
+        Some(48_000.0)
+    );
+    assert_eq!(supported_sample_rate(&discrete, 44_100.5, false), None);
+    assert_eq!(
+        supported_sample_rate(&discrete, 44_100.5, true),
+        Some(44_100.0)
+    );
+    assert_eq!(
+        supported_sample_rate(&discrete, 46_050.0, true),
+        Some(48_000.0)
+    );
+    assert_eq!(supported_sample_rate(&[], 48_000.0, true), None);
+
+    let continuous = [range(8_000.0, 96_000.0), range(192_000.0, 192_000.0)];
+    assert_eq!(
+        supported_sample_rate(&continuous, 22_050.0, false),
+        Some(22_050.0)
+    );
+    assert_eq!(supported_sample_rate(&continuous, 4_000.0, false), None);
+    assert_eq!(
+        supported_sample_rate(&continuous, 4_000.0, true),
+        Some(8_000.0)
+    );
+    assert_eq!(
+        supported_sample_rate(&continuous, 150_000.0, true),
+        Some(192_000.0)
+    );
+}
+
+#[test]
+fn test_physical_formats() {
+    use crate::format::audio_format::LinearPcmFlags;
+    use crate::format::SampleFormat;
+
+    let backend = fake_system();
+    let stereo_float = StreamFormat {
+        sample_rate: 48_000.0,
+        sample_format: SampleFormat::F32,
+        flags: LinearPcmFlags::empty(),
+        channels: 2,
+    };
+    let asbd = matching_physical_format(&backend, 11, stereo_float).unwrap();
+    assert_eq!(asbd.mChannelsPerFrame, 2);
+    let stereo_int = StreamFormat {
+        sample_format: SampleFormat::I16,
+        ..stereo_float
+    };
+    let asbd_int = matching_physical_format(&backend, 11, stereo_int).unwrap();
+    assert_eq!(asbd_int.mChannelsPerFrame, 8);
+    assert!(matching_physical_format(&backend, 10, stereo_float).is_none());
+
+    let timeout = Duration::from_millis(50);
+    set_physical_stream_format(&backend, 11, asbd, timeout).unwrap();
+    let address = global_address(kAudioStreamPropertyPhysicalFormat);
+    let current = backend
+        .get::<AudioStreamBasicDescription>(11, address)
+        .unwrap();
+    assert!(asbds_are_equal(&current, &asbd));
+
+    // Sample rates are compared within the tolerance rather than truncated.
+    let rate = |sample_rate| AudioStreamBasicDescription {
+        mSampleRate: sample_rate,
+        ..asbd
+    };
+    assert!(asbds_are_equal(&rate(44_100.0), &rate(44_100.004)));
+    assert!(!asbds_are_equal(&rate(44_100.0), &rate(44_100.5)));
+
+    // A device which never reports the new format times out.
+    let revert = backend.clone();
+    let _listener = backend
+        .add_property_listener(
+            11,
+            &address,
+            Box::new(move |_, _| revert.insert(11, address, &current)),
+        )
+        .unwrap();
+    assert!(matches!(
+        set_physical_stream_format(&backend, 11, asbd_int, timeout),
+        Err(Error::Device(DeviceError::ChangeTimedOut(selector)))
+            if selector == kAudioStreamPropertyPhysicalFormat
+    ));
+}
+
+#[test]
+fn test_rate_and_alive_listeners() {
+    let fake = fake_system();
+    let backend: Arc<dyn AudioObjectBackend> = Arc::new(fake.clone());
+    let rate_address = global_address(kAudioDevicePropertyNominalSampleRate);
+    let alive_address = global_address(kAudioDevicePropertyDeviceIsAlive);
+    let mut rates = RateListener::with_backend(10, None, backend.clone());
+    let mut alive = AliveListener::with_backend(10, backend);
+    rates.register().unwrap();
+    alive.register().unwrap();
+
+    fake.insert(10, rate_address, &48_000.0f64);
+    fake.notify(10, rate_address);
+    fake.insert(10, alive_address, &0u32);
+    fake.notify(10, alive_address);
+    assert_eq!(rates.drain_values(), [48_000.0]);
+    assert!(!alive.is_alive());
+
+    // Nothing is received once the listener is unregistered.
+    rates.unregister().unwrap();
+    fake.notify(10, rate_address);
+    assert_eq!(rates.get_nbr_values(), 0);
+}
+
+#[test]
+fn test_hog_mode() {
+    let backend = fake_system();
+    assert_eq!(hogging_pid(&backend, 10).unwrap(), -1);
+    assert_eq!(toggle_hog(&backend, 10).unwrap(), -1);
+    assert!(hogging_pid(&backend, 99).is_err());
+}
@@ -0,0 +1,182 @@
+//! Property access shared by [**Device**](../device/struct.Device) and
+//! [**Stream**](../stream/struct.Stream).
+
+use objc2_core_audio::{
+    kAudioObjectPropertyScopeGlobal, AudioObjectID, AudioObjectPropertyAddress,
+    AudioObjectPropertyScope, AudioObjectPropertySelector,
+};
+
+use super::backend::{
+    get_property, get_property_vec, get_string_property, property_address, set_property,
+    AudioObjectBackend, ObjectPropertyListener,
+};
+use crate::error::{DeviceError, Error};
+use crate::OSStatus;
+use std::sync::Arc;
+use std::{mem, slice};
+
+/// A HAL object along with the backend through which it is queried.
+///
+/// Errors returned by the backend are converted into [**DeviceError**]s naming the property
+/// involved.
+#[derive(Clone)]
+pub(super) struct Object {
+    pub(super) id: AudioObjectID,
+    pub(super) backend: Arc<dyn AudioObjectBackend>,
+}
+
+/// The status carried by an error returned from an
+/// [**AudioObjectBackend**](../backend/trait.AudioObjectBackend).
+fn os_status(err: Error) -> OSStatus {
+    match err {
+        Error::Unknown(os_status) => os_status,
+        err => err.as_os_status(),
+    }
+}
+
+impl Object {
+    pub(super) fn new(id: AudioObjectID, backend: Arc<dyn AudioObjectBackend>) -> Self {
+        Object { id, backend }
+    }
+
+    /// Convert an error from the backend into a [**DeviceError**] naming the property.
+    ///
+    /// Errors that already are **DeviceError**s are passed through unchanged.
+    pub(super) fn property_error(
+        &self,
+        selector: AudioObjectPropertySelector,
+        err: Error,
+    ) -> Error {
+        match err {
+            Error::Device(err) => Error::Device(err),
+            err => {
+                let err = DeviceError::from_property_status(self.id, selector, os_status(err));
+                Error::Device(err)
+            }
+        }
+    }
+
+    /// Read a fixed size property.
+    ///
+    /// # Safety
+    ///
+    /// The property must hold a value of type `T`.
+    pub(super) unsafe fn get<T>(
+        &self,
+        selector: AudioObjectPropertySelector,
+        scope: AudioObjectPropertyScope,
+    ) -> Result<T, Error> {
+        self.get_at(&property_address(selector, scope))
+    }
+
+    /// Read a fixed size property at the given address, such as that of a single channel.
+    ///
+    /// # Safety
+    ///
+    /// The property must hold a value of type `T`.
+    pub(super) unsafe fn get_at<T>(
+        &self,
+        address: &AudioObjectPropertyAddress,
+    ) -> Result<T, Error> {
+        get_property(&*self.backend, self.id, address)
+            .map_err(|err| self.property_error(address.mSelector, err))
+    }
+
+    /// Read a property that converts the value passed in, such as
+    /// `kAudioDevicePropertyVolumeDecibelsToScalar`.
+    ///
+    /// # Safety
+    ///
+    /// The property must convert a value of type `T` in place.
+    pub(super) unsafe fn convert<T: Copy>(
+        &self,
+        address: &AudioObjectPropertyAddress,
+        mut value: T,
+    ) -> Result<T, Error> {
+        let bytes = slice::from_raw_parts_mut(&mut value as *mut T as *mut u8, mem::size_of::<T>());
+        self.backend
+            .get_property_data(self.id, address, &[], bytes)
+            .map_err(|err| self.property_error(address.mSelector, err))?;
+        Ok(value)
+    }
+
+    /// Read a variable length property holding an array.
+    ///
+    /// # Safety
+    ///
+    /// The property must hold an array of values of type `T`.
+    pub(super) unsafe fn get_vec<T>(
+        &self,
+        selector: AudioObjectPropertySelector,
+        scope: AudioObjectPropertyScope,
+    ) -> Result<Vec<T>, Error> {
+        let address = property_address(selector, scope);
+        get_property_vec(&*self.backend, self.id, &address)
+            .map_err(|err| self.property_error(selector, err))
+    }
+
+    /// Read a global `CFStringRef` property.
+    pub(super) fn get_string(
+        &self,
+        selector: AudioObjectPropertySelector,
+    ) -> Result<String, Error> {
+        let address = property_address(selector, kAudioObjectPropertyScopeGlobal);
+        // SAFETY: The string properties of HAL objects are `CFStringRef`s owned by the caller.
+        unsafe { get_string_property(&*self.backend, self.id, &address) }
+            .map_err(|err| self.property_error(selector, err))
+    }
+
+    /// Change a fixed size property.
+    ///
+    /// # Safety
+    ///
+    /// The property must hold a value of type `T`.
+    pub(super) unsafe fn set<T>(
+        &self,
+        selector: AudioObjectPropertySelector,
+        scope: AudioObjectPropertyScope,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.set_at(&property_address(selector, scope), value)
+    }
+
+    /// Change a fixed size property at the given address.
+    ///
+    /// # Safety
+    ///
+    /// The property must hold a value of type `T`.
+    pub(super) unsafe fn set_at<T>(
+        &self,
+        address: &AudioObjectPropertyAddress,
+        value: &T,
+    ) -> Result<(), Error> {
+        set_property(&*self.backend, self.id, address, value)
+            .map_err(|err| self.property_error(address.mSelector, err))
+    }
+
+    /// Whether or not the object has the property at the given address.
+    pub(super) fn has(&self, address: &AudioObjectPropertyAddress) -> bool {
+        self.backend.has_property(self.id, address)
+    }
+
+    /// Whether or not the property at the given address may be changed.
+    pub(super) fn is_settable(&self, address: &AudioObjectPropertyAddress) -> Result<bool, Error> {
+        self.backend
+            .is_property_settable(self.id, address)
+            .map_err(|err| self.property_error(address.mSelector, err))
+    }
+
+    /// Call `f` whenever the property at the given address changes.
+    pub(super) fn add_listener<F>(
+        &self,
+        address: &AudioObjectPropertyAddress,
+        mut f: F,
+    ) -> Result<ObjectPropertyListener, Error>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.backend
+            .add_property_listener(self.id, address, Box::new(move |_, _| f()))
+            .map_err(|err| self.property_error(address.mSelector, err))
+    }
+}
@@ -0,0 +1,212 @@
+//! The streams of an audio device.
+//!
+//! A device presents its channels as one or more streams per direction, each with its own
+//! formats. Multi-stream interfaces must have their formats queried and changed per
+//! [**Stream**](./struct.Stream) rather than on the device, which only addresses its first
+//! stream.
+
+use objc2_core_audio::{
+    kAudioObjectPropertyScopeGlobal, kAudioStreamPropertyAvailablePhysicalFormats,
+    kAudioStreamPropertyAvailableVirtualFormats, kAudioStreamPropertyDirection,
+    kAudioStreamPropertyLatency, kAudioStreamPropertyPhysicalFormat,
+    kAudioStreamPropertyStartingChannel, kAudioStreamPropertyTerminalType,
+    kAudioStreamPropertyVirtualFormat, AudioStreamID, AudioStreamRangedDescription,
+};
+use objc2_core_audio_types::AudioStreamBasicDescription;
+
+use super::backend::{AudioObjectBackend, CoreAudioBackend};
+use super::object::Object;
+use crate::audio_unit::Scope;
+use crate::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// A stream of an audio device, carrying a contiguous range of the device's channels in one
+/// direction.
+///
+/// The physical format is the format of the data exchanged with the hardware, while the virtual
+/// format is the format of the data exchanged with clients of the HAL.
+#[derive(Clone)]
+pub struct Stream {
+    object: Object,
+}
+
+impl Stream {
+    /// The stream with the given id, queried through the Core Audio HAL.
+    pub fn new(id: AudioStreamID) -> Self {
+        Self::with_backend(id, Arc::new(CoreAudioBackend))
+    }
+
+    /// The stream with the given id, queried through the given backend.
+    pub fn with_backend(id: AudioStreamID, backend: Arc<dyn AudioObjectBackend>) -> Self {
+        Stream {
+            object: Object::new(id, backend),
+        }
+    }
+
+    /// The `AudioStreamID` of the stream.
+    pub fn id(&self) -> AudioStreamID {
+        self.object.id
+    }
+
+    /// Whether the stream carries input or output, as **Scope::Input** or **Scope::Output**.
+    pub fn direction(&self) -> Result<Scope, Error> {
+        let direction: u32 = unsafe {
+            self.object.get(
+                kAudioStreamPropertyDirection,
+                kAudioObjectPropertyScopeGlobal,
+            )?
+        };
+        match direction {
+            0 => Ok(Scope::Output),
+            _ => Ok(Scope::Input),
+        }
+    }
+
+    /// The device channel number of the first channel of the stream, counting from `1`.
+    pub fn starting_channel(&self) -> Result<u32, Error> {
+        unsafe {
+            self.object.get(
+                kAudioStreamPropertyStartingChannel,
+                kAudioObjectPropertyScopeGlobal,
+            )
+        }
+    }
+
+    /// The `kAudioStreamTerminalType*` code describing what the stream is connected to, such
+    /// as a speaker or a line level connector.
+    pub fn terminal_type(&self) -> Result<u32, Error> {
+        unsafe {
+            self.object.get(
+                kAudioStreamPropertyTerminalType,
+                kAudioObjectPropertyScopeGlobal,
+            )
+        }
+    }
+
+    /// The latency of the stream in frames, in addition to the latency of its device.
+    pub fn latency(&self) -> Result<u32, Error> {
+        unsafe {
+            self.object
+                .get(kAudioStreamPropertyLatency, kAudioObjectPropertyScopeGlobal)
+        }
+    }
+
+    /// The current format of the data exchanged with the hardware.
+    pub fn physical_format(&self) -> Result<AudioStreamBasicDescription, Error> {
+        unsafe {
+            self.object.get(
+                kAudioStreamPropertyPhysicalFormat,
+                kAudioObjectPropertyScopeGlobal,
+            )
+        }
+    }
+
+    /// Request a change of the physical format.
+    ///
+    /// The change takes effect asynchronously, and may also change the virtual format and the
+    /// nominal sample rate of the device.
+    pub fn set_physical_format(&self, format: AudioStreamBasicDescription) -> Result<(), Error> {
+        unsafe {
+            self.object.set(
+                kAudioStreamPropertyPhysicalFormat,
+                kAudioObjectPropertyScopeGlobal,
+                &format,
+            )
+        }
+    }
+
+    /// The physical formats supported by the stream.
+    pub fn available_physical_formats(&self) -> Result<Vec<AudioStreamRangedDescription>, Error> {
+        unsafe {
+            self.object.get_vec(
+                kAudioStreamPropertyAvailablePhysicalFormats,
+                kAudioObjectPropertyScopeGlobal,
+            )
+        }
+    }
+
+    /// The current format of the data exchanged with clients of the HAL.
+    pub fn virtual_format(&self) -> Result<AudioStreamBasicDescription, Error> {
+        unsafe {
+            self.object.get(
+                kAudioStreamPropertyVirtualFormat,
+                kAudioObjectPropertyScopeGlobal,
+            )
+        }
+    }
+
+    /// Request a change of the virtual format.
+    ///
+    /// The change takes effect asynchronously, and may also change the physical format.
+    pub fn set_virtual_format(&self, format: AudioStreamBasicDescription) -> Result<(), Error> {
+        unsafe {
+            self.object.set(
+                kAudioStreamPropertyVirtualFormat,
+                kAudioObjectPropertyScopeGlobal,
+                &format,
+            )
+        }
+    }
+
+    /// The virtual formats supported by the stream.
+    pub fn available_virtual_formats(&self) -> Result<Vec<AudioStreamRangedDescription>, Error> {
+        unsafe {
+            self.object.get_vec(
+                kAudioStreamPropertyAvailableVirtualFormats,
+                kAudioObjectPropertyScopeGlobal,
+            )
+        }
+    }
+}
+
+impl fmt::Debug for Stream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Stream")
+            .field("id", &self.object.id)
+            .finish()
+    }
+}
+
+#[test]
+fn test_stream_properties() {
+    use crate::error::DeviceError;
+    use objc2_core_audio::{kAudioStreamTerminalTypeMicrophone, kAudioStreamTerminalTypeSpeaker};
+
+    let backend: Arc<dyn AudioObjectBackend> = Arc::new(super::fake::fake_system());
+
+    let second_input = Stream::with_backend(1102, backend.clone());
+    assert!(matches!(second_input.direction().unwrap(), Scope::Input));
+    assert_eq!(second_input.starting_channel().unwrap(), 3);
+    assert_eq!(
+        second_input.terminal_type().unwrap(),
+        kAudioStreamTerminalTypeMicrophone
+    );
+    assert_eq!(second_input.latency().unwrap(), 0);
+    // The streams of the fake offer the physical formats of their device.
+    let format = second_input.physical_format().unwrap();
+    assert_eq!(format.mChannelsPerFrame, 8);
+    assert_eq!(format.mSampleRate, 48_000.0);
+    assert_eq!(second_input.available_physical_formats().unwrap().len(), 2);
+    assert_eq!(second_input.virtual_format().unwrap().mChannelsPerFrame, 8);
+
+    let output = Stream::with_backend(1103, backend.clone());
+    assert!(matches!(output.direction().unwrap(), Scope::Output));
+    assert_eq!(output.starting_channel().unwrap(), 1);
+    assert_eq!(
+        output.terminal_type().unwrap(),
+        kAudioStreamTerminalTypeSpeaker
+    );
+    let mut format = output.available_virtual_formats().unwrap()[0].mFormat;
+    format.mSampleRate = 44_100.0;
+    output.set_virtual_format(format).unwrap();
+    assert_eq!(output.virtual_format().unwrap().mSampleRate, 44_100.0);
+    output.set_physical_format(format).unwrap();
+    assert_eq!(output.physical_format().unwrap().mSampleRate, 44_100.0);
+
+    let missing = Stream::with_backend(99, backend);
+    assert!(matches!(
+        missing.physical_format(),
+        Err(Error::Device(DeviceError::NotFound(99)))
+    ));
+}
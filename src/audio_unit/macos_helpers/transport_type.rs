@@ -0,0 +1,206 @@
+//! The ways in which an audio device may be connected to the system.
+
+use objc2_core_audio::{
+    kAudioDeviceTransportTypeAVB, kAudioDeviceTransportTypeAggregate,
+    kAudioDeviceTransportTypeAirPlay, kAudioDeviceTransportTypeBluetooth,
+    kAudioDeviceTransportTypeBluetoothLE, kAudioDeviceTransportTypeBuiltIn,
+    kAudioDeviceTransportTypeContinuityCapture, kAudioDeviceTransportTypeContinuityCaptureWired,
+    kAudioDeviceTransportTypeContinuityCaptureWireless, kAudioDeviceTransportTypeDisplayPort,
+    kAudioDeviceTransportTypeFireWire, kAudioDeviceTransportTypeHDMI, kAudioDeviceTransportTypePCI,
+    kAudioDeviceTransportTypeThunderbolt, kAudioDeviceTransportTypeUSB,
+    kAudioDeviceTransportTypeVirtual,
+};
+
+use crate::error::FourCharCode;
+use std::fmt;
+
+/// How an audio device is connected, as given by its `kAudioDevicePropertyTransportType`.
+///
+/// Codes without a variant of their own, including `kAudioDeviceTransportTypeUnknown`, are held
+/// by **TransportType::Unknown**, so that converting a code to a **TransportType** and back
+/// always yields the original code. Each code has exactly one **TransportType**, as
+/// **TransportType::Unknown** can only be created from codes without a variant.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TransportType {
+    /// A device built into the computer, such as its speakers or microphone.
+    BuiltIn,
+    /// An aggregate device combining other devices.
+    Aggregate,
+    /// A virtual device implemented in software.
+    Virtual,
+    /// A PCI or PCI Express card.
+    Pci,
+    /// A USB device.
+    Usb,
+    /// A FireWire device.
+    FireWire,
+    /// A Bluetooth device.
+    Bluetooth,
+    /// A Bluetooth Low Energy device.
+    BluetoothLe,
+    /// A display connected via HDMI.
+    Hdmi,
+    /// A display connected via DisplayPort.
+    DisplayPort,
+    /// An AirPlay device.
+    AirPlay,
+    /// An Audio Video Bridging network device.
+    Avb,
+    /// A Thunderbolt device.
+    Thunderbolt,
+    /// A Continuity Camera device.
+    ContinuityCapture,
+    /// A Continuity Camera device connected via a cable.
+    ContinuityCaptureWired,
+    /// A Continuity Camera device connected wirelessly.
+    ContinuityCaptureWireless,
+    /// Any other transport type code.
+    Unknown(UnknownTransportType),
+}
+
+/// A transport type code without a variant of its own.
+///
+/// Only [**TransportType::from_u32**](./enum.TransportType#method.from_u32) creates one, so that it
+/// never holds the code of a named variant.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UnknownTransportType(u32);
+
+impl UnknownTransportType {
+    /// The `kAudioDeviceTransportType*` code.
+    pub fn code(&self) -> u32 {
+        self.0
+    }
+}
+
+/// The transport types having a variant of their own.
+const KNOWN: [TransportType; 16] = [
+    TransportType::BuiltIn,
+    TransportType::Aggregate,
+    TransportType::Virtual,
+    TransportType::Pci,
+    TransportType::Usb,
+    TransportType::FireWire,
+    TransportType::Bluetooth,
+    TransportType::BluetoothLe,
+    TransportType::Hdmi,
+    TransportType::DisplayPort,
+    TransportType::AirPlay,
+    TransportType::Avb,
+    TransportType::Thunderbolt,
+    TransportType::ContinuityCapture,
+    TransportType::ContinuityCaptureWired,
+    TransportType::ContinuityCaptureWireless,
+];
+
+impl TransportType {
+    /// Convert a `kAudioDeviceTransportType*` code into a **TransportType**.
+    pub fn from_u32(code: u32) -> Self {
+        KNOWN
+            .iter()
+            .copied()
+            .find(|transport_type| transport_type.as_u32() == code)
+            .unwrap_or(TransportType::Unknown(UnknownTransportType(code)))
+    }
+
+    /// The `kAudioDeviceTransportType*` code of the transport type.
+    pub fn as_u32(&self) -> u32 {
+        match *self {
+            TransportType::BuiltIn => kAudioDeviceTransportTypeBuiltIn,
+            TransportType::Aggregate => kAudioDeviceTransportTypeAggregate,
+            TransportType::Virtual => kAudioDeviceTransportTypeVirtual,
+            TransportType::Pci => kAudioDeviceTransportTypePCI,
+            TransportType::Usb => kAudioDeviceTransportTypeUSB,
+            TransportType::FireWire => kAudioDeviceTransportTypeFireWire,
+            TransportType::Bluetooth => kAudioDeviceTransportTypeBluetooth,
+            TransportType::BluetoothLe => kAudioDeviceTransportTypeBluetoothLE,
+            TransportType::Hdmi => kAudioDeviceTransportTypeHDMI,
+            TransportType::DisplayPort => kAudioDeviceTransportTypeDisplayPort,
+            TransportType::AirPlay => kAudioDeviceTransportTypeAirPlay,
+            TransportType::Avb => kAudioDeviceTransportTypeAVB,
+            TransportType::Thunderbolt => kAudioDeviceTransportTypeThunderbolt,
+            TransportType::ContinuityCapture => kAudioDeviceTransportTypeContinuityCapture,
+            TransportType::ContinuityCaptureWired => {
+                kAudioDeviceTransportTypeContinuityCaptureWired
+            }
+            TransportType::ContinuityCaptureWireless => {
+                kAudioDeviceTransportTypeContinuityCaptureWireless
+            }
+            TransportType::Unknown(unknown) => unknown.code(),
+        }
+    }
+
+    /// Whether or not the transport is Bluetooth, either classic or Low Energy.
+    pub fn is_bluetooth(&self) -> bool {
+        matches!(*self, TransportType::Bluetooth | TransportType::BluetoothLe)
+    }
+}
+
+impl From<u32> for TransportType {
+    fn from(code: u32) -> Self {
+        TransportType::from_u32(code)
+    }
+}
+
+impl From<TransportType> for u32 {
+    fn from(transport_type: TransportType) -> Self {
+        transport_type.as_u32()
+    }
+}
+
+impl fmt::Display for TransportType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match *self {
+            TransportType::BuiltIn => "Built-in",
+            TransportType::Aggregate => "Aggregate",
+            TransportType::Virtual => "Virtual",
+            TransportType::Pci => "PCI",
+            TransportType::Usb => "USB",
+            TransportType::FireWire => "FireWire",
+            TransportType::Bluetooth => "Bluetooth",
+            TransportType::BluetoothLe => "Bluetooth LE",
+            TransportType::Hdmi => "HDMI",
+            TransportType::DisplayPort => "DisplayPort",
+            TransportType::AirPlay => "AirPlay",
+            TransportType::Avb => "AVB",
+            TransportType::Thunderbolt => "Thunderbolt",
+            TransportType::ContinuityCapture => "Continuity Capture",
+            TransportType::ContinuityCaptureWired => "Continuity Capture (wired)",
+            TransportType::ContinuityCaptureWireless => "Continuity Capture (wireless)",
+            TransportType::Unknown(UnknownTransportType(0)) => "Unknown",
+            TransportType::Unknown(unknown) => {
+                return write!(f, "Unknown ({})", FourCharCode(unknown.code()))
+            }
+        };
+        write!(f, "{description}")
+    }
+}
+
+#[test]
+fn test_transport_type_codes() {
+    for transport_type in KNOWN.iter() {
+        let code = transport_type.as_u32();
+        assert_eq!(TransportType::from_u32(code), *transport_type);
+        assert!(!matches!(transport_type, TransportType::Unknown(_)));
+    }
+    assert_eq!(TransportType::from(0x75736220), TransportType::Usb);
+    assert_eq!(u32::from(TransportType::Usb), 0x75736220);
+    let code = u32::from_be_bytes(*b"abcd");
+    assert!(matches!(
+        TransportType::from_u32(code),
+        TransportType::Unknown(unknown) if unknown.code() == code
+    ));
+    assert_eq!(TransportType::from_u32(code).as_u32(), code);
+    assert!(TransportType::BluetoothLe.is_bluetooth());
+    assert!(!TransportType::Usb.is_bluetooth());
+}
+
+#[test]
+fn test_transport_type_display() {
+    assert_eq!(TransportType::BuiltIn.to_string(), "Built-in");
+    assert_eq!(TransportType::BluetoothLe.to_string(), "Bluetooth LE");
+    assert_eq!(TransportType::from_u32(0).to_string(), "Unknown");
+    assert_eq!(
+        TransportType::from_u32(u32::from_be_bytes(*b"abcd")).to_string(),
+        "Unknown ('abcd')"
+    );
+}
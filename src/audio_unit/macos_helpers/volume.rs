@@ -0,0 +1,300 @@
+//! Volume, mute and stereo pan controls of audio devices.
+//!
+//! Controls are addressed by scope and channel. Channel `0` is the main element, which controls
+//! every channel of the scope at once, and the channels of the device are numbered from `1`. A
+//! device may offer a control on the main element, on individual channels, on both or on
+//! neither, so check with [**Device::has_control**](../device/struct.Device#method.has_control)
+//! before relying on one.
+
+use objc2_core_audio::{
+    kAudioDevicePropertyMute, kAudioDevicePropertyStereoPan, kAudioDevicePropertyStereoPanChannels,
+    kAudioDevicePropertyVolumeDecibels, kAudioDevicePropertyVolumeDecibelsToScalar,
+    kAudioDevicePropertyVolumeRangeDecibels, kAudioDevicePropertyVolumeScalar,
+    kAudioDevicePropertyVolumeScalarToDecibels, kAudioObjectPropertyElementMaster,
+    AudioObjectPropertyAddress, AudioObjectPropertySelector,
+};
+use objc2_core_audio_types::AudioValueRange;
+
+use super::backend::ObjectPropertyListener;
+use super::device::Device;
+use super::object_scope;
+use crate::audio_unit::Scope;
+use crate::error::Error;
+
+/// The controls of a device that may be inspected and listened to with
+/// [**Device::has_control**](../device/struct.Device#method.has_control) and friends.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum VolumeControl {
+    /// The volume, both as a scalar and in decibels.
+    Volume,
+    /// Whether or not the audio is muted.
+    Mute,
+    /// The stereo pan, which is only present on the main element.
+    StereoPan,
+}
+
+impl VolumeControl {
+    /// The `kAudioDeviceProperty*` selector of the control.
+    pub fn selector(&self) -> AudioObjectPropertySelector {
+        match *self {
+            VolumeControl::Volume => kAudioDevicePropertyVolumeScalar,
+            VolumeControl::Mute => kAudioDevicePropertyMute,
+            VolumeControl::StereoPan => kAudioDevicePropertyStereoPan,
+        }
+    }
+}
+
+/// The address of a property of the given channel, where channel `0` is the main element.
+fn channel_address(
+    selector: AudioObjectPropertySelector,
+    scope: Scope,
+    channel: u32,
+) -> AudioObjectPropertyAddress {
+    AudioObjectPropertyAddress {
+        mSelector: selector,
+        mScope: object_scope(scope),
+        mElement: channel,
+    }
+}
+
+impl Device {
+    /// Whether or not the device has the control on the given channel.
+    pub fn has_control(&self, control: VolumeControl, scope: Scope, channel: u32) -> bool {
+        let address = channel_address(control.selector(), scope, channel);
+        self.object.has(&address)
+    }
+
+    /// Whether or not the control on the given channel may be changed.
+    ///
+    /// Some devices report a volume or mute control that only reflects a hardware knob.
+    pub fn is_control_settable(
+        &self,
+        control: VolumeControl,
+        scope: Scope,
+        channel: u32,
+    ) -> Result<bool, Error> {
+        let address = channel_address(control.selector(), scope, channel);
+        self.object.is_settable(&address)
+    }
+
+    /// Call `f` whenever the control on the given channel changes, whether through this crate,
+    /// another application or the hardware.
+    ///
+    /// The listener remains registered until the returned
+    /// [**ObjectPropertyListener**](../backend/struct.ObjectPropertyListener) is dropped.
+    pub fn add_control_listener<F>(
+        &self,
+        control: VolumeControl,
+        scope: Scope,
+        channel: u32,
+        f: F,
+    ) -> Result<ObjectPropertyListener, Error>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let address = channel_address(control.selector(), scope, channel);
+        self.object.add_listener(&address, f)
+    }
+
+    /// The volume of the given channel, from `0.0` for silence to `1.0` for full volume.
+    ///
+    /// The scalar is mapped onto the decibel range of the control so as to feel linear to the
+    /// listener.
+    pub fn volume(&self, scope: Scope, channel: u32) -> Result<f32, Error> {
+        let address = channel_address(kAudioDevicePropertyVolumeScalar, scope, channel);
+        unsafe { self.object.get_at(&address) }
+    }
+
+    /// Change the volume of the given channel to a scalar from `0.0` to `1.0`.
+    pub fn set_volume(&self, scope: Scope, channel: u32, volume: f32) -> Result<(), Error> {
+        let address = channel_address(kAudioDevicePropertyVolumeScalar, scope, channel);
+        unsafe { self.object.set_at(&address, &volume) }
+    }
+
+    /// The volume of the given channel in decibels.
+    pub fn volume_decibels(&self, scope: Scope, channel: u32) -> Result<f32, Error> {
+        let address = channel_address(kAudioDevicePropertyVolumeDecibels, scope, channel);
+        unsafe { self.object.get_at(&address) }
+    }
+
+    /// Change the volume of the given channel to a value in decibels within
+    /// [**volume_decibel_range**](#method.volume_decibel_range).
+    pub fn set_volume_decibels(
+        &self,
+        scope: Scope,
+        channel: u32,
+        decibels: f32,
+    ) -> Result<(), Error> {
+        let address = channel_address(kAudioDevicePropertyVolumeDecibels, scope, channel);
+        unsafe { self.object.set_at(&address, &decibels) }
+    }
+
+    /// The range of the volume of the given channel in decibels.
+    pub fn volume_decibel_range(
+        &self,
+        scope: Scope,
+        channel: u32,
+    ) -> Result<AudioValueRange, Error> {
+        let address = channel_address(kAudioDevicePropertyVolumeRangeDecibels, scope, channel);
+        unsafe { self.object.get_at(&address) }
+    }
+
+    /// Convert a volume in decibels into the scalar used by the control of the given channel.
+    pub fn decibels_to_scalar(
+        &self,
+        scope: Scope,
+        channel: u32,
+        decibels: f32,
+    ) -> Result<f32, Error> {
+        let address = channel_address(kAudioDevicePropertyVolumeDecibelsToScalar, scope, channel);
+        unsafe { self.object.convert(&address, decibels) }
+    }
+
+    /// Convert a volume scalar of the control of the given channel into decibels.
+    pub fn scalar_to_decibels(
+        &self,
+        scope: Scope,
+        channel: u32,
+        scalar: f32,
+    ) -> Result<f32, Error> {
+        let address = channel_address(kAudioDevicePropertyVolumeScalarToDecibels, scope, channel);
+        unsafe { self.object.convert(&address, scalar) }
+    }
+
+    /// The volume scalar of each channel of the scope, from channel `1`, or `None` for the
+    /// channels without a volume control of their own.
+    pub fn channel_volumes(&self, scope: Scope) -> Result<Vec<Option<f32>>, Error> {
+        let channels = self.channel_count(scope)?;
+        (1..=channels)
+            .map(|channel| {
+                if self.has_control(VolumeControl::Volume, scope, channel) {
+                    self.volume(scope, channel).map(Some)
+                } else {
+                    Ok(None)
+                }
+            })
+            .collect()
+    }
+
+    /// Whether or not the given channel is muted.
+    pub fn is_muted(&self, scope: Scope, channel: u32) -> Result<bool, Error> {
+        let address = channel_address(kAudioDevicePropertyMute, scope, channel);
+        let muted: u32 = unsafe { self.object.get_at(&address)? };
+        Ok(muted != 0)
+    }
+
+    /// Mute or unmute the given channel.
+    pub fn set_muted(&self, scope: Scope, channel: u32, muted: bool) -> Result<(), Error> {
+        let address = channel_address(kAudioDevicePropertyMute, scope, channel);
+        unsafe { self.object.set_at(&address, &(muted as u32)) }
+    }
+
+    /// The stereo pan, or balance, from `0.0` for fully left through `0.5` for centred to
+    /// `1.0` for fully right.
+    pub fn stereo_pan(&self, scope: Scope) -> Result<f32, Error> {
+        let address = channel_address(
+            kAudioDevicePropertyStereoPan,
+            scope,
+            kAudioObjectPropertyElementMaster,
+        );
+        unsafe { self.object.get_at(&address) }
+    }
+
+    /// Change the stereo pan to a value from `0.0` for fully left to `1.0` for fully right.
+    pub fn set_stereo_pan(&self, scope: Scope, pan: f32) -> Result<(), Error> {
+        let address = channel_address(
+            kAudioDevicePropertyStereoPan,
+            scope,
+            kAudioObjectPropertyElementMaster,
+        );
+        unsafe { self.object.set_at(&address, &pan) }
+    }
+
+    /// The left and right channels affected by the stereo pan.
+    pub fn stereo_pan_channels(&self, scope: Scope) -> Result<[u32; 2], Error> {
+        let address = channel_address(
+            kAudioDevicePropertyStereoPanChannels,
+            scope,
+            kAudioObjectPropertyElementMaster,
+        );
+        unsafe { self.object.get_at(&address) }
+    }
+}
+
+#[test]
+fn test_volume_controls() {
+    use super::AudioObjectBackend;
+    use crate::error::DeviceError;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let fake = super::fake::fake_system();
+    let backend: Arc<dyn AudioObjectBackend> = Arc::new(fake.clone());
+    let device = Device::with_backend(11, backend);
+
+    assert!(device.has_control(VolumeControl::Volume, Scope::Output, 0));
+    assert!(device.has_control(VolumeControl::Volume, Scope::Output, 2));
+    assert!(!device.has_control(VolumeControl::Volume, Scope::Output, 3));
+    assert!(device.has_control(VolumeControl::Mute, Scope::Input, 0));
+    assert!(!device.has_control(VolumeControl::StereoPan, Scope::Input, 0));
+    assert!(device
+        .is_control_settable(VolumeControl::Volume, Scope::Output, 1)
+        .unwrap());
+
+    let changes = Arc::new(AtomicUsize::new(0));
+    let counter = changes.clone();
+    let _listener = device
+        .add_control_listener(VolumeControl::Volume, Scope::Output, 0, move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+    assert_eq!(device.volume(Scope::Output, 0).unwrap(), 0.5);
+    assert_eq!(device.volume_decibels(Scope::Output, 0).unwrap(), -32.0);
+    device.set_volume_decibels(Scope::Output, 0, -16.0).unwrap();
+    assert_eq!(device.volume(Scope::Output, 0).unwrap(), 0.75);
+    device.set_volume(Scope::Output, 0, 1.0).unwrap();
+    assert_eq!(device.volume_decibels(Scope::Output, 0).unwrap(), 0.0);
+    assert_eq!(changes.load(Ordering::SeqCst), 2);
+    assert_eq!(
+        device
+            .volume_decibel_range(Scope::Output, 0)
+            .unwrap()
+            .mMinimum,
+        -64.0
+    );
+    assert_eq!(
+        device.decibels_to_scalar(Scope::Output, 0, -48.0).unwrap(),
+        0.25
+    );
+    assert_eq!(
+        device.scalar_to_decibels(Scope::Input, 0, 0.5).unwrap(),
+        -32.0
+    );
+
+    let mut volumes = device.channel_volumes(Scope::Output).unwrap();
+    assert_eq!(volumes.len(), 8);
+    assert!(volumes.drain(2..).all(|volume| volume.is_none()));
+    assert_eq!(volumes, [Some(1.0), Some(0.25)]);
+    assert_eq!(device.channel_volumes(Scope::Input).unwrap(), [None; 4]);
+    assert!(matches!(
+        device.volume(Scope::Output, 3),
+        Err(Error::Device(DeviceError::UnknownProperty(selector)))
+            if selector == kAudioDevicePropertyVolumeScalar
+    ));
+
+    assert!(!device.is_muted(Scope::Input, 0).unwrap());
+    device.set_muted(Scope::Input, 0, true).unwrap();
+    assert!(device.is_muted(Scope::Input, 0).unwrap());
+
+    assert_eq!(device.stereo_pan(Scope::Output).unwrap(), 0.5);
+    device.set_stereo_pan(Scope::Output, 0.25).unwrap();
+    assert_eq!(device.stereo_pan(Scope::Output).unwrap(), 0.25);
+    assert_eq!(device.stereo_pan_channels(Scope::Output).unwrap(), [1, 2]);
+
+    let address = channel_address(kAudioDevicePropertyVolumeScalar, Scope::Output, 2);
+    fake.set_settable(11, address, false);
+    assert!(!device
+        .is_control_settable(VolumeControl::Volume, Scope::Output, 2)
+        .unwrap());
+    assert!(device.set_volume(Scope::Output, 2, 0.5).is_err());
+}
@@ -19,36 +19,41 @@
 //! fixes!
 
 use objc2_audio_toolbox::{
-    kAudioUnitManufacturer_Apple, kAudioUnitProperty_SampleRate, kAudioUnitProperty_StreamFormat,
-    kAudioUnitScope_Global, kAudioUnitScope_Group, kAudioUnitScope_Input, kAudioUnitScope_Layer,
-    kAudioUnitScope_LayerItem, kAudioUnitScope_Note, kAudioUnitScope_Output, kAudioUnitScope_Part,
-    AudioComponentDescription, AudioComponentFindNext, AudioComponentInstanceDispose,
-    AudioComponentInstanceNew, AudioOutputUnitStart, AudioOutputUnitStop,
-    AudioUnit as InnerAudioUnit, AudioUnitGetProperty, AudioUnitInitialize, AudioUnitSetProperty,
-    AudioUnitUninitialize,
+    kAudioOutputUnitProperty_IsRunning, kAudioUnitManufacturer_Apple,
+    kAudioUnitProperty_ElementCount, kAudioUnitProperty_SampleRate,
+    kAudioUnitProperty_StreamFormat, kAudioUnitScope_Global, kAudioUnitScope_Group,
+    kAudioUnitScope_Input, kAudioUnitScope_Layer, kAudioUnitScope_LayerItem, kAudioUnitScope_Note,
+    kAudioUnitScope_Output, kAudioUnitScope_Part, AudioComponentDescription,
+    AudioComponentFindNext, AudioComponentInstanceDispose, AudioComponentInstanceNew,
+    AudioOutputUnitStart, AudioOutputUnitStop, AudioUnit as InnerAudioUnit, AudioUnitGetProperty,
+    AudioUnitGetPropertyInfo, AudioUnitInitialize, AudioUnitSetProperty, AudioUnitUninitialize,
 };
 use objc2_core_audio_types::AudioBufferList;
 
-use crate::error::Error;
+use crate::error::{self, Error, TeardownStage};
 use std::mem;
 use std::os::raw::{c_uint, c_void};
 use std::ptr::{self, NonNull};
+use std::sync::{Arc, Mutex};
 
-pub use self::audio_format::AudioFormat;
-pub use self::sample_format::{Sample, SampleFormat};
-pub use self::stream_format::StreamFormat;
 pub use self::types::{
     EffectType, FormatConverterType, GeneratorType, IOType, MixerType, MusicDeviceType, Type,
 };
+pub use crate::format::{audio_format, buffer_list, sample_format, stream_format};
+pub use crate::format::{AudioFormat, OwnedBufferList, Sample, SampleFormat, StreamFormat};
 
 #[cfg(target_os = "macos")]
 pub mod macos_helpers;
 
-pub mod audio_format;
+pub mod effects;
+pub mod graph;
+pub mod music_device;
+pub mod offline;
+pub mod parameter;
+pub mod property_listener;
 pub mod render_callback;
-pub mod sample_format;
-pub mod stream_format;
 pub mod types;
+pub mod typestate;
 
 /// The input and output **Scope**s.
 ///
@@ -66,13 +71,44 @@ pub enum Scope {
     LayerItem = kAudioUnitScope_LayerItem as isize,
 }
 
-/// Represents the **Input** and **Output** **Element**s.
+impl Scope {
+    /// Convert a raw `AudioUnitScope` into a **Scope**.
+    pub fn from_u32(scope: u32) -> Option<Self> {
+        match scope {
+            x if x == kAudioUnitScope_Global => Some(Scope::Global),
+            x if x == kAudioUnitScope_Input => Some(Scope::Input),
+            x if x == kAudioUnitScope_Output => Some(Scope::Output),
+            x if x == kAudioUnitScope_Group => Some(Scope::Group),
+            x if x == kAudioUnitScope_Part => Some(Scope::Part),
+            x if x == kAudioUnitScope_Note => Some(Scope::Note),
+            x if x == kAudioUnitScope_Layer => Some(Scope::Layer),
+            x if x == kAudioUnitScope_LayerItem => Some(Scope::LayerItem),
+            _ => None,
+        }
+    }
+}
+
+/// An audio unit **Element**, also known as a bus.
 ///
-/// These are used when specifying which **Element** we're setting the properties of.
-#[derive(Copy, Clone, Debug)]
-pub enum Element {
-    Output = 0,
-    Input = 1,
+/// These are used when specifying which **Element** we're setting the properties of. Most units
+/// have a single element per scope, while units such as mixers may have many. The **Output** and
+/// **Input** constants name the elements of I/O units, whose element `0` is connected to the
+/// output hardware and whose element `1` is connected to the input hardware.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Element(pub u32);
+
+#[allow(non_upper_case_globals)]
+impl Element {
+    /// Element `0`, connected to the output hardware of an I/O unit.
+    pub const Output: Element = Element(0);
+    /// Element `1`, connected to the input hardware of an I/O unit.
+    pub const Input: Element = Element(1);
+}
+
+impl From<u32> for Element {
+    fn from(element: u32) -> Self {
+        Element(element)
+    }
 }
 
 /// A rust representation of the [`objc2_audio_toolbox::AudioUnit`], including
@@ -81,8 +117,11 @@ pub enum Element {
 /// Find the original Audio Unit Programming Guide [here](https://developer.apple.com/library/mac/documentation/MusicAudio/Conceptual/AudioUnitProgrammingGuide/TheAudioUnit/TheAudioUnit.html).
 pub struct AudioUnit {
     instance: InnerAudioUnit,
-    maybe_render_callback: Option<*mut render_callback::InputProcFnWrapper>,
+    render_callbacks: Vec<(u32, *mut render_callback::InputProcFnWrapper)>,
     maybe_input_callback: Option<InputCallback>,
+    // Set to `true` once the instance has been disposed, so that outstanding
+    // `PropertyListener`s know not to touch it.
+    disposed: Arc<Mutex<bool>>,
 }
 
 struct InputCallback {
@@ -197,8 +236,9 @@ impl AudioUnit {
 
             Ok(AudioUnit {
                 instance,
-                maybe_render_callback: None,
+                render_callbacks: Vec::new(),
                 maybe_input_callback: None,
+                disposed: Arc::new(Mutex::new(false)),
             })
         }
     }
@@ -282,6 +322,64 @@ impl AudioUnit {
         unsafe { get_property(self.instance, id, scope, elem) }
     }
 
+    /// Gets the size in bytes of an **AudioUnit** property along with whether or not the property
+    /// is writable, returned as `(size, writable)`.
+    ///
+    /// This is useful for properties with a variable length, such as `ParameterList`,
+    /// `SupportedNumChannels` or `AudioChannelLayout`.
+    ///
+    /// Parameters
+    /// ----------
+    ///
+    /// - **id**: The identifier of the property.
+    /// - **scope**: The audio unit scope for the property.
+    /// - **elem**: The audio unit element for the property.
+    pub fn property_info(
+        &self,
+        id: u32,
+        scope: Scope,
+        elem: Element,
+    ) -> Result<(u32, bool), Error> {
+        unsafe { get_property_info(self.instance, id, scope, elem) }
+    }
+
+    /// Gets the value of a variable-length **AudioUnit** property as a `Vec<T>`.
+    ///
+    /// The size of the property is first queried with
+    /// [**AudioUnit::property_info**](./struct.AudioUnit#method.property_info) and a buffer of
+    /// the appropriate length is allocated before the value is retrieved.
+    ///
+    /// Returns an `Error` if the size of the property is not a multiple of the size of `T`.
+    ///
+    /// Parameters
+    /// ----------
+    ///
+    /// - **id**: The identifier of the property.
+    /// - **scope**: The audio unit scope for the property.
+    /// - **elem**: The audio unit element for the property.
+    pub fn get_property_vec<T>(
+        &self,
+        id: u32,
+        scope: Scope,
+        elem: Element,
+    ) -> Result<Vec<T>, Error> {
+        unsafe { get_property_vec(self.instance, id, scope, elem) }
+    }
+
+    /// Gets the value of a variable-length **AudioUnit** property as raw bytes.
+    ///
+    /// This is the same as [**AudioUnit::get_property_vec**](./struct.AudioUnit#method.get_property_vec)
+    /// with `u8` elements, and is useful for properties whose layout is only known at runtime,
+    /// such as `ClassInfo` or `AudioChannelLayout`.
+    pub fn get_property_bytes(
+        &self,
+        id: u32,
+        scope: Scope,
+        elem: Element,
+    ) -> Result<Vec<u8>, Error> {
+        self.get_property_vec(id, scope, elem)
+    }
+
     /// Starts an I/O **AudioUnit**, which in turn starts the audio unit processing graph that it is
     /// connected to.
     ///
@@ -304,6 +402,63 @@ impl AudioUnit {
         Ok(())
     }
 
+    /// Stop, uninitialize and dispose of the **AudioUnit**, freeing any render and input
+    /// callbacks.
+    ///
+    /// This performs the same sequence as dropping the **AudioUnit**, but reports failures rather
+    /// than ignoring them. All steps are attempted even if an earlier one fails, in which case the
+    /// first failure is returned as an `Error::Teardown` describing the step at which it occurred.
+    pub fn close(mut self) -> Result<(), Error> {
+        self.teardown()
+    }
+
+    /// Stop, uninitialize and dispose of the instance, returning the first error encountered.
+    ///
+    /// Does nothing if the instance has already been disposed.
+    fn teardown(&mut self) -> Result<(), Error> {
+        if self.instance.is_null() {
+            return Ok(());
+        }
+
+        let mut result = Ok(());
+        let mut check = |stage, os_status| {
+            if Error::from_os_status(os_status).is_err() && result.is_ok() {
+                result = Err(Error::Teardown(stage, os_status));
+            }
+        };
+
+        unsafe {
+            // Only output units can be stopped, so check that the unit is actually running first.
+            let is_running: Result<u32, Error> = self.get_property(
+                kAudioOutputUnitProperty_IsRunning,
+                Scope::Global,
+                Element::Output,
+            );
+            if matches!(is_running, Ok(running) if running != 0) {
+                check(TeardownStage::Stop, AudioOutputUnitStop(self.instance));
+            }
+            check(
+                TeardownStage::Uninitialize,
+                AudioUnitUninitialize(self.instance),
+            );
+
+            self.free_render_callbacks();
+            self.free_input_callback();
+
+            // Hold the lock while disposing so that no `PropertyListener` can attempt to remove
+            // itself from the instance in the meantime.
+            let mut disposed = self.disposed.lock().unwrap_or_else(|e| e.into_inner());
+            check(
+                TeardownStage::Dispose,
+                AudioComponentInstanceDispose(self.instance),
+            );
+            *disposed = true;
+        }
+        self.instance = ptr::null_mut();
+
+        result
+    }
+
     /// Set the **AudioUnit**'s sample rate.
     ///
     /// **Available** in iOS 2.0 and later.
@@ -359,6 +514,12 @@ impl AudioUnit {
     pub fn input_stream_format(&self) -> Result<StreamFormat, Error> {
         self.stream_format(Scope::Output, Element::Input)
     }
+
+    /// The number of **Element**s (buses) within the given scope of the **AudioUnit**.
+    pub fn element_count(&self, scope: Scope) -> Result<u32, Error> {
+        let id = kAudioUnitProperty_ElementCount;
+        self.get_property(id, scope, Element(0))
+    }
 }
 
 impl AsRef<InnerAudioUnit> for AudioUnit {
@@ -377,21 +538,11 @@ unsafe impl Send for AudioUnit {}
 
 impl Drop for AudioUnit {
     fn drop(&mut self) {
-        unsafe {
-            use crate::error;
-
-            // We don't want to panic in `drop`, so we'll ignore returned errors.
-            //
-            // A user should explicitly terminate the `AudioUnit` if they want to handle errors (we
-            // still need to provide a way to actually do that).
-            self.stop().ok();
-            error::Error::from_os_status(AudioUnitUninitialize(self.instance)).ok();
-
-            self.free_render_callback();
-            self.free_input_callback();
-
-            error::Error::from_os_status(AudioComponentInstanceDispose(self.instance)).ok();
-        }
+        // We don't want to panic in `drop`, so we'll ignore returned errors.
+        //
+        // A user should explicitly terminate the `AudioUnit` via `close` if they want to handle
+        // errors.
+        self.teardown().ok();
     }
 }
 
@@ -435,7 +586,7 @@ pub unsafe fn set_property<T>(
         })
         .unwrap_or_else(|| (::std::ptr::null(), 0));
     let scope = scope as c_uint;
-    let elem = elem as c_uint;
+    let elem = elem.0;
     try_os_status!(AudioUnitSetProperty(au, id, scope, elem, data_ptr, size));
     Ok(())
 }
@@ -465,7 +616,7 @@ pub unsafe fn get_property<T>(
     elem: Element,
 ) -> Result<T, Error> {
     let scope = scope as c_uint;
-    let elem = elem as c_uint;
+    let elem = elem.0;
     let mut size = ::std::mem::size_of::<T>() as u32;
     let mut data_uninit = ::std::mem::MaybeUninit::<T>::uninit();
     let data_ptr = NonNull::from(&mut data_uninit).cast::<c_void>();
@@ -477,6 +628,133 @@ pub unsafe fn get_property<T>(
     Ok(data)
 }
 
+/// Gets the size in bytes of an **AudioUnit** property along with whether or not the property is
+/// writable, returned as `(size, writable)`.
+///
+/// Parameters
+/// ----------
+///
+/// - **au**: The AudioUnit instance.
+/// - **id**: The identifier of the property.
+/// - **scope**: The audio unit scope for the property.
+/// - **elem**: The audio unit element for the property.
+///
+/// Safety
+/// ------
+/// This function is safe as long as the **au** parameter is a valid pointer to an AudioUnit instance.
+/// The caller is responsible for ensuring this.
+/// For a safer alternative, consider using an [AudioUnit] instance
+/// and calling the associated [AudioUnit::property_info] method.
+pub unsafe fn get_property_info(
+    au: InnerAudioUnit,
+    id: u32,
+    scope: Scope,
+    elem: Element,
+) -> Result<(u32, bool), Error> {
+    let scope = scope as c_uint;
+    let elem = elem.0;
+    let mut size = 0u32;
+    let mut writable = 0u8;
+    try_os_status!(AudioUnitGetPropertyInfo(
+        au,
+        id,
+        scope,
+        elem,
+        &mut size,
+        &mut writable
+    ));
+    Ok((size, writable != 0))
+}
+
+/// Gets the value of a variable-length **AudioUnit** property as a `Vec<T>`.
+///
+/// Parameters
+/// ----------
+///
+/// - **au**: The AudioUnit instance.
+/// - **id**: The identifier of the property.
+/// - **scope**: The audio unit scope for the property.
+/// - **elem**: The audio unit element for the property.
+///
+/// Safety
+/// ------
+/// This function is safe as long as the **au** parameter is a valid pointer to an AudioUnit instance
+/// and `T` is a valid representation of the elements of the property.
+/// The caller is responsible for ensuring this.
+/// For a safer alternative, consider using an [AudioUnit] instance
+/// and calling the associated [AudioUnit::get_property_vec] method.
+pub unsafe fn get_property_vec<T>(
+    au: InnerAudioUnit,
+    id: u32,
+    scope: Scope,
+    elem: Element,
+) -> Result<Vec<T>, Error> {
+    const BAD_SIZE: Error = Error::AudioUnit(error::audio_unit::Error::InvalidPropertyValue);
+
+    let (size, _writable) = get_property_info(au, id, scope, elem)?;
+    let len = property_element_count(size, mem::size_of::<T>()).ok_or(BAD_SIZE)?;
+    let mut data: Vec<T> = Vec::with_capacity(len);
+    if len == 0 {
+        return Ok(data);
+    }
+
+    let scope = scope as c_uint;
+    let elem = elem.0;
+    let mut size = (len * mem::size_of::<T>()) as u32;
+    let data_ptr = NonNull::new_unchecked(data.as_mut_ptr()).cast::<c_void>();
+    let size_ptr = NonNull::from(&mut size);
+    try_os_status!(AudioUnitGetProperty(
+        au, id, scope, elem, data_ptr, size_ptr
+    ));
+
+    // The audio unit may write less data than it initially reported.
+    let written = property_element_count(size, mem::size_of::<T>()).ok_or(BAD_SIZE)?;
+    data.set_len(written.min(len));
+    Ok(data)
+}
+
+/// The number of elements of `element_size` bytes described by a property of `data_size` bytes.
+///
+/// Returns `None` if `element_size` is zero or if `data_size` is not a multiple of it.
+fn property_element_count(data_size: u32, element_size: usize) -> Option<usize> {
+    let data_size = data_size as usize;
+    if element_size == 0 || data_size % element_size != 0 {
+        return None;
+    }
+    Some(data_size / element_size)
+}
+
+#[test]
+fn test_property_element_count() {
+    assert_eq!(property_element_count(0, 4), Some(0));
+    assert_eq!(property_element_count(16, 4), Some(4));
+    assert_eq!(property_element_count(16, 1), Some(16));
+    assert_eq!(property_element_count(40, 40), Some(1));
+    assert_eq!(property_element_count(18, 4), None);
+    assert_eq!(property_element_count(3, 8), None);
+    assert_eq!(property_element_count(8, 0), None);
+}
+
+#[test]
+fn test_scope_from_u32() {
+    for &scope in &[
+        Scope::Global,
+        Scope::Input,
+        Scope::Output,
+        Scope::Group,
+        Scope::Part,
+        Scope::Note,
+        Scope::Layer,
+        Scope::LayerItem,
+    ] {
+        assert_eq!(
+            Scope::from_u32(scope as u32).map(|s| s as u32),
+            Some(scope as u32)
+        );
+    }
+    assert!(Scope::from_u32(0xFFFF).is_none());
+}
+
 /// Gets the value of a specified audio session property.
 ///
 /// **Available** in iOS 2.0 and later, and tvOS 9.0 and later.
@@ -500,3 +778,10 @@ pub fn audio_session_get_property<T>(id: u32) -> Result<T, Error> {
         Ok(data)
     }
 }
+
+#[test]
+fn test_element() {
+    assert_eq!(Element::Output, Element(0));
+    assert_eq!(Element::Input, Element(1));
+    assert_eq!(Element::from(7), Element(7));
+}
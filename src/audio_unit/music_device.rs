@@ -0,0 +1,130 @@
+//! Delivering MIDI events to **MusicDevice** units, e.g. the `Sampler` and `DLSSynth`.
+//!
+//! Messages are described by the [**MidiMessage**](./enum.MidiMessage) type and sent with
+//! [**AudioUnit::send_midi**](../struct.AudioUnit#method.send_midi). The message type and its
+//! encoding live in the platform independent [**midi**](../../midi/index.html) module.
+//!
+//! Find the original MusicDevice reference [here](https://developer.apple.com/documentation/audiotoolbox/music_device).
+
+use objc2_audio_toolbox::{
+    kMusicNoteEvent_UseGroupInstrument, MusicDeviceMIDIEvent, MusicDeviceNoteParams,
+    MusicDeviceStartNote, MusicDeviceStdNoteParams, MusicDeviceStopNote, MusicDeviceSysEx,
+};
+
+use super::AudioUnit;
+use crate::error::Error;
+use std::ptr::NonNull;
+
+pub use crate::midi::{MidiMessage, PITCH_BEND_CENTER, PITCH_BEND_MAX, SYSEX_END, SYSEX_START};
+
+/// A note started with [**AudioUnit::start_note**](../struct.AudioUnit#method.start_note).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NoteInstance {
+    /// The group (typically the MIDI channel) in which the note was started.
+    pub group: u32,
+    /// The identifier assigned to the note by the music device.
+    pub id: u32,
+}
+
+impl AudioUnit {
+    /// Deliver a MIDI message to a **MusicDevice** unit.
+    ///
+    /// When called from the audio unit's render thread, `offset_frames` schedules the message
+    /// that many sample frames into the next render. Otherwise it should be `0`. System exclusive
+    /// messages cannot be scheduled and are delivered immediately, ignoring `offset_frames`.
+    ///
+    /// Parameters
+    /// ----------
+    ///
+    /// - **message**: The message to deliver.
+    /// - **offset_frames**: The offset into the next render at which the message is applied.
+    pub fn send_midi(&mut self, message: &MidiMessage, offset_frames: u32) -> Result<(), Error> {
+        match message.short_message().map_err(Error::Midi)? {
+            Some([status, data1, data2]) => unsafe {
+                Error::from_os_status(MusicDeviceMIDIEvent(
+                    self.instance,
+                    status as u32,
+                    data1 as u32,
+                    data2 as u32,
+                    offset_frames,
+                ))?;
+            },
+            None => {
+                let bytes = message.to_bytes().map_err(Error::Midi)?;
+                let data = NonNull::new(bytes.as_ptr() as *mut u8).expect("empty sysex");
+                unsafe {
+                    Error::from_os_status(MusicDeviceSysEx(
+                        self.instance,
+                        data,
+                        bytes.len() as u32,
+                    ))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Deliver each of the given MIDI messages at its respective frame offset.
+    ///
+    /// Stops at the first message that fails to be delivered.
+    pub fn send_midi_events<'a, I>(&mut self, events: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = (u32, &'a MidiMessage)>,
+    {
+        for (offset_frames, message) in events {
+            self.send_midi(message, offset_frames)?;
+        }
+        Ok(())
+    }
+
+    /// Start a note using the instrument of the given group via `MusicDeviceStartNote`.
+    ///
+    /// Unlike a MIDI **NoteOn**, the pitch and velocity may be fractional, e.g. a pitch of `60.5`
+    /// is middle C plus 50 cents.
+    ///
+    /// Parameters
+    /// ----------
+    ///
+    /// - **group**: The group in which to start the note, typically the MIDI channel.
+    /// - **pitch**: The pitch of the note as a MIDI note number.
+    /// - **velocity**: The velocity of the note in the range `0.0..128.0`.
+    /// - **offset_frames**: The offset into the next render at which the note starts.
+    pub fn start_note(
+        &mut self,
+        group: u32,
+        pitch: f32,
+        velocity: f32,
+        offset_frames: u32,
+    ) -> Result<NoteInstance, Error> {
+        let params = MusicDeviceStdNoteParams {
+            argCount: 2,
+            mPitch: pitch,
+            mVelocity: velocity,
+        };
+        let mut id = 0;
+        unsafe {
+            Error::from_os_status(MusicDeviceStartNote(
+                self.instance,
+                kMusicNoteEvent_UseGroupInstrument,
+                group,
+                NonNull::from(&mut id),
+                offset_frames,
+                NonNull::from(&params).cast::<MusicDeviceNoteParams>(),
+            ))?;
+        }
+        Ok(NoteInstance { group, id })
+    }
+
+    /// Stop a note previously started with [**AudioUnit::start_note**](#method.start_note).
+    pub fn stop_note(&mut self, note: NoteInstance, offset_frames: u32) -> Result<(), Error> {
+        unsafe {
+            Error::from_os_status(MusicDeviceStopNote(
+                self.instance,
+                note.group,
+                note.id,
+                offset_frames,
+            ))?;
+        }
+        Ok(())
+    }
+}
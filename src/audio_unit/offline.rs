@@ -0,0 +1,234 @@
+//! Pulling audio from an **AudioUnit** directly via `AudioUnitRender`.
+//!
+//! Rather than having an I/O unit pull audio in realtime, the methods within this module allow
+//! the caller to pull audio from any unit (e.g. a `GenericOutput` unit or an effect at the end of
+//! a chain) at whatever pace they like. This is useful for testing and for bouncing audio to disk.
+//!
+//! Audio is fed into the start of the chain as usual, e.g. using
+//! [**AudioUnit::set_render_callback**](../struct.AudioUnit#method.set_render_callback). The
+//! `time_stamp` passed to that callback carries the sample time of the slice being rendered.
+
+use objc2_audio_toolbox::{AudioUnitRender, AudioUnitRenderActionFlags};
+use objc2_core_audio_types::{AudioTimeStamp, AudioTimeStampFlags};
+
+use super::buffer_list::OwnedBufferList;
+use super::render_callback::action_flags::ActionFlags;
+use super::AudioUnit;
+use crate::error::Error;
+use std::mem;
+use std::ptr::NonNull;
+
+/// A single slice of an offline render.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OfflineSlice {
+    /// The sample time of the first frame of the slice.
+    pub sample_time: f64,
+    /// The number of frames within the slice.
+    pub frames: u32,
+    /// Whether or not this is the final slice of the render.
+    pub is_last: bool,
+}
+
+impl OfflineSlice {
+    /// The action flags with which the slice should be rendered.
+    ///
+    /// Every slice is flagged with `OFFLINE_RENDER`, while the final slice is additionally flagged
+    /// with `OFFLINE_COMPLETE`.
+    pub fn action_flags(&self) -> ActionFlags {
+        if self.is_last {
+            ActionFlags::OFFLINE_RENDER | ActionFlags::OFFLINE_COMPLETE
+        } else {
+            ActionFlags::OFFLINE_RENDER
+        }
+    }
+
+    /// A timestamp for the slice with a valid sample time.
+    pub fn time_stamp(&self) -> AudioTimeStamp {
+        sample_time_stamp(self.sample_time)
+    }
+}
+
+/// Splits an offline render of `total_frames` frames into slices of at most `slice_frames`
+/// frames, keeping track of the sample time of each.
+#[derive(Clone, Debug)]
+pub struct OfflineClock {
+    total_frames: u64,
+    slice_frames: u32,
+    position: u64,
+}
+
+impl OfflineClock {
+    /// Construct a clock starting at sample time `0`.
+    ///
+    /// Returns an `Error` if `slice_frames` is zero.
+    pub fn new(total_frames: u64, slice_frames: u32) -> Result<Self, Error> {
+        if slice_frames == 0 {
+            return Err(Error::ZeroFrameCapacity);
+        }
+        Ok(OfflineClock {
+            total_frames,
+            slice_frames,
+            position: 0,
+        })
+    }
+
+    /// The number of frames that have been yielded so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+impl Iterator for OfflineClock {
+    type Item = OfflineSlice;
+    fn next(&mut self) -> Option<OfflineSlice> {
+        let remaining = self.total_frames - self.position;
+        if remaining == 0 {
+            return None;
+        }
+        let frames = remaining.min(self.slice_frames as u64) as u32;
+        let slice = OfflineSlice {
+            sample_time: self.position as f64,
+            frames,
+            is_last: remaining == frames as u64,
+        };
+        self.position += frames as u64;
+        Some(slice)
+    }
+}
+
+/// A timestamp with only its sample time set.
+pub fn sample_time_stamp(sample_time: f64) -> AudioTimeStamp {
+    // `AudioTimeStamp` is plain old data for which all zeroes is a valid value.
+    let mut time_stamp: AudioTimeStamp = unsafe { mem::zeroed() };
+    time_stamp.mSampleTime = sample_time;
+    time_stamp.mFlags = AudioTimeStampFlags::SampleTimeValid;
+    time_stamp
+}
+
+impl AudioUnit {
+    /// Render `frames` frames from the given output bus into `buffers`.
+    ///
+    /// The buffers are prepared to hold `frames` frames before rendering. Returns an `Error` if
+    /// `frames` exceeds their capacity.
+    ///
+    /// Parameters
+    /// ----------
+    ///
+    /// - **bus**: The output bus to render.
+    /// - **frames**: The number of frames to render.
+    /// - **time_stamp**: The timestamp of the first frame to render.
+    /// - **buffers**: The buffers into which audio is rendered.
+    pub fn render(
+        &mut self,
+        bus: u32,
+        frames: u32,
+        time_stamp: &AudioTimeStamp,
+        buffers: &mut OwnedBufferList,
+    ) -> Result<(), Error> {
+        self.render_with_flags(bus, frames, time_stamp, buffers, ActionFlags::empty())
+            .map(|_| ())
+    }
+
+    /// The same as [**AudioUnit::render**](#method.render) but with the given action flags,
+    /// returning the flags as modified by the audio unit.
+    pub fn render_with_flags(
+        &mut self,
+        bus: u32,
+        frames: u32,
+        time_stamp: &AudioTimeStamp,
+        buffers: &mut OwnedBufferList,
+        flags: ActionFlags,
+    ) -> Result<ActionFlags, Error> {
+        buffers.prepare(frames)?;
+        let mut raw_flags = AudioUnitRenderActionFlags(flags.bits());
+        unsafe {
+            Error::from_os_status(AudioUnitRender(
+                self.instance,
+                &mut raw_flags,
+                NonNull::from(time_stamp),
+                bus,
+                frames,
+                buffers.as_non_null(),
+            ))?;
+        }
+        Ok(ActionFlags::from_bits_truncate(raw_flags.0))
+    }
+
+    /// Render `total_frames` frames from the given output bus offline, one slice at a time.
+    ///
+    /// Slices are at most `buffers.frame_capacity()` frames long and are rendered with the
+    /// `OFFLINE_RENDER` action flag, with the final slice additionally flagged with
+    /// `OFFLINE_COMPLETE`. Each timestamp carries the sample time of the slice, starting from `0`.
+    ///
+    /// After each slice is rendered, `f` is called with the slice and the buffers containing its
+    /// audio. Rendering stops at the first error returned by either the audio unit or `f`.
+    ///
+    /// Returns an `Error` if the buffers cannot hold a single frame.
+    pub fn render_offline<F>(
+        &mut self,
+        bus: u32,
+        total_frames: u64,
+        buffers: &mut OwnedBufferList,
+        mut f: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(&OfflineSlice, &OwnedBufferList) -> Result<(), Error>,
+    {
+        for slice in OfflineClock::new(total_frames, buffers.frame_capacity())? {
+            let time_stamp = slice.time_stamp();
+            self.render_with_flags(
+                bus,
+                slice.frames,
+                &time_stamp,
+                buffers,
+                slice.action_flags(),
+            )?;
+            f(&slice, buffers)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_offline_clock() {
+    let slices: Vec<_> = OfflineClock::new(1000, 256).unwrap().collect();
+    let frames: Vec<u32> = slices.iter().map(|s| s.frames).collect();
+    let times: Vec<f64> = slices.iter().map(|s| s.sample_time).collect();
+    assert_eq!(frames, vec![256, 256, 256, 232]);
+    assert_eq!(times, vec![0.0, 256.0, 512.0, 768.0]);
+    assert_eq!(
+        slices.iter().map(|s| s.is_last).collect::<Vec<_>>(),
+        vec![false, false, false, true]
+    );
+
+    let exact: Vec<_> = OfflineClock::new(512, 256).unwrap().collect();
+    assert_eq!(exact.len(), 2);
+    assert!(exact[1].is_last);
+    assert_eq!(OfflineClock::new(0, 256).unwrap().count(), 0);
+    assert!(matches!(
+        OfflineClock::new(10, 0),
+        Err(Error::ZeroFrameCapacity)
+    ));
+
+    let mut clock = OfflineClock::new(10, 4).unwrap();
+    clock.next();
+    assert_eq!(clock.position(), 4);
+}
+
+#[test]
+fn test_offline_slice_flags() {
+    let mut clock = OfflineClock::new(300, 256).unwrap();
+    let first = clock.next().unwrap();
+    let last = clock.next().unwrap();
+    assert_eq!(
+        first.action_flags().bits(),
+        ActionFlags::OFFLINE_RENDER.bits()
+    );
+    assert!(last.action_flags().contains(ActionFlags::OFFLINE_RENDER));
+    assert!(last.action_flags().contains(ActionFlags::OFFLINE_COMPLETE));
+    assert_eq!(last.time_stamp().mSampleTime, 256.0);
+    assert_eq!(
+        last.time_stamp().mFlags,
+        AudioTimeStampFlags::SampleTimeValid
+    );
+}
@@ -0,0 +1,555 @@
+//! Getting, setting and scheduling the parameters of an **AudioUnit**.
+//!
+//! Unlike properties, parameters are intended to be changed by the user while the audio unit is
+//! rendering, e.g. the cutoff frequency of a low pass filter or the volume of a mixer input.
+//!
+//! Find the original Audio Unit Parameters reference [here](https://developer.apple.com/documentation/audiotoolbox/audio_unit_parameters).
+
+use objc2_audio_toolbox::{
+    kAudioUnitProperty_ParameterInfo, kAudioUnitProperty_ParameterList, AUParameterEventType,
+    AudioUnit as InnerAudioUnit, AudioUnitGetParameter, AudioUnitParameterInfo,
+    AudioUnitParameterOptions, AudioUnitParameterUnit, AudioUnitSetParameter,
+};
+use objc2_core_foundation::{CFRetained, CFString};
+
+use super::{AudioUnit, Element, Scope};
+use crate::error::Error;
+use crate::OSStatus;
+use std::os::raw::{c_char, c_uint};
+use std::ptr::NonNull;
+
+/// The unit in which the value of a parameter is expressed.
+///
+/// Original documentation [here](https://developer.apple.com/documentation/audiotoolbox/audiounitparameterunit).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ParameterUnit {
+    /// Untyped value generally between 0.0 and 1.0.
+    Generic = AudioUnitParameterUnit::Generic.0 as isize,
+    /// Takes an integer value (good for menu selections).
+    Indexed = AudioUnitParameterUnit::Indexed.0 as isize,
+    /// 0.0 means false, non-zero means true.
+    Boolean = AudioUnitParameterUnit::Boolean.0 as isize,
+    /// Usually from 0 -> 100, sometimes -50 -> +50.
+    Percent = AudioUnitParameterUnit::Percent.0 as isize,
+    /// Absolute or relative time.
+    Seconds = AudioUnitParameterUnit::Seconds.0 as isize,
+    /// One sample frame equals (1.0 / sample rate) seconds.
+    SampleFrames = AudioUnitParameterUnit::SampleFrames.0 as isize,
+    /// -180 to 180 degrees.
+    Phase = AudioUnitParameterUnit::Phase.0 as isize,
+    /// Rate multiplier, for playback speed, etc. (e.g. 2.0 == twice as fast).
+    Rate = AudioUnitParameterUnit::Rate.0 as isize,
+    /// Absolute frequency/pitch in cycles/second.
+    Hertz = AudioUnitParameterUnit::Hertz.0 as isize,
+    /// Unit of relative pitch.
+    Cents = AudioUnitParameterUnit::Cents.0 as isize,
+    /// Useful for coarse detuning.
+    RelativeSemiTones = AudioUnitParameterUnit::RelativeSemiTones.0 as isize,
+    /// Absolute pitch as defined in the MIDI spec (exact freq may depend on tuning table).
+    MidiNoteNumber = AudioUnitParameterUnit::MIDINoteNumber.0 as isize,
+    /// A generic MIDI controller value from 0 -> 127.
+    MidiController = AudioUnitParameterUnit::MIDIController.0 as isize,
+    /// Logarithmic relative gain.
+    Decibels = AudioUnitParameterUnit::Decibels.0 as isize,
+    /// Linear relative gain.
+    LinearGain = AudioUnitParameterUnit::LinearGain.0 as isize,
+    /// -180 to 180 degrees, similar to phase but more general (good for 3D coord system).
+    Degrees = AudioUnitParameterUnit::Degrees.0 as isize,
+    /// 0 -> 1, crossfade mix two sources according to sqrt(x) and sqrt(1.0 - x).
+    EqualPowerCrossfade = AudioUnitParameterUnit::EqualPowerCrossfade.0 as isize,
+    /// 0.0 -> 1.0, pow(x, 3.0) -> linear gain to simulate a reasonable mixer channel fader
+    /// response.
+    MixerFaderCurve1 = AudioUnitParameterUnit::MixerFaderCurve1.0 as isize,
+    /// Standard left to right mixer pan.
+    Pan = AudioUnitParameterUnit::Pan.0 as isize,
+    /// Distance measured in meters.
+    Meters = AudioUnitParameterUnit::Meters.0 as isize,
+    /// Absolute frequency measurement: if f is freq in hertz then absoluteCents =
+    /// 1200 * log2(f / 440) + 6900.
+    AbsoluteCents = AudioUnitParameterUnit::AbsoluteCents.0 as isize,
+    /// Octaves in relative pitch where a value of 1 is equal to 1200 cents.
+    Octaves = AudioUnitParameterUnit::Octaves.0 as isize,
+    /// Beats per minute, ie tempo.
+    Bpm = AudioUnitParameterUnit::BPM.0 as isize,
+    /// Time relative to tempo, i.e. 1.0 at 120 BPM would equal 1/2 a second.
+    Beats = AudioUnitParameterUnit::Beats.0 as isize,
+    /// Parameter is expressed in milliseconds.
+    Milliseconds = AudioUnitParameterUnit::Milliseconds.0 as isize,
+    /// For compression, expansion ratio, etc.
+    Ratio = AudioUnitParameterUnit::Ratio.0 as isize,
+    /// This is used if none of the above units apply, in which case the unit name is provided by
+    /// the audio unit.
+    CustomUnit = AudioUnitParameterUnit::CustomUnit.0 as isize,
+}
+
+impl ParameterUnit {
+    /// Create a `ParameterUnit` from its `u32` representation.
+    pub fn from_u32(u: u32) -> Option<ParameterUnit> {
+        let unit = match AudioUnitParameterUnit(u) {
+            AudioUnitParameterUnit::Generic => ParameterUnit::Generic,
+            AudioUnitParameterUnit::Indexed => ParameterUnit::Indexed,
+            AudioUnitParameterUnit::Boolean => ParameterUnit::Boolean,
+            AudioUnitParameterUnit::Percent => ParameterUnit::Percent,
+            AudioUnitParameterUnit::Seconds => ParameterUnit::Seconds,
+            AudioUnitParameterUnit::SampleFrames => ParameterUnit::SampleFrames,
+            AudioUnitParameterUnit::Phase => ParameterUnit::Phase,
+            AudioUnitParameterUnit::Rate => ParameterUnit::Rate,
+            AudioUnitParameterUnit::Hertz => ParameterUnit::Hertz,
+            AudioUnitParameterUnit::Cents => ParameterUnit::Cents,
+            AudioUnitParameterUnit::RelativeSemiTones => ParameterUnit::RelativeSemiTones,
+            AudioUnitParameterUnit::MIDINoteNumber => ParameterUnit::MidiNoteNumber,
+            AudioUnitParameterUnit::MIDIController => ParameterUnit::MidiController,
+            AudioUnitParameterUnit::Decibels => ParameterUnit::Decibels,
+            AudioUnitParameterUnit::LinearGain => ParameterUnit::LinearGain,
+            AudioUnitParameterUnit::Degrees => ParameterUnit::Degrees,
+            AudioUnitParameterUnit::EqualPowerCrossfade => ParameterUnit::EqualPowerCrossfade,
+            AudioUnitParameterUnit::MixerFaderCurve1 => ParameterUnit::MixerFaderCurve1,
+            AudioUnitParameterUnit::Pan => ParameterUnit::Pan,
+            AudioUnitParameterUnit::Meters => ParameterUnit::Meters,
+            AudioUnitParameterUnit::AbsoluteCents => ParameterUnit::AbsoluteCents,
+            AudioUnitParameterUnit::Octaves => ParameterUnit::Octaves,
+            AudioUnitParameterUnit::BPM => ParameterUnit::Bpm,
+            AudioUnitParameterUnit::Beats => ParameterUnit::Beats,
+            AudioUnitParameterUnit::Milliseconds => ParameterUnit::Milliseconds,
+            AudioUnitParameterUnit::Ratio => ParameterUnit::Ratio,
+            AudioUnitParameterUnit::CustomUnit => ParameterUnit::CustomUnit,
+            _ => return None,
+        };
+        Some(unit)
+    }
+}
+
+bitflags! {
+    /// Flags describing the behaviour of a parameter.
+    ///
+    /// Original documentation [here](https://developer.apple.com/documentation/audiotoolbox/audiounitparameteroptions).
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct ParameterFlags: u32 {
+        /// The host should release the parameter's `cfNameString`, and the `unitName` of a custom
+        /// unit, when it is finished with them.
+        const CF_NAME_RELEASE = AudioUnitParameterOptions::Flag_CFNameRelease.0;
+        /// The parameter should not be saved in presets.
+        const OMIT_FROM_PRESETS = AudioUnitParameterOptions::Flag_OmitFromPresets.0;
+        /// Hosts should plot the history of this parameter's value.
+        const PLOT_HISTORY = AudioUnitParameterOptions::Flag_PlotHistory.0;
+        /// The parameter is a read-only meter.
+        const METER_READ_ONLY = AudioUnitParameterOptions::Flag_MeterReadOnly.0;
+        /// The parameter belongs to a clump of related parameters.
+        const HAS_CLUMP = AudioUnitParameterOptions::Flag_HasClump.0;
+        /// The parameter's values can be described by strings.
+        const VALUES_HAVE_STRINGS = AudioUnitParameterOptions::Flag_ValuesHaveStrings.0;
+        /// The parameter should be displayed on a logarithmic scale.
+        const DISPLAY_LOGARITHMIC = AudioUnitParameterOptions::Flag_DisplayLogarithmic.0;
+        /// The parameter's value has a high resolution.
+        const IS_HIGH_RESOLUTION = AudioUnitParameterOptions::Flag_IsHighResolution.0;
+        /// Changing the parameter is not safe to do while rendering.
+        const NON_REAL_TIME = AudioUnitParameterOptions::Flag_NonRealTime.0;
+        /// The parameter supports ramped parameter events.
+        const CAN_RAMP = AudioUnitParameterOptions::Flag_CanRamp.0;
+        /// The parameter is intended for expert users.
+        const EXPERT_MODE = AudioUnitParameterOptions::Flag_ExpertMode.0;
+        /// The parameter's `cfNameString` field is valid.
+        const HAS_CF_NAME_STRING = AudioUnitParameterOptions::Flag_HasCFNameString.0;
+        /// Changing the parameter changes the value of all elements in the scope.
+        const IS_GLOBAL_META = AudioUnitParameterOptions::Flag_IsGlobalMeta.0;
+        /// Changing the parameter changes the value of other parameters of the same element.
+        const IS_ELEMENT_META = AudioUnitParameterOptions::Flag_IsElementMeta.0;
+        /// The parameter can be read.
+        const IS_READABLE = AudioUnitParameterOptions::Flag_IsReadable.0;
+        /// The parameter can be written.
+        const IS_WRITABLE = AudioUnitParameterOptions::Flag_IsWritable.0;
+    }
+}
+
+/// A rust representation of the `AudioUnitParameterInfo` describing a single parameter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterInfo {
+    /// The identifier of the parameter.
+    pub id: u32,
+    /// The human-readable name of the parameter.
+    pub name: String,
+    /// The unit in which the parameter's value is expressed, or `None` if the unit is unknown to
+    /// this crate.
+    pub unit: Option<ParameterUnit>,
+    /// The name of the unit, for parameters whose unit is
+    /// [**ParameterUnit::CustomUnit**](./enum.ParameterUnit#variant.CustomUnit).
+    pub custom_unit_name: Option<String>,
+    /// The minimum value of the parameter.
+    pub min_value: f32,
+    /// The maximum value of the parameter.
+    pub max_value: f32,
+    /// The default value of the parameter.
+    pub default_value: f32,
+    /// Flags describing the behaviour of the parameter.
+    pub flags: ParameterFlags,
+}
+
+impl ParameterInfo {
+    /// Whether or not the given value lies within the parameter's range.
+    pub fn contains(&self, value: f32) -> bool {
+        value >= self.min_value && value <= self.max_value
+    }
+
+    /// Clamp the given value to the parameter's range.
+    pub fn clamp(&self, value: f32) -> f32 {
+        value.max(self.min_value).min(self.max_value)
+    }
+}
+
+/// The kind of change described by a [**ParameterEvent**](./struct.ParameterEvent).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ParameterChange {
+    /// Change the parameter to `value` at `buffer_offset` sample frames into the next render
+    /// buffer.
+    Immediate { buffer_offset: u32, value: f32 },
+    /// Linearly ramp the parameter from `start_value` to `end_value` over `duration_in_frames`
+    /// sample frames, starting at `start_buffer_offset` sample frames relative to the next render
+    /// buffer.
+    ///
+    /// Only parameters with the `CAN_RAMP` flag support ramped events.
+    Ramped {
+        start_buffer_offset: i32,
+        duration_in_frames: u32,
+        start_value: f32,
+        end_value: f32,
+    },
+}
+
+/// A parameter change scheduled with
+/// [**AudioUnit::schedule_parameters**](../struct.AudioUnit#method.schedule_parameters).
+#[derive(Copy, Clone, Debug)]
+pub struct ParameterEvent {
+    /// The identifier of the parameter.
+    pub id: u32,
+    /// The audio unit scope for the parameter.
+    pub scope: Scope,
+    /// The audio unit element for the parameter.
+    pub element: Element,
+    /// The change to apply to the parameter.
+    pub change: ParameterChange,
+}
+
+impl ParameterEvent {
+    /// An event changing the parameter to `value` at `buffer_offset` sample frames into the next
+    /// render buffer.
+    pub fn immediate(
+        id: u32,
+        scope: Scope,
+        element: Element,
+        buffer_offset: u32,
+        value: f32,
+    ) -> Self {
+        ParameterEvent {
+            id,
+            scope,
+            element,
+            change: ParameterChange::Immediate {
+                buffer_offset,
+                value,
+            },
+        }
+    }
+
+    /// An event linearly ramping the parameter from `start_value` to `end_value` over
+    /// `duration_in_frames` sample frames.
+    pub fn ramped(
+        id: u32,
+        scope: Scope,
+        element: Element,
+        start_buffer_offset: i32,
+        duration_in_frames: u32,
+        start_value: f32,
+        end_value: f32,
+    ) -> Self {
+        ParameterEvent {
+            id,
+            scope,
+            element,
+            change: ParameterChange::Ramped {
+                start_buffer_offset,
+                duration_in_frames,
+                start_value,
+                end_value,
+            },
+        }
+    }
+
+    fn to_raw(self) -> AudioUnitParameterEvent {
+        let (event_type, event_values) = match self.change {
+            ParameterChange::Immediate {
+                buffer_offset,
+                value,
+            } => (
+                AUParameterEventType::ParameterEvent_Immediate,
+                AudioUnitParameterEventValues {
+                    immediate: AudioUnitParameterEventImmediate {
+                        bufferOffset: buffer_offset,
+                        value,
+                    },
+                },
+            ),
+            ParameterChange::Ramped {
+                start_buffer_offset,
+                duration_in_frames,
+                start_value,
+                end_value,
+            } => (
+                AUParameterEventType::ParameterEvent_Ramped,
+                AudioUnitParameterEventValues {
+                    ramp: AudioUnitParameterEventRamp {
+                        startBufferOffset: start_buffer_offset,
+                        durationInFrames: duration_in_frames,
+                        startValue: start_value,
+                        endValue: end_value,
+                    },
+                },
+            ),
+        };
+        AudioUnitParameterEvent {
+            scope: self.scope as c_uint,
+            element: self.element.0,
+            parameter: self.id,
+            eventType: event_type,
+            eventValues: event_values,
+        }
+    }
+}
+
+// `AudioUnitParameterEvent` and `AudioUnitScheduleParameters` are not provided by
+// `objc2_audio_toolbox`, so we declare them ourselves as they appear in `AUComponent.h`.
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+#[allow(non_snake_case)]
+struct AudioUnitParameterEventRamp {
+    startBufferOffset: i32,
+    durationInFrames: u32,
+    startValue: f32,
+    endValue: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+#[allow(non_snake_case)]
+struct AudioUnitParameterEventImmediate {
+    bufferOffset: u32,
+    value: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+union AudioUnitParameterEventValues {
+    ramp: AudioUnitParameterEventRamp,
+    immediate: AudioUnitParameterEventImmediate,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+#[allow(non_snake_case)]
+struct AudioUnitParameterEvent {
+    scope: u32,
+    element: u32,
+    parameter: u32,
+    eventType: AUParameterEventType,
+    eventValues: AudioUnitParameterEventValues,
+}
+
+#[link(name = "AudioToolbox", kind = "framework")]
+extern "C-unwind" {
+    fn AudioUnitScheduleParameters(
+        in_unit: InnerAudioUnit,
+        in_parameter_event: *const AudioUnitParameterEvent,
+        in_num_param_events: u32,
+    ) -> OSStatus;
+}
+
+impl AudioUnit {
+    /// Gets the current value of a parameter of the **AudioUnit**.
+    ///
+    /// Parameters
+    /// ----------
+    ///
+    /// - **id**: The identifier of the parameter.
+    /// - **scope**: The audio unit scope for the parameter.
+    /// - **elem**: The audio unit element for the parameter.
+    pub fn parameter(&self, id: u32, scope: Scope, elem: Element) -> Result<f32, Error> {
+        let mut value = 0.0f32;
+        unsafe {
+            Error::from_os_status(AudioUnitGetParameter(
+                self.instance,
+                id,
+                scope as c_uint,
+                elem.0,
+                NonNull::from(&mut value),
+            ))?;
+        }
+        Ok(value)
+    }
+
+    /// Sets the value of a parameter of the **AudioUnit**, taking effect immediately.
+    ///
+    /// To change a parameter at a specific sample offset within the next render buffer, or to
+    /// ramp between two values, see
+    /// [**AudioUnit::schedule_parameters**](./struct.AudioUnit#method.schedule_parameters).
+    ///
+    /// Parameters
+    /// ----------
+    ///
+    /// - **id**: The identifier of the parameter.
+    /// - **scope**: The audio unit scope for the parameter.
+    /// - **elem**: The audio unit element for the parameter.
+    /// - **value**: The new value of the parameter.
+    pub fn set_parameter(
+        &mut self,
+        id: u32,
+        scope: Scope,
+        elem: Element,
+        value: f32,
+    ) -> Result<(), Error> {
+        unsafe {
+            Error::from_os_status(AudioUnitSetParameter(
+                self.instance,
+                id,
+                scope as c_uint,
+                elem.0,
+                value,
+                0,
+            ))
+        }
+    }
+
+    /// Schedules one or more parameter changes to be applied during the next render cycle of the
+    /// **AudioUnit**.
+    ///
+    /// This is intended to be called from within a render callback (or render notification) prior
+    /// to the audio unit rendering, so that changes are applied with sample accuracy.
+    pub fn schedule_parameters(&mut self, events: &[ParameterEvent]) -> Result<(), Error> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let raw_events: Vec<AudioUnitParameterEvent> =
+            events.iter().map(|event| event.to_raw()).collect();
+        unsafe {
+            Error::from_os_status(AudioUnitScheduleParameters(
+                self.instance,
+                raw_events.as_ptr(),
+                raw_events.len() as u32,
+            ))
+        }
+    }
+
+    /// Returns the identifiers of all parameters of the **AudioUnit** within the given scope.
+    pub fn parameter_ids(&self, scope: Scope) -> Result<Vec<u32>, Error> {
+        self.get_property_vec(kAudioUnitProperty_ParameterList, scope, Element::Output)
+    }
+
+    /// Returns the description of a single parameter of the **AudioUnit**.
+    pub fn parameter_info(&self, id: u32, scope: Scope) -> Result<ParameterInfo, Error> {
+        // Note: the element of the `ParameterInfo` property is the parameter identifier.
+        let info: AudioUnitParameterInfo =
+            self.get_property(kAudioUnitProperty_ParameterInfo, scope, Element(id))?;
+
+        let flags = ParameterFlags::from_bits_truncate(info.flags.0);
+        // With `CF_NAME_RELEASE` set, the host owns both the name and the custom unit name.
+        let release = flags.contains(ParameterFlags::CF_NAME_RELEASE);
+        let name = match NonNull::new(info.cfNameString as *mut CFString) {
+            Some(cf_name) if flags.contains(ParameterFlags::HAS_CF_NAME_STRING) => unsafe {
+                cf_string(cf_name, release)
+            },
+            _ => name_from_c_chars(&info.name),
+        };
+        let unit = ParameterUnit::from_u32(info.unit.0);
+        let custom_unit_name = match NonNull::new(info.unitName as *mut CFString) {
+            Some(unit_name) if unit == Some(ParameterUnit::CustomUnit) => unsafe {
+                Some(cf_string(unit_name, release))
+            },
+            _ => None,
+        };
+
+        Ok(ParameterInfo {
+            id,
+            name,
+            unit,
+            custom_unit_name,
+            min_value: info.minValue,
+            max_value: info.maxValue,
+            default_value: info.defaultValue,
+            flags,
+        })
+    }
+
+    /// Returns the description of every parameter of the **AudioUnit** within the given scope.
+    pub fn parameters(&self, scope: Scope) -> Result<Vec<ParameterInfo>, Error> {
+        self.parameter_ids(scope)?
+            .into_iter()
+            .map(|id| self.parameter_info(id, scope))
+            .collect()
+    }
+}
+
+/// Read a `CFStringRef` returned within an `AudioUnitParameterInfo`, releasing it if `release` is
+/// set.
+///
+/// # Safety
+///
+/// `string` must be a valid `CFString`, owned by the caller if `release` is set.
+unsafe fn cf_string(string: NonNull<CFString>, release: bool) -> String {
+    if release {
+        CFRetained::from_raw(string).to_string()
+    } else {
+        string.as_ref().to_string()
+    }
+}
+
+/// Read a fixed-size, possibly nul-terminated C string into a `String`.
+fn name_from_c_chars(chars: &[c_char]) -> String {
+    let bytes: Vec<u8> = chars
+        .iter()
+        .take_while(|&&c| c != 0)
+        .map(|&c| c as u8)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[test]
+fn test_name_from_c_chars() {
+    let mut chars = [0 as c_char; 52];
+    for (c, b) in chars.iter_mut().zip(b"Cutoff Frequency") {
+        *c = *b as c_char;
+    }
+    assert_eq!(name_from_c_chars(&chars), "Cutoff Frequency");
+    assert_eq!(name_from_c_chars(&[0; 4]), "");
+    let unterminated = [b'a' as c_char, b'b' as c_char];
+    assert_eq!(name_from_c_chars(&unterminated), "ab");
+}
+
+#[test]
+fn test_parameter_unit_from_u32() {
+    assert_eq!(ParameterUnit::from_u32(8), Some(ParameterUnit::Hertz));
+    assert_eq!(ParameterUnit::from_u32(13), Some(ParameterUnit::Decibels));
+    for unit in 0..=26 {
+        let parsed = ParameterUnit::from_u32(unit).expect("known parameter unit");
+        assert_eq!(parsed as u32, unit);
+    }
+    assert_eq!(ParameterUnit::from_u32(1000), None);
+}
+
+#[test]
+fn test_parameter_info_range() {
+    let info = ParameterInfo {
+        id: 0,
+        name: "Cutoff Frequency".to_string(),
+        unit: Some(ParameterUnit::Hertz),
+        custom_unit_name: None,
+        min_value: 10.0,
+        max_value: 22050.0,
+        default_value: 6900.0,
+        flags: ParameterFlags::IS_READABLE | ParameterFlags::IS_WRITABLE,
+    };
+    assert!(info.contains(10.0));
+    assert!(info.contains(22050.0));
+    assert!(!info.contains(5.0));
+    assert_eq!(info.clamp(5.0), 10.0);
+    assert_eq!(info.clamp(30000.0), 22050.0);
+    assert_eq!(info.clamp(440.0), 440.0);
+}
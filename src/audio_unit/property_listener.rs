@@ -0,0 +1,151 @@
+//! Notifications for changes to the properties of an **AudioUnit**.
+//!
+//! See [**AudioUnit::add_property_listener**](../struct.AudioUnit#method.add_property_listener).
+
+use objc2_audio_toolbox::{
+    AudioUnit as InnerAudioUnit, AudioUnitAddPropertyListener, AudioUnitElement,
+    AudioUnitPropertyID, AudioUnitRemovePropertyListenerWithUserData, AudioUnitScope,
+};
+
+use super::{AudioUnit, Element, Scope};
+use crate::error::Error;
+use crate::shared_callback::{self, SharedCallback};
+use std::os::raw::c_void;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex, TryLockError};
+
+/// Describes a change to a property of an **AudioUnit**.
+#[derive(Copy, Clone, Debug)]
+pub struct PropertyChange {
+    /// The identifier of the property whose value has changed.
+    pub id: u32,
+    /// The scope of the property, or `None` if the audio unit reported an unknown scope.
+    pub scope: Option<Scope>,
+    /// The element of the property.
+    pub element: Element,
+}
+
+/// The closure type called upon property changes.
+pub type PropertyListenerFn = dyn FnMut(PropertyChange) + Send + 'static;
+
+/// A property listener registered with an **AudioUnit**.
+///
+/// The listener is removed and its closure freed when the **PropertyListener** is dropped. It is
+/// fine for the listener to outlive the **AudioUnit**, in which case dropping it only frees the
+/// closure. A call already in progress on another thread keeps the closure alive until it
+/// returns.
+///
+/// The listener may be dropped from within a listener closure. Should the **AudioUnit** be
+/// disposing of itself meanwhile, the listener is then left to be removed along with it.
+pub struct PropertyListener {
+    instance: InnerAudioUnit,
+    id: u32,
+    // Only read by calls in progress, through `user_data`.
+    _callback: SharedCallback<PropertyListenerFn>,
+    user_data: *mut c_void,
+    disposed: Arc<Mutex<bool>>,
+}
+
+unsafe impl Send for PropertyListener {}
+
+impl PropertyListener {
+    /// The identifier of the property being listened to.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl Drop for PropertyListener {
+    fn drop(&mut self) {
+        // Within a listener closure, this thread may be disposing of the instance, or another
+        // thread may be waiting for the closure to return while disposing of it. Rather than
+        // deadlock, removal is skipped, as the instance no longer reaches the freed closure.
+        let disposed = if shared_callback::in_call() {
+            match self.disposed.try_lock() {
+                Ok(disposed) => Some(disposed),
+                Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+                Err(TryLockError::WouldBlock) => None,
+            }
+        } else {
+            Some(self.disposed.lock().unwrap_or_else(|e| e.into_inner()))
+        };
+        if disposed.as_deref() == Some(&false) {
+            // We don't want to panic in `drop`, so we'll ignore returned errors.
+            unsafe {
+                Error::from_os_status(AudioUnitRemovePropertyListenerWithUserData(
+                    self.instance,
+                    self.id,
+                    Some(property_listener_proc),
+                    self.user_data,
+                ))
+                .ok();
+            }
+        }
+        // The closure itself is freed along with `_callback`, or by the last call still in
+        // progress.
+    }
+}
+
+impl AudioUnit {
+    /// Register a closure to be called whenever the property with the given identifier changes.
+    ///
+    /// The closure is passed the scope and element of the property that changed, and is called on
+    /// whichever thread the audio unit reports the change from. Use a channel within the closure
+    /// in order to handle changes elsewhere. Changes made from within the closure itself do not
+    /// call it again.
+    ///
+    /// The listener remains registered until the returned
+    /// [**PropertyListener**](./property_listener/struct.PropertyListener) is dropped.
+    ///
+    /// Parameters
+    /// ----------
+    ///
+    /// - **id**: The identifier of the property, e.g. `kAudioUnitProperty_StreamFormat`.
+    /// - **f**: The closure to call upon each change.
+    pub fn add_property_listener<F>(&mut self, id: u32, f: F) -> Result<PropertyListener, Error>
+    where
+        F: FnMut(PropertyChange) + Send + 'static,
+    {
+        let callback = SharedCallback::<PropertyListenerFn>::new(Box::new(f));
+        let user_data = callback.user_data();
+        unsafe {
+            let status = AudioUnitAddPropertyListener(
+                self.instance,
+                id,
+                Some(property_listener_proc),
+                user_data,
+            );
+            if let Err(err) = Error::from_os_status(status) {
+                SharedCallback::<PropertyListenerFn>::release(user_data);
+                return Err(err);
+            }
+        }
+        Ok(PropertyListener {
+            instance: self.instance,
+            id,
+            _callback: callback,
+            user_data,
+            disposed: self.disposed.clone(),
+        })
+    }
+}
+
+/// Callback procedure that will be called each time a listened-to property changes.
+extern "C-unwind" fn property_listener_proc(
+    in_ref_con: NonNull<c_void>,
+    _in_unit: InnerAudioUnit,
+    in_id: AudioUnitPropertyID,
+    in_scope: AudioUnitScope,
+    in_element: AudioUnitElement,
+) {
+    let change = PropertyChange {
+        id: in_id,
+        scope: Scope::from_u32(in_scope),
+        element: Element(in_element),
+    };
+    unsafe {
+        SharedCallback::<PropertyListenerFn>::with(in_ref_con.as_ptr(), true, |callback| {
+            callback(change)
+        });
+    }
+}
@@ -1,18 +1,21 @@
 use objc2_audio_toolbox::{
     kAudioOutputUnitProperty_SetInputCallback, kAudioUnitProperty_SetRenderCallback,
-    kAudioUnitProperty_StreamFormat, AURenderCallbackStruct, AudioUnitRender,
+    kAudioUnitProperty_StreamFormat, AURenderCallbackStruct, AudioUnit as InnerAudioUnit,
+    AudioUnitAddRenderNotify, AudioUnitRemoveRenderNotify, AudioUnitRender,
     AudioUnitRenderActionFlags,
 };
 use objc2_core_audio_types::{AudioBuffer, AudioBufferList, AudioTimeStamp};
 
 use super::audio_format::LinearPcmFlags;
-use super::{AudioUnit, Element, Scope};
+use super::{AudioUnit, Element, Scope, StreamFormat};
 use crate::error::{self, Error};
+use crate::shared_callback::SharedCallback;
 use crate::OSStatus;
 use std::mem;
 use std::os::raw::c_void;
 use std::ptr::NonNull;
 use std::slice;
+use std::sync::{Arc, Mutex};
 
 pub use self::action_flags::ActionFlags;
 pub use self::data::Data;
@@ -35,6 +38,16 @@ pub struct InputProcFnWrapper {
     callback: Box<InputProcFn>,
 }
 
+/// The closure type wrapping the callback given to `add_render_notify`.
+type RenderNotifyFn = dyn FnMut(
+        NonNull<AudioUnitRenderActionFlags>,
+        NonNull<AudioTimeStamp>,
+        u32,
+        u32,
+        *mut AudioBufferList,
+    ) -> OSStatus
+    + Send;
+
 /// Arguments given to the render callback function.
 #[derive(Debug)]
 pub struct Args<D> {
@@ -55,247 +68,66 @@ pub struct Args<D> {
     pub flags: action_flags::Handle,
 }
 
-/// Format specific render callback data.
-pub mod data {
-    use objc2_core_audio_types::AudioBuffer;
-    use objc2_core_audio_types::AudioBufferList;
-
-    use super::super::Sample;
-    use super::super::StreamFormat;
-    use crate::audio_unit::audio_format::LinearPcmFlags;
-    use std::marker::PhantomData;
-    use std::slice;
-
-    /// Audio data wrappers specific to the `AudioUnit`'s `AudioFormat`.
-    pub trait Data {
-        /// Check whether the stream format matches this type of data.
-        fn does_stream_format_match(stream_format: &StreamFormat) -> bool;
-        /// We must be able to construct Self from arguments given to the `input_proc`.
-        /// # Safety
-        /// TODO document how to use this function safely.
-        unsafe fn from_input_proc_args(num_frames: u32, io_data: *mut AudioBufferList) -> Self;
-    }
-
-    /// A raw pointer to the audio data so that the user may handle it themselves.
-    #[derive(Debug)]
-    pub struct Raw {
-        pub data: *mut AudioBufferList,
-    }
-
-    impl Data for Raw {
-        fn does_stream_format_match(_: &StreamFormat) -> bool {
-            true
-        }
-        unsafe fn from_input_proc_args(_num_frames: u32, io_data: *mut AudioBufferList) -> Self {
-            Raw { data: io_data }
-        }
-    }
-
-    /// An interleaved linear PCM buffer with samples of type `S`.
-    pub struct Interleaved<S: 'static> {
-        /// The audio buffer.
-        pub buffer: &'static mut [S],
-        pub channels: usize,
-        sample_format: PhantomData<S>,
-    }
-
-    /// An interleaved linear PCM buffer with samples stored as plain bytes.
-    pub struct InterleavedBytes<S: 'static> {
-        /// The audio buffer.
-        pub buffer: &'static mut [u8],
-        pub channels: usize,
-        sample_format: PhantomData<S>,
-    }
-
-    /// A wrapper around the pointer to the `mBuffers` array.
-    pub struct NonInterleaved<S> {
-        /// The list of audio buffers.
-        buffers: &'static mut [AudioBuffer],
-        /// The number of frames in each channel.
-        frames: usize,
-        sample_format: PhantomData<S>,
-    }
-
-    /// An iterator produced by a `NonInterleaved`, yielding a reference to each channel.
-    pub struct Channels<'a, S: 'a> {
-        buffers: slice::Iter<'a, AudioBuffer>,
-        frames: usize,
-        sample_format: PhantomData<S>,
-    }
-
-    /// An iterator produced by a `NonInterleaved`, yielding a mutable reference to each channel.
-    pub struct ChannelsMut<'a, S: 'a> {
-        buffers: slice::IterMut<'a, AudioBuffer>,
-        frames: usize,
-        sample_format: PhantomData<S>,
-    }
-
-    unsafe impl<S> Send for NonInterleaved<S> where S: Send {}
-
-    impl<'a, S> Iterator for Channels<'a, S> {
-        type Item = &'a [S];
-        #[allow(non_snake_case)]
-        fn next(&mut self) -> Option<Self::Item> {
-            self.buffers.next().map(
-                |&AudioBuffer {
-                     mNumberChannels,
-                     mData,
-                     ..
-                 }| {
-                    let len = mNumberChannels as usize * self.frames;
-                    let ptr = mData as *mut S;
-                    unsafe { slice::from_raw_parts(ptr, len) }
-                },
-            )
-        }
-    }
-
-    impl<'a, S> Iterator for ChannelsMut<'a, S> {
-        type Item = &'a mut [S];
-        #[allow(non_snake_case)]
-        fn next(&mut self) -> Option<Self::Item> {
-            self.buffers.next().map(
-                |&mut AudioBuffer {
-                     mNumberChannels,
-                     mData,
-                     ..
-                 }| {
-                    let len = mNumberChannels as usize * self.frames;
-                    let ptr = mData as *mut S;
-                    unsafe { slice::from_raw_parts_mut(ptr, len) }
-                },
-            )
-        }
-    }
-
-    impl<S> NonInterleaved<S> {
-        /// An iterator yielding a reference to each channel in the array.
-        pub fn channels(&self) -> Channels<'_, S> {
-            Channels {
-                buffers: self.buffers.iter(),
-                frames: self.frames,
-                sample_format: PhantomData,
-            }
-        }
-
-        /// An iterator yielding a mutable reference to each channel in the array.
-        pub fn channels_mut(&mut self) -> ChannelsMut<'_, S> {
-            ChannelsMut {
-                buffers: self.buffers.iter_mut(),
-                frames: self.frames,
-                sample_format: PhantomData,
-            }
-        }
-    }
-
-    // Implementation for a non-interleaved linear PCM audio format.
-    impl<S> Data for NonInterleaved<S>
-    where
-        S: Sample,
-    {
-        fn does_stream_format_match(stream_format: &StreamFormat) -> bool {
-            stream_format
-                .flags
-                .contains(LinearPcmFlags::IS_NON_INTERLEAVED)
-                && S::sample_format().does_match_flags(stream_format.flags)
-        }
-
-        #[allow(non_snake_case)]
-        unsafe fn from_input_proc_args(frames: u32, io_data: *mut AudioBufferList) -> Self {
-            let ptr = (*io_data).mBuffers.as_ptr() as *mut AudioBuffer;
-            let len = (*io_data).mNumberBuffers as usize;
-            let buffers = slice::from_raw_parts_mut(ptr, len);
-            NonInterleaved {
-                buffers,
-                frames: frames as usize,
-                sample_format: PhantomData,
-            }
-        }
-    }
-
-    // Implementation for an interleaved linear PCM audio format.
-    impl<S> Data for Interleaved<S>
-    where
-        S: Sample,
-    {
-        fn does_stream_format_match(stream_format: &StreamFormat) -> bool {
-            !stream_format
-                .flags
-                .contains(LinearPcmFlags::IS_NON_INTERLEAVED)
-                && S::sample_format().does_match_flags(stream_format.flags)
-        }
-
-        #[allow(non_snake_case)]
-        unsafe fn from_input_proc_args(frames: u32, io_data: *mut AudioBufferList) -> Self {
-            // // We're expecting a single interleaved buffer which will be the first in the array.
-            let AudioBuffer {
-                mNumberChannels,
-                mDataByteSize,
-                mData,
-            } = (*io_data).mBuffers[0];
-            // // Ensure that the size of the data matches the size of the sample format
-            // // multiplied by the number of frames.
-            // //
-            // // TODO: Return an Err instead of `panic`ing.
-            let buffer_len = frames as usize * mNumberChannels as usize;
-            let expected_size = ::std::mem::size_of::<S>() * buffer_len;
-            assert!(mDataByteSize as usize == expected_size);
-
-            let buffer: &mut [S] = {
-                let buffer_ptr = mData as *mut S;
-                slice::from_raw_parts_mut(buffer_ptr, buffer_len)
-            };
+/// The phase of rendering during which a render notification is delivered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RenderPhase {
+    /// The audio unit is about to render. The buffers do not yet contain its output.
+    Pre,
+    /// The audio unit has finished rendering. The buffers contain its output.
+    Post,
+}
 
-            Interleaved {
-                buffer,
-                channels: mNumberChannels as usize,
-                sample_format: PhantomData,
-            }
+impl RenderPhase {
+    /// Determine the phase from the action flags given to a render notification.
+    pub fn from_flags(flags: &ActionFlags) -> Option<Self> {
+        if flags.contains(ActionFlags::PRE_RENDER) {
+            Some(RenderPhase::Pre)
+        } else if flags.contains(ActionFlags::POST_RENDER) {
+            Some(RenderPhase::Post)
+        } else {
+            None
         }
     }
+}
 
-    // Implementation for an interleaved linear PCM audio format using plain bytes.
-    impl<S> Data for InterleavedBytes<S>
-    where
-        S: Sample,
-    {
-        fn does_stream_format_match(stream_format: &StreamFormat) -> bool {
-            !stream_format
-                .flags
-                .contains(LinearPcmFlags::IS_NON_INTERLEAVED)
-                && S::sample_format().does_match_flags(stream_format.flags)
-        }
+/// A render notification registered with an **AudioUnit**.
+///
+/// The notification is removed and its closure freed when the **RenderNotify** is dropped. It is
+/// fine for the **RenderNotify** to outlive the **AudioUnit**, in which case dropping it only
+/// frees the closure. A notification already in progress on the render thread keeps the closure
+/// alive until it returns.
+pub struct RenderNotify {
+    instance: InnerAudioUnit,
+    // Only read by notifications in progress, through `user_data`.
+    _callback: SharedCallback<RenderNotifyFn>,
+    user_data: *mut c_void,
+    disposed: Arc<Mutex<bool>>,
+}
 
-        #[allow(non_snake_case)]
-        unsafe fn from_input_proc_args(frames: u32, io_data: *mut AudioBufferList) -> Self {
-            // // We're expecting a single interleaved buffer which will be the first in the array.
-            let AudioBuffer {
-                mNumberChannels,
-                mDataByteSize,
-                mData,
-            } = (*io_data).mBuffers[0];
-            // // Ensure that the size of the data matches the size of the sample format
-            // // multiplied by the number of frames.
-            // //
-            // // TODO: Return an Err instead of `panic`ing.
-            let buffer_len = frames as usize * mNumberChannels as usize;
-            let expected_size = ::std::mem::size_of::<S>() * buffer_len;
-            assert!(mDataByteSize as usize == expected_size);
-
-            let buffer: &mut [u8] = {
-                let buffer_ptr = mData as *mut u8;
-                slice::from_raw_parts_mut(buffer_ptr, mDataByteSize as usize)
-            };
+unsafe impl Send for RenderNotify {}
 
-            InterleavedBytes {
-                buffer,
-                channels: mNumberChannels as usize,
-                sample_format: PhantomData,
+impl Drop for RenderNotify {
+    fn drop(&mut self) {
+        let disposed = self.disposed.lock().unwrap_or_else(|e| e.into_inner());
+        if !*disposed {
+            // We don't want to panic in `drop`, so we'll ignore returned errors.
+            unsafe {
+                Error::from_os_status(AudioUnitRemoveRenderNotify(
+                    self.instance,
+                    Some(render_notify_proc),
+                    self.user_data,
+                ))
+                .ok();
             }
         }
+        // The closure itself is freed along with `_callback`, or by the last notification still
+        // in progress.
     }
 }
 
+/// Format specific render callback data.
+pub use crate::format::data;
+
 pub mod action_flags {
     use objc2_audio_toolbox::AudioUnitRenderActionFlags;
 
@@ -472,7 +304,11 @@ pub mod action_flags {
 
 impl AudioUnit {
     /// Pass a render callback (aka "Input Procedure") to the **AudioUnit**.
-    pub fn set_render_callback<F, D>(&mut self, mut f: F) -> Result<(), Error>
+    ///
+    /// The callback feeds input bus `0`. See
+    /// [**AudioUnit::set_render_callback_for_bus**](#method.set_render_callback_for_bus) for
+    /// units with multiple input buses.
+    pub fn set_render_callback<F, D>(&mut self, f: F) -> Result<(), Error>
     where
         F: FnMut(Args<D>) -> Result<(), ()> + 'static,
         D: Data,
@@ -480,7 +316,33 @@ impl AudioUnit {
         // First, we'll retrieve the stream format so that we can ensure that the given callback
         // format matches the audio unit's format.
         let stream_format = self.output_stream_format()?;
+        self.set_render_callback_with_format(0, stream_format, f)
+    }
+
+    /// Pass a render callback (aka "Input Procedure") feeding the given input bus of the
+    /// **AudioUnit**, e.g. one of the inputs of a `MixerType::MultiChannelMixer`.
+    ///
+    /// The callback format is checked against the stream format of the input bus. Any callback
+    /// previously set for the same bus is replaced and freed.
+    pub fn set_render_callback_for_bus<F, D>(&mut self, bus: u32, f: F) -> Result<(), Error>
+    where
+        F: FnMut(Args<D>) -> Result<(), ()> + 'static,
+        D: Data,
+    {
+        let stream_format = self.stream_format(Scope::Input, Element(bus))?;
+        self.set_render_callback_with_format(bus, stream_format, f)
+    }
 
+    fn set_render_callback_with_format<F, D>(
+        &mut self,
+        bus: u32,
+        stream_format: StreamFormat,
+        mut f: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(Args<D>) -> Result<(), ()> + 'static,
+        D: Data,
+    {
         // If the stream format does not match, return an error indicating this.
         if !D::does_stream_format_match(&stream_format) {
             return Err(Error::RenderCallbackBufferFormatDoesNotMatchAudioUnitStreamFormat);
@@ -530,15 +392,21 @@ impl AudioUnit {
             inputProcRefCon: input_proc_fn_wrapper_ptr,
         };
 
-        self.set_property(
+        let status = self.set_property(
             kAudioUnitProperty_SetRenderCallback,
             Scope::Input,
-            Element::Output,
+            Element(bus),
             Some(&render_callback),
-        )?;
+        );
+        if let Err(err) = status {
+            let _: Box<InputProcFnWrapper> =
+                unsafe { Box::from_raw(input_proc_fn_wrapper_ptr as *mut InputProcFnWrapper) };
+            return Err(err);
+        }
 
-        self.free_render_callback();
-        self.maybe_render_callback = Some(input_proc_fn_wrapper_ptr as *mut InputProcFnWrapper);
+        self.free_render_callback_for_bus(bus);
+        self.render_callbacks
+            .push((bus, input_proc_fn_wrapper_ptr as *mut InputProcFnWrapper));
         Ok(())
     }
 
@@ -720,16 +588,117 @@ impl AudioUnit {
         Ok(())
     }
 
-    /// Retrieves ownership over the render callback and returns it where it can be re-used or
-    /// safely dropped.
+    /// Register a closure to be notified both before and after the **AudioUnit** renders.
+    ///
+    /// Unlike a render callback, a render notification does not replace the source of the unit's
+    /// audio, making it suitable for metering or tapping the output of any unit. The closure is
+    /// given the unit's output buffers along with the [**RenderPhase**](./enum.RenderPhase) of
+    /// the notification. During the `Post` phase, the buffers contain the rendered output.
+    ///
+    /// The closure is only notified of renders of the output buses whose stream format matches
+    /// `D` upon registration, as given by `bus_number`. Returns an `Error` if the format of bus
+    /// `0`, the unit's output format, does not match.
+    ///
+    /// The notification remains registered until the returned
+    /// [**RenderNotify**](./struct.RenderNotify) is dropped.
+    pub fn add_render_notify<F, D>(&mut self, mut f: F) -> Result<RenderNotify, Error>
+    where
+        F: FnMut(Args<D>, RenderPhase) -> Result<(), ()> + Send + 'static,
+        D: Data,
+    {
+        // The notification is given the same buffers as those rendered by the unit, so check
+        // that the callback format matches the format of each output bus it may be given.
+        let stream_format = self.output_stream_format()?;
+        if !D::does_stream_format_match(&stream_format) {
+            return Err(Error::RenderCallbackBufferFormatDoesNotMatchAudioUnitStreamFormat);
+        }
+        let bus_count = self.element_count(Scope::Output).unwrap_or(1).max(1);
+        let matching_buses: Vec<bool> = (0..bus_count)
+            .map(|bus| {
+                bus == 0
+                    || self
+                        .stream_format(Scope::Output, Element(bus))
+                        .is_ok_and(|format| D::does_stream_format_match(&format))
+            })
+            .collect();
+
+        let input_proc_fn = move |io_action_flags: NonNull<AudioUnitRenderActionFlags>,
+                                  in_time_stamp: NonNull<AudioTimeStamp>,
+                                  in_bus_number: u32,
+                                  in_number_frames: u32,
+                                  io_data: *mut AudioBufferList|
+              -> OSStatus {
+            let flags = action_flags::Handle::from_ptr(io_action_flags.as_ptr());
+            let phase = match RenderPhase::from_flags(&flags.get()) {
+                Some(phase) => phase,
+                None => return 0,
+            };
+            if !matching_buses
+                .get(in_bus_number as usize)
+                .copied()
+                .unwrap_or(false)
+            {
+                return 0;
+            }
+            let args = unsafe {
+                let data = D::from_input_proc_args(in_number_frames, io_data);
+                Args {
+                    data,
+                    time_stamp: in_time_stamp.read(),
+                    flags,
+                    bus_number: in_bus_number,
+                    num_frames: in_number_frames as usize,
+                }
+            };
+
+            match f(args, phase) {
+                Ok(()) => 0,
+                Err(()) => error::Error::Unspecified.as_os_status(),
+            }
+        };
+
+        let callback = SharedCallback::<RenderNotifyFn>::new(Box::new(input_proc_fn));
+        let user_data = callback.user_data();
+
+        unsafe {
+            let status =
+                AudioUnitAddRenderNotify(self.instance, Some(render_notify_proc), user_data);
+            if let Err(err) = Error::from_os_status(status) {
+                SharedCallback::<RenderNotifyFn>::release(user_data);
+                return Err(err);
+            }
+        }
+
+        Ok(RenderNotify {
+            instance: self.instance,
+            _callback: callback,
+            user_data,
+            disposed: self.disposed.clone(),
+        })
+    }
+
+    /// Retrieves ownership over the render callback of input bus `0` and returns it where it can
+    /// be re-used or safely dropped.
     pub fn free_render_callback(&mut self) -> Option<Box<InputProcFnWrapper>> {
-        if let Some(callback) = self.maybe_render_callback.take() {
-            // Here, we transfer ownership of the callback back to the current scope so that it
-            // is dropped and cleaned up. Without this line, we would leak the Boxed callback.
-            let callback: Box<InputProcFnWrapper> = unsafe { Box::from_raw(callback) };
-            return Some(callback);
+        self.free_render_callback_for_bus(0)
+    }
+
+    /// Retrieves ownership over the render callback of the given input bus and returns it where
+    /// it can be re-used or safely dropped.
+    pub fn free_render_callback_for_bus(&mut self, bus: u32) -> Option<Box<InputProcFnWrapper>> {
+        let index = self.render_callbacks.iter().position(|&(b, _)| b == bus)?;
+        let (_, callback) = self.render_callbacks.swap_remove(index);
+        // Here, we transfer ownership of the callback back to the current scope so that it
+        // is dropped and cleaned up. Without this line, we would leak the Boxed callback.
+        let callback: Box<InputProcFnWrapper> = unsafe { Box::from_raw(callback) };
+        Some(callback)
+    }
+
+    /// Frees the render callbacks of all input buses.
+    pub(crate) fn free_render_callbacks(&mut self) {
+        for (_, callback) in self.render_callbacks.drain(..) {
+            let _: Box<InputProcFnWrapper> = unsafe { Box::from_raw(callback) };
         }
-        None
     }
 
     /// Retrieves ownership over the input callback and returns it where it can be re-used or
@@ -762,6 +731,19 @@ impl AudioUnit {
     }
 }
 
+#[test]
+fn test_render_phase_from_flags() {
+    let phase = |flags| RenderPhase::from_flags(&flags);
+    assert_eq!(phase(ActionFlags::PRE_RENDER), Some(RenderPhase::Pre));
+    assert_eq!(phase(ActionFlags::POST_RENDER), Some(RenderPhase::Post));
+    assert_eq!(
+        phase(ActionFlags::POST_RENDER | ActionFlags::POST_RENDER_ERROR),
+        Some(RenderPhase::Post)
+    );
+    assert_eq!(phase(ActionFlags::OUTPUT_IS_SILENCE), None);
+    assert_eq!(phase(ActionFlags::empty()), None);
+}
+
 /// Callback procedure that will be called each time our audio_unit requests audio.
 extern "C-unwind" fn input_proc(
     in_ref_con: NonNull<c_void>,
@@ -780,3 +762,29 @@ extern "C-unwind" fn input_proc(
         io_data,
     )
 }
+
+/// Callback procedure that will be called before and after our audio_unit renders.
+///
+/// The notification is skipped, rather than blocking the render thread, should the closure be
+/// busy on another thread.
+extern "C-unwind" fn render_notify_proc(
+    in_ref_con: NonNull<c_void>,
+    io_action_flags: NonNull<AudioUnitRenderActionFlags>,
+    in_time_stamp: NonNull<AudioTimeStamp>,
+    in_bus_number: u32,
+    in_number_frames: u32,
+    io_data: *mut AudioBufferList,
+) -> OSStatus {
+    unsafe {
+        SharedCallback::<RenderNotifyFn>::with(in_ref_con.as_ptr(), false, |callback| {
+            callback(
+                io_action_flags,
+                in_time_stamp,
+                in_bus_number,
+                in_number_frames,
+                io_data,
+            )
+        })
+    }
+    .unwrap_or(0)
+}
@@ -0,0 +1,469 @@
+//! An optional typestate API for the **AudioUnit** lifecycle.
+//!
+//! The dynamic [**AudioUnit**](../struct.AudioUnit) allows `initialize`, `uninitialize`, `start`
+//! and `stop` to be called in any order. The types within this module instead track the state of
+//! the unit at compile time, only exposing the transitions that are valid from each state:
+//!
+//! ```text
+//! AudioUnit<Uninitialized> --initialize--> AudioUnit<Initialized> --start--> RunningAudioUnit
+//!                          <-uninitialize-                        <--stop---
+//! ```
+//!
+//! The sample rate and stream formats, which must be configured before initialization, can only
+//! be set on an `AudioUnit<Uninitialized>`. Other properties, parameters, callbacks and listeners
+//! may be changed in any state, including while running.
+//!
+//! ```no_run
+//! use coreaudio::audio_unit::typestate::{AudioUnit, Uninitialized};
+//! use coreaudio::audio_unit::IOType;
+//!
+//! let unit = AudioUnit::<Uninitialized>::new(IOType::DefaultOutput).unwrap();
+//! let unit = unit.initialize().map_err(|e| e.error).unwrap();
+//! let running = unit.start().map_err(|e| e.error).unwrap();
+//! let _stopped = running.stop().map_err(|e| e.error).unwrap();
+//! ```
+//!
+//! Invalid transitions do not compile. An uninitialized unit cannot be started:
+//!
+//! ```compile_fail
+//! use coreaudio::audio_unit::typestate::{AudioUnit, Uninitialized};
+//! use coreaudio::audio_unit::IOType;
+//!
+//! let unit = AudioUnit::<Uninitialized>::new(IOType::DefaultOutput).unwrap();
+//! let _running = unit.start();
+//! ```
+//!
+//! Nor may the stream format be changed once initialized:
+//!
+//! ```compile_fail
+//! use coreaudio::audio_unit::typestate::{AudioUnit, Initialized};
+//! use coreaudio::audio_unit::{Element, IOType, Scope};
+//!
+//! let mut unit = AudioUnit::<Initialized>::new(IOType::DefaultOutput).unwrap();
+//! let format = unit.output_stream_format().unwrap();
+//! unit.set_stream_format(format, Scope::Input, Element::Output).unwrap();
+//! ```
+//!
+//! A running unit can only be stopped, not uninitialized:
+//!
+//! ```compile_fail
+//! use coreaudio::audio_unit::typestate::{AudioUnit, Initialized};
+//! use coreaudio::audio_unit::IOType;
+//!
+//! let unit = AudioUnit::<Initialized>::new(IOType::DefaultOutput).unwrap();
+//! let running = unit.start().map_err(|e| e.error).unwrap();
+//! let _unit = running.uninitialize();
+//! ```
+//!
+//! Each transition consumes the unit. Upon failure the unit is handed back within a
+//! [**TransitionError**](./struct.TransitionError) in its original state.
+//!
+//! All states dereference to the dynamic **AudioUnit** for read-only access to properties,
+//! parameters and stream formats, and may be converted to it with `into_inner`.
+
+use super::effects::EffectParameter;
+use super::music_device::NoteInstance;
+use super::parameter::ParameterEvent;
+use super::property_listener::{PropertyChange, PropertyListener};
+use super::render_callback::{data::Data, Args, RenderNotify, RenderPhase};
+use super::{AudioUnit as DynamicAudioUnit, Element, Scope, StreamFormat, Type};
+use crate::error::Error;
+use crate::midi::MidiMessage;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+/// The state of an **AudioUnit** that has not yet been initialized.
+#[derive(Copy, Clone, Debug)]
+pub enum Uninitialized {}
+
+/// The state of an **AudioUnit** that has been initialized but is not running.
+#[derive(Copy, Clone, Debug)]
+pub enum Initialized {}
+
+/// An **AudioUnit** whose lifecycle state `S` is tracked at compile time.
+pub struct AudioUnit<S> {
+    unit: DynamicAudioUnit,
+    state: PhantomData<S>,
+}
+
+/// An initialized **AudioUnit** that has been started.
+pub struct RunningAudioUnit {
+    unit: DynamicAudioUnit,
+}
+
+/// A failed state transition.
+///
+/// Contains the unit in the state it was in prior to the attempted transition.
+pub struct TransitionError<U> {
+    /// The unit on which the transition was attempted.
+    pub unit: U,
+    /// The error returned by the audio unit.
+    pub error: Error,
+}
+
+impl<U> TransitionError<U> {
+    /// Consume the error, returning the unit.
+    pub fn into_unit(self) -> U {
+        self.unit
+    }
+}
+
+impl<U> fmt::Debug for TransitionError<U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TransitionError")
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+impl<U> fmt::Display for TransitionError<U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl<U> From<TransitionError<U>> for Error {
+    fn from(err: TransitionError<U>) -> Self {
+        err.error
+    }
+}
+
+fn transition<U, T, F>(
+    mut unit: DynamicAudioUnit,
+    f: F,
+    ok: fn(DynamicAudioUnit) -> T,
+    err: fn(DynamicAudioUnit) -> U,
+) -> Result<T, TransitionError<U>>
+where
+    F: FnOnce(&mut DynamicAudioUnit) -> Result<(), Error>,
+{
+    match f(&mut unit) {
+        Ok(()) => Ok(ok(unit)),
+        Err(error) => Err(TransitionError {
+            unit: err(unit),
+            error,
+        }),
+    }
+}
+
+/// The operations that are valid in every state, forwarded to the dynamic **AudioUnit**.
+macro_rules! any_state_methods {
+    () => {
+        /// Sets the value for some property of the **AudioUnit**.
+        ///
+        /// See [**AudioUnit::set_property**](../struct.AudioUnit#method.set_property).
+        pub fn set_property<T>(
+            &mut self,
+            id: u32,
+            scope: Scope,
+            elem: Element,
+            maybe_data: Option<&T>,
+        ) -> Result<(), Error> {
+            self.unit.set_property(id, scope, elem, maybe_data)
+        }
+
+        /// Sets the value of a parameter of the **AudioUnit**.
+        ///
+        /// See [**AudioUnit::set_parameter**](../struct.AudioUnit#method.set_parameter).
+        pub fn set_parameter(
+            &mut self,
+            id: u32,
+            scope: Scope,
+            elem: Element,
+            value: f32,
+        ) -> Result<(), Error> {
+            self.unit.set_parameter(id, scope, elem, value)
+        }
+
+        /// Sets the value of a typed effect parameter.
+        ///
+        /// See [**AudioUnit::set_effect_parameter**](../struct.AudioUnit#method.set_effect_parameter).
+        pub fn set_effect_parameter<P>(&mut self, param: P, value: f32) -> Result<(), Error>
+        where
+            P: EffectParameter,
+        {
+            self.unit.set_effect_parameter(param, value)
+        }
+
+        /// Schedule changes to parameters of the **AudioUnit**.
+        ///
+        /// See [**AudioUnit::schedule_parameters**](../struct.AudioUnit#method.schedule_parameters).
+        pub fn schedule_parameters(&mut self, events: &[ParameterEvent]) -> Result<(), Error> {
+            self.unit.schedule_parameters(events)
+        }
+
+        /// Pass a render callback (aka "Input Procedure") to the **AudioUnit**.
+        pub fn set_render_callback<F, D>(&mut self, f: F) -> Result<(), Error>
+        where
+            F: FnMut(Args<D>) -> Result<(), ()> + 'static,
+            D: Data,
+        {
+            self.unit.set_render_callback(f)
+        }
+
+        /// Pass a render callback (aka "Input Procedure") feeding the given input bus of the
+        /// **AudioUnit**.
+        pub fn set_render_callback_for_bus<F, D>(&mut self, bus: u32, f: F) -> Result<(), Error>
+        where
+            F: FnMut(Args<D>) -> Result<(), ()> + 'static,
+            D: Data,
+        {
+            self.unit.set_render_callback_for_bus(bus, f)
+        }
+
+        /// Pass an input callback (aka "Input Procedure") to the **AudioUnit**.
+        pub fn set_input_callback<F, D>(&mut self, f: F) -> Result<(), Error>
+        where
+            F: FnMut(Args<D>) -> Result<(), ()> + 'static,
+            D: Data,
+        {
+            self.unit.set_input_callback(f)
+        }
+
+        /// Register a closure to be called before and after each render of the **AudioUnit**.
+        ///
+        /// See [**AudioUnit::add_render_notify**](../struct.AudioUnit#method.add_render_notify).
+        pub fn add_render_notify<F, D>(&mut self, f: F) -> Result<RenderNotify, Error>
+        where
+            F: FnMut(Args<D>, RenderPhase) -> Result<(), ()> + Send + 'static,
+            D: Data,
+        {
+            self.unit.add_render_notify(f)
+        }
+
+        /// Register a closure to be called whenever the property with the given identifier
+        /// changes.
+        ///
+        /// See [**AudioUnit::add_property_listener**](../struct.AudioUnit#method.add_property_listener).
+        pub fn add_property_listener<F>(&mut self, id: u32, f: F) -> Result<PropertyListener, Error>
+        where
+            F: FnMut(PropertyChange) + Send + 'static,
+        {
+            self.unit.add_property_listener(id, f)
+        }
+
+        /// Deliver a MIDI message to a **MusicDevice** unit.
+        ///
+        /// See [**AudioUnit::send_midi**](../struct.AudioUnit#method.send_midi).
+        pub fn send_midi(
+            &mut self,
+            message: &MidiMessage,
+            offset_frames: u32,
+        ) -> Result<(), Error> {
+            self.unit.send_midi(message, offset_frames)
+        }
+
+        /// Deliver each of the given MIDI messages at its respective frame offset.
+        ///
+        /// See [**AudioUnit::send_midi_events**](../struct.AudioUnit#method.send_midi_events).
+        pub fn send_midi_events<'a, I>(&mut self, events: I) -> Result<(), Error>
+        where
+            I: IntoIterator<Item = (u32, &'a MidiMessage)>,
+        {
+            self.unit.send_midi_events(events)
+        }
+
+        /// Start a note on a **MusicDevice** unit.
+        ///
+        /// See [**AudioUnit::start_note**](../struct.AudioUnit#method.start_note).
+        pub fn start_note(
+            &mut self,
+            group: u32,
+            pitch: f32,
+            velocity: f32,
+            offset_frames: u32,
+        ) -> Result<NoteInstance, Error> {
+            self.unit.start_note(group, pitch, velocity, offset_frames)
+        }
+
+        /// Stop a note previously started with `start_note`.
+        ///
+        /// See [**AudioUnit::stop_note**](../struct.AudioUnit#method.stop_note).
+        pub fn stop_note(&mut self, note: NoteInstance, offset_frames: u32) -> Result<(), Error> {
+            self.unit.stop_note(note, offset_frames)
+        }
+    };
+}
+
+impl<S> AudioUnit<S> {
+    fn wrap(unit: DynamicAudioUnit) -> Self {
+        AudioUnit {
+            unit,
+            state: PhantomData,
+        }
+    }
+
+    /// Consume the typestate wrapper, returning the dynamic **AudioUnit**.
+    pub fn into_inner(self) -> DynamicAudioUnit {
+        self.unit
+    }
+
+    any_state_methods!();
+}
+
+impl AudioUnit<Uninitialized> {
+    /// Construct a new, uninitialized **AudioUnit** of the given type.
+    ///
+    /// See [**AudioUnit::new**](../struct.AudioUnit#method.new).
+    pub fn new<T>(ty: T) -> Result<Self, Error>
+    where
+        T: Into<Type>,
+    {
+        DynamicAudioUnit::new_uninitialized(ty).map(AudioUnit::wrap)
+    }
+
+    /// The same as [**AudioUnit::new**](#method.new) but with the given component flags and
+    /// mask.
+    pub fn new_with_flags<T>(ty: T, flags: u32, mask: u32) -> Result<Self, Error>
+    where
+        T: Into<Type>,
+    {
+        DynamicAudioUnit::new_with_flags_uninitialized(ty, flags, mask).map(AudioUnit::wrap)
+    }
+
+    /// Sets the sample rate of the **AudioUnit**'s output stream.
+    pub fn set_sample_rate(&mut self, sample_rate: f64) -> Result<(), Error> {
+        self.unit.set_sample_rate(sample_rate)
+    }
+
+    /// Sets the current **StreamFormat** of the **AudioUnit**.
+    pub fn set_stream_format(
+        &mut self,
+        stream_format: StreamFormat,
+        scope: Scope,
+        element: Element,
+    ) -> Result<(), Error> {
+        self.unit.set_stream_format(stream_format, scope, element)
+    }
+
+    /// Initialize the **AudioUnit**, allocating the resources required for rendering.
+    pub fn initialize(
+        self,
+    ) -> Result<AudioUnit<Initialized>, TransitionError<AudioUnit<Uninitialized>>> {
+        transition(
+            self.unit,
+            DynamicAudioUnit::initialize,
+            AudioUnit::wrap,
+            AudioUnit::wrap,
+        )
+    }
+}
+
+impl AudioUnit<Initialized> {
+    /// Construct and initialize a new **AudioUnit** of the given type.
+    ///
+    /// See [**AudioUnit::new**](../struct.AudioUnit#method.new).
+    pub fn new<T>(ty: T) -> Result<Self, Error>
+    where
+        T: Into<Type>,
+    {
+        DynamicAudioUnit::new(ty).map(AudioUnit::wrap)
+    }
+
+    /// Uninitialize the **AudioUnit** so that its processing characteristics may be changed.
+    pub fn uninitialize(
+        self,
+    ) -> Result<AudioUnit<Uninitialized>, TransitionError<AudioUnit<Initialized>>> {
+        transition(
+            self.unit,
+            DynamicAudioUnit::uninitialize,
+            AudioUnit::wrap,
+            AudioUnit::wrap,
+        )
+    }
+
+    /// Start the **AudioUnit**.
+    pub fn start(self) -> Result<RunningAudioUnit, TransitionError<AudioUnit<Initialized>>> {
+        transition(
+            self.unit,
+            DynamicAudioUnit::start,
+            |unit| RunningAudioUnit { unit },
+            AudioUnit::wrap,
+        )
+    }
+}
+
+impl RunningAudioUnit {
+    /// Consume the typestate wrapper, returning the dynamic **AudioUnit**.
+    pub fn into_inner(self) -> DynamicAudioUnit {
+        self.unit
+    }
+
+    any_state_methods!();
+
+    /// Stop the **AudioUnit**.
+    pub fn stop(self) -> Result<AudioUnit<Initialized>, TransitionError<RunningAudioUnit>> {
+        transition(self.unit, DynamicAudioUnit::stop, AudioUnit::wrap, |unit| {
+            RunningAudioUnit { unit }
+        })
+    }
+}
+
+impl<S> Deref for AudioUnit<S> {
+    type Target = DynamicAudioUnit;
+    fn deref(&self) -> &DynamicAudioUnit {
+        &self.unit
+    }
+}
+
+impl Deref for RunningAudioUnit {
+    type Target = DynamicAudioUnit;
+    fn deref(&self) -> &DynamicAudioUnit {
+        &self.unit
+    }
+}
+
+#[test]
+fn test_transition_error() {
+    let err = TransitionError {
+        unit: "unit",
+        error: Error::Unspecified,
+    };
+    assert_eq!(format!("{}", err), format!("{}", Error::Unspecified));
+    assert_eq!(err.into_unit(), "unit");
+
+    let err = TransitionError {
+        unit: (),
+        error: Error::UnsupportedSampleRate,
+    };
+    assert!(matches!(Error::from(err), Error::UnsupportedSampleRate));
+}
+
+#[test]
+fn test_lifecycle() {
+    use super::render_callback::data::NonInterleaved;
+    use super::IOType;
+    use objc2_audio_toolbox::kAudioOutputUnitProperty_IsRunning;
+
+    let is_running = |unit: &DynamicAudioUnit| -> u32 {
+        unit.get_property(
+            kAudioOutputUnitProperty_IsRunning,
+            Scope::Global,
+            Element::Output,
+        )
+        .unwrap()
+    };
+    let silence = |_: Args<NonInterleaved<f32>>| Ok(());
+
+    let mut unit = AudioUnit::<Uninitialized>::new(IOType::GenericOutput).unwrap();
+    unit.set_sample_rate(48_000.0).unwrap();
+    let mut unit = unit.initialize().unwrap();
+    assert_eq!(unit.sample_rate().unwrap(), 48_000.0);
+    unit.set_render_callback(silence).unwrap();
+    let _listener = unit
+        .add_property_listener(kAudioOutputUnitProperty_IsRunning, |_| ())
+        .unwrap();
+
+    let mut running = unit.start().unwrap();
+    assert_eq!(is_running(&running), 1);
+    running.set_render_callback(silence).unwrap();
+
+    let unit = running.stop().unwrap();
+    assert_eq!(is_running(&unit), 0);
+    let mut unit = unit.uninitialize().unwrap();
+    unit.set_sample_rate(44_100.0).unwrap();
+    let unit = unit.initialize().unwrap();
+    assert_eq!(unit.sample_rate().unwrap(), 44_100.0);
+    let _unit: DynamicAudioUnit = unit.into_inner();
+}
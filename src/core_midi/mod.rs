@@ -0,0 +1,583 @@
+//! A friendly interface to Apple's CoreMIDI API.
+//!
+//! A [**Client**](./struct.Client) is required in order to create ports and virtual endpoints.
+//! The devices, entities and endpoints of the system may be enumerated without one:
+//!
+//! - [**sources**](./fn.sources) and [**destinations**](./fn.destinations) list every endpoint.
+//! - [**devices**](./fn.devices) lists every device, whose entities group related endpoints.
+//!
+//! MIDI 1.0 data is exchanged as a [**PacketBuffer**](./packet/struct.PacketBuffer) and Universal
+//! MIDI Packets as an [**EventBuffer**](./packet/struct.EventBuffer).
+//!
+//! Find the original CoreMIDI reference [here](https://developer.apple.com/documentation/coremidi).
+
+use objc2_core_foundation::{CFRetained, CFString};
+
+use crate::error::Error;
+use crate::shared_callback::SharedCallback;
+use crate::OSStatus;
+use std::os::raw::{c_ulong, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr::{self, NonNull};
+use std::sync::Arc;
+
+pub use self::packet::{EventBuffer, EventList, Packet, PacketBuffer, PacketList, Protocol};
+pub use crate::midi::packet;
+
+// CoreMIDI is not covered by the objc2 crates used elsewhere, so we declare the parts we need
+// ourselves as they appear in `MIDIServices.h`.
+
+type MIDIObjectRef = u32;
+type ItemCount = c_ulong;
+type MIDIReadProc = extern "C-unwind" fn(
+    pktlist: *const u8,
+    read_proc_ref_con: *mut c_void,
+    src_conn_ref_con: *mut c_void,
+);
+type MIDINotifyProc = extern "C-unwind" fn(message: *const c_void, ref_con: *mut c_void);
+
+#[link(name = "CoreMIDI", kind = "framework")]
+extern "C-unwind" {
+    static kMIDIPropertyName: *const CFString;
+    static kMIDIPropertyDisplayName: *const CFString;
+    static kMIDIPropertyManufacturer: *const CFString;
+    static kMIDIPropertyModel: *const CFString;
+    static kMIDIPropertyUniqueID: *const CFString;
+    static kMIDIPropertyOffline: *const CFString;
+
+    fn MIDIClientCreate(
+        name: *const CFString,
+        notify_proc: Option<MIDINotifyProc>,
+        notify_ref_con: *mut c_void,
+        out_client: *mut MIDIObjectRef,
+    ) -> OSStatus;
+    fn MIDIClientDispose(client: MIDIObjectRef) -> OSStatus;
+
+    fn MIDIGetNumberOfDevices() -> ItemCount;
+    fn MIDIGetDevice(index: ItemCount) -> MIDIObjectRef;
+    fn MIDIDeviceGetNumberOfEntities(device: MIDIObjectRef) -> ItemCount;
+    fn MIDIDeviceGetEntity(device: MIDIObjectRef, index: ItemCount) -> MIDIObjectRef;
+    fn MIDIEntityGetNumberOfSources(entity: MIDIObjectRef) -> ItemCount;
+    fn MIDIEntityGetSource(entity: MIDIObjectRef, index: ItemCount) -> MIDIObjectRef;
+    fn MIDIEntityGetNumberOfDestinations(entity: MIDIObjectRef) -> ItemCount;
+    fn MIDIEntityGetDestination(entity: MIDIObjectRef, index: ItemCount) -> MIDIObjectRef;
+    fn MIDIEntityGetDevice(entity: MIDIObjectRef, out_device: *mut MIDIObjectRef) -> OSStatus;
+    fn MIDIGetNumberOfSources() -> ItemCount;
+    fn MIDIGetSource(index: ItemCount) -> MIDIObjectRef;
+    fn MIDIGetNumberOfDestinations() -> ItemCount;
+    fn MIDIGetDestination(index: ItemCount) -> MIDIObjectRef;
+    fn MIDIEndpointGetEntity(endpoint: MIDIObjectRef, out_entity: *mut MIDIObjectRef) -> OSStatus;
+
+    fn MIDIObjectGetStringProperty(
+        obj: MIDIObjectRef,
+        property_id: *const CFString,
+        out_str: *mut *const CFString,
+    ) -> OSStatus;
+    fn MIDIObjectGetIntegerProperty(
+        obj: MIDIObjectRef,
+        property_id: *const CFString,
+        out_value: *mut i32,
+    ) -> OSStatus;
+
+    fn MIDIInputPortCreate(
+        client: MIDIObjectRef,
+        port_name: *const CFString,
+        read_proc: MIDIReadProc,
+        ref_con: *mut c_void,
+        out_port: *mut MIDIObjectRef,
+    ) -> OSStatus;
+    fn MIDIOutputPortCreate(
+        client: MIDIObjectRef,
+        port_name: *const CFString,
+        out_port: *mut MIDIObjectRef,
+    ) -> OSStatus;
+    fn MIDIPortConnectSource(
+        port: MIDIObjectRef,
+        source: MIDIObjectRef,
+        conn_ref_con: *mut c_void,
+    ) -> OSStatus;
+    fn MIDIPortDisconnectSource(port: MIDIObjectRef, source: MIDIObjectRef) -> OSStatus;
+    fn MIDIPortDispose(port: MIDIObjectRef) -> OSStatus;
+    fn MIDISend(port: MIDIObjectRef, dest: MIDIObjectRef, pktlist: *const u8) -> OSStatus;
+    fn MIDISendEventList(port: MIDIObjectRef, dest: MIDIObjectRef, evtlist: *const u32)
+        -> OSStatus;
+
+    fn MIDISourceCreate(
+        client: MIDIObjectRef,
+        name: *const CFString,
+        out_src: *mut MIDIObjectRef,
+    ) -> OSStatus;
+    fn MIDIDestinationCreate(
+        client: MIDIObjectRef,
+        name: *const CFString,
+        read_proc: MIDIReadProc,
+        ref_con: *mut c_void,
+        out_dest: *mut MIDIObjectRef,
+    ) -> OSStatus;
+    fn MIDIReceived(src: MIDIObjectRef, pktlist: *const u8) -> OSStatus;
+    fn MIDIReceivedEventList(src: MIDIObjectRef, evtlist: *const u32) -> OSStatus;
+    fn MIDIEndpointDispose(endpoint: MIDIObjectRef) -> OSStatus;
+}
+
+fn string_property(obj: MIDIObjectRef, id: *const CFString) -> Result<String, Error> {
+    let mut value: *const CFString = ptr::null();
+    unsafe {
+        Error::from_os_status(MIDIObjectGetStringProperty(obj, id, &mut value))?;
+        let value = NonNull::new(value as *mut CFString).ok_or(Error::MissingPropertyValue)?;
+        Ok(CFRetained::from_raw(value).to_string())
+    }
+}
+
+fn integer_property(obj: MIDIObjectRef, id: *const CFString) -> Result<i32, Error> {
+    let mut value = 0;
+    unsafe {
+        Error::from_os_status(MIDIObjectGetIntegerProperty(obj, id, &mut value))?;
+    }
+    Ok(value)
+}
+
+/// Collect the objects returned by a CoreMIDI `Get`/`GetNumberOf` function pair, skipping any
+/// that have since disappeared.
+fn collect<T, F>(count: ItemCount, get: F, wrap: fn(MIDIObjectRef) -> T) -> Vec<T>
+where
+    F: Fn(ItemCount) -> MIDIObjectRef,
+{
+    (0..count)
+        .map(get)
+        .filter(|&obj| obj != 0)
+        .map(wrap)
+        .collect()
+}
+
+macro_rules! midi_object {
+    ($(#[$attr:meta])* $Name:ident) => {
+        $(#[$attr])*
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+        pub struct $Name(MIDIObjectRef);
+
+        impl $Name {
+            /// The raw `MIDIObjectRef`.
+            pub fn as_raw(&self) -> u32 {
+                self.0
+            }
+
+            /// The name of the object.
+            pub fn name(&self) -> Result<String, Error> {
+                string_property(self.0, unsafe { kMIDIPropertyName })
+            }
+
+            /// The system-wide unique identifier of the object.
+            ///
+            /// Unlike the raw `MIDIObjectRef`, this persists across launches and may be used to
+            /// find the same object again later.
+            pub fn unique_id(&self) -> Result<i32, Error> {
+                integer_property(self.0, unsafe { kMIDIPropertyUniqueID })
+            }
+
+            /// Whether or not the object is offline, i.e. temporarily absent.
+            pub fn is_offline(&self) -> Result<bool, Error> {
+                integer_property(self.0, unsafe { kMIDIPropertyOffline }).map(|v| v != 0)
+            }
+        }
+    };
+}
+
+midi_object! {
+    /// A MIDI device, e.g. an interface or a synthesizer.
+    Device
+}
+
+midi_object! {
+    /// A component of a **Device** grouping related source and destination endpoints, e.g. a
+    /// single pair of MIDI ports.
+    Entity
+}
+
+midi_object! {
+    /// A MIDI source or destination.
+    Endpoint
+}
+
+impl Device {
+    /// The manufacturer of the device.
+    pub fn manufacturer(&self) -> Result<String, Error> {
+        string_property(self.0, unsafe { kMIDIPropertyManufacturer })
+    }
+
+    /// The model of the device.
+    pub fn model(&self) -> Result<String, Error> {
+        string_property(self.0, unsafe { kMIDIPropertyModel })
+    }
+
+    /// The entities of the device.
+    pub fn entities(&self) -> Vec<Entity> {
+        let device = self.0;
+        unsafe {
+            collect(
+                MIDIDeviceGetNumberOfEntities(device),
+                |i| MIDIDeviceGetEntity(device, i),
+                Entity,
+            )
+        }
+    }
+}
+
+impl Entity {
+    /// The source endpoints of the entity.
+    pub fn sources(&self) -> Vec<Endpoint> {
+        let entity = self.0;
+        unsafe {
+            collect(
+                MIDIEntityGetNumberOfSources(entity),
+                |i| MIDIEntityGetSource(entity, i),
+                Endpoint,
+            )
+        }
+    }
+
+    /// The destination endpoints of the entity.
+    pub fn destinations(&self) -> Vec<Endpoint> {
+        let entity = self.0;
+        unsafe {
+            collect(
+                MIDIEntityGetNumberOfDestinations(entity),
+                |i| MIDIEntityGetDestination(entity, i),
+                Endpoint,
+            )
+        }
+    }
+
+    /// The device to which the entity belongs.
+    pub fn device(&self) -> Result<Device, Error> {
+        let mut device = 0;
+        unsafe {
+            Error::from_os_status(MIDIEntityGetDevice(self.0, &mut device))?;
+        }
+        Ok(Device(device))
+    }
+}
+
+impl Endpoint {
+    /// The name of the endpoint as it should be presented to the user.
+    ///
+    /// This combines the names of the endpoint, its entity and its device as appropriate.
+    pub fn display_name(&self) -> Result<String, Error> {
+        string_property(self.0, unsafe { kMIDIPropertyDisplayName })
+    }
+
+    /// The entity to which the endpoint belongs, or `None` for virtual endpoints.
+    pub fn entity(&self) -> Result<Option<Entity>, Error> {
+        let mut entity = 0;
+        unsafe {
+            Error::from_os_status(MIDIEndpointGetEntity(self.0, &mut entity))?;
+        }
+        Ok(if entity == 0 {
+            None
+        } else {
+            Some(Entity(entity))
+        })
+    }
+}
+
+/// All MIDI devices known to the system.
+pub fn devices() -> Vec<Device> {
+    unsafe { collect(MIDIGetNumberOfDevices(), |i| MIDIGetDevice(i), Device) }
+}
+
+/// All MIDI source endpoints, including virtual sources created by other applications.
+pub fn sources() -> Vec<Endpoint> {
+    unsafe { collect(MIDIGetNumberOfSources(), |i| MIDIGetSource(i), Endpoint) }
+}
+
+/// All MIDI destination endpoints, including virtual destinations created by other applications.
+pub fn destinations() -> Vec<Endpoint> {
+    unsafe {
+        collect(
+            MIDIGetNumberOfDestinations(),
+            |i| MIDIGetDestination(i),
+            Endpoint,
+        )
+    }
+}
+
+/// The closure type called with each packet received by an
+/// [**InputPort**](./struct.InputPort) or [**VirtualDestination**](./struct.VirtualDestination).
+///
+/// The first argument is the source from which the packet was received, or `None` for packets
+/// sent to a virtual destination.
+pub type ReadFn = dyn FnMut(Option<Endpoint>, Packet<'_>) + Send + 'static;
+
+/// Callback procedure called by CoreMIDI with each received packet list.
+extern "C-unwind" fn read_proc(
+    pktlist: *const u8,
+    read_proc_ref_con: *mut c_void,
+    src_conn_ref_con: *mut c_void,
+) {
+    let source = match src_conn_ref_con as usize as MIDIObjectRef {
+        0 => None,
+        source => Some(Endpoint(source)),
+    };
+    // A malformed list is dropped, as panicking would unwind into CoreMIDI.
+    let packets = match unsafe { PacketList::from_ptr(pktlist) } {
+        Ok(packets) => packets,
+        Err(_) => return,
+    };
+    unsafe {
+        SharedCallback::<ReadFn>::with(read_proc_ref_con, true, |callback| {
+            // Nor may a panicking closure unwind into CoreMIDI, so the rest of the list is dropped.
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+                for packet in packets.iter() {
+                    callback(source, packet);
+                }
+            }));
+        });
+    }
+}
+
+/// Owns the `MIDIClientRef`, disposing of it once the client and all of its ports and endpoints
+/// have been dropped.
+#[derive(Debug)]
+struct ClientRef(MIDIObjectRef);
+
+impl Drop for ClientRef {
+    fn drop(&mut self) {
+        // We don't want to panic in `drop`, so we'll ignore returned errors.
+        unsafe {
+            Error::from_os_status(MIDIClientDispose(self.0)).ok();
+        }
+    }
+}
+
+/// A CoreMIDI client, through which ports and virtual endpoints are created.
+#[derive(Clone, Debug)]
+pub struct Client {
+    client: Arc<ClientRef>,
+}
+
+impl Client {
+    /// Create a new client with the given name.
+    pub fn new(name: &str) -> Result<Self, Error> {
+        let name = CFString::from_str(name);
+        let mut client = 0;
+        unsafe {
+            Error::from_os_status(MIDIClientCreate(&*name, None, ptr::null_mut(), &mut client))?;
+        }
+        Ok(Client {
+            client: Arc::new(ClientRef(client)),
+        })
+    }
+
+    /// Create a port through which packets are received from connected sources.
+    ///
+    /// `f` is called on a CoreMIDI thread with each packet received. Use a channel within the
+    /// closure in order to handle packets elsewhere.
+    pub fn input_port<F>(&self, name: &str, f: F) -> Result<InputPort, Error>
+    where
+        F: FnMut(Option<Endpoint>, Packet<'_>) + Send + 'static,
+    {
+        let name = CFString::from_str(name);
+        let callback = SharedCallback::<ReadFn>::new(Box::new(f));
+        let user_data = callback.user_data();
+        let mut port = 0;
+        unsafe {
+            let status =
+                MIDIInputPortCreate(self.client.0, &*name, read_proc, user_data, &mut port);
+            if let Err(err) = Error::from_os_status(status) {
+                SharedCallback::<ReadFn>::release(user_data);
+                return Err(err);
+            }
+        }
+        Ok(InputPort {
+            port,
+            _callback: callback,
+            _client: self.client.clone(),
+        })
+    }
+
+    /// Create a port through which packets are sent to destinations.
+    pub fn output_port(&self, name: &str) -> Result<OutputPort, Error> {
+        let name = CFString::from_str(name);
+        let mut port = 0;
+        unsafe {
+            Error::from_os_status(MIDIOutputPortCreate(self.client.0, &*name, &mut port))?;
+        }
+        Ok(OutputPort {
+            port,
+            _client: self.client.clone(),
+        })
+    }
+
+    /// Create a virtual source through which this client may send packets to other clients.
+    pub fn virtual_source(&self, name: &str) -> Result<VirtualSource, Error> {
+        let name = CFString::from_str(name);
+        let mut endpoint = 0;
+        unsafe {
+            Error::from_os_status(MIDISourceCreate(self.client.0, &*name, &mut endpoint))?;
+        }
+        Ok(VirtualSource {
+            endpoint: Endpoint(endpoint),
+            _client: self.client.clone(),
+        })
+    }
+
+    /// Create a virtual destination to which other clients may send packets.
+    ///
+    /// `f` is called on a CoreMIDI thread with each packet received.
+    pub fn virtual_destination<F>(&self, name: &str, f: F) -> Result<VirtualDestination, Error>
+    where
+        F: FnMut(Option<Endpoint>, Packet<'_>) + Send + 'static,
+    {
+        let name = CFString::from_str(name);
+        let callback = SharedCallback::<ReadFn>::new(Box::new(f));
+        let user_data = callback.user_data();
+        let mut endpoint = 0;
+        unsafe {
+            let status =
+                MIDIDestinationCreate(self.client.0, &*name, read_proc, user_data, &mut endpoint);
+            if let Err(err) = Error::from_os_status(status) {
+                SharedCallback::<ReadFn>::release(user_data);
+                return Err(err);
+            }
+        }
+        Ok(VirtualDestination {
+            endpoint: Endpoint(endpoint),
+            _callback: callback,
+            _client: self.client.clone(),
+        })
+    }
+}
+
+/// A port receiving packets from any number of connected sources.
+///
+/// The port is disposed of when the **InputPort** is dropped, and its closure freed once any call
+/// in progress has returned.
+pub struct InputPort {
+    port: MIDIObjectRef,
+    // Only read by calls in progress, through the `user_data` handed to CoreMIDI.
+    _callback: SharedCallback<ReadFn>,
+    _client: Arc<ClientRef>,
+}
+
+impl InputPort {
+    /// Begin receiving packets from the given source.
+    pub fn connect_source(&mut self, source: &Endpoint) -> Result<(), Error> {
+        // The source is passed back to `read_proc` as the connection's reference constant.
+        let conn_ref_con = source.0 as usize as *mut c_void;
+        unsafe {
+            Error::from_os_status(MIDIPortConnectSource(self.port, source.0, conn_ref_con))?;
+        }
+        Ok(())
+    }
+
+    /// Stop receiving packets from the given source.
+    pub fn disconnect_source(&mut self, source: &Endpoint) -> Result<(), Error> {
+        unsafe {
+            Error::from_os_status(MIDIPortDisconnectSource(self.port, source.0))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for InputPort {
+    fn drop(&mut self) {
+        unsafe {
+            // We don't want to panic in `drop`, so we'll ignore returned errors.
+            Error::from_os_status(MIDIPortDispose(self.port)).ok();
+        }
+    }
+}
+
+/// A port sending packets to destinations.
+pub struct OutputPort {
+    port: MIDIObjectRef,
+    _client: Arc<ClientRef>,
+}
+
+impl OutputPort {
+    /// Send the given MIDI 1.0 packets to a destination.
+    pub fn send(&self, destination: &Endpoint, packets: &PacketBuffer) -> Result<(), Error> {
+        unsafe {
+            Error::from_os_status(MIDISend(self.port, destination.0, packets.as_ptr()))?;
+        }
+        Ok(())
+    }
+
+    /// Send the given Universal MIDI Packets to a destination.
+    pub fn send_events(&self, destination: &Endpoint, events: &EventBuffer) -> Result<(), Error> {
+        unsafe {
+            Error::from_os_status(MIDISendEventList(self.port, destination.0, events.as_ptr()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for OutputPort {
+    fn drop(&mut self) {
+        unsafe {
+            Error::from_os_status(MIDIPortDispose(self.port)).ok();
+        }
+    }
+}
+
+/// A source created by this client, through which packets are sent to other clients.
+pub struct VirtualSource {
+    endpoint: Endpoint,
+    _client: Arc<ClientRef>,
+}
+
+impl VirtualSource {
+    /// The endpoint of the source.
+    pub fn endpoint(&self) -> Endpoint {
+        self.endpoint
+    }
+
+    /// Distribute the given MIDI 1.0 packets to every client connected to the source.
+    pub fn received(&self, packets: &PacketBuffer) -> Result<(), Error> {
+        unsafe {
+            Error::from_os_status(MIDIReceived(self.endpoint.0, packets.as_ptr()))?;
+        }
+        Ok(())
+    }
+
+    /// Distribute the given Universal MIDI Packets to every client connected to the source.
+    pub fn received_events(&self, events: &EventBuffer) -> Result<(), Error> {
+        unsafe {
+            Error::from_os_status(MIDIReceivedEventList(self.endpoint.0, events.as_ptr()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for VirtualSource {
+    fn drop(&mut self) {
+        unsafe {
+            Error::from_os_status(MIDIEndpointDispose(self.endpoint.0)).ok();
+        }
+    }
+}
+
+/// A destination created by this client, to which other clients may send packets.
+///
+/// The endpoint is disposed of when the **VirtualDestination** is dropped, and its closure freed
+/// once any call in progress has returned.
+pub struct VirtualDestination {
+    endpoint: Endpoint,
+    // Only read by calls in progress, through the `user_data` handed to CoreMIDI.
+    _callback: SharedCallback<ReadFn>,
+    _client: Arc<ClientRef>,
+}
+
+impl VirtualDestination {
+    /// The endpoint of the destination.
+    pub fn endpoint(&self) -> Endpoint {
+        self.endpoint
+    }
+}
+
+impl Drop for VirtualDestination {
+    fn drop(&mut self) {
+        unsafe {
+            Error::from_os_status(MIDIEndpointDispose(self.endpoint.0)).ok();
+        }
+    }
+}
@@ -1,22 +1,48 @@
 //! This module is an attempt at rustifying the OSStatus result.
+//!
+//! The status codes are declared here with their Core Audio names rather than imported from the
+//! framework bindings, so that these errors are available on every platform.
+
+#![allow(non_upper_case_globals)]
 
 pub use self::audio::Error as AudioError;
 pub use self::audio_codec::Error as AudioCodecError;
 pub use self::audio_format::Error as AudioFormatError;
 pub use self::audio_unit::Error as AudioUnitError;
+pub use self::device::Error as DeviceError;
+pub use self::graph::Error as GraphError;
+pub use self::midi::Error as MidiError;
+pub use self::smf::Error as SmfError;
 use crate::OSStatus;
 
-use objc2_audio_toolbox::{
-    kAudioServicesSystemSoundClientTimedOutError, kAudioServicesSystemSoundUnspecifiedError,
-};
+/// Displays a Core Audio code as its four characters where they are printable.
+pub(crate) struct FourCharCode(pub u32);
+
+impl ::std::fmt::Display for FourCharCode {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        let bytes = self.0.to_be_bytes();
+        if bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+            let code: String = bytes.iter().map(|&b| b as char).collect();
+            write!(f, "'{code}'")
+        } else {
+            write!(f, "{:#010x}", self.0)
+        }
+    }
+}
+
+const kAudioServicesSystemSoundClientTimedOutError: OSStatus = -1501;
+const kAudioServicesSystemSoundUnspecifiedError: OSStatus = -1500;
 
 pub mod audio {
     use crate::OSStatus;
-    use objc2_core_audio_types::{
-        kAudio_BadFilePathError, kAudio_FileNotFoundError, kAudio_FilePermissionError,
-        kAudio_MemFullError, kAudio_ParamError, kAudio_TooManyFilesOpenError,
-        kAudio_UnimplementedError,
-    };
+
+    const kAudio_BadFilePathError: OSStatus = 0x21707468;
+    const kAudio_FileNotFoundError: OSStatus = -43;
+    const kAudio_FilePermissionError: OSStatus = -54;
+    const kAudio_MemFullError: OSStatus = -108;
+    const kAudio_ParamError: OSStatus = -50;
+    const kAudio_TooManyFilesOpenError: OSStatus = -42;
+    const kAudio_UnimplementedError: OSStatus = -4;
 
     #[derive(Copy, Clone, Debug)]
     pub enum Error {
@@ -71,12 +97,15 @@ pub mod audio {
 
 pub mod audio_codec {
     use crate::OSStatus;
-    use objc2_audio_toolbox::{
-        kAudioCodecBadDataError, kAudioCodecBadPropertySizeError, kAudioCodecIllegalOperationError,
-        kAudioCodecNotEnoughBufferSpaceError, kAudioCodecStateError,
-        kAudioCodecUnknownPropertyError, kAudioCodecUnspecifiedError,
-        kAudioCodecUnsupportedFormatError,
-    };
+
+    const kAudioCodecBadDataError: OSStatus = 0x62616461;
+    const kAudioCodecBadPropertySizeError: OSStatus = 0x2173697a;
+    const kAudioCodecIllegalOperationError: OSStatus = 0x6e6f7065;
+    const kAudioCodecNotEnoughBufferSpaceError: OSStatus = 0x21627566;
+    const kAudioCodecStateError: OSStatus = 0x21737474;
+    const kAudioCodecUnknownPropertyError: OSStatus = 0x77686f3f;
+    const kAudioCodecUnspecifiedError: OSStatus = 0x77686174;
+    const kAudioCodecUnsupportedFormatError: OSStatus = 0x21646174;
 
     #[derive(Copy, Clone, Debug)]
     pub enum Error {
@@ -138,11 +167,13 @@ pub mod audio_codec {
 
 pub mod audio_format {
     use crate::OSStatus;
-    use objc2_audio_toolbox::{
-        kAudioFormatBadPropertySizeError, kAudioFormatBadSpecifierSizeError,
-        kAudioFormatUnknownFormatError, kAudioFormatUnspecifiedError,
-        kAudioFormatUnsupportedDataFormatError, kAudioFormatUnsupportedPropertyError,
-    };
+
+    const kAudioFormatBadPropertySizeError: OSStatus = 0x2173697a;
+    const kAudioFormatBadSpecifierSizeError: OSStatus = 0x21737063;
+    const kAudioFormatUnknownFormatError: OSStatus = 0x21666d74;
+    const kAudioFormatUnspecifiedError: OSStatus = 0x77686174;
+    const kAudioFormatUnsupportedDataFormatError: OSStatus = 0x666d743f;
+    const kAudioFormatUnsupportedPropertyError: OSStatus = 0x70726f70;
 
     // TODO: Finish implementing these values.
     #[derive(Copy, Clone, Debug)]
@@ -199,16 +230,24 @@ pub mod audio_format {
 
 pub mod audio_unit {
     use crate::OSStatus;
-    use objc2_audio_toolbox::{
-        kAudioUnitErr_CannotDoInCurrentContext, kAudioUnitErr_FailedInitialization,
-        kAudioUnitErr_FormatNotSupported, kAudioUnitErr_Initialized, kAudioUnitErr_InvalidElement,
-        kAudioUnitErr_InvalidFile, kAudioUnitErr_InvalidOfflineRender,
-        kAudioUnitErr_InvalidParameter, kAudioUnitErr_InvalidProperty,
-        kAudioUnitErr_InvalidPropertyValue, kAudioUnitErr_InvalidScope, kAudioUnitErr_NoConnection,
-        kAudioUnitErr_PropertyNotInUse, kAudioUnitErr_PropertyNotWritable,
-        kAudioUnitErr_TooManyFramesToProcess, kAudioUnitErr_Unauthorized,
-        kAudioUnitErr_Uninitialized,
-    };
+
+    const kAudioUnitErr_CannotDoInCurrentContext: OSStatus = -10863;
+    const kAudioUnitErr_FailedInitialization: OSStatus = -10875;
+    const kAudioUnitErr_FormatNotSupported: OSStatus = -10868;
+    const kAudioUnitErr_Initialized: OSStatus = -10849;
+    const kAudioUnitErr_InvalidElement: OSStatus = -10877;
+    const kAudioUnitErr_InvalidFile: OSStatus = -10871;
+    const kAudioUnitErr_InvalidOfflineRender: OSStatus = -10848;
+    const kAudioUnitErr_InvalidParameter: OSStatus = -10878;
+    const kAudioUnitErr_InvalidProperty: OSStatus = -10879;
+    const kAudioUnitErr_InvalidPropertyValue: OSStatus = -10851;
+    const kAudioUnitErr_InvalidScope: OSStatus = -10866;
+    const kAudioUnitErr_NoConnection: OSStatus = -10876;
+    const kAudioUnitErr_PropertyNotInUse: OSStatus = -10850;
+    const kAudioUnitErr_PropertyNotWritable: OSStatus = -10865;
+    const kAudioUnitErr_TooManyFramesToProcess: OSStatus = -10874;
+    const kAudioUnitErr_Unauthorized: OSStatus = -10847;
+    const kAudioUnitErr_Uninitialized: OSStatus = -10867;
 
     #[derive(Copy, Clone, Debug)]
     pub enum Error {
@@ -304,6 +343,229 @@ pub mod audio_unit {
     }
 }
 
+pub mod device {
+    use super::FourCharCode;
+    use crate::OSStatus;
+
+    const kAudioHardwareBadDeviceError: OSStatus = 0x21646576;
+    const kAudioHardwareBadObjectError: OSStatus = 0x216f626a;
+    const kAudioHardwareUnknownPropertyError: OSStatus = 0x77686f3f;
+
+    /// Errors arising from querying an audio device or stream through a
+    /// [**Device**](../../audio_unit/macos_helpers/struct.Device) or
+    /// [**Stream**](../../audio_unit/macos_helpers/struct.Stream).
+    ///
+    /// Property selectors are the `kAudioDeviceProperty*` and `kAudioObjectProperty*` codes.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum Error {
+        /// The system has no default device for the requested direction.
+        NoDefaultDevice,
+        /// The device or stream with the given id does not exist, e.g. because it was
+        /// disconnected.
+        NotFound(u32),
+        /// No device has the requested UID, e.g. because it is disconnected.
+        UidNotFound,
+        /// The device or stream does not have the property with the given selector.
+        UnknownProperty(u32),
+        /// Accessing the property with the given selector failed with the given status.
+        Property(u32, OSStatus),
+        /// The device did not report the requested value of the property with the given
+        /// selector in time after it was changed.
+        ChangeTimedOut(u32),
+        /// The device or stream returned no value for the property with the given selector.
+        MissingValue(u32),
+    }
+
+    impl Error {
+        /// Classify the status returned by the HAL when accessing a property of a device or
+        /// stream.
+        pub fn from_property_status(object: u32, selector: u32, os_status: OSStatus) -> Error {
+            match os_status {
+                kAudioHardwareBadDeviceError | kAudioHardwareBadObjectError => {
+                    Error::NotFound(object)
+                }
+                kAudioHardwareUnknownPropertyError => Error::UnknownProperty(selector),
+                _ => Error::Property(selector, os_status),
+            }
+        }
+    }
+
+    impl ::std::fmt::Display for Error {
+        fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+            match *self {
+                Error::NoDefaultDevice => write!(f, "There is no default audio device"),
+                Error::NotFound(object) => write!(f, "The audio object {object} does not exist"),
+                Error::UidNotFound => write!(f, "No audio device has the requested UID"),
+                Error::UnknownProperty(selector) => write!(
+                    f,
+                    "The audio object does not have the {} property",
+                    FourCharCode(selector)
+                ),
+                Error::ChangeTimedOut(selector) => write!(
+                    f,
+                    "Timed out waiting for the {} audio object property to change",
+                    FourCharCode(selector)
+                ),
+                Error::MissingValue(selector) => write!(
+                    f,
+                    "The audio object returned no value for the {} property",
+                    FourCharCode(selector)
+                ),
+                Error::Property(selector, os_status) => {
+                    match super::Error::from_os_status(os_status) {
+                        Err(err) => write!(
+                            f,
+                            "Failed to access the {} audio object property: {err}",
+                            FourCharCode(selector)
+                        ),
+                        Ok(()) => write!(
+                            f,
+                            "Failed to access the {} audio object property",
+                            FourCharCode(selector)
+                        ),
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub mod graph {
+    /// Errors arising from the construction of an audio unit
+    /// [**Graph**](../../audio_unit/graph/struct.Graph).
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum Error {
+        /// The given node does not belong to the graph.
+        InvalidNode,
+        /// The destination input bus already has a connection.
+        InputAlreadyConnected,
+        /// The destination input bus has no connection.
+        NotConnected,
+        /// The connection would introduce a cycle.
+        Cycle,
+        /// The source output format does not match the destination input format.
+        FormatMismatch,
+        /// The graph has no head node.
+        NoHead,
+    }
+
+    impl ::std::fmt::Display for Error {
+        fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+            let description = match *self {
+                Error::InvalidNode => "The node does not belong to the graph",
+                Error::InputAlreadyConnected => "The input bus is already connected",
+                Error::NotConnected => "The input bus is not connected",
+                Error::Cycle => "The connection would introduce a cycle",
+                Error::FormatMismatch => "The stream formats of the connected buses do not match",
+                Error::NoHead => "The graph has no head node",
+            };
+            write!(f, "{description}")
+        }
+    }
+}
+
+pub mod midi {
+    /// Errors arising from the encoding and decoding of MIDI messages.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum Error {
+        /// A channel was outside of the range `0..16`.
+        InvalidChannel,
+        /// A Universal MIDI Packet group was outside of the range `0..16`.
+        InvalidGroup,
+        /// A data byte or value was outside of its valid range.
+        InvalidDataByte,
+        /// The status byte does not begin a supported message.
+        InvalidStatus,
+        /// The message ended before all of its bytes were read.
+        Incomplete,
+        /// More bytes followed the end of the message.
+        TrailingBytes,
+        /// The message is longer than the packet holding it allows.
+        TooLong,
+    }
+
+    impl ::std::fmt::Display for Error {
+        fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+            let description = match *self {
+                Error::InvalidChannel => "The MIDI channel is out of range",
+                Error::InvalidGroup => "The MIDI group is out of range",
+                Error::InvalidDataByte => "The MIDI data is out of range",
+                Error::InvalidStatus => "The MIDI status byte is not supported",
+                Error::Incomplete => "The MIDI message is incomplete",
+                Error::TrailingBytes => "The MIDI message is followed by unexpected bytes",
+                Error::TooLong => "The MIDI message is too long for its packet",
+            };
+            write!(f, "{description}")
+        }
+    }
+}
+
+pub mod smf {
+    use super::MidiError;
+
+    /// Errors arising from the reading and writing of Standard MIDI Files.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum Error {
+        /// The data does not begin with an `MThd` header chunk.
+        NotStandardMidiFile,
+        /// The file is neither format 0 nor format 1.
+        UnsupportedFormat,
+        /// The division is zero or uses an unknown SMPTE frame rate.
+        InvalidDivision,
+        /// A format 0 file holds other than one track, or there are too many tracks to write.
+        InvalidTrackCount,
+        /// The file ended before all of its chunks or events were read.
+        Truncated,
+        /// A variable length quantity exceeded four bytes.
+        InvalidVariableLength,
+        /// A data byte appeared where no running status was in effect.
+        MissingRunningStatus,
+        /// An event could not be read or written, e.g. due to an unsupported status byte.
+        InvalidEvent,
+        /// The events of a track are not in order of their ticks.
+        UnorderedEvents,
+        /// A MIDI message within a track is invalid.
+        Message(MidiError),
+    }
+
+    impl ::std::fmt::Display for Error {
+        fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+            let description = match *self {
+                Error::NotStandardMidiFile => "The data is not a Standard MIDI File",
+                Error::UnsupportedFormat => "Only format 0 and format 1 MIDI files are supported",
+                Error::InvalidDivision => "The MIDI file division is invalid",
+                Error::InvalidTrackCount => "The MIDI file has an invalid number of tracks",
+                Error::Truncated => "The MIDI file is truncated",
+                Error::InvalidVariableLength => "The MIDI file variable length quantity is invalid",
+                Error::MissingRunningStatus => "The MIDI file event has no running status",
+                Error::InvalidEvent => "The MIDI file event is invalid",
+                Error::UnorderedEvents => "The MIDI file track events are out of order",
+                Error::Message(err) => return write!(f, "Invalid MIDI file message: {err}"),
+            };
+            write!(f, "{description}")
+        }
+    }
+}
+
+/// The step of tearing down an **AudioUnit** during which an error occurred.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TeardownStage {
+    Stop,
+    Uninitialize,
+    Dispose,
+}
+
+impl ::std::fmt::Display for TeardownStage {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        let description = match *self {
+            TeardownStage::Stop => "stop",
+            TeardownStage::Uninitialize => "uninitialize",
+            TeardownStage::Dispose => "dispose",
+        };
+        write!(f, "{description}")
+    }
+}
+
 /// A wrapper around all possible Core Audio errors.
 #[derive(Copy, Clone, Debug)]
 pub enum Error {
@@ -315,10 +577,18 @@ pub enum Error {
     NonInterleavedInputOnlySupportsMono,
     UnsupportedSampleRate,
     UnsupportedStreamFormat,
+    ParameterValueOutOfRange,
+    MissingPropertyValue,
+    ZeroFrameCapacity,
+    Teardown(TeardownStage, OSStatus),
     Audio(AudioError),
     AudioCodec(AudioCodecError),
     AudioFormat(AudioFormatError),
     AudioUnit(AudioUnitError),
+    Device(DeviceError),
+    Graph(GraphError),
+    Midi(MidiError),
+    Smf(SmfError),
     Unknown(OSStatus),
 }
 
@@ -369,6 +639,7 @@ impl Error {
             Error::Audio(err) => err as OSStatus,
             Error::AudioCodec(err) => err as OSStatus,
             Error::AudioUnit(err) => err as OSStatus,
+            Error::Teardown(_, os_status) => os_status,
             _ => kAudioServicesSystemSoundUnspecifiedError,
         }
     }
@@ -388,12 +659,235 @@ impl ::std::fmt::Display for Error {
             Error::NonInterleavedInputOnlySupportsMono => write!(f, "In non-interleaved mode input only supports one channel"),
             Error::UnsupportedSampleRate => write!(f, "The requested sample rate is not available"),
             Error::UnsupportedStreamFormat => write!(f, "The requested stream format is not available"),
+            Error::ParameterValueOutOfRange => write!(f, "The parameter value is outside of its valid range"),
+            Error::MissingPropertyValue => write!(f, "The property has no value"),
+            Error::ZeroFrameCapacity => write!(f, "The render slices or buffers cannot hold a single frame"),
+            Error::Teardown(stage, os_status) => match Error::from_os_status(os_status) {
+                Err(err) => write!(f, "Failed to {stage} the audio unit: {err}"),
+                Ok(()) => write!(f, "Failed to {stage} the audio unit"),
+            },
             Error::Audio(ref err) => write!(f, "{err}"),
             Error::AudioCodec(ref err) => write!(f, "{err}"),
             Error::AudioFormat(ref err) => write!(f, "{err}"),
             Error::AudioUnit(ref err) => write!(f, "{err}"),
+            Error::Device(ref err) => write!(f, "{err}"),
+            Error::Graph(ref err) => write!(f, "{err}"),
+            Error::Midi(ref err) => write!(f, "{err}"),
+            Error::Smf(ref err) => write!(f, "{err}"),
             Error::Unknown(os_status) => write!(f, "An error unknown to the coreaudio-rs API occurred, OSStatus: {os_status}"),
 
         }
     }
 }
+
+#[test]
+fn test_os_status_round_trip() {
+    assert!(matches!(
+        Error::from_os_status(-10867),
+        Err(Error::AudioUnit(AudioUnitError::Uninitialized))
+    ));
+    assert!(matches!(
+        Error::from_os_status(0x21666d74),
+        Err(Error::AudioFormat(AudioFormatError::UnknownFormat))
+    ));
+    assert!(matches!(
+        Error::from_os_status(-1501),
+        Err(Error::SystemSoundClientMessageTimedOut)
+    ));
+    assert!(matches!(Error::from_os_status(1), Err(Error::Unknown(1))));
+    assert_eq!(Error::Audio(AudioError::Param).as_os_status(), -50);
+}
+
+#[test]
+fn test_device_error() {
+    assert_eq!(
+        DeviceError::from_property_status(42, 0x6c6e616d, 0x216f626a),
+        DeviceError::NotFound(42)
+    );
+    let unknown = DeviceError::from_property_status(42, 0x6c6e616d, 0x77686f3f);
+    assert_eq!(unknown, DeviceError::UnknownProperty(0x6c6e616d));
+    assert_eq!(
+        unknown.to_string(),
+        "The audio object does not have the 'lnam' property"
+    );
+    let failed = DeviceError::from_property_status(42, 1, -50);
+    assert_eq!(failed, DeviceError::Property(1, -50));
+    assert_eq!(
+        Error::Device(failed).to_string(),
+        "Failed to access the 0x00000001 audio object property: Param"
+    );
+    assert_eq!(
+        DeviceError::ChangeTimedOut(0x6e737274).to_string(),
+        "Timed out waiting for the 'nsrt' audio object property to change"
+    );
+    assert_eq!(
+        DeviceError::MissingValue(0x6c6e616d).to_string(),
+        "The audio object returned no value for the 'lnam' property"
+    );
+}
+
+#[cfg(feature = "audio_toolbox")]
+#[test]
+fn test_os_status_matches_framework() {
+    use objc2_audio_toolbox as at;
+    use objc2_core_audio_types as cat;
+
+    assert_eq!(
+        AudioError::Unimplemented as OSStatus,
+        cat::kAudio_UnimplementedError
+    );
+    assert_eq!(
+        AudioError::FileNotFound as OSStatus,
+        cat::kAudio_FileNotFoundError
+    );
+    assert_eq!(
+        AudioError::FilePermission as OSStatus,
+        cat::kAudio_FilePermissionError
+    );
+    assert_eq!(
+        AudioError::TooManyFilesOpen as OSStatus,
+        cat::kAudio_TooManyFilesOpenError
+    );
+    assert_eq!(
+        AudioError::BadFilePath as OSStatus,
+        cat::kAudio_BadFilePathError
+    );
+    assert_eq!(AudioError::Param as OSStatus, cat::kAudio_ParamError);
+    assert_eq!(AudioError::MemFull as OSStatus, cat::kAudio_MemFullError);
+
+    assert_eq!(
+        AudioCodecError::Unspecified as OSStatus,
+        at::kAudioCodecUnspecifiedError
+    );
+    assert_eq!(
+        AudioCodecError::UnknownProperty as OSStatus,
+        at::kAudioCodecUnknownPropertyError
+    );
+    assert_eq!(
+        AudioCodecError::BadPropertySize as OSStatus,
+        at::kAudioCodecBadPropertySizeError
+    );
+    assert_eq!(
+        AudioCodecError::IllegalOperation as OSStatus,
+        at::kAudioCodecIllegalOperationError
+    );
+    assert_eq!(
+        AudioCodecError::UnsupportedFormat as OSStatus,
+        at::kAudioCodecUnsupportedFormatError
+    );
+    assert_eq!(
+        AudioCodecError::State as OSStatus,
+        at::kAudioCodecStateError
+    );
+    assert_eq!(
+        AudioCodecError::NotEnoughBufferSpace as OSStatus,
+        at::kAudioCodecNotEnoughBufferSpaceError
+    );
+    assert_eq!(
+        AudioCodecError::BadData as OSStatus,
+        at::kAudioCodecBadDataError
+    );
+
+    assert_eq!(
+        AudioFormatError::Unspecified as OSStatus,
+        at::kAudioFormatUnspecifiedError
+    );
+    assert_eq!(
+        AudioFormatError::UnsupportedProperty as OSStatus,
+        at::kAudioFormatUnsupportedPropertyError
+    );
+    assert_eq!(
+        AudioFormatError::BadPropertySize as OSStatus,
+        at::kAudioFormatBadPropertySizeError
+    );
+    assert_eq!(
+        AudioFormatError::BadSpecifierSize as OSStatus,
+        at::kAudioFormatBadSpecifierSizeError
+    );
+    assert_eq!(
+        AudioFormatError::UnsupportedDataFormat as OSStatus,
+        at::kAudioFormatUnsupportedDataFormatError
+    );
+    assert_eq!(
+        AudioFormatError::UnknownFormat as OSStatus,
+        at::kAudioFormatUnknownFormatError
+    );
+
+    assert_eq!(
+        AudioUnitError::InvalidProperty as OSStatus,
+        at::kAudioUnitErr_InvalidProperty
+    );
+    assert_eq!(
+        AudioUnitError::InvalidParameter as OSStatus,
+        at::kAudioUnitErr_InvalidParameter
+    );
+    assert_eq!(
+        AudioUnitError::InvalidElement as OSStatus,
+        at::kAudioUnitErr_InvalidElement
+    );
+    assert_eq!(
+        AudioUnitError::NoConnection as OSStatus,
+        at::kAudioUnitErr_NoConnection
+    );
+    assert_eq!(
+        AudioUnitError::FailedInitialization as OSStatus,
+        at::kAudioUnitErr_FailedInitialization
+    );
+    assert_eq!(
+        AudioUnitError::TooManyFramesToProcess as OSStatus,
+        at::kAudioUnitErr_TooManyFramesToProcess
+    );
+    assert_eq!(
+        AudioUnitError::InvalidFile as OSStatus,
+        at::kAudioUnitErr_InvalidFile
+    );
+    assert_eq!(
+        AudioUnitError::FormatNotSupported as OSStatus,
+        at::kAudioUnitErr_FormatNotSupported
+    );
+    assert_eq!(
+        AudioUnitError::Uninitialized as OSStatus,
+        at::kAudioUnitErr_Uninitialized
+    );
+    assert_eq!(
+        AudioUnitError::InvalidScope as OSStatus,
+        at::kAudioUnitErr_InvalidScope
+    );
+    assert_eq!(
+        AudioUnitError::PropertyNotWritable as OSStatus,
+        at::kAudioUnitErr_PropertyNotWritable
+    );
+    assert_eq!(
+        AudioUnitError::CannotDoInCurrentContext as OSStatus,
+        at::kAudioUnitErr_CannotDoInCurrentContext
+    );
+    assert_eq!(
+        AudioUnitError::InvalidPropertyValue as OSStatus,
+        at::kAudioUnitErr_InvalidPropertyValue
+    );
+    assert_eq!(
+        AudioUnitError::PropertyNotInUse as OSStatus,
+        at::kAudioUnitErr_PropertyNotInUse
+    );
+    assert_eq!(
+        AudioUnitError::Initialized as OSStatus,
+        at::kAudioUnitErr_Initialized
+    );
+    assert_eq!(
+        AudioUnitError::InvalidOfflineRender as OSStatus,
+        at::kAudioUnitErr_InvalidOfflineRender
+    );
+    assert_eq!(
+        AudioUnitError::Unauthorized as OSStatus,
+        at::kAudioUnitErr_Unauthorized
+    );
+
+    assert_eq!(
+        kAudioServicesSystemSoundUnspecifiedError,
+        at::kAudioServicesSystemSoundUnspecifiedError
+    );
+    assert_eq!(
+        kAudioServicesSystemSoundClientTimedOutError,
+        at::kAudioServicesSystemSoundClientTimedOutError
+    );
+}
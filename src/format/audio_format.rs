@@ -3,27 +3,82 @@
 //! See the Core Audio Data Types Reference
 //! [here](https://developer.apple.com/library/mac/documentation/MusicAudio/Reference/CoreAudioDataTypesRef/#//apple_ref/doc/constant_group/Audio_Data_Format_Identifiers) for more info.
 
-use objc2_core_audio_types::{
-    kAppleLosslessFormatFlag_16BitSourceData, kAppleLosslessFormatFlag_20BitSourceData,
-    kAppleLosslessFormatFlag_24BitSourceData, kAppleLosslessFormatFlag_32BitSourceData,
-    kAudioFormat60958AC3, kAudioFormatAC3, kAudioFormatAES3, kAudioFormatALaw, kAudioFormatAMR,
-    kAudioFormatAMR_WB, kAudioFormatAppleIMA4, kAudioFormatAppleLossless, kAudioFormatAudible,
-    kAudioFormatDVIIntelIMA, kAudioFormatFlagIsAlignedHigh, kAudioFormatFlagIsBigEndian,
-    kAudioFormatFlagIsFloat, kAudioFormatFlagIsNonInterleaved, kAudioFormatFlagIsNonMixable,
-    kAudioFormatFlagIsPacked, kAudioFormatFlagIsSignedInteger, kAudioFormatLinearPCM,
-    kAudioFormatMACE3, kAudioFormatMACE6, kAudioFormatMIDIStream, kAudioFormatMPEG4AAC,
-    kAudioFormatMPEG4AAC_ELD, kAudioFormatMPEG4AAC_ELD_SBR, kAudioFormatMPEG4AAC_ELD_V2,
-    kAudioFormatMPEG4AAC_HE, kAudioFormatMPEG4AAC_HE_V2, kAudioFormatMPEG4AAC_LD,
-    kAudioFormatMPEG4AAC_Spatial, kAudioFormatMPEG4CELP, kAudioFormatMPEG4HVXC,
-    kAudioFormatMPEG4TwinVQ, kAudioFormatMPEGLayer1, kAudioFormatMPEGLayer2,
-    kAudioFormatMPEGLayer3, kAudioFormatMicrosoftGSM, kAudioFormatParameterValueStream,
-    kAudioFormatQDesign, kAudioFormatQDesign2, kAudioFormatQUALCOMM, kAudioFormatTimeCode,
-    kAudioFormatULaw, kAudioFormatiLBC, kLinearPCMFormatFlagsSampleFractionMask,
-    kLinearPCMFormatFlagsSampleFractionShift, AudioTimeStampFlags as Objc2AudioTimeStampFlags,
-    MPEG4ObjectID as Objc2MPEG4ObjectID,
-};
+#![allow(non_upper_case_globals)]
+
 use std::os::raw::c_uint;
 
+// The Core Audio constants are declared here rather than imported from the FFI bindings so that
+// these types remain available on platforms without Core Audio. Names and values mirror
+// `CoreAudioTypes.h`.
+const kAudioFormatLinearPCM: u32 = 0x6c70636d;
+const kAudioFormatAC3: u32 = 0x61632d33;
+const kAudioFormat60958AC3: u32 = 0x63616333;
+const kAudioFormatAppleIMA4: u32 = 0x696d6134;
+const kAudioFormatMPEG4AAC: u32 = 0x61616320;
+const kAudioFormatMPEG4CELP: u32 = 0x63656c70;
+const kAudioFormatMPEG4HVXC: u32 = 0x68767863;
+const kAudioFormatMPEG4TwinVQ: u32 = 0x74777671;
+const kAudioFormatMACE3: u32 = 0x4d414333;
+const kAudioFormatMACE6: u32 = 0x4d414336;
+const kAudioFormatULaw: u32 = 0x756c6177;
+const kAudioFormatALaw: u32 = 0x616c6177;
+const kAudioFormatQDesign: u32 = 0x51444d43;
+const kAudioFormatQDesign2: u32 = 0x51444d32;
+const kAudioFormatQUALCOMM: u32 = 0x51636c70;
+const kAudioFormatMPEGLayer1: u32 = 0x2e6d7031;
+const kAudioFormatMPEGLayer2: u32 = 0x2e6d7032;
+const kAudioFormatMPEGLayer3: u32 = 0x2e6d7033;
+const kAudioFormatTimeCode: u32 = 0x74696d65;
+const kAudioFormatMIDIStream: u32 = 0x6d696469;
+const kAudioFormatParameterValueStream: u32 = 0x61707673;
+const kAudioFormatAppleLossless: u32 = 0x616c6163;
+const kAudioFormatMPEG4AAC_HE: u32 = 0x61616368;
+const kAudioFormatMPEG4AAC_LD: u32 = 0x6161636c;
+const kAudioFormatMPEG4AAC_ELD: u32 = 0x61616365;
+const kAudioFormatMPEG4AAC_ELD_SBR: u32 = 0x61616366;
+const kAudioFormatMPEG4AAC_ELD_V2: u32 = 0x61616367;
+const kAudioFormatMPEG4AAC_HE_V2: u32 = 0x61616370;
+const kAudioFormatMPEG4AAC_Spatial: u32 = 0x61616373;
+const kAudioFormatAMR: u32 = 0x73616d72;
+const kAudioFormatAMR_WB: u32 = 0x73617762;
+const kAudioFormatAudible: u32 = 0x41554442;
+const kAudioFormatiLBC: u32 = 0x696c6263;
+const kAudioFormatDVIIntelIMA: u32 = 0x6d730011;
+const kAudioFormatMicrosoftGSM: u32 = 0x6d730031;
+const kAudioFormatAES3: u32 = 0x61657333;
+
+const kAudioFormatFlagIsFloat: u32 = 1 << 0;
+const kAudioFormatFlagIsBigEndian: u32 = 1 << 1;
+const kAudioFormatFlagIsSignedInteger: u32 = 1 << 2;
+const kAudioFormatFlagIsPacked: u32 = 1 << 3;
+const kAudioFormatFlagIsAlignedHigh: u32 = 1 << 4;
+const kAudioFormatFlagIsNonInterleaved: u32 = 1 << 5;
+const kAudioFormatFlagIsNonMixable: u32 = 1 << 6;
+const kLinearPCMFormatFlagsSampleFractionShift: u32 = 7;
+const kLinearPCMFormatFlagsSampleFractionMask: u32 =
+    0x3f << kLinearPCMFormatFlagsSampleFractionShift;
+
+const kAppleLosslessFormatFlag_16BitSourceData: u32 = 1;
+const kAppleLosslessFormatFlag_20BitSourceData: u32 = 2;
+const kAppleLosslessFormatFlag_24BitSourceData: u32 = 3;
+const kAppleLosslessFormatFlag_32BitSourceData: u32 = 4;
+
+const kMPEG4Object_AAC_Main: u32 = 1;
+const kMPEG4Object_AAC_LC: u32 = 2;
+const kMPEG4Object_AAC_SSR: u32 = 3;
+const kMPEG4Object_AAC_LTP: u32 = 4;
+const kMPEG4Object_AAC_SBR: u32 = 5;
+const kMPEG4Object_AAC_Scalable: u32 = 6;
+const kMPEG4Object_TwinVQ: u32 = 7;
+const kMPEG4Object_CELP: u32 = 8;
+const kMPEG4Object_HVXC: u32 = 9;
+
+const kAudioTimeStampSampleTimeValid: u32 = 1 << 0;
+const kAudioTimeStampHostTimeValid: u32 = 1 << 1;
+const kAudioTimeStampRateScalarValid: u32 = 1 << 2;
+const kAudioTimeStampWordClockTimeValid: u32 = 1 << 3;
+const kAudioTimeStampSMPTETimeValid: u32 = 1 << 4;
+
 /// A type-safe representation of both the `AudioFormatId` and their associated flags.
 #[derive(Copy, Clone, Debug)]
 #[allow(non_camel_case_types)]
@@ -452,42 +507,40 @@ bitflags! {
 #[allow(non_camel_case_types)]
 pub enum Mpeg4ObjectId {
     /// Advanced audio coding; the baisc MPEG-4 technology.
-    AAC_Main = Objc2MPEG4ObjectID::AAC_Main.0 as isize,
+    AAC_Main = kMPEG4Object_AAC_Main as isize,
     /// Lossless coding; provides compression with no loss of quality.
-    AAC_LC = Objc2MPEG4ObjectID::AAC_LC.0 as isize,
+    AAC_LC = kMPEG4Object_AAC_LC as isize,
     /// Scalable sampling rate; provides different sampling frequencies for different targets.
-    AAC_SSR = Objc2MPEG4ObjectID::AAC_SSR.0 as isize,
+    AAC_SSR = kMPEG4Object_AAC_SSR as isize,
     /// Long term prediction; reduces redundancy in a coded signal.
-    AAC_LTP = Objc2MPEG4ObjectID::AAC_LTP.0 as isize,
+    AAC_LTP = kMPEG4Object_AAC_LTP as isize,
     /// Spectral band replication; reconstructs high-frequency content from lower frequencies
     /// and side information.
-    AAC_SBR = Objc2MPEG4ObjectID::AAC_SBR.0 as isize,
+    AAC_SBR = kMPEG4Object_AAC_SBR as isize,
     /// Scalable lossless coding.
-    AAC_Scalable = Objc2MPEG4ObjectID::AAC_Scalable.0 as isize,
+    AAC_Scalable = kMPEG4Object_AAC_Scalable as isize,
     /// Transform-domain weighted interleaved vector quantization; an audio codec optimised for
     /// audio coding at ultra low bit rates around 8kbit/s.
-    TwinVQ = Objc2MPEG4ObjectID::TwinVQ.0 as isize,
+    TwinVQ = kMPEG4Object_TwinVQ as isize,
     /// Code Excited Linear Prediction; a narrow-band/wide-band speech codec.
-    CELP = Objc2MPEG4ObjectID::CELP.0 as isize,
+    CELP = kMPEG4Object_CELP as isize,
     /// Harmonic Vector Excitation Coding; a very-low bit-rate parametric speech codec.
-    HVXC = Objc2MPEG4ObjectID::HVXC.0 as isize,
+    HVXC = kMPEG4Object_HVXC as isize,
 }
 
 impl Mpeg4ObjectId {
     /// Create an Mpeg4ObjectId from a u32.
     pub fn from_u32(u: u32) -> Option<Mpeg4ObjectId> {
         match u {
-            _ if u == Objc2MPEG4ObjectID::AAC_Main.0 as u32 => Some(Mpeg4ObjectId::AAC_Main),
-            _ if u == Objc2MPEG4ObjectID::AAC_LC.0 as u32 => Some(Mpeg4ObjectId::AAC_LC),
-            _ if u == Objc2MPEG4ObjectID::AAC_SSR.0 as u32 => Some(Mpeg4ObjectId::AAC_SSR),
-            _ if u == Objc2MPEG4ObjectID::AAC_LTP.0 as u32 => Some(Mpeg4ObjectId::AAC_LTP),
-            _ if u == Objc2MPEG4ObjectID::AAC_SBR.0 as u32 => Some(Mpeg4ObjectId::AAC_SBR),
-            _ if u == Objc2MPEG4ObjectID::AAC_Scalable.0 as u32 => {
-                Some(Mpeg4ObjectId::AAC_Scalable)
-            }
-            _ if u == Objc2MPEG4ObjectID::TwinVQ.0 as u32 => Some(Mpeg4ObjectId::TwinVQ),
-            _ if u == Objc2MPEG4ObjectID::CELP.0 as u32 => Some(Mpeg4ObjectId::CELP),
-            _ if u == Objc2MPEG4ObjectID::HVXC.0 as u32 => Some(Mpeg4ObjectId::HVXC),
+            _ if u == kMPEG4Object_AAC_Main => Some(Mpeg4ObjectId::AAC_Main),
+            _ if u == kMPEG4Object_AAC_LC => Some(Mpeg4ObjectId::AAC_LC),
+            _ if u == kMPEG4Object_AAC_SSR => Some(Mpeg4ObjectId::AAC_SSR),
+            _ if u == kMPEG4Object_AAC_LTP => Some(Mpeg4ObjectId::AAC_LTP),
+            _ if u == kMPEG4Object_AAC_SBR => Some(Mpeg4ObjectId::AAC_SBR),
+            _ if u == kMPEG4Object_AAC_Scalable => Some(Mpeg4ObjectId::AAC_Scalable),
+            _ if u == kMPEG4Object_TwinVQ => Some(Mpeg4ObjectId::TwinVQ),
+            _ if u == kMPEG4Object_CELP => Some(Mpeg4ObjectId::CELP),
+            _ if u == kMPEG4Object_HVXC => Some(Mpeg4ObjectId::HVXC),
             _ => None,
         }
     }
@@ -502,14 +555,71 @@ bitflags! {
     #[derive(Copy, Clone, Debug)]
     pub struct AudioTimeStampFlags: u32 {
         /// The sample frame time is valid.
-        const SAMPLE_TIME_VALID = Objc2AudioTimeStampFlags::SampleTimeValid.0;
+        const SAMPLE_TIME_VALID = kAudioTimeStampSampleTimeValid;
         /// The host time is valid.
-        const HOST_TIME_VALID = Objc2AudioTimeStampFlags::HostTimeValid.0;
+        const HOST_TIME_VALID = kAudioTimeStampHostTimeValid;
         /// The rate scalar is valid.
-        const RATE_SCALAR_VALID = Objc2AudioTimeStampFlags::RateScalarValid.0;
+        const RATE_SCALAR_VALID = kAudioTimeStampRateScalarValid;
         /// The world clock time is valid.
-        const WORLD_CLOCK_TIME_VALID = Objc2AudioTimeStampFlags::WordClockTimeValid.0;
+        const WORLD_CLOCK_TIME_VALID = kAudioTimeStampWordClockTimeValid;
         /// The SMPTE time is valid.
-        const SMPTE_TIME_VALID = Objc2AudioTimeStampFlags::SMPTETimeValid.0;
+        const SMPTE_TIME_VALID = kAudioTimeStampSMPTETimeValid;
     }
 }
+
+#[test]
+fn test_format_and_flag_round_trip() {
+    let formats = [
+        AudioFormat::LinearPCM(LinearPcmFlags::IS_FLOAT | LinearPcmFlags::IS_PACKED),
+        AudioFormat::AC3,
+        AudioFormat::MPEG4AAC(Mpeg4ObjectId::AAC_LC),
+        AudioFormat::TimeCode(AudioTimeStampFlags::SAMPLE_TIME_VALID),
+        AudioFormat::AppleLossless(AppleLosslessFlags::BIT_24_SOURCE_DATA),
+        AudioFormat::MPEG4AAC_ELD_V2,
+        AudioFormat::AES3,
+    ];
+    for format in formats.iter() {
+        let (id, flag) = format.as_format_and_flag();
+        let parsed = AudioFormat::from_format_and_flag(id, flag).unwrap();
+        assert_eq!(parsed.as_format_and_flag(), (id, flag));
+    }
+    assert_eq!(
+        AudioFormat::LinearPCM(LinearPcmFlags::empty()).as_format_and_flag(),
+        (u32::from_be_bytes(*b"lpcm"), Some(0))
+    );
+    assert!(AudioFormat::from_format_and_flag(0, None).is_none());
+}
+
+#[cfg(feature = "audio_toolbox")]
+#[test]
+fn test_constants_match_framework() {
+    use objc2_core_audio_types as cat;
+
+    assert_eq!(kAudioFormatLinearPCM, cat::kAudioFormatLinearPCM);
+    assert_eq!(kAudioFormatMPEG4AAC, cat::kAudioFormatMPEG4AAC);
+    assert_eq!(kAudioFormatAppleLossless, cat::kAudioFormatAppleLossless);
+    assert_eq!(
+        kAudioFormatMPEG4AAC_ELD_V2,
+        cat::kAudioFormatMPEG4AAC_ELD_V2
+    );
+    assert_eq!(kAudioFormatDVIIntelIMA, cat::kAudioFormatDVIIntelIMA);
+    assert_eq!(kAudioFormatMicrosoftGSM, cat::kAudioFormatMicrosoftGSM);
+    assert_eq!(kAudioFormatAES3, cat::kAudioFormatAES3);
+    assert_eq!(
+        kAudioFormatFlagIsNonMixable,
+        cat::kAudioFormatFlagIsNonMixable
+    );
+    assert_eq!(
+        kLinearPCMFormatFlagsSampleFractionMask,
+        cat::kLinearPCMFormatFlagsSampleFractionMask
+    );
+    assert_eq!(
+        kAppleLosslessFormatFlag_32BitSourceData,
+        cat::kAppleLosslessFormatFlag_32BitSourceData
+    );
+    assert_eq!(kMPEG4Object_HVXC, cat::MPEG4ObjectID::HVXC.0 as u32);
+    assert_eq!(
+        kAudioTimeStampSMPTETimeValid,
+        cat::AudioTimeStampFlags::SMPTETimeValid.0
+    );
+}
@@ -0,0 +1,259 @@
+//! An owned, heap allocated `AudioBufferList`.
+//!
+//! Core Audio's `AudioBufferList` is a variable length struct, declared with a single trailing
+//! `AudioBuffer` but allocated with as many as are required. The
+//! [**OwnedBufferList**](./struct.OwnedBufferList) type handles this allocation along with the
+//! storage for each buffer's audio data, so that it may be passed to functions such as
+//! [**AudioUnit::render**](../audio_unit/struct.AudioUnit#method.render).
+
+use super::sys::{AudioBuffer, AudioBufferList};
+
+use super::audio_format::LinearPcmFlags;
+use super::{Sample, StreamFormat};
+use crate::error::{self, Error};
+use std::alloc::{self, Layout};
+use std::mem;
+use std::ptr::{self, NonNull};
+use std::slice;
+
+/// A heap allocated `AudioBufferList` along with the data of each of its buffers.
+///
+/// Buffer data is allocated with 4-byte alignment, so that it may be viewed as any
+/// [**Sample**](../sample_format/trait.Sample) type.
+pub struct OwnedBufferList {
+    list: NonNull<AudioBufferList>,
+    layout: Layout,
+    data: Vec<Box<[u32]>>,
+    bytes_per_frame: usize,
+    frame_capacity: u32,
+}
+
+unsafe impl Send for OwnedBufferList {}
+
+/// The layout of an `AudioBufferList` holding the given number of buffers.
+fn list_layout(buffer_count: usize) -> Layout {
+    let extra = buffer_count.saturating_sub(1) * mem::size_of::<AudioBuffer>();
+    let size = mem::size_of::<AudioBufferList>() + extra;
+    Layout::from_size_align(size, mem::align_of::<AudioBufferList>())
+        .expect("invalid AudioBufferList layout")
+}
+
+/// A pointer to the first of the trailing `AudioBuffer`s of the given list.
+///
+/// The pointer is derived without creating a reference to the declared single element
+/// `mBuffers` array, as it is used to access elements beyond it.
+unsafe fn buffers_ptr(list: *mut AudioBufferList) -> *mut AudioBuffer {
+    ptr::addr_of_mut!((*list).mBuffers) as *mut AudioBuffer
+}
+
+impl OwnedBufferList {
+    /// Allocate `buffer_count` zeroed buffers, each holding up to `frame_capacity` frames of
+    /// `channels_per_buffer` interleaved channels.
+    ///
+    /// Parameters
+    /// ----------
+    ///
+    /// - **buffer_count**: The number of buffers within the list.
+    /// - **channels_per_buffer**: The number of interleaved channels within each buffer.
+    /// - **bytes_per_frame**: The size of a single frame within a buffer in bytes.
+    /// - **frame_capacity**: The maximum number of frames each buffer may hold.
+    pub fn new(
+        buffer_count: u32,
+        channels_per_buffer: u32,
+        bytes_per_frame: usize,
+        frame_capacity: u32,
+    ) -> Self {
+        let byte_capacity = bytes_per_frame * frame_capacity as usize;
+        let words = byte_capacity.div_ceil(mem::size_of::<u32>());
+        let mut data: Vec<Box<[u32]>> = (0..buffer_count)
+            .map(|_| vec![0u32; words].into_boxed_slice())
+            .collect();
+
+        let layout = list_layout(buffer_count as usize);
+        let list = unsafe {
+            let ptr = alloc::alloc_zeroed(layout) as *mut AudioBufferList;
+            let list = match NonNull::new(ptr) {
+                Some(list) => list,
+                None => alloc::handle_alloc_error(layout),
+            };
+            (*list.as_ptr()).mNumberBuffers = buffer_count;
+            let buffers = buffers_ptr(list.as_ptr());
+            for (i, words) in data.iter_mut().enumerate() {
+                buffers.add(i).write(AudioBuffer {
+                    mNumberChannels: channels_per_buffer,
+                    mDataByteSize: byte_capacity as u32,
+                    mData: words.as_mut_ptr() as *mut _,
+                });
+            }
+            list
+        };
+
+        OwnedBufferList {
+            list,
+            layout,
+            data,
+            bytes_per_frame,
+            frame_capacity,
+        }
+    }
+
+    /// Allocate a buffer list suitable for rendering up to `frame_capacity` frames in the given
+    /// stream format.
+    ///
+    /// Non-interleaved formats are given one single-channel buffer per channel, while interleaved
+    /// formats are given a single buffer holding all channels.
+    pub fn for_format(format: &StreamFormat, frame_capacity: u32) -> Self {
+        let sample_bytes = format.sample_format.size_in_bytes();
+        if format.flags.contains(LinearPcmFlags::IS_NON_INTERLEAVED) {
+            OwnedBufferList::new(format.channels, 1, sample_bytes, frame_capacity)
+        } else {
+            let bytes_per_frame = sample_bytes * format.channels as usize;
+            OwnedBufferList::new(1, format.channels, bytes_per_frame, frame_capacity)
+        }
+    }
+
+    /// The number of buffers within the list.
+    pub fn buffer_count(&self) -> usize {
+        self.data.len()
+    }
+
+    /// The maximum number of frames that each buffer may hold.
+    pub fn frame_capacity(&self) -> u32 {
+        self.frame_capacity
+    }
+
+    /// The size of a single frame within a buffer in bytes.
+    pub fn bytes_per_frame(&self) -> usize {
+        self.bytes_per_frame
+    }
+
+    /// Point each buffer back at its own storage and set its size to hold `frames` frames, ready
+    /// to be rendered into.
+    ///
+    /// Returns an `Error` if `frames` exceeds the capacity of the list.
+    pub fn prepare(&mut self, frames: u32) -> Result<(), Error> {
+        if frames > self.frame_capacity {
+            return Err(Error::AudioUnit(
+                error::audio_unit::Error::TooManyFramesToProcess,
+            ));
+        }
+        let byte_size = (frames as usize * self.bytes_per_frame) as u32;
+        let buffers =
+            unsafe { slice::from_raw_parts_mut(buffers_ptr(self.list.as_ptr()), self.data.len()) };
+        for (buffer, words) in buffers.iter_mut().zip(self.data.iter_mut()) {
+            buffer.mDataByteSize = byte_size;
+            buffer.mData = words.as_mut_ptr() as *mut _;
+        }
+        Ok(())
+    }
+
+    fn buffers(&self) -> &[AudioBuffer] {
+        unsafe { slice::from_raw_parts(buffers_ptr(self.list.as_ptr()), self.data.len()) }
+    }
+
+    fn buffers_mut(&mut self) -> &mut [AudioBuffer] {
+        unsafe { slice::from_raw_parts_mut(buffers_ptr(self.list.as_ptr()), self.data.len()) }
+    }
+
+    /// The number of interleaved channels within the buffer at the given index.
+    pub fn channels(&self, index: usize) -> u32 {
+        self.buffers()[index].mNumberChannels
+    }
+
+    /// The valid bytes of the buffer at the given index.
+    ///
+    /// Panics if `index` is out of range.
+    pub fn bytes(&self, index: usize) -> &[u8] {
+        let buffer = &self.buffers()[index];
+        if buffer.mData.is_null() {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(buffer.mData as *const u8, buffer.mDataByteSize as usize) }
+    }
+
+    /// The valid bytes of the buffer at the given index.
+    ///
+    /// Panics if `index` is out of range.
+    pub fn bytes_mut(&mut self, index: usize) -> &mut [u8] {
+        let buffer = &mut self.buffers_mut()[index];
+        if buffer.mData.is_null() {
+            return &mut [];
+        }
+        unsafe { slice::from_raw_parts_mut(buffer.mData as *mut u8, buffer.mDataByteSize as usize) }
+    }
+
+    /// The valid samples of the buffer at the given index.
+    ///
+    /// Panics if `index` is out of range.
+    pub fn samples<S: Sample>(&self, index: usize) -> &[S] {
+        let bytes = self.bytes(index);
+        let len = bytes.len() / mem::size_of::<S>();
+        unsafe { slice::from_raw_parts(bytes.as_ptr() as *const S, len) }
+    }
+
+    /// The valid samples of the buffer at the given index.
+    ///
+    /// Panics if `index` is out of range.
+    pub fn samples_mut<S: Sample>(&mut self, index: usize) -> &mut [S] {
+        let bytes = self.bytes_mut(index);
+        let len = bytes.len() / mem::size_of::<S>();
+        unsafe { slice::from_raw_parts_mut(bytes.as_mut_ptr() as *mut S, len) }
+    }
+
+    /// A pointer to the underlying `AudioBufferList`.
+    pub fn as_ptr(&self) -> *const AudioBufferList {
+        self.list.as_ptr()
+    }
+
+    /// A mutable pointer to the underlying `AudioBufferList`.
+    pub fn as_mut_ptr(&mut self) -> *mut AudioBufferList {
+        self.list.as_ptr()
+    }
+
+    /// A mutable pointer to the underlying `AudioBufferList`, which is never null.
+    pub fn as_non_null(&mut self) -> NonNull<AudioBufferList> {
+        self.list
+    }
+}
+
+impl Drop for OwnedBufferList {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.list.as_ptr() as *mut u8, self.layout) }
+    }
+}
+
+#[test]
+fn test_list_layout() {
+    let one = list_layout(1);
+    assert_eq!(one.size(), mem::size_of::<AudioBufferList>());
+    assert_eq!(list_layout(0).size(), one.size());
+    assert_eq!(
+        list_layout(4).size(),
+        one.size() + 3 * mem::size_of::<AudioBuffer>()
+    );
+}
+
+#[test]
+fn test_owned_buffer_list_for_format() {
+    let mut format = StreamFormat {
+        sample_rate: 44_100.0,
+        sample_format: super::SampleFormat::F32,
+        flags: LinearPcmFlags::IS_FLOAT | LinearPcmFlags::IS_NON_INTERLEAVED,
+        channels: 2,
+    };
+    let mut list = OwnedBufferList::for_format(&format, 256);
+    assert_eq!(list.buffer_count(), 2);
+    assert_eq!(list.channels(1), 1);
+    assert_eq!(list.samples::<f32>(1).len(), 256);
+    list.prepare(64).unwrap();
+    assert_eq!(list.bytes(0).len(), 256);
+    list.samples_mut::<f32>(1)[63] = 0.5;
+    assert_eq!(list.samples::<f32>(1)[63], 0.5);
+    assert!(list.prepare(257).is_err());
+
+    format.flags.remove(LinearPcmFlags::IS_NON_INTERLEAVED);
+    let list = OwnedBufferList::for_format(&format, 256);
+    assert_eq!(list.buffer_count(), 1);
+    assert_eq!(list.channels(0), 2);
+    assert_eq!(list.samples::<f32>(0).len(), 512);
+}
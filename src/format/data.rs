@@ -0,0 +1,257 @@
+//! Format specific audio data views over an `AudioBufferList`.
+//!
+//! These are the types handed to render and input callbacks, though they only depend on the
+//! buffer list layout and so are available without the Audio Unit framework.
+
+use super::audio_format::LinearPcmFlags;
+use super::sys::{AudioBuffer, AudioBufferList};
+use super::{Sample, StreamFormat};
+use std::marker::PhantomData;
+use std::slice;
+
+/// Audio data wrappers specific to the `AudioUnit`'s `AudioFormat`.
+pub trait Data {
+    /// Check whether the stream format matches this type of data.
+    fn does_stream_format_match(stream_format: &StreamFormat) -> bool;
+    /// We must be able to construct Self from arguments given to the `input_proc`.
+    /// # Safety
+    /// TODO document how to use this function safely.
+    unsafe fn from_input_proc_args(num_frames: u32, io_data: *mut AudioBufferList) -> Self;
+}
+
+/// A raw pointer to the audio data so that the user may handle it themselves.
+#[derive(Debug)]
+pub struct Raw {
+    pub data: *mut AudioBufferList,
+}
+
+impl Data for Raw {
+    fn does_stream_format_match(_: &StreamFormat) -> bool {
+        true
+    }
+    unsafe fn from_input_proc_args(_num_frames: u32, io_data: *mut AudioBufferList) -> Self {
+        Raw { data: io_data }
+    }
+}
+
+/// An interleaved linear PCM buffer with samples of type `S`.
+pub struct Interleaved<S: 'static> {
+    /// The audio buffer.
+    pub buffer: &'static mut [S],
+    pub channels: usize,
+    sample_format: PhantomData<S>,
+}
+
+/// An interleaved linear PCM buffer with samples stored as plain bytes.
+pub struct InterleavedBytes<S: 'static> {
+    /// The audio buffer.
+    pub buffer: &'static mut [u8],
+    pub channels: usize,
+    sample_format: PhantomData<S>,
+}
+
+/// A wrapper around the pointer to the `mBuffers` array.
+pub struct NonInterleaved<S> {
+    /// The list of audio buffers.
+    buffers: &'static mut [AudioBuffer],
+    /// The number of frames in each channel.
+    frames: usize,
+    sample_format: PhantomData<S>,
+}
+
+/// An iterator produced by a `NonInterleaved`, yielding a reference to each channel.
+pub struct Channels<'a, S: 'a> {
+    buffers: slice::Iter<'a, AudioBuffer>,
+    frames: usize,
+    sample_format: PhantomData<S>,
+}
+
+/// An iterator produced by a `NonInterleaved`, yielding a mutable reference to each channel.
+pub struct ChannelsMut<'a, S: 'a> {
+    buffers: slice::IterMut<'a, AudioBuffer>,
+    frames: usize,
+    sample_format: PhantomData<S>,
+}
+
+unsafe impl<S> Send for NonInterleaved<S> where S: Send {}
+
+impl<'a, S> Iterator for Channels<'a, S> {
+    type Item = &'a [S];
+    #[allow(non_snake_case)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buffers.next().map(
+            |&AudioBuffer {
+                 mNumberChannels,
+                 mData,
+                 ..
+             }| {
+                let len = mNumberChannels as usize * self.frames;
+                let ptr = mData as *mut S;
+                unsafe { slice::from_raw_parts(ptr, len) }
+            },
+        )
+    }
+}
+
+impl<'a, S> Iterator for ChannelsMut<'a, S> {
+    type Item = &'a mut [S];
+    #[allow(non_snake_case)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buffers.next().map(
+            |&mut AudioBuffer {
+                 mNumberChannels,
+                 mData,
+                 ..
+             }| {
+                let len = mNumberChannels as usize * self.frames;
+                let ptr = mData as *mut S;
+                unsafe { slice::from_raw_parts_mut(ptr, len) }
+            },
+        )
+    }
+}
+
+impl<S> NonInterleaved<S> {
+    /// An iterator yielding a reference to each channel in the array.
+    pub fn channels(&self) -> Channels<'_, S> {
+        Channels {
+            buffers: self.buffers.iter(),
+            frames: self.frames,
+            sample_format: PhantomData,
+        }
+    }
+
+    /// An iterator yielding a mutable reference to each channel in the array.
+    pub fn channels_mut(&mut self) -> ChannelsMut<'_, S> {
+        ChannelsMut {
+            buffers: self.buffers.iter_mut(),
+            frames: self.frames,
+            sample_format: PhantomData,
+        }
+    }
+}
+
+// Implementation for a non-interleaved linear PCM audio format.
+impl<S> Data for NonInterleaved<S>
+where
+    S: Sample,
+{
+    fn does_stream_format_match(stream_format: &StreamFormat) -> bool {
+        stream_format
+            .flags
+            .contains(LinearPcmFlags::IS_NON_INTERLEAVED)
+            && S::sample_format().does_match_flags(stream_format.flags)
+    }
+
+    #[allow(non_snake_case)]
+    unsafe fn from_input_proc_args(frames: u32, io_data: *mut AudioBufferList) -> Self {
+        let ptr = (*io_data).mBuffers.as_ptr() as *mut AudioBuffer;
+        let len = (*io_data).mNumberBuffers as usize;
+        let buffers = slice::from_raw_parts_mut(ptr, len);
+        NonInterleaved {
+            buffers,
+            frames: frames as usize,
+            sample_format: PhantomData,
+        }
+    }
+}
+
+// Implementation for an interleaved linear PCM audio format.
+impl<S> Data for Interleaved<S>
+where
+    S: Sample,
+{
+    fn does_stream_format_match(stream_format: &StreamFormat) -> bool {
+        !stream_format
+            .flags
+            .contains(LinearPcmFlags::IS_NON_INTERLEAVED)
+            && S::sample_format().does_match_flags(stream_format.flags)
+    }
+
+    #[allow(non_snake_case)]
+    unsafe fn from_input_proc_args(frames: u32, io_data: *mut AudioBufferList) -> Self {
+        // // We're expecting a single interleaved buffer which will be the first in the array.
+        let AudioBuffer {
+            mNumberChannels,
+            mDataByteSize,
+            mData,
+        } = (*io_data).mBuffers[0];
+        // // Ensure that the size of the data matches the size of the sample format
+        // // multiplied by the number of frames.
+        // //
+        // // TODO: Return an Err instead of `panic`ing.
+        let buffer_len = frames as usize * mNumberChannels as usize;
+        let expected_size = ::std::mem::size_of::<S>() * buffer_len;
+        assert!(mDataByteSize as usize == expected_size);
+
+        let buffer: &mut [S] = {
+            let buffer_ptr = mData as *mut S;
+            slice::from_raw_parts_mut(buffer_ptr, buffer_len)
+        };
+
+        Interleaved {
+            buffer,
+            channels: mNumberChannels as usize,
+            sample_format: PhantomData,
+        }
+    }
+}
+
+// Implementation for an interleaved linear PCM audio format using plain bytes.
+impl<S> Data for InterleavedBytes<S>
+where
+    S: Sample,
+{
+    fn does_stream_format_match(stream_format: &StreamFormat) -> bool {
+        !stream_format
+            .flags
+            .contains(LinearPcmFlags::IS_NON_INTERLEAVED)
+            && S::sample_format().does_match_flags(stream_format.flags)
+    }
+
+    #[allow(non_snake_case)]
+    unsafe fn from_input_proc_args(frames: u32, io_data: *mut AudioBufferList) -> Self {
+        // // We're expecting a single interleaved buffer which will be the first in the array.
+        let AudioBuffer {
+            mNumberChannels,
+            mDataByteSize,
+            mData,
+        } = (*io_data).mBuffers[0];
+        // // Ensure that the size of the data matches the size of the sample format
+        // // multiplied by the number of frames.
+        // //
+        // // TODO: Return an Err instead of `panic`ing.
+        let buffer_len = frames as usize * mNumberChannels as usize;
+        let expected_size = ::std::mem::size_of::<S>() * buffer_len;
+        assert!(mDataByteSize as usize == expected_size);
+
+        let buffer: &mut [u8] = {
+            let buffer_ptr = mData as *mut u8;
+            slice::from_raw_parts_mut(buffer_ptr, mDataByteSize as usize)
+        };
+
+        InterleavedBytes {
+            buffer,
+            channels: mNumberChannels as usize,
+            sample_format: PhantomData,
+        }
+    }
+}
+
+#[test]
+fn test_non_interleaved_over_owned_buffer_list() {
+    use super::OwnedBufferList;
+
+    let mut list = OwnedBufferList::new(2, 1, 4, 8);
+    list.samples_mut::<f32>(1)[7] = 0.25;
+    {
+        let mut data = unsafe { NonInterleaved::<f32>::from_input_proc_args(8, list.as_mut_ptr()) };
+        for channel in data.channels_mut() {
+            channel[0] = 1.0;
+        }
+        let channels: Vec<&[f32]> = data.channels().collect();
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels[1][7], 0.25);
+    }
+    assert_eq!(list.samples::<f32>(0)[0], 1.0);
+}
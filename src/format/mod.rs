@@ -0,0 +1,19 @@
+//! Platform independent audio format and buffer types.
+//!
+//! These types describe linear PCM streams and own or view their sample data. They do not call
+//! into any Apple framework and so are available on every target, while the
+//! [audio_unit](../audio_unit/index.html) module layers the FFI on top of them.
+
+pub use self::audio_format::AudioFormat;
+pub use self::buffer_list::OwnedBufferList;
+pub use self::negotiation::FormatRequest;
+pub use self::sample_format::{Sample, SampleFormat};
+pub use self::stream_format::StreamFormat;
+
+pub mod audio_format;
+pub mod buffer_list;
+pub mod data;
+pub mod negotiation;
+pub mod sample_format;
+pub mod stream_format;
+pub mod sys;
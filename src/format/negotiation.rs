@@ -0,0 +1,465 @@
+//! Ranking the formats supported by a stream against a requested format.
+//!
+//! A [**FormatRequest**](./struct.FormatRequest) holds the constraints a format must satisfy and
+//! those it should preferably satisfy. Negotiating a request against a list of
+//! `AudioStreamRangedDescription`s, such as the available physical formats of a stream, ranks
+//! the acceptable formats from best to worst and explains why each of the others was rejected.
+//!
+//! Negotiation does not query any device, so that it may be used with any list of formats.
+
+use std::fmt;
+
+use super::audio_format::{AudioFormat, LinearPcmFlags};
+use super::sys::{AudioStreamBasicDescription, AudioStreamRangedDescription};
+use super::{SampleFormat, StreamFormat};
+
+/// Constraints on the properties of a linear PCM format.
+///
+/// Each field left as `None` does not constrain the format.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FormatConstraints {
+    /// The sample rate, which must lie within the sample rate range of the format.
+    pub sample_rate: Option<f64>,
+    /// The sample format.
+    pub sample_format: Option<SampleFormat>,
+    /// The number of channels.
+    ///
+    /// As a requirement this is the minimum number of channels of the format, as a device may
+    /// carry more channels than are used. As a preference it is the exact number of channels.
+    pub channels: Option<u32>,
+    /// Whether or not the channels are interleaved.
+    pub interleaved: Option<bool>,
+    /// Whether or not the format may be mixed with the audio of other clients.
+    pub mixable: Option<bool>,
+}
+
+/// A request for a format, as the constraints it must satisfy and those it preferably satisfies.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FormatRequest {
+    /// The constraints that every candidate satisfies.
+    pub required: FormatConstraints,
+    /// The constraints used to rank the candidates.
+    pub preferred: FormatConstraints,
+}
+
+/// A property of a format that failed to satisfy a constraint of a
+/// [**FormatRequest**](./struct.FormatRequest).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mismatch {
+    /// The format is not linear PCM, and holds the `AudioFormatID` of the format instead.
+    NotLinearPcm(u32),
+    /// The requested sample rate lies outside the sample rate range of the format.
+    SampleRate {
+        requested: f64,
+        minimum: f64,
+        maximum: f64,
+    },
+    /// The format has a different sample format, or one not representable by **SampleFormat**.
+    SampleFormat {
+        requested: SampleFormat,
+        available: Option<SampleFormat>,
+    },
+    /// The format has too few channels for a requirement, or not exactly the preferred number.
+    Channels { requested: u32, available: u32 },
+    /// The channels of the format are interleaved when they should not be, or the other way
+    /// around.
+    Interleaved { requested: bool },
+    /// The format is mixable when it should not be, or the other way around.
+    Mixable { requested: bool },
+}
+
+/// A format satisfying every requirement of a [**FormatRequest**](./struct.FormatRequest).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    /// The index of the format within the negotiated list.
+    pub index: usize,
+    /// The format, with its sample rate set to the requested rate whenever the rate lies within
+    /// the sample rate range of the format.
+    pub format: AudioStreamBasicDescription,
+    /// The sum of the weights of the satisfied preferences. Candidates with a higher score are
+    /// ranked first.
+    pub score: u32,
+    /// The preferences that the format does not satisfy, most important first.
+    pub unmet: Vec<Mismatch>,
+}
+
+/// A format failing at least one requirement of a [**FormatRequest**](./struct.FormatRequest).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rejection {
+    /// The index of the format within the negotiated list.
+    pub index: usize,
+    /// The requirements that the format does not satisfy, most important first.
+    pub reasons: Vec<Mismatch>,
+}
+
+/// The result of negotiating a [**FormatRequest**](./struct.FormatRequest) against a list of
+/// formats.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Negotiation {
+    /// The formats satisfying every requirement, best first.
+    pub candidates: Vec<Candidate>,
+    /// The formats failing at least one requirement, in the order of the negotiated list.
+    pub rejections: Vec<Rejection>,
+}
+
+// The weights of the preferences, such that each preference outweighs all of the less important
+// preferences combined.
+const SAMPLE_RATE_WEIGHT: u32 = 16;
+const SAMPLE_FORMAT_WEIGHT: u32 = 8;
+const CHANNELS_WEIGHT: u32 = 4;
+const MIXABLE_WEIGHT: u32 = 2;
+const INTERLEAVED_WEIGHT: u32 = 1;
+
+impl FormatConstraints {
+    /// The sum of the weights of the constraints, as preferences.
+    fn weight(&self) -> u32 {
+        let weights = [
+            (self.sample_rate.is_some(), SAMPLE_RATE_WEIGHT),
+            (self.sample_format.is_some(), SAMPLE_FORMAT_WEIGHT),
+            (self.channels.is_some(), CHANNELS_WEIGHT),
+            (self.mixable.is_some(), MIXABLE_WEIGHT),
+            (self.interleaved.is_some(), INTERLEAVED_WEIGHT),
+        ];
+        weights
+            .iter()
+            .filter(|(constrained, _)| *constrained)
+            .map(|(_, weight)| weight)
+            .sum()
+    }
+}
+
+impl FormatRequest {
+    /// A request requiring the sample rate, the sample format and at least the number of
+    /// channels of the given **StreamFormat**.
+    ///
+    /// The exact number of channels, the interleaving given by the flags of the **StreamFormat**
+    /// and mixable formats are preferred.
+    pub fn from_stream_format(stream_format: StreamFormat) -> Self {
+        let interleaved = !stream_format
+            .flags
+            .contains(LinearPcmFlags::IS_NON_INTERLEAVED);
+        FormatRequest {
+            required: FormatConstraints {
+                sample_rate: Some(stream_format.sample_rate),
+                sample_format: Some(stream_format.sample_format),
+                channels: Some(stream_format.channels),
+                ..Default::default()
+            },
+            preferred: FormatConstraints {
+                channels: Some(stream_format.channels),
+                interleaved: Some(interleaved),
+                mixable: Some(true),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Rank the given formats against the request.
+    ///
+    /// Candidates are ordered by descending score. Candidates with equal scores are ordered by
+    /// how far their number of channels is from the requested number, and then by their
+    /// position in `formats`.
+    pub fn negotiate(&self, formats: &[AudioStreamRangedDescription]) -> Negotiation {
+        let mut negotiation = Negotiation::default();
+        for (index, ranged) in formats.iter().enumerate() {
+            match self.evaluate(index, ranged) {
+                Ok(candidate) => negotiation.candidates.push(candidate),
+                Err(reasons) => negotiation.rejections.push(Rejection { index, reasons }),
+            }
+        }
+        let channels = self.preferred.channels.or(self.required.channels);
+        negotiation.candidates.sort_by_key(|candidate| {
+            let available = candidate.format.mChannelsPerFrame;
+            let distance = channels.map_or(0, |requested| {
+                (i64::from(available) - i64::from(requested)).abs()
+            });
+            (
+                std::cmp::Reverse(candidate.score),
+                distance,
+                candidate.index,
+            )
+        });
+        negotiation
+    }
+
+    fn evaluate(
+        &self,
+        index: usize,
+        ranged: &AudioStreamRangedDescription,
+    ) -> Result<Candidate, Vec<Mismatch>> {
+        let mut format = ranged.mFormat;
+        let flags =
+            match AudioFormat::from_format_and_flag(format.mFormatID, Some(format.mFormatFlags)) {
+                Some(AudioFormat::LinearPCM(flags)) => flags,
+                _ => return Err(vec![Mismatch::NotLinearPcm(format.mFormatID)]),
+            };
+
+        let reasons = check(&self.required, ranged, flags, true);
+        if !reasons.is_empty() {
+            return Err(reasons);
+        }
+        let unmet = check(&self.preferred, ranged, flags, false);
+
+        let sample_rate = self.preferred.sample_rate.or(self.required.sample_rate);
+        if let Some(rate) = sample_rate {
+            if in_range(rate, ranged) {
+                format.mSampleRate = rate;
+            }
+        }
+
+        let score = self.preferred.weight() - unmet.iter().map(Mismatch::weight).sum::<u32>();
+
+        Ok(Candidate {
+            index,
+            format,
+            score,
+            unmet,
+        })
+    }
+}
+
+/// Whether or not the rate is the rate of the format or lies within its sample rate range.
+fn in_range(rate: f64, ranged: &AudioStreamRangedDescription) -> bool {
+    let range = &ranged.mSampleRateRange;
+    rate == ranged.mFormat.mSampleRate || (rate >= range.mMinimum && rate <= range.mMaximum)
+}
+
+/// The constraints not satisfied by the linear PCM format with the given flags, most important
+/// first.
+///
+/// A required number of channels is a minimum, whereas a preferred number is exact.
+fn check(
+    constraints: &FormatConstraints,
+    ranged: &AudioStreamRangedDescription,
+    flags: LinearPcmFlags,
+    required: bool,
+) -> Vec<Mismatch> {
+    let format = &ranged.mFormat;
+    let mut mismatches = Vec::new();
+
+    if let Some(requested) = constraints.sample_rate {
+        if !in_range(requested, ranged) {
+            mismatches.push(Mismatch::SampleRate {
+                requested,
+                minimum: ranged.mSampleRateRange.mMinimum,
+                maximum: ranged.mSampleRateRange.mMaximum,
+            });
+        }
+    }
+    if let Some(requested) = constraints.sample_format {
+        let available = SampleFormat::from_flags_and_bits_per_sample(flags, format.mBitsPerChannel);
+        if available != Some(requested) {
+            mismatches.push(Mismatch::SampleFormat {
+                requested,
+                available,
+            });
+        }
+    }
+    if let Some(requested) = constraints.channels {
+        let available = format.mChannelsPerFrame;
+        let satisfied = if required {
+            available >= requested
+        } else {
+            available == requested
+        };
+        if !satisfied {
+            mismatches.push(Mismatch::Channels {
+                requested,
+                available,
+            });
+        }
+    }
+    if let Some(requested) = constraints.mixable {
+        if flags.contains(LinearPcmFlags::IS_NON_MIXABLE) == requested {
+            mismatches.push(Mismatch::Mixable { requested });
+        }
+    }
+    if let Some(requested) = constraints.interleaved {
+        if flags.contains(LinearPcmFlags::IS_NON_INTERLEAVED) == requested {
+            mismatches.push(Mismatch::Interleaved { requested });
+        }
+    }
+    mismatches
+}
+
+impl Mismatch {
+    /// The weight of the constraint as a preference.
+    fn weight(&self) -> u32 {
+        match *self {
+            Mismatch::NotLinearPcm(_) => 0,
+            Mismatch::SampleRate { .. } => SAMPLE_RATE_WEIGHT,
+            Mismatch::SampleFormat { .. } => SAMPLE_FORMAT_WEIGHT,
+            Mismatch::Channels { .. } => CHANNELS_WEIGHT,
+            Mismatch::Mixable { .. } => MIXABLE_WEIGHT,
+            Mismatch::Interleaved { .. } => INTERLEAVED_WEIGHT,
+        }
+    }
+}
+
+impl Negotiation {
+    /// The best candidate, if any format satisfies every requirement.
+    pub fn best(&self) -> Option<&Candidate> {
+        self.candidates.first()
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Mismatch::NotLinearPcm(format_id) => {
+                write!(f, "format {format_id:#010x} is not linear PCM")
+            }
+            Mismatch::SampleRate {
+                requested,
+                minimum,
+                maximum,
+            } => write!(
+                f,
+                "sample rate {requested} is outside of the range {minimum} to {maximum}"
+            ),
+            Mismatch::SampleFormat {
+                requested,
+                available: Some(available),
+            } => write!(
+                f,
+                "sample format is {available:?} rather than {requested:?}"
+            ),
+            Mismatch::SampleFormat {
+                requested,
+                available: None,
+            } => write!(f, "sample format is not {requested:?}"),
+            Mismatch::Channels {
+                requested,
+                available,
+            } => write!(f, "{available} channels rather than {requested}"),
+            Mismatch::Interleaved { requested: true } => write!(f, "channels are not interleaved"),
+            Mismatch::Interleaved { requested: false } => write!(f, "channels are interleaved"),
+            Mismatch::Mixable { requested: true } => write!(f, "format is not mixable"),
+            Mismatch::Mixable { requested: false } => write!(f, "format is mixable"),
+        }
+    }
+}
+
+/// A ranged description of a linear PCM format, spanning the given range of sample rates and
+/// described at the lower rate.
+#[cfg(test)]
+pub(crate) fn ranged(
+    sample_format: SampleFormat,
+    flags: LinearPcmFlags,
+    channels: u32,
+    rates: (f64, f64),
+) -> AudioStreamRangedDescription {
+    let stream_format = StreamFormat {
+        sample_rate: rates.0,
+        sample_format,
+        flags,
+        channels,
+    };
+    AudioStreamRangedDescription {
+        mFormat: stream_format.to_asbd(),
+        mSampleRateRange: super::sys::AudioValueRange {
+            mMinimum: rates.0,
+            mMaximum: rates.1,
+        },
+    }
+}
+
+#[test]
+fn test_negotiate_ranks_candidates() {
+    let int = LinearPcmFlags::IS_SIGNED_INTEGER | LinearPcmFlags::IS_PACKED;
+    let float = LinearPcmFlags::IS_FLOAT | LinearPcmFlags::IS_PACKED;
+    let formats = [
+        ranged(SampleFormat::I16, int, 48, (44_100.0, 96_000.0)),
+        ranged(SampleFormat::F32, float, 2, (44_100.0, 96_000.0)),
+        ranged(
+            SampleFormat::I16,
+            int | LinearPcmFlags::IS_NON_MIXABLE,
+            2,
+            (44_100.0, 96_000.0),
+        ),
+        ranged(SampleFormat::I16, int, 2, (44_100.0, 48_000.0)),
+        ranged(SampleFormat::I16, int, 8, (48_000.0, 48_000.0)),
+        ranged(SampleFormat::I16, int, 1, (48_000.0, 48_000.0)),
+    ];
+    let request = FormatRequest::from_stream_format(StreamFormat {
+        sample_rate: 88_200.0,
+        sample_format: SampleFormat::I16,
+        flags: int,
+        channels: 2,
+    });
+    let negotiation = request.negotiate(&formats);
+
+    let ranking: Vec<usize> = negotiation.candidates.iter().map(|c| c.index).collect();
+    assert_eq!(ranking, [2, 0]);
+    let best = negotiation.best().unwrap();
+    assert_eq!(best.format.mSampleRate, 88_200.0);
+    assert_eq!(best.unmet, [Mismatch::Mixable { requested: true }]);
+    assert!(negotiation.candidates[1]
+        .unmet
+        .iter()
+        .any(|mismatch| matches!(
+            mismatch,
+            Mismatch::Channels {
+                requested: 2,
+                available: 48
+            }
+        )));
+
+    let rejected: Vec<usize> = negotiation.rejections.iter().map(|r| r.index).collect();
+    assert_eq!(rejected, [1, 3, 4, 5]);
+    let reasons: Vec<String> = negotiation
+        .rejections
+        .iter()
+        .map(|rejection| rejection.reasons[0].to_string())
+        .collect();
+    assert_eq!(
+        reasons,
+        [
+            "sample format is F32 rather than I16",
+            "sample rate 88200 is outside of the range 44100 to 48000",
+            "sample rate 88200 is outside of the range 48000 to 48000",
+            "sample rate 88200 is outside of the range 48000 to 48000",
+        ]
+    );
+    assert_eq!(negotiation.rejections[3].reasons.len(), 2);
+}
+
+#[test]
+fn test_negotiate_preferences_only() {
+    let int = LinearPcmFlags::IS_SIGNED_INTEGER | LinearPcmFlags::IS_PACKED;
+    let float = LinearPcmFlags::IS_FLOAT | LinearPcmFlags::IS_PACKED;
+    let mut formats = vec![
+        ranged(SampleFormat::I24, int, 2, (48_000.0, 48_000.0)),
+        ranged(
+            SampleFormat::F32,
+            float | LinearPcmFlags::IS_NON_INTERLEAVED,
+            2,
+            (44_100.0, 44_100.0),
+        ),
+        ranged(SampleFormat::F32, float, 2, (48_000.0, 48_000.0)),
+    ];
+    formats[0].mFormat.mFormatID = u32::from_be_bytes(*b"ac-3");
+    let request = FormatRequest {
+        preferred: FormatConstraints {
+            sample_rate: Some(48_000.0),
+            sample_format: Some(SampleFormat::F32),
+            interleaved: Some(false),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let negotiation = request.negotiate(&formats);
+
+    let scores: Vec<(usize, u32)> = negotiation
+        .candidates
+        .iter()
+        .map(|candidate| (candidate.index, candidate.score))
+        .collect();
+    assert_eq!(scores, [(2, 24), (1, 9)]);
+    assert_eq!(negotiation.candidates[1].format.mSampleRate, 44_100.0);
+    assert_eq!(
+        negotiation.rejections[0].reasons,
+        [Mismatch::NotLinearPcm(u32::from_be_bytes(*b"ac-3"))]
+    );
+    assert!(FormatRequest::default().negotiate(&[]).best().is_none());
+}
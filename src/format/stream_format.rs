@@ -2,7 +2,7 @@
 //!
 //! Find the original `AudioStreamBasicDescription` reference [here](https://developer.apple.com/library/mac/documentation/MusicAudio/Reference/CoreAudioDataTypesRef/#//apple_ref/c/tdef/AudioStreamBasicDescription).
 
-use objc2_core_audio_types::AudioStreamBasicDescription;
+use super::sys::AudioStreamBasicDescription;
 
 use super::audio_format::AudioFormat;
 use super::audio_format::LinearPcmFlags;
@@ -143,3 +143,25 @@ impl StreamFormat {
         }
     }
 }
+
+#[test]
+fn test_asbd_round_trip() {
+    let format = StreamFormat {
+        sample_rate: 48_000.0,
+        sample_format: SampleFormat::I16,
+        flags: LinearPcmFlags::IS_SIGNED_INTEGER | LinearPcmFlags::IS_PACKED,
+        channels: 2,
+    };
+    let asbd = format.to_asbd();
+    assert_eq!(asbd.mBytesPerFrame, 4);
+    assert_eq!(asbd.mBitsPerChannel, 16);
+    let parsed = StreamFormat::from_asbd(asbd).unwrap();
+    assert_eq!(parsed.sample_rate, 48_000.0);
+    assert_eq!(parsed.sample_format, SampleFormat::I16);
+    assert_eq!(parsed.flags.bits(), format.flags.bits());
+    assert_eq!(parsed.channels, 2);
+
+    let mut asbd = asbd;
+    asbd.mFormatID = u32::from_be_bytes(*b"ac-3");
+    assert!(StreamFormat::from_asbd(asbd).is_err());
+}
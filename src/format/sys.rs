@@ -0,0 +1,86 @@
+//! The Core Audio data types underlying the format and buffer types.
+//!
+//! When either Apple framework feature is enabled these are the `objc2-core-audio-types`
+//! definitions, so that values may be passed directly to the FFI layer. Otherwise, layout
+//! compatible mirrors are declared here so that the platform independent types still build.
+
+#![allow(non_snake_case)]
+
+#[cfg(any(feature = "audio_toolbox", feature = "core_audio"))]
+pub use objc2_core_audio::AudioStreamRangedDescription;
+#[cfg(any(feature = "audio_toolbox", feature = "core_audio"))]
+pub use objc2_core_audio_types::{
+    AudioBuffer, AudioBufferList, AudioStreamBasicDescription, AudioValueRange,
+};
+
+#[cfg(not(any(feature = "audio_toolbox", feature = "core_audio")))]
+pub use self::mirror::{
+    AudioBuffer, AudioBufferList, AudioStreamBasicDescription, AudioStreamRangedDescription,
+    AudioValueRange,
+};
+
+#[cfg(not(any(feature = "audio_toolbox", feature = "core_audio")))]
+mod mirror {
+    use std::os::raw::c_void;
+
+    /// A buffer of audio data, laid out as Core Audio's `AudioBuffer`.
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct AudioBuffer {
+        pub mNumberChannels: u32,
+        pub mDataByteSize: u32,
+        pub mData: *mut c_void,
+    }
+
+    /// A variable length list of buffers, laid out as Core Audio's `AudioBufferList`.
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct AudioBufferList {
+        pub mNumberBuffers: u32,
+        pub mBuffers: [AudioBuffer; 1],
+    }
+
+    /// A description of a stream's format, laid out as Core Audio's
+    /// `AudioStreamBasicDescription`.
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct AudioStreamBasicDescription {
+        pub mSampleRate: f64,
+        pub mFormatID: u32,
+        pub mFormatFlags: u32,
+        pub mBytesPerPacket: u32,
+        pub mFramesPerPacket: u32,
+        pub mBytesPerFrame: u32,
+        pub mChannelsPerFrame: u32,
+        pub mBitsPerChannel: u32,
+        pub mReserved: u32,
+    }
+
+    /// An inclusive range of values, laid out as Core Audio's `AudioValueRange`.
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct AudioValueRange {
+        pub mMinimum: f64,
+        pub mMaximum: f64,
+    }
+
+    /// A format along with the range of sample rates it supports, laid out as Core Audio's
+    /// `AudioStreamRangedDescription`.
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct AudioStreamRangedDescription {
+        pub mFormat: AudioStreamBasicDescription,
+        pub mSampleRateRange: AudioValueRange,
+    }
+}
+
+#[test]
+fn test_layout() {
+    use std::mem;
+    assert_eq!(mem::size_of::<AudioStreamBasicDescription>(), 40);
+    assert_eq!(mem::size_of::<AudioStreamRangedDescription>(), 56);
+    assert_eq!(
+        mem::size_of::<AudioBufferList>(),
+        mem::align_of::<AudioBuffer>() + mem::size_of::<AudioBuffer>()
+    );
+}
@@ -8,6 +8,15 @@
 //! Currently, this crate provides the [audio_unit] module that covers the
 //! [Audio Unit framework](https://developer.apple.com/documentation/audiounit)
 //! (now part of [Audio Toolbox](https://developer.apple.com/documentation/AudioToolbox)).
+//!
+//! The [core_midi] module, enabled by the `core_midi` feature, covers the
+//! [Core MIDI framework](https://developer.apple.com/documentation/coremidi).
+//!
+//! The [format](mod@format) module holds the stream format and buffer types shared by the above, and builds
+//! on every platform.
+//!
+//! The [midi] module encodes and decodes MIDI 1.0 messages and MIDI 2.0 Universal MIDI Packets
+//! without depending on any framework.
 
 #[macro_use]
 extern crate bitflags;
@@ -16,7 +25,17 @@ pub use error::Error;
 
 #[cfg(feature = "audio_toolbox")]
 pub mod audio_unit;
+#[cfg(feature = "core_midi")]
+pub mod core_midi;
 pub mod error;
+pub mod format;
+pub mod midi;
+// Only used by the framework modules, but built everywhere so that its tests run on any platform.
+#[cfg_attr(
+    not(any(feature = "audio_toolbox", feature = "core_midi")),
+    allow(dead_code)
+)]
+mod shared_callback;
 
 // MacTypes.h
 pub type OSStatus = i32;
@@ -0,0 +1,450 @@
+//! 14-bit controllers and registered and non-registered parameter numbers.
+//!
+//! MIDI 1.0 controllers `0..32` may be paired with controllers `32..64` to form 14-bit values,
+//! with the former carrying the most significant bits. Parameter numbers extend this further:
+//! controllers `101` and `100` (RPN) or `99` and `98` (NRPN) select a 14-bit parameter, after
+//! which data entry controllers `6` and `38` set its 14-bit value and controllers `96` and `97`
+//! increment or decrement it.
+//!
+//! The [**ControllerDecoder**](./struct.ControllerDecoder) tracks this state for each channel,
+//! while the functions within this module produce the messages for each.
+
+use super::{check_channel, MidiMessage};
+use crate::error::MidiError;
+
+/// The data entry controller carrying the most significant bits of a parameter value.
+pub const DATA_ENTRY_MSB: u8 = 6;
+/// The data entry controller carrying the least significant bits of a parameter value.
+pub const DATA_ENTRY_LSB: u8 = 38;
+/// The controller incrementing the selected parameter.
+pub const DATA_INCREMENT: u8 = 96;
+/// The controller decrementing the selected parameter.
+pub const DATA_DECREMENT: u8 = 97;
+/// The controller selecting the least significant bits of a non-registered parameter number.
+pub const NRPN_LSB: u8 = 98;
+/// The controller selecting the most significant bits of a non-registered parameter number.
+pub const NRPN_MSB: u8 = 99;
+/// The controller selecting the least significant bits of a registered parameter number.
+pub const RPN_LSB: u8 = 100;
+/// The controller selecting the most significant bits of a registered parameter number.
+pub const RPN_MSB: u8 = 101;
+/// The parameter number that deselects any parameter, so that stray data entry is ignored.
+pub const NULL_PARAMETER: u16 = 0x3FFF;
+
+/// Registered parameter `0`, the pitch bend range in semitones (MSB) and cents (LSB).
+pub const RPN_PITCH_BEND_SENSITIVITY: u16 = 0x0000;
+/// Registered parameter `1`, the fine tuning of a channel.
+pub const RPN_FINE_TUNING: u16 = 0x0001;
+/// Registered parameter `2`, the coarse tuning of a channel.
+pub const RPN_COARSE_TUNING: u16 = 0x0002;
+
+/// Whether a parameter number is registered (RPN) or non-registered (NRPN).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ParameterKind {
+    Registered,
+    NonRegistered,
+}
+
+impl ParameterKind {
+    /// The controllers selecting the most and least significant bits of the parameter number.
+    fn controllers(&self) -> (u8, u8) {
+        match *self {
+            ParameterKind::Registered => (RPN_MSB, RPN_LSB),
+            ParameterKind::NonRegistered => (NRPN_MSB, NRPN_LSB),
+        }
+    }
+}
+
+/// A control change interpreted in the context of the messages that came before it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ControlEvent {
+    /// A controller without a 14-bit interpretation.
+    Control {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+    /// A change to one half of a 14-bit controller in the range `0..32`, combined with the most
+    /// recent value of the other half.
+    ///
+    /// Upon receiving the most significant bits the least significant are taken to be `0`, as
+    /// recommended by the MIDI 1.0 specification.
+    Control14 {
+        channel: u8,
+        controller: u8,
+        value: u16,
+    },
+    /// Data entry for the selected parameter.
+    Parameter {
+        channel: u8,
+        kind: ParameterKind,
+        parameter: u16,
+        value: u16,
+    },
+    /// An increment or decrement of the selected parameter.
+    ParameterStep {
+        channel: u8,
+        kind: ParameterKind,
+        parameter: u16,
+        increment: bool,
+    },
+}
+
+#[derive(Copy, Clone, Debug)]
+struct ChannelState {
+    msb: [u8; 32],
+    kind: ParameterKind,
+    parameter: [u8; 2],
+    data_msb: u8,
+}
+
+impl Default for ChannelState {
+    fn default() -> Self {
+        ChannelState {
+            msb: [0; 32],
+            kind: ParameterKind::Registered,
+            parameter: [0x7F, 0x7F],
+            data_msb: 0,
+        }
+    }
+}
+
+impl ChannelState {
+    /// The selected parameter, or `None` if the null parameter is selected.
+    fn parameter(&self) -> Option<(ParameterKind, u16)> {
+        let number = (self.parameter[0] as u16) << 7 | self.parameter[1] as u16;
+        if number == NULL_PARAMETER {
+            None
+        } else {
+            Some((self.kind, number))
+        }
+    }
+
+    fn select(&mut self, kind: ParameterKind, index: usize, value: u8) {
+        if self.kind != kind {
+            self.kind = kind;
+            self.parameter = [0, 0];
+        }
+        self.parameter[index] = value;
+        self.data_msb = 0;
+    }
+}
+
+/// Interprets control changes on all sixteen channels, tracking 14-bit controllers and the
+/// selected parameter of each.
+///
+/// No parameter is selected initially, so data entry is treated as a 14-bit controller until a
+/// parameter number is received.
+#[derive(Clone, Debug, Default)]
+pub struct ControllerDecoder {
+    channels: [ChannelState; 16],
+}
+
+impl ControllerDecoder {
+    /// Construct a decoder with no parameters selected.
+    pub fn new() -> Self {
+        ControllerDecoder::default()
+    }
+
+    /// Interpret the given message.
+    ///
+    /// Returns `None` for messages other than control changes, along with control changes that
+    /// only select a parameter.
+    pub fn decode(&mut self, message: &MidiMessage) -> Option<ControlEvent> {
+        match *message {
+            MidiMessage::ControlChange {
+                channel,
+                controller,
+                value,
+            } if channel < 16 => self.control_change(channel, controller, value),
+            _ => None,
+        }
+    }
+
+    fn control_change(&mut self, channel: u8, controller: u8, value: u8) -> Option<ControlEvent> {
+        let state = &mut self.channels[channel as usize];
+        let event = match controller {
+            RPN_MSB | RPN_LSB | NRPN_MSB | NRPN_LSB => {
+                let kind = match controller {
+                    RPN_MSB | RPN_LSB => ParameterKind::Registered,
+                    _ => ParameterKind::NonRegistered,
+                };
+                let index = match controller {
+                    RPN_MSB | NRPN_MSB => 0,
+                    _ => 1,
+                };
+                state.select(kind, index, value);
+                return None;
+            }
+            DATA_ENTRY_MSB | DATA_ENTRY_LSB | DATA_INCREMENT | DATA_DECREMENT
+                if state.parameter().is_some() =>
+            {
+                let (kind, parameter) = state.parameter().expect("selected parameter");
+                match controller {
+                    DATA_ENTRY_MSB => {
+                        state.data_msb = value;
+                        ControlEvent::Parameter {
+                            channel,
+                            kind,
+                            parameter,
+                            value: (value as u16) << 7,
+                        }
+                    }
+                    DATA_ENTRY_LSB => ControlEvent::Parameter {
+                        channel,
+                        kind,
+                        parameter,
+                        value: (state.data_msb as u16) << 7 | value as u16,
+                    },
+                    _ => ControlEvent::ParameterStep {
+                        channel,
+                        kind,
+                        parameter,
+                        increment: controller == DATA_INCREMENT,
+                    },
+                }
+            }
+            0..=31 => {
+                state.msb[controller as usize] = value;
+                ControlEvent::Control14 {
+                    channel,
+                    controller,
+                    value: (value as u16) << 7,
+                }
+            }
+            32..=63 => {
+                let msb = state.msb[controller as usize - 32];
+                ControlEvent::Control14 {
+                    channel,
+                    controller: controller - 32,
+                    value: (msb as u16) << 7 | value as u16,
+                }
+            }
+            _ => ControlEvent::Control {
+                channel,
+                controller,
+                value,
+            },
+        };
+        Some(event)
+    }
+}
+
+fn control(channel: u8, controller: u8, value: u8) -> MidiMessage {
+    MidiMessage::ControlChange {
+        channel,
+        controller,
+        value,
+    }
+}
+
+fn split(value: u16) -> Result<(u8, u8), MidiError> {
+    if value > 0x3FFF {
+        return Err(MidiError::InvalidDataByte);
+    }
+    Ok(((value >> 7) as u8, (value & 0x7F) as u8))
+}
+
+/// The pair of control changes setting a 14-bit controller, most significant bits first.
+///
+/// Returns an `Error` if `controller` is not in the range `0..32` or `value` exceeds 14 bits.
+pub fn control_14(channel: u8, controller: u8, value: u16) -> Result<[MidiMessage; 2], MidiError> {
+    check_channel(channel)?;
+    if controller >= 32 {
+        return Err(MidiError::InvalidDataByte);
+    }
+    let (msb, lsb) = split(value)?;
+    Ok([
+        control(channel, controller, msb),
+        control(channel, controller + 32, lsb),
+    ])
+}
+
+/// The control changes selecting a parameter and setting its 14-bit value.
+///
+/// The parameter remains selected afterwards. Follow with
+/// [**select_null_parameter**](./fn.select_null_parameter.html) to prevent later data entry
+/// from changing it.
+pub fn set_parameter(
+    channel: u8,
+    kind: ParameterKind,
+    parameter: u16,
+    value: u16,
+) -> Result<[MidiMessage; 4], MidiError> {
+    let [select_msb, select_lsb] = select_parameter(channel, kind, parameter)?;
+    let (msb, lsb) = split(value)?;
+    Ok([
+        select_msb,
+        select_lsb,
+        control(channel, DATA_ENTRY_MSB, msb),
+        control(channel, DATA_ENTRY_LSB, lsb),
+    ])
+}
+
+/// The pair of control changes selecting a parameter.
+pub fn select_parameter(
+    channel: u8,
+    kind: ParameterKind,
+    parameter: u16,
+) -> Result<[MidiMessage; 2], MidiError> {
+    check_channel(channel)?;
+    let (msb_controller, lsb_controller) = kind.controllers();
+    let (msb, lsb) = split(parameter)?;
+    Ok([
+        control(channel, msb_controller, msb),
+        control(channel, lsb_controller, lsb),
+    ])
+}
+
+/// The pair of control changes deselecting any parameter.
+pub fn select_null_parameter(channel: u8) -> Result<[MidiMessage; 2], MidiError> {
+    select_parameter(channel, ParameterKind::Registered, NULL_PARAMETER)
+}
+
+#[cfg(test)]
+fn decode_all(decoder: &mut ControllerDecoder, messages: &[MidiMessage]) -> Vec<ControlEvent> {
+    messages.iter().filter_map(|m| decoder.decode(m)).collect()
+}
+
+#[test]
+fn test_control_14() {
+    let messages = control_14(3, 7, 0x1234).unwrap();
+    assert_eq!(messages[0], control(3, 7, 0x24));
+    assert_eq!(messages[1], control(3, 39, 0x34));
+    assert!(control_14(3, 32, 0).is_err());
+    assert!(control_14(3, 1, 0x4000).is_err());
+    assert!(control_14(16, 1, 0).is_err());
+
+    let mut decoder = ControllerDecoder::new();
+    assert_eq!(
+        decode_all(&mut decoder, &messages),
+        vec![
+            ControlEvent::Control14 {
+                channel: 3,
+                controller: 7,
+                value: 0x1200
+            },
+            ControlEvent::Control14 {
+                channel: 3,
+                controller: 7,
+                value: 0x1234
+            },
+        ]
+    );
+    // Another LSB reuses the most recent MSB, while other channels are unaffected.
+    assert_eq!(
+        decoder.decode(&control(3, 39, 1)),
+        Some(ControlEvent::Control14 {
+            channel: 3,
+            controller: 7,
+            value: 0x1201
+        })
+    );
+    assert_eq!(
+        decoder.decode(&control(4, 39, 1)),
+        Some(ControlEvent::Control14 {
+            channel: 4,
+            controller: 7,
+            value: 1
+        })
+    );
+    assert_eq!(
+        decoder.decode(&control(0, 64, 127)),
+        Some(ControlEvent::Control {
+            channel: 0,
+            controller: 64,
+            value: 127
+        })
+    );
+    assert_eq!(decoder.decode(&MidiMessage::TimingClock), None);
+}
+
+#[test]
+fn test_parameter_numbers() {
+    let mut decoder = ControllerDecoder::new();
+    let messages = set_parameter(
+        0,
+        ParameterKind::Registered,
+        RPN_PITCH_BEND_SENSITIVITY,
+        12 << 7,
+    )
+    .unwrap();
+    assert_eq!(
+        messages,
+        [
+            control(0, RPN_MSB, 0),
+            control(0, RPN_LSB, 0),
+            control(0, DATA_ENTRY_MSB, 12),
+            control(0, DATA_ENTRY_LSB, 0),
+        ]
+    );
+    let event = |value| ControlEvent::Parameter {
+        channel: 0,
+        kind: ParameterKind::Registered,
+        parameter: RPN_PITCH_BEND_SENSITIVITY,
+        value,
+    };
+    assert_eq!(
+        decode_all(&mut decoder, &messages),
+        vec![event(12 << 7), event(12 << 7)]
+    );
+    assert_eq!(
+        decoder.decode(&control(0, DATA_ENTRY_LSB, 50)),
+        Some(event(12 << 7 | 50))
+    );
+    assert_eq!(
+        decoder.decode(&control(0, DATA_INCREMENT, 0)),
+        Some(ControlEvent::ParameterStep {
+            channel: 0,
+            kind: ParameterKind::Registered,
+            parameter: RPN_PITCH_BEND_SENSITIVITY,
+            increment: true
+        })
+    );
+
+    // Switching to a non-registered parameter.
+    let messages = set_parameter(0, ParameterKind::NonRegistered, 0x0155, 0x3FFF).unwrap();
+    assert_eq!(messages[0], control(0, NRPN_MSB, 0x02));
+    assert_eq!(messages[1], control(0, NRPN_LSB, 0x55));
+    assert_eq!(
+        decode_all(&mut decoder, &messages).last(),
+        Some(&ControlEvent::Parameter {
+            channel: 0,
+            kind: ParameterKind::NonRegistered,
+            parameter: 0x0155,
+            value: 0x3FFF
+        })
+    );
+    assert_eq!(
+        decoder.decode(&control(0, DATA_DECREMENT, 0)),
+        Some(ControlEvent::ParameterStep {
+            channel: 0,
+            kind: ParameterKind::NonRegistered,
+            parameter: 0x0155,
+            increment: false
+        })
+    );
+
+    // After selecting the null parameter, data entry is a plain 14-bit controller.
+    let null = select_null_parameter(0).unwrap();
+    assert_eq!(null, [control(0, RPN_MSB, 127), control(0, RPN_LSB, 127)]);
+    assert!(decode_all(&mut decoder, &null).is_empty());
+    assert_eq!(
+        decoder.decode(&control(0, DATA_ENTRY_MSB, 1)),
+        Some(ControlEvent::Control14 {
+            channel: 0,
+            controller: DATA_ENTRY_MSB,
+            value: 1 << 7
+        })
+    );
+    assert_eq!(
+        decoder.decode(&control(0, DATA_INCREMENT, 0)),
+        Some(ControlEvent::Control {
+            channel: 0,
+            controller: DATA_INCREMENT,
+            value: 0
+        })
+    );
+    assert!(set_parameter(0, ParameterKind::Registered, 0x4000, 0).is_err());
+    assert!(set_parameter(0, ParameterKind::Registered, 0, 0x4000).is_err());
+}
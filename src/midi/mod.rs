@@ -0,0 +1,513 @@
+//! Platform independent encoding and decoding of MIDI messages.
+//!
+//! - [**MidiMessage**](./enum.MidiMessage) describes a single MIDI 1.0 message.
+//! - The [**parser**](./parser/index.html) module decodes byte streams, handling running status
+//!   and realtime messages interleaved with other messages.
+//! - The [**controller**](./controller/index.html) module interprets 14-bit controllers and
+//!   registered and non-registered parameter numbers (RPN and NRPN).
+//! - The [**smf**](./smf/index.html) module reads and writes Standard MIDI Files and schedules
+//!   their messages by sample time.
+//! - The [**ump**](./ump/index.html) module handles MIDI 2.0 Universal MIDI Packets (UMP) and
+//!   their conversion to and from MIDI 1.0.
+//! - The [**packet**](./packet/index.html) module builds and reads the packet and event lists
+//!   exchanged with CoreMIDI.
+//!
+//! Nothing within this module depends on the CoreAudio or CoreMIDI frameworks.
+
+use crate::error::MidiError;
+
+pub use self::parser::{Encoder, Parser};
+
+pub mod controller;
+pub mod packet;
+pub mod parser;
+pub mod smf;
+pub mod ump;
+
+/// The status byte beginning a system exclusive message.
+pub const SYSEX_START: u8 = 0xF0;
+/// The status byte ending a system exclusive message.
+pub const SYSEX_END: u8 = 0xF7;
+/// The value of a **PitchBend** message with no bend applied.
+pub const PITCH_BEND_CENTER: u16 = 0x2000;
+/// The largest value of a **PitchBend** message.
+pub const PITCH_BEND_MAX: u16 = 0x3FFF;
+/// The largest value of a **SongPosition** message.
+pub const SONG_POSITION_MAX: u16 = 0x3FFF;
+
+const NOTE_OFF: u8 = 0x80;
+const NOTE_ON: u8 = 0x90;
+const POLY_PRESSURE: u8 = 0xA0;
+const CONTROL_CHANGE: u8 = 0xB0;
+const PROGRAM_CHANGE: u8 = 0xC0;
+const CHANNEL_PRESSURE: u8 = 0xD0;
+const PITCH_BEND: u8 = 0xE0;
+const TIME_CODE_QUARTER_FRAME: u8 = 0xF1;
+const SONG_POSITION: u8 = 0xF2;
+const SONG_SELECT: u8 = 0xF3;
+const TUNE_REQUEST: u8 = 0xF6;
+const TIMING_CLOCK: u8 = 0xF8;
+const START: u8 = 0xFA;
+const CONTINUE: u8 = 0xFB;
+const STOP: u8 = 0xFC;
+const ACTIVE_SENSING: u8 = 0xFE;
+const RESET: u8 = 0xFF;
+
+/// A MIDI 1.0 message.
+///
+/// Channels are in the range `0..16` and data values in the range `0..128` unless stated
+/// otherwise.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MidiMessage {
+    /// Stop the given note.
+    NoteOff { channel: u8, note: u8, velocity: u8 },
+    /// Start the given note.
+    ///
+    /// Note that many devices treat a **NoteOn** with a velocity of `0` as a **NoteOff**.
+    NoteOn { channel: u8, note: u8, velocity: u8 },
+    /// Change the pressure (aftertouch) of a single held note.
+    PolyPressure { channel: u8, note: u8, pressure: u8 },
+    /// Set the value of a controller (CC).
+    ControlChange {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+    /// Select the program (instrument) of a channel.
+    ProgramChange { channel: u8, program: u8 },
+    /// Change the pressure (aftertouch) of all notes held on a channel.
+    ChannelPressure { channel: u8, pressure: u8 },
+    /// Bend the pitch of a channel, where [**PITCH_BEND_CENTER**](./constant.PITCH_BEND_CENTER.html)
+    /// applies no bend and the value is at most [**PITCH_BEND_MAX**](./constant.PITCH_BEND_MAX.html).
+    PitchBend { channel: u8, value: u16 },
+    /// A system exclusive message, excluding the surrounding `0xF0` and `0xF7` bytes.
+    SysEx(Vec<u8>),
+    /// A MIDI time code quarter frame, holding the piece type and value.
+    TimeCodeQuarterFrame(u8),
+    /// The song position in MIDI beats (sixteenth notes), at most
+    /// [**SONG_POSITION_MAX**](./constant.SONG_POSITION_MAX.html).
+    SongPosition(u16),
+    /// Select the song or sequence to play.
+    SongSelect(u8),
+    /// Request that analog synthesizers tune their oscillators.
+    TuneRequest,
+    /// Sent 24 times per quarter note while playing.
+    TimingClock,
+    /// Start playback from the beginning of the song.
+    Start,
+    /// Continue playback from the current song position.
+    Continue,
+    /// Stop playback.
+    Stop,
+    /// Sent periodically to indicate that the connection is alive.
+    ActiveSensing,
+    /// Reset all receivers to their power-up state.
+    Reset,
+}
+
+fn check_channel(channel: u8) -> Result<u8, MidiError> {
+    if channel < 16 {
+        Ok(channel)
+    } else {
+        Err(MidiError::InvalidChannel)
+    }
+}
+
+fn check_data(byte: u8) -> Result<u8, MidiError> {
+    if byte < 0x80 {
+        Ok(byte)
+    } else {
+        Err(MidiError::InvalidDataByte)
+    }
+}
+
+/// Split a 14-bit value into its least and most significant data bytes.
+fn split_14(value: u16) -> Result<[u8; 2], MidiError> {
+    if value > 0x3FFF {
+        return Err(MidiError::InvalidDataByte);
+    }
+    Ok([(value & 0x7F) as u8, (value >> 7) as u8])
+}
+
+/// Join the least and most significant data bytes of a 14-bit value.
+fn join_14(lsb: u8, msb: u8) -> u16 {
+    lsb as u16 | (msb as u16) << 7
+}
+
+/// The number of data bytes following the given status byte, or `None` for status bytes that do
+/// not begin a message of fixed length.
+pub(crate) fn data_len(status: u8) -> Option<usize> {
+    match status {
+        0x80..=0xEF => match status & 0xF0 {
+            PROGRAM_CHANGE | CHANNEL_PRESSURE => Some(1),
+            _ => Some(2),
+        },
+        TIME_CODE_QUARTER_FRAME | SONG_SELECT => Some(1),
+        SONG_POSITION => Some(2),
+        TUNE_REQUEST | TIMING_CLOCK | START | CONTINUE | STOP | ACTIVE_SENSING | RESET => Some(0),
+        _ => None,
+    }
+}
+
+impl MidiMessage {
+    /// The channel of the message, or `None` for system messages.
+    pub fn channel(&self) -> Option<u8> {
+        match *self {
+            MidiMessage::NoteOff { channel, .. }
+            | MidiMessage::NoteOn { channel, .. }
+            | MidiMessage::PolyPressure { channel, .. }
+            | MidiMessage::ControlChange { channel, .. }
+            | MidiMessage::ProgramChange { channel, .. }
+            | MidiMessage::ChannelPressure { channel, .. }
+            | MidiMessage::PitchBend { channel, .. } => Some(channel),
+            _ => None,
+        }
+    }
+
+    /// Whether or not this is a system realtime message.
+    ///
+    /// Realtime messages may appear anywhere within a byte stream, including between the data
+    /// bytes of other messages, and do not affect running status.
+    pub fn is_realtime(&self) -> bool {
+        matches!(
+            *self,
+            MidiMessage::TimingClock
+                | MidiMessage::Start
+                | MidiMessage::Continue
+                | MidiMessage::Stop
+                | MidiMessage::ActiveSensing
+                | MidiMessage::Reset
+        )
+    }
+
+    /// The status byte of the message.
+    ///
+    /// Returns an `Error` if the channel is out of range.
+    pub fn status(&self) -> Result<u8, MidiError> {
+        let (kind, channel) = match *self {
+            MidiMessage::NoteOff { channel, .. } => (NOTE_OFF, channel),
+            MidiMessage::NoteOn { channel, .. } => (NOTE_ON, channel),
+            MidiMessage::PolyPressure { channel, .. } => (POLY_PRESSURE, channel),
+            MidiMessage::ControlChange { channel, .. } => (CONTROL_CHANGE, channel),
+            MidiMessage::ProgramChange { channel, .. } => (PROGRAM_CHANGE, channel),
+            MidiMessage::ChannelPressure { channel, .. } => (CHANNEL_PRESSURE, channel),
+            MidiMessage::PitchBend { channel, .. } => (PITCH_BEND, channel),
+            MidiMessage::SysEx(_) => return Ok(SYSEX_START),
+            MidiMessage::TimeCodeQuarterFrame(_) => return Ok(TIME_CODE_QUARTER_FRAME),
+            MidiMessage::SongPosition(_) => return Ok(SONG_POSITION),
+            MidiMessage::SongSelect(_) => return Ok(SONG_SELECT),
+            MidiMessage::TuneRequest => return Ok(TUNE_REQUEST),
+            MidiMessage::TimingClock => return Ok(TIMING_CLOCK),
+            MidiMessage::Start => return Ok(START),
+            MidiMessage::Continue => return Ok(CONTINUE),
+            MidiMessage::Stop => return Ok(STOP),
+            MidiMessage::ActiveSensing => return Ok(ACTIVE_SENSING),
+            MidiMessage::Reset => return Ok(RESET),
+        };
+        Ok(kind | check_channel(channel)?)
+    }
+
+    /// The status and data bytes of any message other than a system exclusive message, e.g. as
+    /// passed to `MusicDeviceMIDIEvent`.
+    ///
+    /// Unused data bytes are set to `0`. Returns `None` for system exclusive messages.
+    pub fn short_message(&self) -> Result<Option<[u8; 3]>, MidiError> {
+        let status = self.status()?;
+        let data = match *self {
+            MidiMessage::NoteOff { note, velocity, .. }
+            | MidiMessage::NoteOn { note, velocity, .. } => {
+                [check_data(note)?, check_data(velocity)?]
+            }
+            MidiMessage::PolyPressure { note, pressure, .. } => {
+                [check_data(note)?, check_data(pressure)?]
+            }
+            MidiMessage::ControlChange {
+                controller, value, ..
+            } => [check_data(controller)?, check_data(value)?],
+            MidiMessage::ProgramChange { program, .. } => [check_data(program)?, 0],
+            MidiMessage::ChannelPressure { pressure, .. } => [check_data(pressure)?, 0],
+            MidiMessage::PitchBend { value, .. } => split_14(value)?,
+            MidiMessage::SysEx(_) => return Ok(None),
+            MidiMessage::TimeCodeQuarterFrame(value) | MidiMessage::SongSelect(value) => {
+                [check_data(value)?, 0]
+            }
+            MidiMessage::SongPosition(position) => split_14(position)?,
+            _ => [0, 0],
+        };
+        Ok(Some([status, data[0], data[1]]))
+    }
+
+    /// Append the MIDI 1.0 bytes of the message to `bytes`.
+    ///
+    /// System exclusive messages are surrounded by `0xF0` and `0xF7`. Nothing is appended if the
+    /// message is invalid.
+    pub fn write_to(&self, bytes: &mut Vec<u8>) -> Result<(), MidiError> {
+        if let MidiMessage::SysEx(ref data) = *self {
+            for &byte in data {
+                check_data(byte)?;
+            }
+            bytes.reserve(data.len() + 2);
+            bytes.push(SYSEX_START);
+            bytes.extend_from_slice(data);
+            bytes.push(SYSEX_END);
+            return Ok(());
+        }
+        let short = self.short_message()?.expect("non-sysex message");
+        let len = 1 + data_len(short[0]).expect("fixed length message");
+        bytes.extend_from_slice(&short[..len]);
+        Ok(())
+    }
+
+    /// Encode the message as MIDI 1.0 bytes.
+    ///
+    /// System exclusive messages are surrounded by `0xF0` and `0xF7`.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MidiError> {
+        let mut bytes = Vec::with_capacity(3);
+        self.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Decode a single complete message from MIDI 1.0 bytes, without running status.
+    ///
+    /// Returns an `Error` if the bytes do not hold exactly one message. Use a
+    /// [**Parser**](./parser/struct.Parser) to decode streams of messages.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MidiError> {
+        let (&status, data) = bytes.split_first().ok_or(MidiError::Incomplete)?;
+        if status == SYSEX_START {
+            let (&end, payload) = data.split_last().ok_or(MidiError::Incomplete)?;
+            if end != SYSEX_END {
+                return Err(MidiError::Incomplete);
+            }
+            for &byte in payload {
+                check_data(byte)?;
+            }
+            return Ok(MidiMessage::SysEx(payload.to_vec()));
+        }
+        let expected = data_len(status).ok_or(MidiError::InvalidStatus)?;
+        if data.len() < expected {
+            return Err(MidiError::Incomplete);
+        }
+        if data.len() > expected {
+            return Err(MidiError::TrailingBytes);
+        }
+        for &byte in data {
+            check_data(byte)?;
+        }
+        Ok(MidiMessage::from_parts(status, data))
+    }
+
+    /// Construct a message from a status byte and its complete, valid data bytes.
+    fn from_parts(status: u8, data: &[u8]) -> Self {
+        let channel = status & 0x0F;
+        let byte = |i: usize| data[i];
+        match status {
+            0x80..=0xEF => match status & 0xF0 {
+                NOTE_OFF => MidiMessage::NoteOff {
+                    channel,
+                    note: byte(0),
+                    velocity: byte(1),
+                },
+                NOTE_ON => MidiMessage::NoteOn {
+                    channel,
+                    note: byte(0),
+                    velocity: byte(1),
+                },
+                POLY_PRESSURE => MidiMessage::PolyPressure {
+                    channel,
+                    note: byte(0),
+                    pressure: byte(1),
+                },
+                CONTROL_CHANGE => MidiMessage::ControlChange {
+                    channel,
+                    controller: byte(0),
+                    value: byte(1),
+                },
+                PROGRAM_CHANGE => MidiMessage::ProgramChange {
+                    channel,
+                    program: byte(0),
+                },
+                CHANNEL_PRESSURE => MidiMessage::ChannelPressure {
+                    channel,
+                    pressure: byte(0),
+                },
+                _ => MidiMessage::PitchBend {
+                    channel,
+                    value: join_14(byte(0), byte(1)),
+                },
+            },
+            TIME_CODE_QUARTER_FRAME => MidiMessage::TimeCodeQuarterFrame(byte(0)),
+            SONG_POSITION => MidiMessage::SongPosition(join_14(byte(0), byte(1))),
+            SONG_SELECT => MidiMessage::SongSelect(byte(0)),
+            TUNE_REQUEST => MidiMessage::TuneRequest,
+            TIMING_CLOCK => MidiMessage::TimingClock,
+            START => MidiMessage::Start,
+            CONTINUE => MidiMessage::Continue,
+            STOP => MidiMessage::Stop,
+            ACTIVE_SENSING => MidiMessage::ActiveSensing,
+            _ => MidiMessage::Reset,
+        }
+    }
+}
+
+#[cfg(test)]
+fn all_message_kinds() -> Vec<MidiMessage> {
+    vec![
+        MidiMessage::NoteOff {
+            channel: 0,
+            note: 60,
+            velocity: 64,
+        },
+        MidiMessage::NoteOn {
+            channel: 15,
+            note: 127,
+            velocity: 0,
+        },
+        MidiMessage::PolyPressure {
+            channel: 4,
+            note: 61,
+            pressure: 90,
+        },
+        MidiMessage::ControlChange {
+            channel: 3,
+            controller: 7,
+            value: 100,
+        },
+        MidiMessage::ProgramChange {
+            channel: 9,
+            program: 42,
+        },
+        MidiMessage::ChannelPressure {
+            channel: 2,
+            pressure: 127,
+        },
+        MidiMessage::PitchBend {
+            channel: 1,
+            value: PITCH_BEND_MAX,
+        },
+        MidiMessage::SysEx(vec![0x7E, 0x7F, 0x09, 0x01]),
+        MidiMessage::SysEx(vec![]),
+        MidiMessage::TimeCodeQuarterFrame(0x35),
+        MidiMessage::SongPosition(SONG_POSITION_MAX),
+        MidiMessage::SongSelect(3),
+        MidiMessage::TuneRequest,
+        MidiMessage::TimingClock,
+        MidiMessage::Start,
+        MidiMessage::Continue,
+        MidiMessage::Stop,
+        MidiMessage::ActiveSensing,
+        MidiMessage::Reset,
+    ]
+}
+
+#[test]
+fn test_midi_message_round_trip() {
+    for message in all_message_kinds() {
+        let bytes = message.to_bytes().unwrap();
+        assert_eq!(MidiMessage::from_bytes(&bytes).unwrap(), message);
+        if let Some(short) = message.short_message().unwrap() {
+            let len = 1 + data_len(short[0]).unwrap();
+            assert_eq!(&short[..len], &bytes[..]);
+        }
+    }
+}
+
+#[test]
+fn test_midi_message_encoding() {
+    let note_on = MidiMessage::NoteOn {
+        channel: 2,
+        note: 60,
+        velocity: 100,
+    };
+    assert_eq!(note_on.to_bytes().unwrap(), vec![0x92, 60, 100]);
+    assert_eq!(note_on.short_message().unwrap(), Some([0x92, 60, 100]));
+    assert_eq!(note_on.channel(), Some(2));
+    assert!(!note_on.is_realtime());
+
+    let program = MidiMessage::ProgramChange {
+        channel: 0,
+        program: 5,
+    };
+    assert_eq!(program.to_bytes().unwrap(), vec![0xC0, 5]);
+    assert_eq!(program.short_message().unwrap(), Some([0xC0, 5, 0]));
+
+    let bend = MidiMessage::PitchBend {
+        channel: 0,
+        value: PITCH_BEND_CENTER,
+    };
+    assert_eq!(bend.to_bytes().unwrap(), vec![0xE0, 0x00, 0x40]);
+
+    let position = MidiMessage::SongPosition(0x81);
+    assert_eq!(position.to_bytes().unwrap(), vec![0xF2, 0x01, 0x01]);
+
+    let sysex = MidiMessage::SysEx(vec![0x43, 0x10]);
+    assert_eq!(sysex.to_bytes().unwrap(), vec![0xF0, 0x43, 0x10, 0xF7]);
+    assert_eq!(sysex.short_message().unwrap(), None);
+    assert_eq!(sysex.channel(), None);
+
+    assert_eq!(MidiMessage::TimingClock.to_bytes().unwrap(), vec![0xF8]);
+    assert!(MidiMessage::TimingClock.is_realtime());
+    assert!(!MidiMessage::TuneRequest.is_realtime());
+}
+
+#[test]
+fn test_midi_message_errors() {
+    let bad_channel = MidiMessage::ProgramChange {
+        channel: 16,
+        program: 0,
+    };
+    assert_eq!(bad_channel.to_bytes(), Err(MidiError::InvalidChannel));
+    let bad_note = MidiMessage::NoteOn {
+        channel: 0,
+        note: 128,
+        velocity: 1,
+    };
+    assert_eq!(bad_note.to_bytes(), Err(MidiError::InvalidDataByte));
+    let bad_bend = MidiMessage::PitchBend {
+        channel: 0,
+        value: PITCH_BEND_MAX + 1,
+    };
+    assert_eq!(bad_bend.to_bytes(), Err(MidiError::InvalidDataByte));
+    assert_eq!(
+        MidiMessage::SysEx(vec![0x80]).to_bytes(),
+        Err(MidiError::InvalidDataByte)
+    );
+    let mut bytes = vec![];
+    assert!(MidiMessage::SysEx(vec![1, 0xF7])
+        .write_to(&mut bytes)
+        .is_err());
+    assert!(bytes.is_empty());
+
+    assert_eq!(MidiMessage::from_bytes(&[]), Err(MidiError::Incomplete));
+    assert_eq!(
+        MidiMessage::from_bytes(&[0x90, 60]),
+        Err(MidiError::Incomplete)
+    );
+    assert_eq!(
+        MidiMessage::from_bytes(&[0x90, 60, 200]),
+        Err(MidiError::InvalidDataByte)
+    );
+    assert_eq!(
+        MidiMessage::from_bytes(&[0xC0, 1, 2]),
+        Err(MidiError::TrailingBytes)
+    );
+    assert_eq!(
+        MidiMessage::from_bytes(&[0xF4]),
+        Err(MidiError::InvalidStatus)
+    );
+    assert_eq!(
+        MidiMessage::from_bytes(&[0xF7]),
+        Err(MidiError::InvalidStatus)
+    );
+    assert_eq!(
+        MidiMessage::from_bytes(&[60, 10]),
+        Err(MidiError::InvalidStatus)
+    );
+    assert_eq!(
+        MidiMessage::from_bytes(&[0xF0, 0x43]),
+        Err(MidiError::Incomplete)
+    );
+    assert_eq!(
+        MidiMessage::from_bytes(&[0xF8, 0xF8]),
+        Err(MidiError::TrailingBytes)
+    );
+}
@@ -0,0 +1,628 @@
+//! Reading and writing the packet lists exchanged with CoreMIDI.
+//!
+//! CoreMIDI delivers and accepts MIDI 1.0 byte streams as a `MIDIPacketList` and Universal MIDI
+//! Packets (UMP) as a `MIDIEventList`. Both are variable length structs, so rather than mirroring
+//! their declarations this module reads and writes their layout directly. The `core_midi` module
+//! re-exports these types for use with its ports and endpoints.
+
+use crate::error::MidiError;
+use std::mem;
+use std::slice;
+
+pub use super::ump::{ump_len, UmpMessages};
+
+/// The size of the `numPackets` field that begins a `MIDIPacketList`.
+const PACKET_LIST_HEADER: usize = 4;
+/// The size of the `timeStamp` and `length` fields that begin a `MIDIPacket`.
+const PACKET_HEADER: usize = 10;
+/// The size of the `protocol` and `numPackets` fields that begin a `MIDIEventList`, in words.
+const EVENT_LIST_HEADER: usize = 2;
+/// The size of the `timeStamp` and `wordCount` fields that begin a `MIDIEventPacket`, in words.
+const EVENT_PACKET_HEADER: usize = 3;
+
+/// The alignment of each `MIDIPacket` after the first, as applied by `MIDIPacketNext`.
+///
+/// Packets are 4-byte aligned on ARM, while on Intel they directly follow one another.
+pub const PACKET_ALIGNMENT: usize = if cfg!(any(target_arch = "arm", target_arch = "aarch64")) {
+    4
+} else {
+    1
+};
+
+fn align(offset: usize, alignment: usize) -> usize {
+    offset.div_ceil(alignment) * alignment
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_ne_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut raw = [0; 4];
+    raw.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_ne_bytes(raw)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut raw = [0; 8];
+    raw.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_ne_bytes(raw)
+}
+
+/// Join the two words of a 64-bit timestamp as laid out in memory.
+fn join_time_stamp(first: u32, second: u32) -> u64 {
+    let mut raw = [0; 8];
+    raw[..4].copy_from_slice(&first.to_ne_bytes());
+    raw[4..].copy_from_slice(&second.to_ne_bytes());
+    u64::from_ne_bytes(raw)
+}
+
+/// Split a 64-bit timestamp into two words as laid out in memory.
+fn split_time_stamp(time_stamp: u64) -> [u32; 2] {
+    let raw = time_stamp.to_ne_bytes();
+    [
+        u32::from_ne_bytes([raw[0], raw[1], raw[2], raw[3]]),
+        u32::from_ne_bytes([raw[4], raw[5], raw[6], raw[7]]),
+    ]
+}
+
+/// A single packet of MIDI 1.0 bytes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Packet<'a> {
+    /// The host time at which the packet's events occurred, or `0` for "now".
+    pub time_stamp: u64,
+    /// One or more complete MIDI messages, or part of a system exclusive message.
+    pub data: &'a [u8],
+}
+
+/// A borrowed, validated `MIDIPacketList`.
+#[derive(Copy, Clone, Debug)]
+pub struct PacketList<'a> {
+    bytes: &'a [u8],
+    alignment: usize,
+}
+
+impl<'a> PacketList<'a> {
+    /// Interpret the given bytes as a `MIDIPacketList` with the native packet alignment.
+    ///
+    /// Returns an `Error` if the bytes end before the last packet does.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, MidiError> {
+        PacketList::from_bytes_with_alignment(bytes, PACKET_ALIGNMENT)
+    }
+
+    fn from_bytes_with_alignment(bytes: &'a [u8], alignment: usize) -> Result<Self, MidiError> {
+        let size = packet_list_size(bytes.len(), alignment, |offset, len| {
+            if offset + len > bytes.len() {
+                None
+            } else {
+                Some(&bytes[offset..offset + len])
+            }
+        })
+        .ok_or(MidiError::Incomplete)?;
+        Ok(PacketList {
+            bytes: &bytes[..size],
+            alignment,
+        })
+    }
+
+    /// Interpret the `MIDIPacketList` at the given address.
+    ///
+    /// Returns an `Error` if the packet lengths overflow the address space.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a readable `MIDIPacketList` that outlives `'a`.
+    pub unsafe fn from_ptr(ptr: *const u8) -> Result<Self, MidiError> {
+        let size = packet_list_size(usize::MAX, PACKET_ALIGNMENT, |offset, len| {
+            Some(slice::from_raw_parts(ptr.add(offset), len))
+        })
+        .ok_or(MidiError::Incomplete)?;
+        Ok(PacketList {
+            bytes: slice::from_raw_parts(ptr, size),
+            alignment: PACKET_ALIGNMENT,
+        })
+    }
+
+    /// The number of packets within the list.
+    pub fn len(&self) -> usize {
+        read_u32(self.bytes, 0) as usize
+    }
+
+    /// Whether or not the list contains no packets.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The raw bytes of the list.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// An iterator yielding each packet within the list.
+    pub fn iter(&self) -> Packets<'a> {
+        Packets {
+            bytes: self.bytes,
+            alignment: self.alignment,
+            offset: PACKET_LIST_HEADER,
+            remaining: self.len(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for PacketList<'a> {
+    type Item = Packet<'a>;
+    type IntoIter = Packets<'a>;
+    fn into_iter(self) -> Packets<'a> {
+        self.iter()
+    }
+}
+
+/// Walk a packet list, returning its total size in bytes.
+///
+/// `read` returns the bytes at the given offset and length, or `None` if they are out of bounds.
+fn packet_list_size<'b, R>(limit: usize, alignment: usize, read: R) -> Option<usize>
+where
+    R: Fn(usize, usize) -> Option<&'b [u8]>,
+{
+    let count = read_u32(read(0, PACKET_LIST_HEADER)?, 0);
+    let mut offset = PACKET_LIST_HEADER;
+    for i in 0..count {
+        if i > 0 {
+            offset = align(offset, alignment);
+        }
+        let length = read_u16(read(offset, PACKET_HEADER)?, 8) as usize;
+        offset += PACKET_HEADER + length;
+        if offset > limit {
+            return None;
+        }
+    }
+    Some(offset)
+}
+
+/// An iterator yielding each [**Packet**](./struct.Packet) of a
+/// [**PacketList**](./struct.PacketList).
+#[derive(Clone, Debug)]
+pub struct Packets<'a> {
+    bytes: &'a [u8],
+    alignment: usize,
+    offset: usize,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Packets<'a> {
+    type Item = Packet<'a>;
+    fn next(&mut self) -> Option<Packet<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let time_stamp = read_u64(self.bytes, self.offset);
+        let length = read_u16(self.bytes, self.offset + 8) as usize;
+        let start = self.offset + PACKET_HEADER;
+        let data = &self.bytes[start..start + length];
+        self.offset = align(start + length, self.alignment);
+        self.remaining -= 1;
+        Some(Packet { time_stamp, data })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// An owned `MIDIPacketList`, built one packet at a time.
+#[derive(Clone, Debug)]
+pub struct PacketBuffer {
+    // Stored as words so that the list is 4-byte aligned, as required by CoreMIDI.
+    words: Vec<u32>,
+    len: usize,
+    alignment: usize,
+}
+
+impl PacketBuffer {
+    /// Construct an empty list with the native packet alignment.
+    pub fn new() -> Self {
+        PacketBuffer::with_alignment(PACKET_ALIGNMENT)
+    }
+
+    fn with_alignment(alignment: usize) -> Self {
+        PacketBuffer {
+            words: vec![0],
+            len: PACKET_LIST_HEADER,
+            alignment,
+        }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        let len = self.words.len() * mem::size_of::<u32>();
+        unsafe { slice::from_raw_parts_mut(self.words.as_mut_ptr() as *mut u8, len) }
+    }
+
+    /// Append a packet holding the given bytes.
+    ///
+    /// Returns an `Error` if `data` is longer than the `65535` bytes a packet may hold.
+    pub fn push(&mut self, time_stamp: u64, data: &[u8]) -> Result<&mut Self, MidiError> {
+        if data.len() > u16::MAX as usize {
+            return Err(MidiError::TooLong);
+        }
+        let count = self.packets().len() as u32;
+        let offset = if count == 0 {
+            self.len
+        } else {
+            align(self.len, self.alignment)
+        };
+        let end = offset + PACKET_HEADER + data.len();
+        let words = end.div_ceil(mem::size_of::<u32>());
+        self.words.resize(words, 0);
+        let bytes = self.bytes_mut();
+        bytes[0..4].copy_from_slice(&(count + 1).to_ne_bytes());
+        bytes[offset..offset + 8].copy_from_slice(&time_stamp.to_ne_bytes());
+        bytes[offset + 8..offset + 10].copy_from_slice(&(data.len() as u16).to_ne_bytes());
+        bytes[offset + PACKET_HEADER..end].copy_from_slice(data);
+        self.len = end;
+        Ok(self)
+    }
+
+    /// Remove all packets from the list.
+    pub fn clear(&mut self) {
+        self.words.clear();
+        self.words.push(0);
+        self.len = PACKET_LIST_HEADER;
+    }
+
+    /// A view of the packets within the list.
+    pub fn packets(&self) -> PacketList<'_> {
+        PacketList {
+            bytes: self.as_bytes(),
+            alignment: self.alignment,
+        }
+    }
+
+    /// The raw bytes of the list.
+    pub fn as_bytes(&self) -> &[u8] {
+        let bytes = unsafe {
+            slice::from_raw_parts(
+                self.words.as_ptr() as *const u8,
+                self.words.len() * mem::size_of::<u32>(),
+            )
+        };
+        &bytes[..self.len]
+    }
+
+    /// A pointer to the underlying `MIDIPacketList`.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.words.as_ptr() as *const u8
+    }
+}
+
+impl Default for PacketBuffer {
+    fn default() -> Self {
+        PacketBuffer::new()
+    }
+}
+
+/// The MIDI protocol of the Universal MIDI Packets within an event list.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    /// MIDI 1.0 messages carried within Universal MIDI Packets.
+    Midi1 = 1,
+    /// MIDI 2.0 messages.
+    Midi2 = 2,
+}
+
+impl Protocol {
+    /// Convert a raw `MIDIProtocolID` to a **Protocol**.
+    pub fn from_u32(id: u32) -> Option<Self> {
+        match id {
+            1 => Some(Protocol::Midi1),
+            2 => Some(Protocol::Midi2),
+            _ => None,
+        }
+    }
+}
+
+/// A single packet of Universal MIDI Packet words.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EventPacket<'a> {
+    /// The host time at which the packet's events occurred, or `0` for "now".
+    pub time_stamp: u64,
+    /// One or more complete Universal MIDI Packets.
+    pub words: &'a [u32],
+}
+
+impl<'a> EventPacket<'a> {
+    /// An iterator yielding each Universal MIDI Packet within the packet.
+    pub fn messages(&self) -> UmpMessages<'a> {
+        UmpMessages::new(self.words)
+    }
+}
+
+/// A borrowed, validated `MIDIEventList`.
+#[derive(Copy, Clone, Debug)]
+pub struct EventList<'a> {
+    protocol: Protocol,
+    words: &'a [u32],
+}
+
+impl<'a> EventList<'a> {
+    /// Interpret the given words as a `MIDIEventList`.
+    ///
+    /// Returns an `Error` if the protocol is unknown or the words end before the last packet does.
+    pub fn from_words(words: &'a [u32]) -> Result<Self, MidiError> {
+        let size = event_list_size(words.len(), |offset| words.get(offset).copied())
+            .ok_or(MidiError::Incomplete)?;
+        let protocol = Protocol::from_u32(words[0]).ok_or(MidiError::InvalidStatus)?;
+        Ok(EventList {
+            protocol,
+            words: &words[..size],
+        })
+    }
+
+    /// Interpret the `MIDIEventList` at the given address.
+    ///
+    /// Returns an `Error` if the protocol is unknown or the packet lengths overflow the address
+    /// space.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a readable `MIDIEventList` that outlives `'a`.
+    pub unsafe fn from_ptr(ptr: *const u32) -> Result<Self, MidiError> {
+        let protocol = Protocol::from_u32(*ptr).ok_or(MidiError::InvalidStatus)?;
+        let size = event_list_size(usize::MAX, |offset| Some(*ptr.add(offset)))
+            .ok_or(MidiError::Incomplete)?;
+        Ok(EventList {
+            protocol,
+            words: slice::from_raw_parts(ptr, size),
+        })
+    }
+
+    /// The protocol of the messages within the list.
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    /// The number of packets within the list.
+    pub fn len(&self) -> usize {
+        self.words[1] as usize
+    }
+
+    /// Whether or not the list contains no packets.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The raw words of the list.
+    pub fn as_words(&self) -> &'a [u32] {
+        self.words
+    }
+
+    /// An iterator yielding each packet within the list.
+    pub fn iter(&self) -> EventPackets<'a> {
+        EventPackets {
+            words: self.words,
+            offset: EVENT_LIST_HEADER,
+            remaining: self.len(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for EventList<'a> {
+    type Item = EventPacket<'a>;
+    type IntoIter = EventPackets<'a>;
+    fn into_iter(self) -> EventPackets<'a> {
+        self.iter()
+    }
+}
+
+/// Walk an event list, returning its total size in words.
+fn event_list_size<R>(limit: usize, read: R) -> Option<usize>
+where
+    R: Fn(usize) -> Option<u32>,
+{
+    let count = read(1)?;
+    let mut offset = EVENT_LIST_HEADER;
+    for _ in 0..count {
+        let word_count = read(offset + 2)? as usize;
+        offset += EVENT_PACKET_HEADER + word_count;
+        if offset > limit {
+            return None;
+        }
+    }
+    Some(offset)
+}
+
+/// An iterator yielding each [**EventPacket**](./struct.EventPacket) of an
+/// [**EventList**](./struct.EventList).
+#[derive(Clone, Debug)]
+pub struct EventPackets<'a> {
+    words: &'a [u32],
+    offset: usize,
+    remaining: usize,
+}
+
+impl<'a> Iterator for EventPackets<'a> {
+    type Item = EventPacket<'a>;
+    fn next(&mut self) -> Option<EventPacket<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let time_stamp = join_time_stamp(self.words[self.offset], self.words[self.offset + 1]);
+        let word_count = self.words[self.offset + 2] as usize;
+        let start = self.offset + EVENT_PACKET_HEADER;
+        self.offset = start + word_count;
+        self.remaining -= 1;
+        Some(EventPacket {
+            time_stamp,
+            words: &self.words[start..self.offset],
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// An owned `MIDIEventList`, built one packet at a time.
+#[derive(Clone, Debug)]
+pub struct EventBuffer {
+    words: Vec<u32>,
+}
+
+impl EventBuffer {
+    /// Construct an empty list of messages of the given protocol.
+    pub fn new(protocol: Protocol) -> Self {
+        EventBuffer {
+            words: vec![protocol as u32, 0],
+        }
+    }
+
+    /// Append a packet holding the given Universal MIDI Packet words.
+    ///
+    /// Returns an `Error` if the words do not hold whole Universal MIDI Packets.
+    pub fn push(&mut self, time_stamp: u64, words: &[u32]) -> Result<&mut Self, MidiError> {
+        for message in UmpMessages::new(words) {
+            message?;
+        }
+        let [first, second] = split_time_stamp(time_stamp);
+        self.words
+            .extend_from_slice(&[first, second, words.len() as u32]);
+        self.words.extend_from_slice(words);
+        self.words[1] += 1;
+        Ok(self)
+    }
+
+    /// Remove all packets from the list.
+    pub fn clear(&mut self) {
+        self.words.truncate(EVENT_LIST_HEADER);
+        self.words[1] = 0;
+    }
+
+    /// A view of the packets within the list.
+    pub fn packets(&self) -> EventList<'_> {
+        EventList {
+            protocol: Protocol::from_u32(self.words[0]).expect("unknown protocol"),
+            words: &self.words,
+        }
+    }
+
+    /// A pointer to the underlying `MIDIEventList`.
+    pub fn as_ptr(&self) -> *const u32 {
+        self.words.as_ptr()
+    }
+}
+
+#[test]
+fn test_packet_buffer_round_trip() {
+    for &alignment in &[1, 4] {
+        let mut buffer = PacketBuffer::with_alignment(alignment);
+        assert!(buffer.packets().is_empty());
+        buffer
+            .push(100, &[0x90, 60, 100])
+            .unwrap()
+            .push(200, &[0xC0, 5])
+            .unwrap()
+            .push(0, &[0xF0, 0x7E, 0x7F, 0x09, 0x01, 0xF7])
+            .unwrap();
+        let packets: Vec<_> = buffer.packets().iter().collect();
+        assert_eq!(
+            packets,
+            vec![
+                Packet {
+                    time_stamp: 100,
+                    data: &[0x90, 60, 100]
+                },
+                Packet {
+                    time_stamp: 200,
+                    data: &[0xC0, 5]
+                },
+                Packet {
+                    time_stamp: 0,
+                    data: &[0xF0, 0x7E, 0x7F, 0x09, 0x01, 0xF7]
+                },
+            ]
+        );
+
+        assert_eq!(
+            buffer.push(0, &vec![0; u16::MAX as usize + 1]).err(),
+            Some(MidiError::TooLong)
+        );
+
+        let list = PacketList::from_bytes_with_alignment(buffer.as_bytes(), alignment).unwrap();
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.iter().count(), 3);
+        if alignment == PACKET_ALIGNMENT {
+            let parsed = unsafe { PacketList::from_ptr(buffer.as_ptr()).unwrap() };
+            assert_eq!(parsed.as_bytes(), buffer.as_bytes());
+        }
+
+        buffer.clear();
+        assert!(buffer.packets().is_empty());
+    }
+}
+
+#[test]
+fn test_packet_list_layout() {
+    let mut buffer = PacketBuffer::with_alignment(4);
+    buffer.push(1, &[0x80, 60, 0]).unwrap();
+    buffer.push(2, &[0xFE]).unwrap();
+    // The second packet begins on the 4-byte boundary following the 17 bytes of the first.
+    assert_eq!(buffer.as_bytes().len(), 20 + PACKET_HEADER + 1);
+    assert_eq!(read_u64(buffer.as_bytes(), 20), 2);
+
+    let mut buffer = PacketBuffer::with_alignment(1);
+    buffer.push(1, &[0x80, 60, 0]).unwrap();
+    buffer.push(2, &[0xFE]).unwrap();
+    assert_eq!(buffer.as_bytes().len(), 17 + PACKET_HEADER + 1);
+    assert_eq!(read_u64(buffer.as_bytes(), 17), 2);
+}
+
+#[test]
+fn test_packet_list_truncated() {
+    let mut buffer = PacketBuffer::new();
+    buffer.push(1, &[0x90, 60, 100]).unwrap();
+    let bytes = buffer.as_bytes();
+    assert!(PacketList::from_bytes(bytes).is_ok());
+    assert_eq!(
+        PacketList::from_bytes(&bytes[..bytes.len() - 1]).err(),
+        Some(MidiError::Incomplete)
+    );
+    assert_eq!(
+        PacketList::from_bytes(&[1, 0]).err(),
+        Some(MidiError::Incomplete)
+    );
+    assert!(PacketBuffer::new().push(0, &[0; 70_000]).is_err());
+}
+
+#[test]
+fn test_event_buffer_round_trip() {
+    let note_on = 0x2090_3C64;
+    let midi2_note_on = [0x4090_3C00, 0xFFFF_0000];
+    let mut buffer = EventBuffer::new(Protocol::Midi2);
+    buffer
+        .push(u64::MAX - 1, &[note_on])
+        .unwrap()
+        .push(5, &midi2_note_on)
+        .unwrap();
+    let list = EventList::from_words(&buffer.words).unwrap();
+    assert_eq!(list.protocol(), Protocol::Midi2);
+    let packets: Vec<_> = list.iter().collect();
+    assert_eq!(packets.len(), 2);
+    assert_eq!(packets[0].time_stamp, u64::MAX - 1);
+    assert_eq!(packets[0].words, &[note_on]);
+    assert_eq!(packets[1].time_stamp, 5);
+    assert_eq!(packets[1].words, &midi2_note_on);
+
+    let parsed = unsafe { EventList::from_ptr(buffer.as_ptr()).unwrap() };
+    assert_eq!(parsed.as_words(), &buffer.words[..]);
+
+    assert!(buffer.push(0, &[0x4090_3C00]).is_err());
+    assert_eq!(
+        EventList::from_words(&buffer.words[..buffer.words.len() - 1]).err(),
+        Some(MidiError::Incomplete)
+    );
+    assert_eq!(
+        EventList::from_words(&[3, 0]).err(),
+        Some(MidiError::InvalidStatus)
+    );
+    buffer.clear();
+    assert!(buffer.packets().is_empty());
+}
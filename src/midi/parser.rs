@@ -0,0 +1,470 @@
+//! Decoding and encoding streams of MIDI 1.0 bytes.
+//!
+//! Unlike [**MidiMessage::from_bytes**](../enum.MidiMessage#method.from_bytes), the
+//! [**Parser**](./struct.Parser) handles the byte streams produced by MIDI devices:
+//!
+//! - **Running status**: channel messages may omit their status byte when it matches that of the
+//!   previous channel message.
+//! - **Realtime messages** may appear anywhere, including between the data bytes of another
+//!   message or within a system exclusive message, without interrupting it.
+//! - Messages may be split across any number of calls, e.g. one per packet.
+
+use super::{data_len, MidiMessage, SYSEX_END, SYSEX_START};
+use crate::error::MidiError;
+
+/// A stateful decoder of MIDI 1.0 byte streams.
+#[derive(Clone, Debug, Default)]
+pub struct Parser {
+    /// The status of the message currently being read, if any.
+    status: Option<u8>,
+    /// The status of the last channel message, reused when a data byte follows a complete
+    /// message.
+    running_status: Option<u8>,
+    data: [u8; 2],
+    data_len: usize,
+    /// The data of the system exclusive message currently being read, if any.
+    sysex: Option<Vec<u8>>,
+}
+
+impl Parser {
+    /// Construct a parser expecting a status byte.
+    pub fn new() -> Self {
+        Parser::default()
+    }
+
+    /// Discard any partially read message along with the running status.
+    pub fn reset(&mut self) {
+        *self = Parser::new();
+    }
+
+    /// Feed a single byte to the parser.
+    ///
+    /// `f` is called with each message completed by the byte, or with an `Error` describing any
+    /// bytes that had to be discarded. A single byte may complete at most one message, though it
+    /// may also cause a partial message to be discarded first.
+    pub fn push<F>(&mut self, byte: u8, mut f: F)
+    where
+        F: FnMut(Result<MidiMessage, MidiError>),
+    {
+        // Realtime messages interrupt nothing.
+        if byte >= 0xF8 {
+            match data_len(byte) {
+                Some(_) => f(Ok(MidiMessage::from_parts(byte, &[]))),
+                None => f(Err(MidiError::InvalidStatus)),
+            }
+            return;
+        }
+
+        if byte < 0x80 {
+            if let Some(ref mut sysex) = self.sysex {
+                sysex.push(byte);
+                return;
+            }
+            let status = match self.status.or(self.running_status) {
+                Some(status) => status,
+                None => return f(Err(MidiError::InvalidDataByte)),
+            };
+            self.status = Some(status);
+            self.data[self.data_len] = byte;
+            self.data_len += 1;
+            self.complete(status, &mut f);
+            return;
+        }
+
+        // Any other status byte ends whatever came before it.
+        if byte == SYSEX_END {
+            match self.sysex.take() {
+                Some(data) => f(Ok(MidiMessage::SysEx(data))),
+                None => {
+                    self.discard(&mut f);
+                    f(Err(MidiError::InvalidStatus));
+                }
+            }
+            return;
+        }
+        self.discard(&mut f);
+        if byte < 0xF0 {
+            self.running_status = Some(byte);
+        } else {
+            // System common messages cancel running status.
+            self.running_status = None;
+        }
+        if byte == SYSEX_START {
+            self.sysex = Some(Vec::new());
+            return;
+        }
+        match data_len(byte) {
+            Some(_) => {
+                self.status = Some(byte);
+                self.complete(byte, &mut f);
+            }
+            None => f(Err(MidiError::InvalidStatus)),
+        }
+    }
+
+    /// Feed each of the given bytes to the parser in turn.
+    ///
+    /// See [**Parser::push**](#method.push).
+    pub fn parse<F>(&mut self, bytes: &[u8], mut f: F)
+    where
+        F: FnMut(Result<MidiMessage, MidiError>),
+    {
+        for &byte in bytes {
+            self.push(byte, &mut f);
+        }
+    }
+
+    /// Emit the current message if all of its data bytes have been read.
+    fn complete<F>(&mut self, status: u8, f: &mut F)
+    where
+        F: FnMut(Result<MidiMessage, MidiError>),
+    {
+        let expected = data_len(status).expect("fixed length message");
+        if self.data_len < expected {
+            return;
+        }
+        let message = MidiMessage::from_parts(status, &self.data[..expected]);
+        self.status = None;
+        self.data_len = 0;
+        f(Ok(message));
+    }
+
+    /// Discard any partially read message, reporting it as incomplete.
+    fn discard<F>(&mut self, f: &mut F)
+    where
+        F: FnMut(Result<MidiMessage, MidiError>),
+    {
+        let partial = self.sysex.take().is_some() || self.data_len > 0;
+        let pending = self.status.take().is_some();
+        self.data_len = 0;
+        if partial || pending {
+            f(Err(MidiError::Incomplete));
+        }
+    }
+}
+
+/// Decode every message within the given bytes, which must begin with a status byte and end
+/// with a complete message.
+///
+/// Running status and interleaved realtime messages are supported. Returns the first `Error`
+/// encountered, if any.
+pub fn parse(bytes: &[u8]) -> Result<Vec<MidiMessage>, MidiError> {
+    let mut parser = Parser::new();
+    let mut messages = Vec::new();
+    let mut error = None;
+    parser.parse(bytes, |result| match result {
+        Ok(message) => messages.push(message),
+        Err(err) => {
+            error.get_or_insert(err);
+        }
+    });
+    if parser.status.is_some() || parser.sysex.is_some() {
+        error.get_or_insert(MidiError::Incomplete);
+    }
+    match error {
+        Some(err) => Err(err),
+        None => Ok(messages),
+    }
+}
+
+/// A stateful encoder of MIDI 1.0 byte streams, optionally omitting repeated status bytes.
+#[derive(Clone, Debug, Default)]
+pub struct Encoder {
+    use_running_status: bool,
+    running_status: Option<u8>,
+}
+
+impl Encoder {
+    /// Construct an encoder that writes the status byte of every message.
+    pub fn new() -> Self {
+        Encoder::default()
+    }
+
+    /// Construct an encoder that omits the status byte of each channel message whose status
+    /// matches that of the previous channel message.
+    pub fn with_running_status() -> Self {
+        Encoder {
+            use_running_status: true,
+            running_status: None,
+        }
+    }
+
+    /// Forget the running status, so that the next channel message is written in full.
+    ///
+    /// This should be called whenever the stream is interrupted, e.g. at the start of each
+    /// packet sent to a receiver that does not track running status across packets.
+    pub fn reset(&mut self) {
+        self.running_status = None;
+    }
+
+    /// Append the bytes of the given message to `bytes`.
+    ///
+    /// Nothing is appended if the message is invalid.
+    pub fn encode(&mut self, message: &MidiMessage, bytes: &mut Vec<u8>) -> Result<(), MidiError> {
+        let start = bytes.len();
+        message.write_to(bytes)?;
+        let status = bytes[start];
+        if message.is_realtime() {
+            return Ok(());
+        }
+        if status >= 0xF0 {
+            self.running_status = None;
+            return Ok(());
+        }
+        if self.use_running_status && self.running_status == Some(status) {
+            bytes.remove(start);
+        }
+        self.running_status = Some(status);
+        Ok(())
+    }
+
+    /// Encode each of the given messages in turn.
+    pub fn encode_all<'a, I>(&mut self, messages: I) -> Result<Vec<u8>, MidiError>
+    where
+        I: IntoIterator<Item = &'a MidiMessage>,
+    {
+        let mut bytes = Vec::new();
+        for message in messages {
+            self.encode(message, &mut bytes)?;
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+fn parse_results(parser: &mut Parser, bytes: &[u8]) -> Vec<Result<MidiMessage, MidiError>> {
+    let mut results = Vec::new();
+    parser.parse(bytes, |result| results.push(result));
+    results
+}
+
+#[test]
+fn test_parse_running_status() {
+    let bytes = [0x90, 60, 100, 62, 100, 64, 0, 0xB1, 7, 100, 10, 64];
+    assert_eq!(
+        parse(&bytes).unwrap(),
+        vec![
+            MidiMessage::NoteOn {
+                channel: 0,
+                note: 60,
+                velocity: 100
+            },
+            MidiMessage::NoteOn {
+                channel: 0,
+                note: 62,
+                velocity: 100
+            },
+            MidiMessage::NoteOn {
+                channel: 0,
+                note: 64,
+                velocity: 0
+            },
+            MidiMessage::ControlChange {
+                channel: 1,
+                controller: 7,
+                value: 100
+            },
+            MidiMessage::ControlChange {
+                channel: 1,
+                controller: 10,
+                value: 64
+            },
+        ]
+    );
+
+    // Single data byte messages.
+    assert_eq!(
+        parse(&[0xC2, 1, 2, 3]).unwrap(),
+        (1..4)
+            .map(|program| MidiMessage::ProgramChange {
+                channel: 2,
+                program
+            })
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_parse_realtime_interleaved() {
+    // Realtime bytes between the data bytes of a message and within a system exclusive message.
+    let bytes = [
+        0x90, 0xF8, 60, 0xFE, 100, 0xF0, 0x43, 0xF8, 0x10, 0xFA, 0xF7, 62, 0xFC,
+    ];
+    let mut parser = Parser::new();
+    let results = parse_results(&mut parser, &bytes);
+    assert_eq!(
+        results,
+        vec![
+            Ok(MidiMessage::TimingClock),
+            Ok(MidiMessage::ActiveSensing),
+            Ok(MidiMessage::NoteOn {
+                channel: 0,
+                note: 60,
+                velocity: 100
+            }),
+            Ok(MidiMessage::TimingClock),
+            Ok(MidiMessage::Start),
+            Ok(MidiMessage::SysEx(vec![0x43, 0x10])),
+            // Running status is cancelled by the system exclusive message.
+            Err(MidiError::InvalidDataByte),
+            Ok(MidiMessage::Stop),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_realtime_keeps_running_status() {
+    let bytes = [0x80, 60, 0, 0xF8, 61, 0];
+    assert_eq!(
+        parse(&bytes).unwrap(),
+        vec![
+            MidiMessage::NoteOff {
+                channel: 0,
+                note: 60,
+                velocity: 0
+            },
+            MidiMessage::TimingClock,
+            MidiMessage::NoteOff {
+                channel: 0,
+                note: 61,
+                velocity: 0
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_system_common() {
+    let bytes = [0xF1, 0x35, 0xF2, 0x01, 0x01, 0xF3, 5, 0xF6];
+    assert_eq!(
+        parse(&bytes).unwrap(),
+        vec![
+            MidiMessage::TimeCodeQuarterFrame(0x35),
+            MidiMessage::SongPosition(0x81),
+            MidiMessage::SongSelect(5),
+            MidiMessage::TuneRequest,
+        ]
+    );
+    // System common messages cancel running status.
+    assert_eq!(
+        parse(&[0x90, 60, 100, 0xF6, 61, 100]),
+        Err(MidiError::InvalidDataByte)
+    );
+}
+
+#[test]
+fn test_parse_split_across_calls() {
+    let mut parser = Parser::new();
+    assert!(parse_results(&mut parser, &[0xF0, 1, 2]).is_empty());
+    assert!(parse_results(&mut parser, &[3]).is_empty());
+    assert_eq!(
+        parse_results(&mut parser, &[4, 0xF7, 0xE0, 0]),
+        vec![Ok(MidiMessage::SysEx(vec![1, 2, 3, 4]))]
+    );
+    assert_eq!(
+        parse_results(&mut parser, &[0x40]),
+        vec![Ok(MidiMessage::PitchBend {
+            channel: 0,
+            value: 0x2000
+        })]
+    );
+    parser.reset();
+    assert_eq!(
+        parse_results(&mut parser, &[0]),
+        vec![Err(MidiError::InvalidDataByte)]
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    let mut parser = Parser::new();
+    // A status byte interrupting a message or a system exclusive message.
+    assert_eq!(
+        parse_results(&mut parser, &[0x90, 60, 0xF0, 1, 0xC0, 5]),
+        vec![
+            Err(MidiError::Incomplete),
+            Err(MidiError::Incomplete),
+            Ok(MidiMessage::ProgramChange {
+                channel: 0,
+                program: 5
+            }),
+        ]
+    );
+    // A system exclusive message interrupted by a single byte message.
+    assert_eq!(
+        parse_results(&mut parser, &[0xF0, 1, 0xF6]),
+        vec![Err(MidiError::Incomplete), Ok(MidiMessage::TuneRequest)]
+    );
+    // Undefined status bytes and stray end of exclusive bytes.
+    assert_eq!(
+        parse_results(&mut parser, &[0xF4, 0xF5, 0xF9, 0xFD, 0xF7]),
+        vec![Err(MidiError::InvalidStatus); 5]
+    );
+    assert_eq!(parse(&[0x90, 60]), Err(MidiError::Incomplete));
+    assert_eq!(parse(&[0xF0, 1]), Err(MidiError::Incomplete));
+    assert_eq!(parse(&[]), Ok(vec![]));
+}
+
+#[test]
+fn test_encode_running_status() {
+    let messages = vec![
+        MidiMessage::NoteOn {
+            channel: 0,
+            note: 60,
+            velocity: 100,
+        },
+        MidiMessage::TimingClock,
+        MidiMessage::NoteOn {
+            channel: 0,
+            note: 62,
+            velocity: 100,
+        },
+        MidiMessage::NoteOn {
+            channel: 1,
+            note: 62,
+            velocity: 100,
+        },
+        MidiMessage::SysEx(vec![1]),
+        MidiMessage::NoteOn {
+            channel: 1,
+            note: 64,
+            velocity: 0,
+        },
+        MidiMessage::NoteOn {
+            channel: 1,
+            note: 65,
+            velocity: 0,
+        },
+    ];
+    let running = Encoder::with_running_status()
+        .encode_all(&messages)
+        .unwrap();
+    assert_eq!(
+        running,
+        vec![0x90, 60, 100, 0xF8, 62, 100, 0x91, 62, 100, 0xF0, 1, 0xF7, 0x91, 64, 0, 65, 0]
+    );
+    assert_eq!(parse(&running).unwrap(), messages);
+
+    let full = Encoder::new().encode_all(&messages).unwrap();
+    assert_eq!(full.len(), running.len() + 2);
+    assert_eq!(parse(&full).unwrap(), messages);
+
+    let mut encoder = Encoder::with_running_status();
+    let mut bytes = vec![];
+    encoder.encode(&messages[0], &mut bytes).unwrap();
+    encoder.reset();
+    encoder.encode(&messages[2], &mut bytes).unwrap();
+    assert_eq!(bytes, vec![0x90, 60, 100, 0x90, 62, 100]);
+    let bad = MidiMessage::SongSelect(0x80);
+    assert!(encoder.encode(&bad, &mut bytes).is_err());
+    assert_eq!(bytes.len(), 6);
+}
+
+#[test]
+fn test_parse_every_message_kind() {
+    let messages = super::all_message_kinds();
+    let bytes = Encoder::with_running_status()
+        .encode_all(&messages)
+        .unwrap();
+    assert_eq!(parse(&bytes).unwrap(), messages);
+}
@@ -0,0 +1,1174 @@
+//! Reading and writing Standard MIDI Files (SMF) and scheduling their events by sample time.
+//!
+//! Format 0 (a single track) and format 1 (simultaneous tracks) files are supported, with either
+//! a metrical (ticks per quarter note) or SMPTE division. Ticks are converted to seconds, and in
+//! turn to sample time at a given sample rate (e.g. **StreamFormat::sample_rate**), by a
+//! [**TempoMap**](./struct.TempoMap) built from the file's tempo events.
+//!
+//! A [**Schedule**](./struct.Schedule) orders the messages of every track by sample time, yielding
+//! those that fall within each render cycle along with their frame offsets. These may be passed
+//! directly to **AudioUnit::send_midi_events** to play the file through a **MusicDevice** unit.
+
+use super::{data_len, MidiMessage, SYSEX_END, SYSEX_START};
+use crate::error::SmfError;
+use std::convert::TryFrom;
+
+const HEADER_CHUNK: &[u8; 4] = b"MThd";
+const TRACK_CHUNK: &[u8; 4] = b"MTrk";
+const HEADER_LEN: u32 = 6;
+const META: u8 = 0xFF;
+const MAX_VARIABLE_LENGTH: u32 = 0x0FFF_FFFF;
+
+const SEQUENCE_NUMBER: u8 = 0x00;
+const CHANNEL_PREFIX: u8 = 0x20;
+const PORT: u8 = 0x21;
+const END_OF_TRACK: u8 = 0x2F;
+const TEMPO: u8 = 0x51;
+const SMPTE_OFFSET: u8 = 0x54;
+const TIME_SIGNATURE: u8 = 0x58;
+const KEY_SIGNATURE: u8 = 0x59;
+const SEQUENCER_SPECIFIC: u8 = 0x7F;
+
+/// The **Text** meta event kind for arbitrary text.
+pub const TEXT: u8 = 0x01;
+/// The **Text** meta event kind for a copyright notice.
+pub const COPYRIGHT: u8 = 0x02;
+/// The **Text** meta event kind for the name of a sequence or track.
+pub const TRACK_NAME: u8 = 0x03;
+/// The **Text** meta event kind for the name of an instrument.
+pub const INSTRUMENT_NAME: u8 = 0x04;
+/// The **Text** meta event kind for a lyric.
+pub const LYRIC: u8 = 0x05;
+/// The **Text** meta event kind for a marker, e.g. a rehearsal letter.
+pub const MARKER: u8 = 0x06;
+/// The **Text** meta event kind for a cue point.
+pub const CUE_POINT: u8 = 0x07;
+
+/// The tempo in effect before the first tempo event: 120 beats per minute.
+pub const DEFAULT_TEMPO: u32 = 500_000;
+
+/// The arrangement of a file's tracks.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Format 0: a single track.
+    SingleTrack = 0,
+    /// Format 1: one or more tracks played simultaneously.
+    MultiTrack = 1,
+}
+
+/// The meaning of the ticks by which events are timed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Division {
+    /// Ticks per quarter note, such that their duration depends upon the tempo.
+    TicksPerQuarter(u16),
+    /// Ticks per SMPTE frame, independent of the tempo.
+    ///
+    /// The frame rate is one of `24`, `25`, `29` (i.e. 29.97 drop frame) or `30`.
+    Smpte {
+        frames_per_second: u8,
+        ticks_per_frame: u8,
+    },
+}
+
+impl Division {
+    fn from_u16(division: u16) -> Result<Self, SmfError> {
+        if division & 0x8000 == 0 {
+            if division == 0 {
+                return Err(SmfError::InvalidDivision);
+            }
+            return Ok(Division::TicksPerQuarter(division));
+        }
+        // The frame rate is stored as a negative two's complement byte.
+        let frames_per_second = ((division >> 8) as u8 as i8).unsigned_abs();
+        let ticks_per_frame = division as u8;
+        let division = Division::Smpte {
+            frames_per_second,
+            ticks_per_frame,
+        };
+        division.to_u16()?;
+        Ok(division)
+    }
+
+    fn to_u16(self) -> Result<u16, SmfError> {
+        match self {
+            Division::TicksPerQuarter(ticks) if ticks > 0 && ticks < 0x8000 => Ok(ticks),
+            Division::Smpte {
+                frames_per_second: frames_per_second @ (24 | 25 | 29 | 30),
+                ticks_per_frame,
+            } if ticks_per_frame > 0 => {
+                let frames = (frames_per_second as i8).wrapping_neg() as u8;
+                Ok((frames as u16) << 8 | ticks_per_frame as u16)
+            }
+            _ => Err(SmfError::InvalidDivision),
+        }
+    }
+
+    /// The number of ticks per second at the given tempo in microseconds per quarter note.
+    ///
+    /// The tempo is ignored for SMPTE divisions.
+    pub fn ticks_per_second(&self, tempo: u32) -> f64 {
+        match *self {
+            Division::TicksPerQuarter(ticks) => ticks as f64 * 1_000_000.0 / tempo as f64,
+            Division::Smpte {
+                frames_per_second,
+                ticks_per_frame,
+            } => {
+                let frames_per_second = match frames_per_second {
+                    29 => 30_000.0 / 1001.0,
+                    frames => frames as f64,
+                };
+                frames_per_second * ticks_per_frame as f64
+            }
+        }
+    }
+}
+
+/// The time signature given by a **TimeSignature** meta event.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimeSignature {
+    /// The number of beats per bar.
+    pub numerator: u8,
+    /// The beat's note value as a power of two, e.g. `2` for quarter notes.
+    pub denominator_power: u8,
+    /// The number of MIDI clocks (24 per quarter note) per metronome click.
+    pub clocks_per_click: u8,
+    /// The number of notated 32nd notes per MIDI quarter note, typically `8`.
+    pub thirty_seconds_per_quarter: u8,
+}
+
+impl TimeSignature {
+    /// The beat's note value, e.g. `4` for quarter notes.
+    pub fn denominator(&self) -> u32 {
+        1 << self.denominator_power.min(31)
+    }
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        TimeSignature {
+            numerator: 4,
+            denominator_power: 2,
+            clocks_per_click: 24,
+            thirty_seconds_per_quarter: 8,
+        }
+    }
+}
+
+/// A meta event, carrying information about the file rather than a MIDI message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MetaEvent {
+    /// The number of the sequence within a collection.
+    SequenceNumber(u16),
+    /// Text of the given kind, e.g. [**TRACK_NAME**](./constant.TRACK_NAME.html), in the range
+    /// `0x01..=0x0F`.
+    Text { kind: u8, text: Vec<u8> },
+    /// The channel to which following meta and system exclusive events apply.
+    ChannelPrefix(u8),
+    /// The output port on which following events are played.
+    Port(u8),
+    /// The end of the track.
+    EndOfTrack,
+    /// A change of tempo in microseconds per quarter note.
+    Tempo(u32),
+    /// The SMPTE time at which the track starts.
+    SmpteOffset {
+        hours: u8,
+        minutes: u8,
+        seconds: u8,
+        frames: u8,
+        subframes: u8,
+    },
+    /// A change of time signature.
+    TimeSignature(TimeSignature),
+    /// A change of key signature, given by the number of sharps (or negative flats) from `-7` to
+    /// `7`.
+    KeySignature { sharps: i8, minor: bool },
+    /// Data specific to a particular sequencer.
+    SequencerSpecific(Vec<u8>),
+    /// Any other meta event, or a known meta event with unexpected data.
+    Unknown { kind: u8, data: Vec<u8> },
+}
+
+impl MetaEvent {
+    fn parse(kind: u8, data: &[u8]) -> Self {
+        match (kind, data) {
+            (SEQUENCE_NUMBER, &[msb, lsb]) => {
+                MetaEvent::SequenceNumber(u16::from_be_bytes([msb, lsb]))
+            }
+            (0x01..=0x0F, text) => MetaEvent::Text {
+                kind,
+                text: text.to_vec(),
+            },
+            (CHANNEL_PREFIX, &[channel]) if channel < 16 => MetaEvent::ChannelPrefix(channel),
+            (PORT, &[port]) => MetaEvent::Port(port),
+            (END_OF_TRACK, &[]) => MetaEvent::EndOfTrack,
+            (TEMPO, &[a, b, c]) if [a, b, c] != [0; 3] => {
+                MetaEvent::Tempo(u32::from_be_bytes([0, a, b, c]))
+            }
+            (SMPTE_OFFSET, &[hours, minutes, seconds, frames, subframes]) => {
+                MetaEvent::SmpteOffset {
+                    hours,
+                    minutes,
+                    seconds,
+                    frames,
+                    subframes,
+                }
+            }
+            (TIME_SIGNATURE, &[numerator, denominator_power, clocks_per_click, thirty_seconds]) => {
+                MetaEvent::TimeSignature(TimeSignature {
+                    numerator,
+                    denominator_power,
+                    clocks_per_click,
+                    thirty_seconds_per_quarter: thirty_seconds,
+                })
+            }
+            (KEY_SIGNATURE, &[sharps, minor @ (0 | 1)]) if (-7..=7).contains(&(sharps as i8)) => {
+                MetaEvent::KeySignature {
+                    sharps: sharps as i8,
+                    minor: minor == 1,
+                }
+            }
+            (SEQUENCER_SPECIFIC, data) => MetaEvent::SequencerSpecific(data.to_vec()),
+            (kind, data) => MetaEvent::Unknown {
+                kind,
+                data: data.to_vec(),
+            },
+        }
+    }
+
+    fn write_to(&self, bytes: &mut Vec<u8>) -> Result<(), SmfError> {
+        let (kind, data) = match *self {
+            MetaEvent::SequenceNumber(number) => (SEQUENCE_NUMBER, number.to_be_bytes().to_vec()),
+            MetaEvent::Text { kind, ref text } => {
+                if !(0x01..=0x0F).contains(&kind) {
+                    return Err(SmfError::InvalidEvent);
+                }
+                (kind, text.clone())
+            }
+            MetaEvent::ChannelPrefix(channel) => {
+                if channel >= 16 {
+                    return Err(SmfError::InvalidEvent);
+                }
+                (CHANNEL_PREFIX, vec![channel])
+            }
+            MetaEvent::Port(port) => (PORT, vec![port]),
+            MetaEvent::EndOfTrack => (END_OF_TRACK, vec![]),
+            MetaEvent::Tempo(tempo) => {
+                if tempo == 0 || tempo > 0x00FF_FFFF {
+                    return Err(SmfError::InvalidEvent);
+                }
+                (TEMPO, tempo.to_be_bytes()[1..].to_vec())
+            }
+            MetaEvent::SmpteOffset {
+                hours,
+                minutes,
+                seconds,
+                frames,
+                subframes,
+            } => (
+                SMPTE_OFFSET,
+                vec![hours, minutes, seconds, frames, subframes],
+            ),
+            MetaEvent::TimeSignature(signature) => (
+                TIME_SIGNATURE,
+                vec![
+                    signature.numerator,
+                    signature.denominator_power,
+                    signature.clocks_per_click,
+                    signature.thirty_seconds_per_quarter,
+                ],
+            ),
+            MetaEvent::KeySignature { sharps, minor } => {
+                if !(-7..=7).contains(&sharps) {
+                    return Err(SmfError::InvalidEvent);
+                }
+                (KEY_SIGNATURE, vec![sharps as u8, minor as u8])
+            }
+            MetaEvent::SequencerSpecific(ref data) => (SEQUENCER_SPECIFIC, data.clone()),
+            MetaEvent::Unknown { kind, ref data } => {
+                if kind >= 0x80 {
+                    return Err(SmfError::InvalidEvent);
+                }
+                (kind, data.clone())
+            }
+        };
+        bytes.push(META);
+        bytes.push(kind);
+        write_data(bytes, &data)
+    }
+}
+
+/// The content of a track event.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    /// A channel message or complete system exclusive message.
+    ///
+    /// Other system messages are written as escaped **SysExPacket**s, as which they are read.
+    Midi(MidiMessage),
+    /// A system exclusive message divided across events, or arbitrary bytes escaped by `0xF7`.
+    ///
+    /// The status is either `0xF0` or `0xF7` and the data is stored exactly as in the file.
+    SysExPacket { status: u8, data: Vec<u8> },
+    /// A meta event.
+    Meta(MetaEvent),
+}
+
+/// An event within a track.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    /// The time of the event in ticks from the start of the track.
+    pub tick: u64,
+    pub kind: EventKind,
+}
+
+/// A sequence of events ordered by tick.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Track {
+    pub events: Vec<Event>,
+}
+
+impl Track {
+    /// Construct an empty track.
+    pub fn new() -> Self {
+        Track::default()
+    }
+
+    /// Append an event at the given tick.
+    ///
+    /// Events must be pushed in order of their ticks for the track to be written.
+    pub fn push(&mut self, tick: u64, kind: EventKind) {
+        self.events.push(Event { tick, kind });
+    }
+
+    /// The tick of the last event, or `0` if the track is empty.
+    pub fn end_tick(&self) -> u64 {
+        self.events.last().map_or(0, |event| event.tick)
+    }
+
+    fn parse(data: &[u8]) -> Result<Self, SmfError> {
+        let mut reader = Reader { bytes: data };
+        let mut events = vec![];
+        let mut tick = 0;
+        let mut running_status = None;
+        while !reader.is_empty() {
+            tick += reader.variable_length()? as u64;
+            let first = reader.u8()?;
+            let kind = match first {
+                META => {
+                    running_status = None;
+                    let kind = reader.u8()?;
+                    let len = reader.variable_length()? as usize;
+                    EventKind::Meta(MetaEvent::parse(kind, reader.take(len)?))
+                }
+                SYSEX_START | SYSEX_END => {
+                    running_status = None;
+                    let len = reader.variable_length()? as usize;
+                    let data = reader.take(len)?;
+                    match data.split_last() {
+                        Some((&SYSEX_END, payload))
+                            if first == SYSEX_START && payload.iter().all(|&b| b < 0x80) =>
+                        {
+                            EventKind::Midi(MidiMessage::SysEx(payload.to_vec()))
+                        }
+                        _ => EventKind::SysExPacket {
+                            status: first,
+                            data: data.to_vec(),
+                        },
+                    }
+                }
+                0x00..=0xEF => {
+                    let mut message = [0; 3];
+                    let mut read = 1;
+                    if first < 0x80 {
+                        message[0] = running_status.ok_or(SmfError::MissingRunningStatus)?;
+                        message[1] = first;
+                        read = 2;
+                    } else {
+                        message[0] = first;
+                        running_status = Some(first);
+                    }
+                    let len = 1 + data_len(message[0]).expect("channel status");
+                    for byte in &mut message[read..len] {
+                        *byte = reader.u8()?;
+                    }
+                    let message =
+                        MidiMessage::from_bytes(&message[..len]).map_err(SmfError::Message)?;
+                    EventKind::Midi(message)
+                }
+                _ => return Err(SmfError::InvalidEvent),
+            };
+            let end = kind == EventKind::Meta(MetaEvent::EndOfTrack);
+            events.push(Event { tick, kind });
+            if end {
+                break;
+            }
+        }
+        Ok(Track { events })
+    }
+
+    /// Append the track chunk to `bytes`, ending it with an **EndOfTrack** event if necessary.
+    fn write_to(&self, bytes: &mut Vec<u8>) -> Result<(), SmfError> {
+        bytes.extend_from_slice(TRACK_CHUNK);
+        let len_index = bytes.len();
+        bytes.extend_from_slice(&[0; 4]);
+        let mut tick = 0;
+        let mut running_status = None;
+        let mut ended = false;
+        for event in &self.events {
+            if ended {
+                return Err(SmfError::InvalidEvent);
+            }
+            let delta = event
+                .tick
+                .checked_sub(tick)
+                .ok_or(SmfError::UnorderedEvents)?;
+            let delta = u32::try_from(delta).map_err(|_| SmfError::InvalidVariableLength)?;
+            write_variable_length(bytes, delta)?;
+            tick = event.tick;
+            match event.kind {
+                EventKind::Midi(ref message) => {
+                    let short = message.short_message().map_err(SmfError::Message)?;
+                    match short {
+                        Some(short) if message.channel().is_some() => {
+                            if running_status != Some(short[0]) {
+                                bytes.push(short[0]);
+                                running_status = Some(short[0]);
+                            }
+                            let len = data_len(short[0]).expect("channel status");
+                            bytes.extend_from_slice(&short[1..=len]);
+                        }
+                        Some(short) => {
+                            running_status = None;
+                            let len = 1 + data_len(short[0]).expect("system status");
+                            bytes.push(SYSEX_END);
+                            write_data(bytes, &short[..len])?;
+                        }
+                        None => {
+                            running_status = None;
+                            let message = message.to_bytes().map_err(SmfError::Message)?;
+                            bytes.push(SYSEX_START);
+                            write_data(bytes, &message[1..])?;
+                        }
+                    }
+                }
+                EventKind::SysExPacket { status, ref data } => {
+                    if status != SYSEX_START && status != SYSEX_END {
+                        return Err(SmfError::InvalidEvent);
+                    }
+                    running_status = None;
+                    bytes.push(status);
+                    write_data(bytes, data)?;
+                }
+                EventKind::Meta(ref meta) => {
+                    running_status = None;
+                    meta.write_to(bytes)?;
+                    ended = *meta == MetaEvent::EndOfTrack;
+                }
+            }
+        }
+        if !ended {
+            write_variable_length(bytes, 0)?;
+            MetaEvent::EndOfTrack.write_to(bytes)?;
+        }
+        let len = bytes.len() - len_index - 4;
+        let len = u32::try_from(len).map_err(|_| SmfError::InvalidEvent)?;
+        bytes[len_index..len_index + 4].copy_from_slice(&len.to_be_bytes());
+        Ok(())
+    }
+}
+
+/// A Standard MIDI File.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Smf {
+    pub format: Format,
+    pub division: Division,
+    pub tracks: Vec<Track>,
+}
+
+impl Smf {
+    /// Construct a file with no tracks.
+    pub fn new(format: Format, division: Division) -> Self {
+        Smf {
+            format,
+            division,
+            tracks: vec![],
+        }
+    }
+
+    /// Read a format 0 or format 1 file.
+    ///
+    /// Unknown chunks are skipped, and each track ends at its first **EndOfTrack** event.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SmfError> {
+        if !bytes.starts_with(HEADER_CHUNK) {
+            return Err(SmfError::NotStandardMidiFile);
+        }
+        let mut reader = Reader {
+            bytes: &bytes[HEADER_CHUNK.len()..],
+        };
+        let header_len = reader.u32()?;
+        if header_len < HEADER_LEN {
+            return Err(SmfError::NotStandardMidiFile);
+        }
+        let mut header = Reader {
+            bytes: reader.take(header_len as usize)?,
+        };
+        let format = match header.u16()? {
+            0 => Format::SingleTrack,
+            1 => Format::MultiTrack,
+            _ => return Err(SmfError::UnsupportedFormat),
+        };
+        let track_count = header.u16()? as usize;
+        let division = Division::from_u16(header.u16()?)?;
+        if format == Format::SingleTrack && track_count != 1 {
+            return Err(SmfError::InvalidTrackCount);
+        }
+
+        let mut tracks = Vec::with_capacity(track_count);
+        while tracks.len() < track_count {
+            let id = reader.take(4)?;
+            let len = reader.u32()? as usize;
+            let data = reader.take(len)?;
+            if id == TRACK_CHUNK {
+                tracks.push(Track::parse(data)?);
+            }
+        }
+        Ok(Smf {
+            format,
+            division,
+            tracks,
+        })
+    }
+
+    /// Write the file, using running status for consecutive channel messages.
+    ///
+    /// Tracks that do not end with an **EndOfTrack** event have one appended at their last tick.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SmfError> {
+        let track_count =
+            u16::try_from(self.tracks.len()).map_err(|_| SmfError::InvalidTrackCount)?;
+        if self.format == Format::SingleTrack && track_count != 1 {
+            return Err(SmfError::InvalidTrackCount);
+        }
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(HEADER_CHUNK);
+        bytes.extend_from_slice(&HEADER_LEN.to_be_bytes());
+        bytes.extend_from_slice(&(self.format as u16).to_be_bytes());
+        bytes.extend_from_slice(&track_count.to_be_bytes());
+        bytes.extend_from_slice(&self.division.to_u16()?.to_be_bytes());
+        for track in &self.tracks {
+            track.write_to(&mut bytes)?;
+        }
+        Ok(bytes)
+    }
+
+    /// The tempo map formed by the **Tempo** events of every track.
+    ///
+    /// Tempo events conventionally appear only in the first track of a format 1 file.
+    pub fn tempo_map(&self) -> TempoMap {
+        let tempos = self.tracks.iter().flat_map(|track| {
+            track.events.iter().filter_map(|event| match event.kind {
+                EventKind::Meta(MetaEvent::Tempo(tempo)) => Some((event.tick, tempo)),
+                _ => None,
+            })
+        });
+        TempoMap::new(self.division, tempos)
+    }
+
+    /// The **TimeSignature** events of every track, ordered by tick.
+    pub fn time_signatures(&self) -> Vec<(u64, TimeSignature)> {
+        let mut signatures: Vec<_> = self
+            .tracks
+            .iter()
+            .flat_map(|track| {
+                track.events.iter().filter_map(|event| match event.kind {
+                    EventKind::Meta(MetaEvent::TimeSignature(signature)) => {
+                        Some((event.tick, signature))
+                    }
+                    _ => None,
+                })
+            })
+            .collect();
+        signatures.sort_by_key(|&(tick, _)| tick);
+        signatures
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct TempoChange {
+    tick: u64,
+    tempo: u32,
+    /// The time of the change in seconds.
+    seconds: f64,
+}
+
+/// Converts between ticks and time according to a file's division and tempo changes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TempoMap {
+    division: Division,
+    changes: Vec<TempoChange>,
+}
+
+impl TempoMap {
+    /// Construct a tempo map from the division and `(tick, tempo)` pairs in any order.
+    ///
+    /// The [**DEFAULT_TEMPO**](./constant.DEFAULT_TEMPO.html) applies until the first change.
+    /// Where several changes share a tick, the last of them applies.
+    pub fn new<I>(division: Division, tempos: I) -> Self
+    where
+        I: IntoIterator<Item = (u64, u32)>,
+    {
+        let mut tempos: Vec<_> = tempos.into_iter().collect();
+        tempos.sort_by_key(|&(tick, _)| tick);
+        let mut changes = vec![TempoChange {
+            tick: 0,
+            tempo: DEFAULT_TEMPO,
+            seconds: 0.0,
+        }];
+        for (tick, tempo) in tempos {
+            let last = changes.last_mut().expect("initial tempo");
+            if last.tick == tick {
+                last.tempo = tempo;
+                continue;
+            }
+            let elapsed = (tick - last.tick) as f64 / division.ticks_per_second(last.tempo);
+            let seconds = last.seconds + elapsed;
+            changes.push(TempoChange {
+                tick,
+                tempo,
+                seconds,
+            });
+        }
+        TempoMap { division, changes }
+    }
+
+    fn change_at_tick(&self, tick: u64) -> &TempoChange {
+        let index = self.changes.partition_point(|change| change.tick <= tick);
+        &self.changes[index - 1]
+    }
+
+    /// The tempo in microseconds per quarter note at the given tick.
+    pub fn tempo(&self, tick: u64) -> u32 {
+        self.change_at_tick(tick).tempo
+    }
+
+    /// The time of the given tick in seconds.
+    pub fn seconds(&self, tick: u64) -> f64 {
+        let change = self.change_at_tick(tick);
+        let ticks_per_second = self.division.ticks_per_second(change.tempo);
+        change.seconds + (tick - change.tick) as f64 / ticks_per_second
+    }
+
+    /// The time of the given tick in samples at the given sample rate, e.g. that of the
+    /// **StreamFormat** of the unit playing the file.
+    pub fn sample_time(&self, tick: u64, sample_rate: f64) -> f64 {
+        self.seconds(tick) * sample_rate
+    }
+
+    /// The last tick at or before the given time in seconds.
+    pub fn tick(&self, seconds: f64) -> u64 {
+        let index = self
+            .changes
+            .partition_point(|change| change.seconds <= seconds)
+            .max(1);
+        let change = &self.changes[index - 1];
+        let ticks_per_second = self.division.ticks_per_second(change.tempo);
+        // Allow for rounding error, such that the time of a tick converts back to that tick.
+        // Converting to an integer rounds towards zero, clamping negative times to the start.
+        let ticks = (seconds - change.seconds) * ticks_per_second + 1e-6;
+        change.tick + ticks as u64
+    }
+}
+
+/// The MIDI messages of a file ordered by sample time, for delivery within a render loop.
+///
+/// Each call to [**render**](#method.render) advances the schedule by a render cycle and yields
+/// the messages within it, along with their offset in frames from the start of the cycle. These
+/// may be passed directly to **AudioUnit::send_midi_events**.
+///
+/// Meta events and divided system exclusive messages are not scheduled.
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    events: Vec<(u64, MidiMessage)>,
+    position: u64,
+    next: usize,
+}
+
+impl Schedule {
+    /// Schedule the messages of every track of the file at the given sample rate.
+    ///
+    /// Messages at the same tick are ordered by track, then by their order within the track.
+    pub fn new(smf: &Smf, sample_rate: f64) -> Self {
+        let tempo_map = smf.tempo_map();
+        let mut messages: Vec<_> = smf
+            .tracks
+            .iter()
+            .flat_map(|track| {
+                track.events.iter().filter_map(|event| match event.kind {
+                    EventKind::Midi(ref message) => Some((event.tick, message)),
+                    _ => None,
+                })
+            })
+            .collect();
+        messages.sort_by_key(|&(tick, _)| tick);
+        let events = messages
+            .into_iter()
+            .map(|(tick, message)| {
+                let sample_time = tempo_map.sample_time(tick, sample_rate).round() as u64;
+                (sample_time, message.clone())
+            })
+            .collect();
+        Schedule {
+            events,
+            position: 0,
+            next: 0,
+        }
+    }
+
+    /// The sample time at which the next render cycle begins.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Whether every message has been rendered.
+    pub fn is_finished(&self) -> bool {
+        self.next == self.events.len()
+    }
+
+    /// Move to the given sample time, such that the next render cycle begins there.
+    pub fn seek(&mut self, sample_time: u64) {
+        self.position = sample_time;
+        self.next = self.events.partition_point(|&(time, _)| time < sample_time);
+    }
+
+    /// Advance by a render cycle of the given number of frames, yielding each message within it
+    /// along with its offset in frames from the start of the cycle.
+    pub fn render(&mut self, frames: u32) -> impl Iterator<Item = (u32, &MidiMessage)> + '_ {
+        let start = self.position;
+        let end = start + frames as u64;
+        let first = self.next;
+        let last = first + self.events[first..].partition_point(|&(time, _)| time < end);
+        self.position = end;
+        self.next = last;
+        self.events[first..last]
+            .iter()
+            .map(move |&(time, ref message)| ((time - start) as u32, message))
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SmfError> {
+        if len > self.bytes.len() {
+            return Err(SmfError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, SmfError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SmfError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, SmfError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn variable_length(&mut self) -> Result<u32, SmfError> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let byte = self.u8()?;
+            value = value << 7 | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(SmfError::InvalidVariableLength)
+    }
+}
+
+fn write_variable_length(bytes: &mut Vec<u8>, value: u32) -> Result<(), SmfError> {
+    if value > MAX_VARIABLE_LENGTH {
+        return Err(SmfError::InvalidVariableLength);
+    }
+    let mut encoded = [0; 4];
+    let mut start = encoded.len() - 1;
+    encoded[start] = (value & 0x7F) as u8;
+    let mut rest = value >> 7;
+    while rest != 0 {
+        start -= 1;
+        encoded[start] = (rest & 0x7F) as u8 | 0x80;
+        rest >>= 7;
+    }
+    bytes.extend_from_slice(&encoded[start..]);
+    Ok(())
+}
+
+/// Append the length of `data` as a variable length quantity, followed by `data` itself.
+fn write_data(bytes: &mut Vec<u8>, data: &[u8]) -> Result<(), SmfError> {
+    let len = u32::try_from(data.len()).map_err(|_| SmfError::InvalidVariableLength)?;
+    write_variable_length(bytes, len)?;
+    bytes.extend_from_slice(data);
+    Ok(())
+}
+
+#[cfg(test)]
+const EXAMPLE_FILE: &[u8] = &[
+    b'M', b'T', b'h', b'd', 0, 0, 0, 6, 0, 0, 0, 1, 0, 96, // Format 0, 96 ticks per quarter.
+    b'M', b'T', b'r', b'k', 0, 0, 0, 26, // 26 bytes of events.
+    0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // Tempo of 500,000us per quarter.
+    0x00, 0xFF, 0x58, 0x04, 0x03, 0x02, 0x18, 0x08, // 3/4 time.
+    0x00, 0x90, 0x3C, 0x64, // Note on.
+    0x60, 0x3C, 0x00, // Note on with zero velocity, using running status.
+    0x00, 0xFF, 0x2F, 0x00, // End of track.
+];
+
+#[test]
+fn test_variable_length() {
+    let cases: &[(u32, &[u8])] = &[
+        (0, &[0x00]),
+        (0x40, &[0x40]),
+        (0x7F, &[0x7F]),
+        (0x80, &[0x81, 0x00]),
+        (0x2000, &[0xC0, 0x00]),
+        (0x3FFF, &[0xFF, 0x7F]),
+        (0x4000, &[0x81, 0x80, 0x00]),
+        (0x1F_FFFF, &[0xFF, 0xFF, 0x7F]),
+        (0x20_0000, &[0x81, 0x80, 0x80, 0x00]),
+        (MAX_VARIABLE_LENGTH, &[0xFF, 0xFF, 0xFF, 0x7F]),
+    ];
+    for &(value, encoded) in cases {
+        let mut bytes = vec![];
+        write_variable_length(&mut bytes, value).unwrap();
+        assert_eq!(bytes, encoded);
+        let mut reader = Reader { bytes: encoded };
+        assert_eq!(reader.variable_length(), Ok(value));
+        assert!(reader.is_empty());
+    }
+    assert_eq!(
+        write_variable_length(&mut vec![], MAX_VARIABLE_LENGTH + 1),
+        Err(SmfError::InvalidVariableLength)
+    );
+    let mut reader = Reader {
+        bytes: &[0x80, 0x80, 0x80, 0x80, 0x00],
+    };
+    assert_eq!(
+        reader.variable_length(),
+        Err(SmfError::InvalidVariableLength)
+    );
+    let mut reader = Reader { bytes: &[0x80] };
+    assert_eq!(reader.variable_length(), Err(SmfError::Truncated));
+}
+
+#[test]
+fn test_read_write() {
+    let smf = Smf::from_bytes(EXAMPLE_FILE).unwrap();
+    assert_eq!(smf.format, Format::SingleTrack);
+    assert_eq!(smf.division, Division::TicksPerQuarter(96));
+    let signature = TimeSignature {
+        numerator: 3,
+        denominator_power: 2,
+        clocks_per_click: 24,
+        thirty_seconds_per_quarter: 8,
+    };
+    let mut track = Track::new();
+    track.push(0, EventKind::Meta(MetaEvent::Tempo(500_000)));
+    track.push(0, EventKind::Meta(MetaEvent::TimeSignature(signature)));
+    let note_on = |velocity| MidiMessage::NoteOn {
+        channel: 0,
+        note: 60,
+        velocity,
+    };
+    track.push(0, EventKind::Midi(note_on(100)));
+    track.push(96, EventKind::Midi(note_on(0)));
+    track.push(96, EventKind::Meta(MetaEvent::EndOfTrack));
+    assert_eq!(smf.tracks, vec![track.clone()]);
+    assert_eq!(smf.time_signatures(), vec![(0, signature)]);
+    assert_eq!(signature.denominator(), 4);
+
+    // Writing reproduces the original bytes, including running status.
+    assert_eq!(smf.to_bytes().unwrap(), EXAMPLE_FILE);
+
+    // The end of track event is appended when missing.
+    track.events.pop();
+    let mut without_end = smf.clone();
+    without_end.tracks = vec![track];
+    assert_eq!(without_end.to_bytes().unwrap(), EXAMPLE_FILE);
+}
+
+#[test]
+fn test_read_write_events() {
+    let mut first = Track::new();
+    let meta_events = vec![
+        MetaEvent::SequenceNumber(7),
+        MetaEvent::Text {
+            kind: TRACK_NAME,
+            text: b"Piano".to_vec(),
+        },
+        MetaEvent::ChannelPrefix(3),
+        MetaEvent::Port(1),
+        MetaEvent::Tempo(DEFAULT_TEMPO),
+        MetaEvent::SmpteOffset {
+            hours: 0x61,
+            minutes: 2,
+            seconds: 3,
+            frames: 4,
+            subframes: 5,
+        },
+        MetaEvent::TimeSignature(TimeSignature::default()),
+        MetaEvent::KeySignature {
+            sharps: -3,
+            minor: true,
+        },
+        MetaEvent::SequencerSpecific(vec![0x00, 0x00, 0x41]),
+        MetaEvent::Unknown {
+            kind: 0x60,
+            data: vec![1, 2],
+        },
+    ];
+    for meta in meta_events {
+        first.push(0, EventKind::Meta(meta));
+    }
+    first.push(10, EventKind::Meta(MetaEvent::EndOfTrack));
+
+    let mut second = Track::new();
+    let messages = super::all_message_kinds()
+        .into_iter()
+        .filter(|message| message.channel().is_some() || matches!(message, MidiMessage::SysEx(_)));
+    for (i, message) in messages.enumerate() {
+        second.push(i as u64 * 200, EventKind::Midi(message));
+    }
+    let tick = second.end_tick();
+    second.push(
+        tick,
+        EventKind::SysExPacket {
+            status: SYSEX_START,
+            data: vec![0x43, 0x12],
+        },
+    );
+    second.push(
+        tick + 1,
+        EventKind::SysExPacket {
+            status: SYSEX_END,
+            data: vec![0x00, SYSEX_END],
+        },
+    );
+    second.push(tick + 1, EventKind::Meta(MetaEvent::EndOfTrack));
+
+    let mut smf = Smf::new(Format::MultiTrack, Division::TicksPerQuarter(480));
+    smf.tracks = vec![first, second];
+    let bytes = smf.to_bytes().unwrap();
+    assert_eq!(Smf::from_bytes(&bytes).unwrap(), smf);
+
+    // System messages other than system exclusive are escaped.
+    let mut track = Track::new();
+    track.push(0, EventKind::Midi(MidiMessage::Start));
+    let mut smf = Smf::new(Format::SingleTrack, Division::TicksPerQuarter(480));
+    smf.tracks = vec![track];
+    let smf = Smf::from_bytes(&smf.to_bytes().unwrap()).unwrap();
+    assert_eq!(
+        smf.tracks[0].events[0].kind,
+        EventKind::SysExPacket {
+            status: SYSEX_END,
+            data: vec![0xFA]
+        }
+    );
+}
+
+#[test]
+fn test_division() {
+    for &division in &[
+        Division::TicksPerQuarter(1),
+        Division::TicksPerQuarter(0x7FFF),
+        Division::Smpte {
+            frames_per_second: 24,
+            ticks_per_frame: 4,
+        },
+        Division::Smpte {
+            frames_per_second: 29,
+            ticks_per_frame: 80,
+        },
+        Division::Smpte {
+            frames_per_second: 30,
+            ticks_per_frame: 255,
+        },
+    ] {
+        assert_eq!(Division::from_u16(division.to_u16().unwrap()), Ok(division));
+    }
+    let division = Division::Smpte {
+        frames_per_second: 25,
+        ticks_per_frame: 40,
+    };
+    assert_eq!(division.to_u16(), Ok(0xE728));
+    assert_eq!(division.ticks_per_second(DEFAULT_TEMPO), 1000.0);
+    assert_eq!(
+        Division::TicksPerQuarter(96).ticks_per_second(DEFAULT_TEMPO),
+        192.0
+    );
+    assert_eq!(Division::from_u16(0), Err(SmfError::InvalidDivision));
+    assert_eq!(Division::from_u16(0xE700), Err(SmfError::InvalidDivision));
+    assert_eq!(Division::from_u16(0xE428), Err(SmfError::InvalidDivision));
+    assert_eq!(
+        Division::TicksPerQuarter(0x8000).to_u16(),
+        Err(SmfError::InvalidDivision)
+    );
+}
+
+#[test]
+fn test_tempo_map() {
+    let division = Division::TicksPerQuarter(96);
+    let tempo_map = TempoMap::new(division, vec![(192, 250_000), (0, 600_000), (0, 500_000)]);
+    assert_eq!(tempo_map.tempo(0), 500_000);
+    assert_eq!(tempo_map.tempo(191), 500_000);
+    assert_eq!(tempo_map.tempo(192), 250_000);
+    assert_eq!(tempo_map.seconds(96), 0.5);
+    assert_eq!(tempo_map.seconds(192), 1.0);
+    assert_eq!(tempo_map.seconds(288), 1.25);
+    assert_eq!(tempo_map.sample_time(288, 44_100.0), 55_125.0);
+    for &tick in &[0, 1, 95, 96, 191, 192, 193, 288, 10_000] {
+        assert_eq!(tempo_map.tick(tempo_map.seconds(tick)), tick);
+    }
+    assert_eq!(tempo_map.tick(-1.0), 0);
+
+    // The default tempo applies until the first change.
+    let tempo_map = TempoMap::new(division, vec![(96, 250_000)]);
+    assert_eq!(tempo_map.tempo(0), DEFAULT_TEMPO);
+    assert_eq!(tempo_map.seconds(192), 0.75);
+
+    // SMPTE divisions ignore the tempo.
+    let smpte = Division::Smpte {
+        frames_per_second: 25,
+        ticks_per_frame: 40,
+    };
+    let tempo_map = TempoMap::new(smpte, vec![(100, 250_000)]);
+    assert_eq!(tempo_map.seconds(500), 0.5);
+    assert_eq!(tempo_map.tick(0.5), 500);
+}
+
+#[test]
+fn test_schedule() {
+    let note = |tick, velocity| Event {
+        tick,
+        kind: EventKind::Midi(MidiMessage::NoteOn {
+            channel: 0,
+            note: 60,
+            velocity,
+        }),
+    };
+    let mut tempo_track = Track::new();
+    tempo_track.push(480, EventKind::Meta(MetaEvent::Tempo(250_000)));
+    let note_track = Track {
+        events: vec![note(0, 100), note(480, 0), note(960, 100)],
+    };
+    let mut smf = Smf::new(Format::MultiTrack, Division::TicksPerQuarter(480));
+    smf.tracks = vec![tempo_track, note_track];
+
+    // At 48kHz a quarter note lasts 24,000 samples, then 12,000 after the tempo change.
+    let mut schedule = Schedule::new(&smf, 48_000.0);
+    let render = |schedule: &mut Schedule, frames| -> Vec<(u32, MidiMessage)> {
+        schedule
+            .render(frames)
+            .map(|(offset, message)| (offset, message.clone()))
+            .collect()
+    };
+    let message = |velocity| match note(0, velocity).kind {
+        EventKind::Midi(message) => message,
+        _ => unreachable!(),
+    };
+    assert_eq!(render(&mut schedule, 512), vec![(0, message(100))]);
+    assert_eq!(schedule.position(), 512);
+    assert!(render(&mut schedule, 512).is_empty());
+    schedule.seek(23_900);
+    assert_eq!(render(&mut schedule, 512), vec![(100, message(0))]);
+    assert!(!schedule.is_finished());
+    schedule.seek(35_999);
+    assert_eq!(render(&mut schedule, 1), vec![]);
+    assert_eq!(render(&mut schedule, 1), vec![(0, message(100))]);
+    assert!(schedule.is_finished());
+    schedule.seek(0);
+    assert_eq!(render(&mut schedule, 48_000).len(), 3);
+}
+
+#[test]
+fn test_smf_errors() {
+    assert_eq!(Smf::from_bytes(b"RIFF"), Err(SmfError::NotStandardMidiFile));
+    assert_eq!(Smf::from_bytes(b"MThd"), Err(SmfError::Truncated));
+
+    let with_header = |header: [u8; 6], track: &[u8]| -> Vec<u8> {
+        let mut bytes = b"MThd\0\0\0\x06".to_vec();
+        bytes.extend_from_slice(&header);
+        bytes.extend_from_slice(b"MTrk");
+        bytes.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(track);
+        bytes
+    };
+    let format_0 = [0, 0, 0, 1, 0, 96];
+    assert_eq!(
+        Smf::from_bytes(&with_header([0, 2, 0, 1, 0, 96], &[])),
+        Err(SmfError::UnsupportedFormat)
+    );
+    assert_eq!(
+        Smf::from_bytes(&with_header([0, 0, 0, 2, 0, 96], &[])),
+        Err(SmfError::InvalidTrackCount)
+    );
+    assert_eq!(
+        Smf::from_bytes(&with_header([0, 1, 0, 2, 0, 96], &[])),
+        Err(SmfError::Truncated)
+    );
+    assert_eq!(
+        Smf::from_bytes(&with_header(format_0, &[0x00, 0x3C, 0x64])),
+        Err(SmfError::MissingRunningStatus)
+    );
+    assert_eq!(
+        Smf::from_bytes(&with_header(format_0, &[0x00, 0x90, 0x3C])),
+        Err(SmfError::Truncated)
+    );
+    assert_eq!(
+        Smf::from_bytes(&with_header(format_0, &[0x00, 0xF8])),
+        Err(SmfError::InvalidEvent)
+    );
+    assert_eq!(
+        Smf::from_bytes(&with_header(format_0, &[0x00, 0x90, 0x3C, 0x80])),
+        Err(SmfError::Message(crate::error::MidiError::InvalidDataByte))
+    );
+    // Events after the end of the track are ignored.
+    let smf = Smf::from_bytes(&with_header(
+        format_0,
+        &[0x00, 0xFF, 0x2F, 0x00, 0x00, 0xF8],
+    ));
+    assert_eq!(smf.unwrap().tracks[0].events.len(), 1);
+
+    let mut track = Track::new();
+    track.push(10, EventKind::Meta(MetaEvent::Tempo(DEFAULT_TEMPO)));
+    track.push(5, EventKind::Meta(MetaEvent::Tempo(DEFAULT_TEMPO)));
+    let mut smf = Smf::new(Format::SingleTrack, Division::TicksPerQuarter(96));
+    assert_eq!(smf.to_bytes(), Err(SmfError::InvalidTrackCount));
+    smf.tracks = vec![track];
+    assert_eq!(smf.to_bytes(), Err(SmfError::UnorderedEvents));
+    smf.tracks[0].events[1].tick = 10;
+    smf.tracks[0].events[0].kind = EventKind::Meta(MetaEvent::EndOfTrack);
+    assert_eq!(smf.to_bytes(), Err(SmfError::InvalidEvent));
+    smf.tracks[0].events.remove(0);
+    smf.tracks[0].events[0].kind = EventKind::Meta(MetaEvent::Tempo(0x0100_0000));
+    assert_eq!(smf.to_bytes(), Err(SmfError::InvalidEvent));
+    smf.tracks[0].events[0].kind = EventKind::Midi(MidiMessage::ProgramChange {
+        channel: 16,
+        program: 0,
+    });
+    assert_eq!(
+        smf.to_bytes(),
+        Err(SmfError::Message(crate::error::MidiError::InvalidChannel))
+    );
+}
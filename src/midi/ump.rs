@@ -0,0 +1,1347 @@
+//! MIDI 2.0 Universal MIDI Packets (UMP) and their conversion to and from MIDI 1.0.
+//!
+//! A Universal MIDI Packet is one to four 32-bit words, the top four bits of which give the
+//! message type and thereby the size of the packet. The [**Ump**](./enum.Ump) type decodes the
+//! message types relevant to MIDI 1.0 and MIDI 2.0 channel voice, system and system exclusive
+//! messages, preserving any others as raw words.
+//!
+//! Conversion between the protocols follows the MIDI 2.0 specification's translation rules:
+//!
+//! - [**from_midi1**](./fn.from_midi1.html) carries MIDI 1.0 messages within packets without
+//!   changing them, as used by the MIDI 1.0 protocol.
+//! - [**Midi1ToMidi2**](./struct.Midi1ToMidi2) translates MIDI 1.0 channel voice messages into
+//!   their higher resolution MIDI 2.0 equivalents, gathering bank selects and parameter numbers.
+//! - [**UmpToMidi1**](./struct.UmpToMidi1) translates packets of either protocol back to MIDI 1.0
+//!   messages, reassembling system exclusive messages.
+
+use super::controller::{self, ControlEvent, ControllerDecoder, ParameterKind};
+use super::{check_channel, check_data, data_len, MidiMessage};
+use crate::error::MidiError;
+
+const UTILITY: u32 = 0x0;
+const SYSTEM: u32 = 0x1;
+const MIDI1_CHANNEL_VOICE: u32 = 0x2;
+const SYSEX7: u32 = 0x3;
+const MIDI2_CHANNEL_VOICE: u32 = 0x4;
+
+const REGISTERED_CONTROLLER: u32 = 0x2;
+const ASSIGNABLE_CONTROLLER: u32 = 0x3;
+const NOTE_OFF: u32 = 0x8;
+const NOTE_ON: u32 = 0x9;
+const POLY_PRESSURE: u32 = 0xA;
+const CONTROL_CHANGE: u32 = 0xB;
+const PROGRAM_CHANGE: u32 = 0xC;
+const CHANNEL_PRESSURE: u32 = 0xD;
+const PITCH_BEND: u32 = 0xE;
+
+/// The bank select controllers, carrying the most and least significant bits of the bank.
+const BANK_SELECT_MSB: u8 = 0;
+const BANK_SELECT_LSB: u8 = 32;
+
+/// The number of data bytes that a single system exclusive packet may hold.
+pub const SYSEX7_MAX_BYTES: usize = 6;
+
+/// The number of words within the Universal MIDI Packet beginning with the given word.
+///
+/// The size is determined by the message type held within the top four bits.
+pub fn ump_len(first_word: u32) -> usize {
+    match first_word >> 28 {
+        0x0 | 0x1 | 0x2 | 0x6 | 0x7 => 1,
+        0x3 | 0x4 | 0x8 | 0x9 | 0xA => 2,
+        0xB | 0xC => 3,
+        _ => 4,
+    }
+}
+
+/// An iterator splitting a sequence of words into Universal MIDI Packets.
+///
+/// Yields an `Error` if the final packet is incomplete, after which iteration ends.
+#[derive(Clone, Debug)]
+pub struct UmpMessages<'a> {
+    words: &'a [u32],
+}
+
+impl<'a> UmpMessages<'a> {
+    /// Split the given words into Universal MIDI Packets.
+    pub fn new(words: &'a [u32]) -> Self {
+        UmpMessages { words }
+    }
+}
+
+impl<'a> Iterator for UmpMessages<'a> {
+    type Item = Result<&'a [u32], MidiError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let &first = self.words.first()?;
+        let len = ump_len(first);
+        if len > self.words.len() {
+            self.words = &[];
+            return Some(Err(MidiError::Incomplete));
+        }
+        let (message, rest) = self.words.split_at(len);
+        self.words = rest;
+        Some(Ok(message))
+    }
+}
+
+/// Scale a value up to a greater number of bits using the MIDI 2.0 "min-center-max" algorithm.
+///
+/// The minimum, center and maximum values of the source range map to those of the destination
+/// range, and scaling back down with [**downscale**](./fn.downscale.html) is lossless.
+pub fn upscale(value: u32, from_bits: u32, to_bits: u32) -> u32 {
+    debug_assert!(from_bits > 1 && from_bits <= to_bits && to_bits <= 32);
+    let value = value as u64 & ((1 << from_bits) - 1);
+    let scale_bits = to_bits - from_bits;
+    let center = 1 << (from_bits - 1);
+    if value <= center {
+        return (value << scale_bits) as u32;
+    }
+    // Above the center, the bits below the most significant are repeated to fill the new bits.
+    let repeat_bits = from_bits - 1;
+    let mut repeat = value & ((1 << repeat_bits) - 1);
+    if scale_bits > repeat_bits {
+        repeat <<= scale_bits - repeat_bits;
+    } else {
+        repeat >>= repeat_bits - scale_bits;
+    }
+    let mut result = value << scale_bits;
+    while repeat != 0 {
+        result |= repeat;
+        repeat >>= repeat_bits;
+    }
+    result as u32
+}
+
+/// Scale a value down to a smaller number of bits by discarding the least significant bits.
+pub fn downscale(value: u32, from_bits: u32, to_bits: u32) -> u32 {
+    debug_assert!(to_bits <= from_bits && from_bits <= 32);
+    ((value as u64 & ((1 << from_bits) - 1)) >> (from_bits - to_bits)) as u32
+}
+
+/// The position of a system exclusive packet within a complete message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SysExStatus {
+    /// The packet holds a complete message.
+    Complete = 0,
+    /// The packet holds the start of a message.
+    Start = 1,
+    /// The packet continues a message.
+    Continue = 2,
+    /// The packet holds the end of a message.
+    End = 3,
+}
+
+impl SysExStatus {
+    fn from_u32(status: u32) -> Option<Self> {
+        match status {
+            0 => Some(SysExStatus::Complete),
+            1 => Some(SysExStatus::Start),
+            2 => Some(SysExStatus::Continue),
+            3 => Some(SysExStatus::End),
+            _ => None,
+        }
+    }
+}
+
+/// A MIDI 2.0 channel voice message.
+///
+/// Notes, controllers and programs are in the range `0..128`, as are the bank and index of
+/// parameter numbers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Midi2Message {
+    /// Stop the given note.
+    NoteOff {
+        channel: u8,
+        note: u8,
+        velocity: u16,
+        attribute_type: u8,
+        attribute: u16,
+    },
+    /// Start the given note.
+    ///
+    /// Unlike MIDI 1.0, a velocity of `0` does not stop the note.
+    NoteOn {
+        channel: u8,
+        note: u8,
+        velocity: u16,
+        attribute_type: u8,
+        attribute: u16,
+    },
+    /// Change the pressure of a single held note.
+    PolyPressure { channel: u8, note: u8, value: u32 },
+    /// Set the value of a controller.
+    ControlChange {
+        channel: u8,
+        controller: u8,
+        value: u32,
+    },
+    /// Select the program of a channel, along with the bank's most and least significant bits if
+    /// the bank is valid.
+    ProgramChange {
+        channel: u8,
+        program: u8,
+        bank: Option<(u8, u8)>,
+    },
+    /// Change the pressure of all notes held on a channel.
+    ChannelPressure { channel: u8, value: u32 },
+    /// Bend the pitch of a channel, where `0x8000_0000` applies no bend.
+    PitchBend { channel: u8, value: u32 },
+    /// Set a registered (RPN) or assignable (NRPN) controller.
+    ///
+    /// The parameter number combines the bank (the most significant seven bits) and index.
+    Parameter {
+        channel: u8,
+        kind: ParameterKind,
+        parameter: u16,
+        value: u32,
+    },
+}
+
+/// A decoded Universal MIDI Packet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Ump {
+    /// A utility message, e.g. a NOOP or jitter reduction timestamp.
+    Utility(u32),
+    /// A MIDI 1.0 system common or realtime message.
+    System { group: u8, message: MidiMessage },
+    /// A MIDI 1.0 channel voice message.
+    Midi1 { group: u8, message: MidiMessage },
+    /// Up to six bytes of a system exclusive message.
+    SysEx7 {
+        group: u8,
+        status: SysExStatus,
+        data: Vec<u8>,
+    },
+    /// A MIDI 2.0 channel voice message.
+    Midi2 { group: u8, message: Midi2Message },
+    /// Any other message, as raw words.
+    Other(Vec<u32>),
+}
+
+fn check_group(group: u8) -> Result<u32, MidiError> {
+    if group < 16 {
+        Ok(group as u32)
+    } else {
+        Err(MidiError::InvalidGroup)
+    }
+}
+
+fn byte(word: u32, index: u32) -> u8 {
+    (word >> (24 - index * 8)) as u8
+}
+
+fn word(bytes: [u8; 4]) -> u32 {
+    u32::from_be_bytes(bytes)
+}
+
+impl Midi2Message {
+    /// The channel of the message.
+    pub fn channel(&self) -> u8 {
+        match *self {
+            Midi2Message::NoteOff { channel, .. }
+            | Midi2Message::NoteOn { channel, .. }
+            | Midi2Message::PolyPressure { channel, .. }
+            | Midi2Message::ControlChange { channel, .. }
+            | Midi2Message::ProgramChange { channel, .. }
+            | Midi2Message::ChannelPressure { channel, .. }
+            | Midi2Message::PitchBend { channel, .. }
+            | Midi2Message::Parameter { channel, .. } => channel,
+        }
+    }
+
+    fn to_words(self, group: u32) -> Result<[u32; 2], MidiError> {
+        let channel = check_channel(self.channel())? as u32;
+        let (opcode, index1, index2, data) = match self {
+            Midi2Message::NoteOff {
+                note,
+                velocity,
+                attribute_type,
+                attribute,
+                ..
+            } => (
+                NOTE_OFF,
+                check_data(note)?,
+                attribute_type,
+                (velocity as u32) << 16 | attribute as u32,
+            ),
+            Midi2Message::NoteOn {
+                note,
+                velocity,
+                attribute_type,
+                attribute,
+                ..
+            } => (
+                NOTE_ON,
+                check_data(note)?,
+                attribute_type,
+                (velocity as u32) << 16 | attribute as u32,
+            ),
+            Midi2Message::PolyPressure { note, value, .. } => {
+                (POLY_PRESSURE, check_data(note)?, 0, value)
+            }
+            Midi2Message::ControlChange {
+                controller, value, ..
+            } => (CONTROL_CHANGE, check_data(controller)?, 0, value),
+            Midi2Message::ProgramChange { program, bank, .. } => {
+                let (flags, msb, lsb) = match bank {
+                    Some((msb, lsb)) => (1, check_data(msb)?, check_data(lsb)?),
+                    None => (0, 0, 0),
+                };
+                let data = word([check_data(program)?, 0, msb, lsb]);
+                (PROGRAM_CHANGE, 0, flags, data)
+            }
+            Midi2Message::ChannelPressure { value, .. } => (CHANNEL_PRESSURE, 0, 0, value),
+            Midi2Message::PitchBend { value, .. } => (PITCH_BEND, 0, 0, value),
+            Midi2Message::Parameter {
+                kind,
+                parameter,
+                value,
+                ..
+            } => {
+                if parameter > 0x3FFF {
+                    return Err(MidiError::InvalidDataByte);
+                }
+                let opcode = match kind {
+                    ParameterKind::Registered => REGISTERED_CONTROLLER,
+                    ParameterKind::NonRegistered => ASSIGNABLE_CONTROLLER,
+                };
+                (
+                    opcode,
+                    (parameter >> 7) as u8,
+                    (parameter & 0x7F) as u8,
+                    value,
+                )
+            }
+        };
+        let first = MIDI2_CHANNEL_VOICE << 28
+            | group << 24
+            | opcode << 20
+            | channel << 16
+            | (index1 as u32) << 8
+            | index2 as u32;
+        Ok([first, data])
+    }
+
+    /// Decode the message within the given pair of words, or `None` for unsupported opcodes.
+    fn from_words(first: u32, data: u32) -> Option<Self> {
+        let channel = (first >> 16) as u8 & 0x0F;
+        let index1 = byte(first, 2);
+        let index2 = byte(first, 3);
+        let message = match (first >> 20) & 0x0F {
+            NOTE_OFF => Midi2Message::NoteOff {
+                channel,
+                note: index1 & 0x7F,
+                velocity: (data >> 16) as u16,
+                attribute_type: index2,
+                attribute: data as u16,
+            },
+            NOTE_ON => Midi2Message::NoteOn {
+                channel,
+                note: index1 & 0x7F,
+                velocity: (data >> 16) as u16,
+                attribute_type: index2,
+                attribute: data as u16,
+            },
+            POLY_PRESSURE => Midi2Message::PolyPressure {
+                channel,
+                note: index1 & 0x7F,
+                value: data,
+            },
+            CONTROL_CHANGE => Midi2Message::ControlChange {
+                channel,
+                controller: index1 & 0x7F,
+                value: data,
+            },
+            PROGRAM_CHANGE => Midi2Message::ProgramChange {
+                channel,
+                program: byte(data, 0) & 0x7F,
+                bank: if index2 & 1 != 0 {
+                    Some((byte(data, 2) & 0x7F, byte(data, 3) & 0x7F))
+                } else {
+                    None
+                },
+            },
+            CHANNEL_PRESSURE => Midi2Message::ChannelPressure {
+                channel,
+                value: data,
+            },
+            PITCH_BEND => Midi2Message::PitchBend {
+                channel,
+                value: data,
+            },
+            opcode @ REGISTERED_CONTROLLER | opcode @ ASSIGNABLE_CONTROLLER => {
+                Midi2Message::Parameter {
+                    channel,
+                    kind: if opcode == REGISTERED_CONTROLLER {
+                        ParameterKind::Registered
+                    } else {
+                        ParameterKind::NonRegistered
+                    },
+                    parameter: ((index1 & 0x7F) as u16) << 7 | (index2 & 0x7F) as u16,
+                    value: data,
+                }
+            }
+            _ => return None,
+        };
+        Some(message)
+    }
+}
+
+impl Ump {
+    /// The group of the packet, or `None` for utility and unrecognised messages.
+    pub fn group(&self) -> Option<u8> {
+        match *self {
+            Ump::System { group, .. }
+            | Ump::Midi1 { group, .. }
+            | Ump::SysEx7 { group, .. }
+            | Ump::Midi2 { group, .. } => Some(group),
+            Ump::Utility(_) | Ump::Other(_) => None,
+        }
+    }
+
+    /// Decode a single, complete packet.
+    ///
+    /// Returns an `Error` if the words do not hold exactly one packet, or if a MIDI 1.0 or system
+    /// exclusive packet holds invalid bytes.
+    pub fn from_words(words: &[u32]) -> Result<Self, MidiError> {
+        let &first = words.first().ok_or(MidiError::Incomplete)?;
+        let len = ump_len(first);
+        if words.len() < len {
+            return Err(MidiError::Incomplete);
+        }
+        if words.len() > len {
+            return Err(MidiError::TrailingBytes);
+        }
+        let group = (first >> 24) as u8 & 0x0F;
+        let ump = match first >> 28 {
+            UTILITY => Ump::Utility(first),
+            message_type @ SYSTEM | message_type @ MIDI1_CHANNEL_VOICE => {
+                let status = byte(first, 1);
+                let channel_voice = (0x80..0xF0).contains(&status);
+                if channel_voice != (message_type == MIDI1_CHANNEL_VOICE) {
+                    return Err(MidiError::InvalidStatus);
+                }
+                let len = data_len(status).ok_or(MidiError::InvalidStatus)?;
+                let data = [byte(first, 2), byte(first, 3)];
+                for &b in &data[..len] {
+                    check_data(b)?;
+                }
+                let message = MidiMessage::from_parts(status, &data[..len]);
+                if channel_voice {
+                    Ump::Midi1 { group, message }
+                } else {
+                    Ump::System { group, message }
+                }
+            }
+            SYSEX7 => {
+                let status =
+                    SysExStatus::from_u32((first >> 20) & 0x0F).ok_or(MidiError::InvalidStatus)?;
+                let count = ((first >> 16) & 0x0F) as usize;
+                if count > SYSEX7_MAX_BYTES {
+                    return Err(MidiError::InvalidDataByte);
+                }
+                let bytes = [
+                    byte(first, 2),
+                    byte(first, 3),
+                    byte(words[1], 0),
+                    byte(words[1], 1),
+                    byte(words[1], 2),
+                    byte(words[1], 3),
+                ];
+                for &b in &bytes[..count] {
+                    check_data(b)?;
+                }
+                Ump::SysEx7 {
+                    group,
+                    status,
+                    data: bytes[..count].to_vec(),
+                }
+            }
+            MIDI2_CHANNEL_VOICE => match Midi2Message::from_words(first, words[1]) {
+                Some(message) => Ump::Midi2 { group, message },
+                None => Ump::Other(words.to_vec()),
+            },
+            _ => Ump::Other(words.to_vec()),
+        };
+        Ok(ump)
+    }
+
+    /// Append the words of the packet to `words`.
+    ///
+    /// Nothing is appended if the packet is invalid.
+    pub fn write_to(&self, words: &mut Vec<u32>) -> Result<(), MidiError> {
+        match *self {
+            Ump::Utility(word) => {
+                if word >> 28 != UTILITY {
+                    return Err(MidiError::InvalidStatus);
+                }
+                words.push(word);
+            }
+            Ump::System { group, ref message } | Ump::Midi1 { group, ref message } => {
+                let group = check_group(group)?;
+                let message_type = match *self {
+                    Ump::Midi1 { .. } => MIDI1_CHANNEL_VOICE,
+                    _ => SYSTEM,
+                };
+                if message.channel().is_some() != (message_type == MIDI1_CHANNEL_VOICE) {
+                    return Err(MidiError::InvalidStatus);
+                }
+                let [status, data1, data2] =
+                    message.short_message()?.ok_or(MidiError::InvalidStatus)?;
+                words.push(message_type << 28 | group << 24 | word([0, status, data1, data2]));
+            }
+            Ump::SysEx7 {
+                group,
+                status,
+                ref data,
+            } => {
+                let group = check_group(group)?;
+                if data.len() > SYSEX7_MAX_BYTES {
+                    return Err(MidiError::InvalidDataByte);
+                }
+                let mut bytes = [0; SYSEX7_MAX_BYTES];
+                for (dst, &src) in bytes.iter_mut().zip(data) {
+                    *dst = check_data(src)?;
+                }
+                let header =
+                    SYSEX7 << 28 | group << 24 | (status as u32) << 20 | (data.len() as u32) << 16;
+                words.push(header | word([0, 0, bytes[0], bytes[1]]));
+                words.push(word([bytes[2], bytes[3], bytes[4], bytes[5]]));
+            }
+            Ump::Midi2 { group, message } => {
+                words.extend_from_slice(&message.to_words(check_group(group)?)?);
+            }
+            Ump::Other(ref raw) => {
+                let &first = raw.first().ok_or(MidiError::Incomplete)?;
+                if raw.len() != ump_len(first) {
+                    return Err(MidiError::Incomplete);
+                }
+                words.extend_from_slice(raw);
+            }
+        }
+        Ok(())
+    }
+
+    /// Encode the packet as words.
+    pub fn to_words(&self) -> Result<Vec<u32>, MidiError> {
+        let mut words = Vec::with_capacity(4);
+        self.write_to(&mut words)?;
+        Ok(words)
+    }
+}
+
+/// Decode every packet within the given words.
+///
+/// Returns the first `Error` encountered, if any.
+pub fn decode(words: &[u32]) -> Result<Vec<Ump>, MidiError> {
+    UmpMessages::new(words)
+        .map(|packet| packet.and_then(Ump::from_words))
+        .collect()
+}
+
+/// Encode each of the given packets in turn.
+pub fn encode<'a, I>(packets: I) -> Result<Vec<u32>, MidiError>
+where
+    I: IntoIterator<Item = &'a Ump>,
+{
+    let mut words = Vec::new();
+    for packet in packets {
+        packet.write_to(&mut words)?;
+    }
+    Ok(words)
+}
+
+/// Split a system exclusive message into packets of at most six bytes.
+fn sysex_packets(group: u8, data: &[u8]) -> Vec<Ump> {
+    if data.len() <= SYSEX7_MAX_BYTES {
+        return vec![Ump::SysEx7 {
+            group,
+            status: SysExStatus::Complete,
+            data: data.to_vec(),
+        }];
+    }
+    let chunks = data.chunks(SYSEX7_MAX_BYTES);
+    let last = chunks.len() - 1;
+    chunks
+        .enumerate()
+        .map(|(i, chunk)| Ump::SysEx7 {
+            group,
+            status: match i {
+                0 => SysExStatus::Start,
+                i if i == last => SysExStatus::End,
+                _ => SysExStatus::Continue,
+            },
+            data: chunk.to_vec(),
+        })
+        .collect()
+}
+
+/// Carry a MIDI 1.0 message within Universal MIDI Packets of the MIDI 1.0 protocol.
+///
+/// Channel voice messages become a single **Midi1** packet, system messages a single **System**
+/// packet and system exclusive messages one or more **SysEx7** packets.
+pub fn from_midi1(group: u8, message: &MidiMessage) -> Result<Vec<Ump>, MidiError> {
+    check_group(group)?;
+    // Validate the message before choosing its packets.
+    message.short_message()?;
+    let packets = match *message {
+        MidiMessage::SysEx(ref data) => {
+            for &b in data {
+                check_data(b)?;
+            }
+            sysex_packets(group, data)
+        }
+        _ if message.channel().is_some() => vec![Ump::Midi1 {
+            group,
+            message: message.clone(),
+        }],
+        _ => vec![Ump::System {
+            group,
+            message: message.clone(),
+        }],
+    };
+    Ok(packets)
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+struct Bank {
+    msb: Option<u8>,
+    lsb: Option<u8>,
+}
+
+/// Translates MIDI 1.0 messages into MIDI 2.0 protocol Universal MIDI Packets.
+///
+/// Channel voice messages are translated to their MIDI 2.0 equivalents with values upscaled to
+/// the higher resolution. Bank select controllers are held and applied to the next program
+/// change, while parameter number and data entry controllers are gathered into **Parameter**
+/// messages. System and system exclusive messages are carried as in
+/// [**from_midi1**](./fn.from_midi1.html).
+#[derive(Clone, Debug)]
+pub struct Midi1ToMidi2 {
+    controllers: Vec<ControllerDecoder>,
+    banks: [[Bank; 16]; 16],
+}
+
+impl Default for Midi1ToMidi2 {
+    fn default() -> Self {
+        Midi1ToMidi2 {
+            controllers: vec![ControllerDecoder::new(); 16],
+            banks: [[Bank::default(); 16]; 16],
+        }
+    }
+}
+
+impl Midi1ToMidi2 {
+    /// Construct a translator with no bank or parameter selected on any group or channel.
+    pub fn new() -> Self {
+        Midi1ToMidi2::default()
+    }
+
+    /// Translate a single MIDI 1.0 message received on the given group.
+    ///
+    /// Returns no packets for controllers that are gathered into later messages.
+    pub fn translate(&mut self, group: u8, message: &MidiMessage) -> Result<Vec<Ump>, MidiError> {
+        let group_index = check_group(group)? as usize;
+        message.short_message()?;
+        let channel = match message.channel() {
+            Some(channel) => channel,
+            None => return from_midi1(group, message),
+        };
+        let bank = &mut self.banks[group_index][channel as usize];
+        let midi2 = match *message {
+            MidiMessage::NoteOff { note, velocity, .. } => Midi2Message::NoteOff {
+                channel,
+                note,
+                velocity: upscale(velocity as u32, 7, 16) as u16,
+                attribute_type: 0,
+                attribute: 0,
+            },
+            // A MIDI 1.0 note on with a velocity of zero is a note off with the default velocity.
+            MidiMessage::NoteOn {
+                note, velocity: 0, ..
+            } => Midi2Message::NoteOff {
+                channel,
+                note,
+                velocity: upscale(64, 7, 16) as u16,
+                attribute_type: 0,
+                attribute: 0,
+            },
+            MidiMessage::NoteOn { note, velocity, .. } => Midi2Message::NoteOn {
+                channel,
+                note,
+                velocity: upscale(velocity as u32, 7, 16) as u16,
+                attribute_type: 0,
+                attribute: 0,
+            },
+            MidiMessage::PolyPressure { note, pressure, .. } => Midi2Message::PolyPressure {
+                channel,
+                note,
+                value: upscale(pressure as u32, 7, 32),
+            },
+            MidiMessage::ControlChange {
+                controller: BANK_SELECT_MSB,
+                value,
+                ..
+            } => {
+                bank.msb = Some(value);
+                return Ok(vec![]);
+            }
+            MidiMessage::ControlChange {
+                controller: BANK_SELECT_LSB,
+                value,
+                ..
+            } => {
+                bank.lsb = Some(value);
+                return Ok(vec![]);
+            }
+            MidiMessage::ControlChange {
+                controller, value, ..
+            } => match self.controllers[group_index].decode(message) {
+                None => return Ok(vec![]),
+                Some(ControlEvent::Parameter {
+                    kind,
+                    parameter,
+                    value,
+                    ..
+                }) => Midi2Message::Parameter {
+                    channel,
+                    kind,
+                    parameter,
+                    value: upscale(value as u32, 14, 32),
+                },
+                Some(_) => Midi2Message::ControlChange {
+                    channel,
+                    controller,
+                    value: upscale(value as u32, 7, 32),
+                },
+            },
+            MidiMessage::ProgramChange { program, .. } => Midi2Message::ProgramChange {
+                channel,
+                program,
+                bank: match (bank.msb, bank.lsb) {
+                    (None, None) => None,
+                    (msb, lsb) => Some((msb.unwrap_or(0), lsb.unwrap_or(0))),
+                },
+            },
+            MidiMessage::ChannelPressure { pressure, .. } => Midi2Message::ChannelPressure {
+                channel,
+                value: upscale(pressure as u32, 7, 32),
+            },
+            MidiMessage::PitchBend { value, .. } => Midi2Message::PitchBend {
+                channel,
+                value: upscale(value as u32, 14, 32),
+            },
+            _ => unreachable!("channel voice message"),
+        };
+        Ok(vec![Ump::Midi2 {
+            group,
+            message: midi2,
+        }])
+    }
+}
+
+/// Translates Universal MIDI Packets of either protocol into MIDI 1.0 messages.
+///
+/// MIDI 2.0 values are downscaled to MIDI 1.0 resolution. Program changes with a valid bank are
+/// preceded by bank select controllers, and **Parameter** messages become the controllers
+/// selecting and setting the parameter. System exclusive packets are gathered until their
+/// message is complete.
+#[derive(Clone, Debug)]
+pub struct UmpToMidi1 {
+    sysex: Vec<Option<Vec<u8>>>,
+}
+
+impl Default for UmpToMidi1 {
+    fn default() -> Self {
+        UmpToMidi1 {
+            sysex: vec![None; 16],
+        }
+    }
+}
+
+impl UmpToMidi1 {
+    /// Construct a translator with no partial system exclusive messages.
+    pub fn new() -> Self {
+        UmpToMidi1::default()
+    }
+
+    /// Translate a single packet.
+    ///
+    /// Returns no messages for utility and unrecognised packets, and for system exclusive packets
+    /// that do not complete a message. Returns an `Error` for system exclusive packets that
+    /// continue or end a message that was never started.
+    pub fn translate(&mut self, ump: &Ump) -> Result<Vec<MidiMessage>, MidiError> {
+        let messages = match *ump {
+            Ump::Utility(_) | Ump::Other(_) => vec![],
+            Ump::System { ref message, .. } | Ump::Midi1 { ref message, .. } => {
+                vec![message.clone()]
+            }
+            Ump::SysEx7 {
+                group,
+                status,
+                ref data,
+            } => {
+                let pending = &mut self.sysex[check_group(group)? as usize];
+                match status {
+                    SysExStatus::Complete => vec![MidiMessage::SysEx(data.clone())],
+                    SysExStatus::Start => {
+                        *pending = Some(data.clone());
+                        vec![]
+                    }
+                    SysExStatus::Continue | SysExStatus::End => {
+                        let mut bytes = pending.take().ok_or(MidiError::Incomplete)?;
+                        bytes.extend_from_slice(data);
+                        if status == SysExStatus::End {
+                            vec![MidiMessage::SysEx(bytes)]
+                        } else {
+                            *pending = Some(bytes);
+                            vec![]
+                        }
+                    }
+                }
+            }
+            Ump::Midi2 { message, .. } => midi2_to_midi1(message)?,
+        };
+        Ok(messages)
+    }
+}
+
+/// Translate a MIDI 2.0 channel voice message into one or more MIDI 1.0 messages.
+pub fn midi2_to_midi1(message: Midi2Message) -> Result<Vec<MidiMessage>, MidiError> {
+    let channel = check_channel(message.channel())?;
+    let messages = match message {
+        Midi2Message::NoteOff { note, velocity, .. } => vec![MidiMessage::NoteOff {
+            channel,
+            note,
+            velocity: downscale(velocity as u32, 16, 7) as u8,
+        }],
+        // A velocity of zero would stop the note in MIDI 1.0.
+        Midi2Message::NoteOn { note, velocity, .. } => vec![MidiMessage::NoteOn {
+            channel,
+            note,
+            velocity: (downscale(velocity as u32, 16, 7) as u8).max(1),
+        }],
+        Midi2Message::PolyPressure { note, value, .. } => vec![MidiMessage::PolyPressure {
+            channel,
+            note,
+            pressure: downscale(value, 32, 7) as u8,
+        }],
+        Midi2Message::ControlChange {
+            controller, value, ..
+        } => vec![MidiMessage::ControlChange {
+            channel,
+            controller,
+            value: downscale(value, 32, 7) as u8,
+        }],
+        Midi2Message::ProgramChange { program, bank, .. } => {
+            let mut messages = Vec::with_capacity(3);
+            if let Some((msb, lsb)) = bank {
+                messages.push(MidiMessage::ControlChange {
+                    channel,
+                    controller: BANK_SELECT_MSB,
+                    value: msb,
+                });
+                messages.push(MidiMessage::ControlChange {
+                    channel,
+                    controller: BANK_SELECT_LSB,
+                    value: lsb,
+                });
+            }
+            messages.push(MidiMessage::ProgramChange { channel, program });
+            messages
+        }
+        Midi2Message::ChannelPressure { value, .. } => vec![MidiMessage::ChannelPressure {
+            channel,
+            pressure: downscale(value, 32, 7) as u8,
+        }],
+        Midi2Message::PitchBend { value, .. } => vec![MidiMessage::PitchBend {
+            channel,
+            value: downscale(value, 32, 14) as u16,
+        }],
+        Midi2Message::Parameter {
+            kind,
+            parameter,
+            value,
+            ..
+        } => controller::set_parameter(channel, kind, parameter, downscale(value, 32, 14) as u16)?
+            .to_vec(),
+    };
+    Ok(messages)
+}
+
+#[test]
+fn test_scaling() {
+    assert_eq!(upscale(0, 7, 16), 0);
+    assert_eq!(upscale(64, 7, 16), 0x8000);
+    assert_eq!(upscale(127, 7, 16), 0xFFFF);
+    assert_eq!(upscale(0, 7, 32), 0);
+    assert_eq!(upscale(64, 7, 32), 0x8000_0000);
+    assert_eq!(upscale(127, 7, 32), 0xFFFF_FFFF);
+    assert_eq!(upscale(0x2000, 14, 32), 0x8000_0000);
+    assert_eq!(upscale(0x3FFF, 14, 32), 0xFFFF_FFFF);
+    assert_eq!(upscale(5, 7, 7), 5);
+    assert_eq!(downscale(0xFFFF_FFFF, 32, 7), 127);
+    assert_eq!(downscale(0x8000_0000, 32, 14), 0x2000);
+
+    // Scaling up is monotonic and scaling back down is lossless for every value.
+    for &(from, to) in &[(7, 16), (7, 32), (14, 32)] {
+        let mut previous = None;
+        for value in 0..1 << from {
+            let scaled = upscale(value, from, to);
+            assert_eq!(downscale(scaled, to, from), value);
+            if let Some(previous) = previous {
+                assert!(previous < scaled);
+            }
+            previous = Some(scaled);
+        }
+    }
+}
+
+#[test]
+fn test_ump_messages() {
+    assert_eq!(ump_len(0x0000_0000), 1);
+    assert_eq!(ump_len(0x10F8_0000), 1);
+    assert_eq!(ump_len(0x2090_3C64), 1);
+    assert_eq!(ump_len(0x3016_0000), 2);
+    assert_eq!(ump_len(0x4090_3C00), 2);
+    assert_eq!(ump_len(0x5000_0000), 4);
+    assert_eq!(ump_len(0xD000_0000), 4);
+    assert_eq!(ump_len(0xF000_0000), 4);
+
+    let words = [
+        0x2090_3C64,
+        0x4090_3C00,
+        0xFFFF_0000,
+        0x1FF8_0000,
+        0x3016_0000,
+    ];
+    let messages: Vec<_> = UmpMessages::new(&words).collect();
+    assert_eq!(
+        messages,
+        vec![
+            Ok(&words[0..1]),
+            Ok(&words[1..3]),
+            Ok(&words[3..4]),
+            Err(MidiError::Incomplete),
+        ]
+    );
+}
+
+#[test]
+fn test_ump_decode() {
+    let words = [
+        0x0000_0000,
+        0x2391_3C64,
+        0x13F2_0101,
+        0x3016_0102,
+        0x0304_0506,
+        0x4090_3C00,
+        0xFFFF_0000,
+        0x41C5_0001,
+        0x0500_0203,
+        0x4023_0105,
+        0x1234_5678,
+        0xD000_0000,
+        0,
+        0,
+        0,
+    ];
+    assert_eq!(
+        decode(&words).unwrap(),
+        vec![
+            Ump::Utility(0),
+            Ump::Midi1 {
+                group: 3,
+                message: MidiMessage::NoteOn {
+                    channel: 1,
+                    note: 60,
+                    velocity: 100
+                }
+            },
+            Ump::System {
+                group: 3,
+                message: MidiMessage::SongPosition(0x81)
+            },
+            Ump::SysEx7 {
+                group: 0,
+                status: SysExStatus::Start,
+                data: vec![1, 2, 3, 4, 5, 6]
+            },
+            Ump::Midi2 {
+                group: 0,
+                message: Midi2Message::NoteOn {
+                    channel: 0,
+                    note: 60,
+                    velocity: 0xFFFF,
+                    attribute_type: 0,
+                    attribute: 0
+                }
+            },
+            Ump::Midi2 {
+                group: 1,
+                message: Midi2Message::ProgramChange {
+                    channel: 5,
+                    program: 5,
+                    bank: Some((2, 3))
+                }
+            },
+            Ump::Midi2 {
+                group: 0,
+                message: Midi2Message::Parameter {
+                    channel: 3,
+                    kind: ParameterKind::Registered,
+                    parameter: 1 << 7 | 5,
+                    value: 0x1234_5678
+                }
+            },
+            Ump::Other(vec![0xD000_0000, 0, 0, 0]),
+        ]
+    );
+    // Encoding the decoded packets reproduces the original words.
+    assert_eq!(encode(&decode(&words).unwrap()).unwrap(), words.to_vec());
+}
+
+#[test]
+fn test_ump_errors() {
+    // Channel voice status within a system packet and vice versa.
+    assert_eq!(
+        Ump::from_words(&[0x1090_3C64]),
+        Err(MidiError::InvalidStatus)
+    );
+    assert_eq!(
+        Ump::from_words(&[0x20F8_0000]),
+        Err(MidiError::InvalidStatus)
+    );
+    // Data bytes with the top bit set.
+    assert_eq!(
+        Ump::from_words(&[0x2090_BC64]),
+        Err(MidiError::InvalidDataByte)
+    );
+    // Too many system exclusive bytes.
+    assert_eq!(
+        Ump::from_words(&[0x3007_0000, 0]),
+        Err(MidiError::InvalidDataByte)
+    );
+    assert_eq!(Ump::from_words(&[0x4090_3C00]), Err(MidiError::Incomplete));
+    assert_eq!(
+        Ump::from_words(&[0x2090_3C64, 0]),
+        Err(MidiError::TrailingBytes)
+    );
+    assert_eq!(Ump::from_words(&[]), Err(MidiError::Incomplete));
+    assert_eq!(
+        decode(&[0x2090_3C64, 0x4090_3C00]),
+        Err(MidiError::Incomplete)
+    );
+
+    let bad_group = Ump::System {
+        group: 16,
+        message: MidiMessage::Start,
+    };
+    assert_eq!(bad_group.to_words(), Err(MidiError::InvalidGroup));
+    let wrong_kind = Ump::System {
+        group: 0,
+        message: MidiMessage::ProgramChange {
+            channel: 0,
+            program: 0,
+        },
+    };
+    assert_eq!(wrong_kind.to_words(), Err(MidiError::InvalidStatus));
+    let sysex = Ump::Midi1 {
+        group: 0,
+        message: MidiMessage::SysEx(vec![]),
+    };
+    assert_eq!(sysex.to_words(), Err(MidiError::InvalidStatus));
+    assert_eq!(
+        Ump::Other(vec![0xD000_0000]).to_words(),
+        Err(MidiError::Incomplete)
+    );
+}
+
+#[test]
+fn test_from_midi1_sysex_packets() {
+    let sysex = |len: u8| MidiMessage::SysEx((0..len).collect());
+    let statuses = |packets: Vec<Ump>| -> Vec<(SysExStatus, usize)> {
+        packets
+            .into_iter()
+            .map(|p| match p {
+                Ump::SysEx7 { status, data, .. } => (status, data.len()),
+                _ => panic!("expected sysex packet"),
+            })
+            .collect()
+    };
+    assert_eq!(
+        statuses(from_midi1(0, &sysex(0)).unwrap()),
+        vec![(SysExStatus::Complete, 0)]
+    );
+    assert_eq!(
+        statuses(from_midi1(0, &sysex(6)).unwrap()),
+        vec![(SysExStatus::Complete, 6)]
+    );
+    assert_eq!(
+        statuses(from_midi1(0, &sysex(12)).unwrap()),
+        vec![(SysExStatus::Start, 6), (SysExStatus::End, 6)]
+    );
+    assert_eq!(
+        statuses(from_midi1(0, &sysex(20)).unwrap()),
+        vec![
+            (SysExStatus::Start, 6),
+            (SysExStatus::Continue, 6),
+            (SysExStatus::Continue, 6),
+            (SysExStatus::End, 2)
+        ]
+    );
+
+    // Reassembly, with packets of another group interleaved.
+    let mut translator = UmpToMidi1::new();
+    let mut messages = vec![];
+    for packet in from_midi1(2, &sysex(20)).unwrap() {
+        messages.extend(translator.translate(&packet).unwrap());
+        let other = from_midi1(1, &MidiMessage::TimingClock).unwrap();
+        messages.extend(translator.translate(&other[0]).unwrap());
+    }
+    assert_eq!(messages.len(), 5);
+    assert_eq!(messages[3], sysex(20));
+    let orphan = Ump::SysEx7 {
+        group: 0,
+        status: SysExStatus::End,
+        data: vec![],
+    };
+    assert_eq!(translator.translate(&orphan), Err(MidiError::Incomplete));
+    assert!(from_midi1(16, &MidiMessage::Start).is_err());
+    assert!(from_midi1(0, &MidiMessage::SysEx(vec![0x80])).is_err());
+}
+
+#[test]
+fn test_midi1_round_trip() {
+    let mut translator = UmpToMidi1::new();
+    for message in super::all_message_kinds() {
+        let packets = from_midi1(7, &message).unwrap();
+        let words = encode(&packets).unwrap();
+        let decoded = decode(&words).unwrap();
+        assert_eq!(decoded, packets);
+        let mut messages = vec![];
+        for packet in &decoded {
+            assert_eq!(packet.group(), Some(7));
+            messages.extend(translator.translate(packet).unwrap());
+        }
+        assert_eq!(messages, vec![message]);
+    }
+}
+
+#[test]
+fn test_midi1_to_midi2() {
+    let mut translator = Midi1ToMidi2::new();
+    let midi2 = |translator: &mut Midi1ToMidi2, message: MidiMessage| -> Vec<Midi2Message> {
+        translator
+            .translate(0, &message)
+            .unwrap()
+            .into_iter()
+            .map(|p| match p {
+                Ump::Midi2 { message, .. } => message,
+                _ => panic!("expected MIDI 2.0 packet"),
+            })
+            .collect()
+    };
+    assert_eq!(
+        midi2(
+            &mut translator,
+            MidiMessage::NoteOn {
+                channel: 1,
+                note: 60,
+                velocity: 127
+            }
+        ),
+        vec![Midi2Message::NoteOn {
+            channel: 1,
+            note: 60,
+            velocity: 0xFFFF,
+            attribute_type: 0,
+            attribute: 0
+        }]
+    );
+    assert_eq!(
+        midi2(
+            &mut translator,
+            MidiMessage::NoteOn {
+                channel: 1,
+                note: 60,
+                velocity: 0
+            }
+        ),
+        vec![Midi2Message::NoteOff {
+            channel: 1,
+            note: 60,
+            velocity: 0x8000,
+            attribute_type: 0,
+            attribute: 0
+        }]
+    );
+    assert_eq!(
+        midi2(
+            &mut translator,
+            MidiMessage::PitchBend {
+                channel: 0,
+                value: 0x2000
+            }
+        ),
+        vec![Midi2Message::PitchBend {
+            channel: 0,
+            value: 0x8000_0000
+        }]
+    );
+
+    // Bank selects are applied to the following program change.
+    let bank_msb = MidiMessage::ControlChange {
+        channel: 2,
+        controller: 0,
+        value: 1,
+    };
+    assert!(midi2(&mut translator, bank_msb).is_empty());
+    assert_eq!(
+        midi2(
+            &mut translator,
+            MidiMessage::ProgramChange {
+                channel: 2,
+                program: 9
+            }
+        ),
+        vec![Midi2Message::ProgramChange {
+            channel: 2,
+            program: 9,
+            bank: Some((1, 0))
+        }]
+    );
+    assert_eq!(
+        midi2(
+            &mut translator,
+            MidiMessage::ProgramChange {
+                channel: 3,
+                program: 9
+            }
+        ),
+        vec![Midi2Message::ProgramChange {
+            channel: 3,
+            program: 9,
+            bank: None
+        }]
+    );
+
+    // Parameter numbers are gathered into parameter messages.
+    let rpn = controller::set_parameter(0, ParameterKind::Registered, 0, 2 << 7).unwrap();
+    let translated: Vec<_> = rpn
+        .iter()
+        .flat_map(|m| midi2(&mut translator, m.clone()))
+        .collect();
+    assert_eq!(
+        translated,
+        vec![
+            Midi2Message::Parameter {
+                channel: 0,
+                kind: ParameterKind::Registered,
+                parameter: 0,
+                value: upscale(2 << 7, 14, 32)
+            };
+            2
+        ]
+    );
+    assert_eq!(
+        midi2(
+            &mut translator,
+            MidiMessage::ControlChange {
+                channel: 0,
+                controller: 7,
+                value: 127
+            }
+        ),
+        vec![Midi2Message::ControlChange {
+            channel: 0,
+            controller: 7,
+            value: 0xFFFF_FFFF
+        }]
+    );
+    assert_eq!(
+        translator.translate(4, &MidiMessage::Stop).unwrap(),
+        vec![Ump::System {
+            group: 4,
+            message: MidiMessage::Stop
+        }]
+    );
+    assert!(translator.translate(16, &MidiMessage::Stop).is_err());
+}
+
+#[test]
+fn test_midi2_to_midi1() {
+    assert_eq!(
+        midi2_to_midi1(Midi2Message::NoteOn {
+            channel: 0,
+            note: 60,
+            velocity: 0x0100,
+            attribute_type: 0,
+            attribute: 0
+        })
+        .unwrap(),
+        vec![MidiMessage::NoteOn {
+            channel: 0,
+            note: 60,
+            velocity: 1
+        }]
+    );
+    assert_eq!(
+        midi2_to_midi1(Midi2Message::ProgramChange {
+            channel: 1,
+            program: 4,
+            bank: Some((2, 3))
+        })
+        .unwrap(),
+        vec![
+            MidiMessage::ControlChange {
+                channel: 1,
+                controller: 0,
+                value: 2
+            },
+            MidiMessage::ControlChange {
+                channel: 1,
+                controller: 32,
+                value: 3
+            },
+            MidiMessage::ProgramChange {
+                channel: 1,
+                program: 4
+            },
+        ]
+    );
+    let parameter = Midi2Message::Parameter {
+        channel: 0,
+        kind: ParameterKind::NonRegistered,
+        parameter: 0x0155,
+        value: 0xFFFF_FFFF,
+    };
+    assert_eq!(
+        midi2_to_midi1(parameter).unwrap(),
+        controller::set_parameter(0, ParameterKind::NonRegistered, 0x0155, 0x3FFF)
+            .unwrap()
+            .to_vec()
+    );
+
+    // Translating up to MIDI 2.0 and back down preserves every MIDI 1.0 message.
+    let mut up = Midi1ToMidi2::new();
+    let mut down = UmpToMidi1::new();
+    for message in super::all_message_kinds() {
+        let mut messages = vec![];
+        for packet in up.translate(0, &message).unwrap() {
+            messages.extend(down.translate(&packet).unwrap());
+        }
+        let expected = match message {
+            MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity: 0,
+            } => MidiMessage::NoteOff {
+                channel,
+                note,
+                velocity: 64,
+            },
+            message => message,
+        };
+        assert_eq!(messages, vec![expected]);
+    }
+}
@@ -0,0 +1,146 @@
+//! Closures shared between their owner and the threads on which a framework calls them.
+//!
+//! A framework may still be running a callback on another thread while the owner unregisters it
+//! and drops its closure. The owner therefore holds the only strong reference to the closure and
+//! the framework is handed a weak reference, which each call upgrades for its duration. The
+//! closure is freed once the owner and every call in flight are done with it.
+//!
+//! The weak reference handed to the framework is never released, as the frameworks do not report
+//! when the last call in flight has returned. This leaks the small allocation holding the
+//! reference counts, but never the closure itself.
+//!
+//! A closure may cause the framework to call it again on the same thread, e.g. by changing the
+//! property it listens to. Such re-entrant calls are skipped, as the closure is already borrowed.
+
+use std::cell::RefCell;
+use std::mem::ManuallyDrop;
+use std::os::raw::c_void;
+use std::sync::{Arc, Mutex, TryLockError, Weak};
+
+thread_local! {
+    // The user data of the closures being called on this thread with `wait` set.
+    static CALLING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Whether or not this thread is within a call to a closure made with `wait` set.
+pub(crate) fn in_call() -> bool {
+    CALLING.with(|calling| !calling.borrow().is_empty())
+}
+
+/// Removes the innermost call from `CALLING`, even if the closure panics.
+struct CallGuard;
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        CALLING.with(|calling| calling.borrow_mut().pop());
+    }
+}
+
+/// A closure of type `F` that may be called from the callbacks of a framework.
+pub(crate) struct SharedCallback<F: ?Sized> {
+    callback: Arc<Mutex<Box<F>>>,
+}
+
+impl<F: ?Sized> SharedCallback<F> {
+    /// Take ownership of the given closure.
+    pub(crate) fn new(callback: Box<F>) -> Self {
+        SharedCallback {
+            callback: Arc::new(Mutex::new(callback)),
+        }
+    }
+
+    /// A new weak reference to the closure, to be registered with the framework as user data.
+    ///
+    /// Pass it to [**release**](#method.release) if the framework never received it.
+    pub(crate) fn user_data(&self) -> *mut c_void {
+        Weak::into_raw(Arc::downgrade(&self.callback)) as *mut c_void
+    }
+
+    /// Release user data that the framework never received, e.g. because registration failed.
+    ///
+    /// The user data must have been returned by [**user_data**](#method.user_data) for the same
+    /// type of closure.
+    pub(crate) unsafe fn release(user_data: *mut c_void) {
+        drop(Weak::from_raw(user_data as *const Mutex<Box<F>>));
+    }
+
+    /// Call `f` with the closure behind the given user data, unless its owner has dropped it.
+    ///
+    /// With `wait` set to `false` the call is skipped, rather than blocking, while another thread
+    /// is running the closure, as befits realtime threads. With `wait` set, a call made from within
+    /// the closure on the same thread is skipped rather than deadlocking.
+    ///
+    /// The user data must have been returned by [**user_data**](#method.user_data) for the same
+    /// type of closure.
+    pub(crate) unsafe fn with<R, G>(user_data: *mut c_void, wait: bool, f: G) -> Option<R>
+    where
+        G: FnOnce(&mut F) -> R,
+    {
+        let weak = ManuallyDrop::new(Weak::from_raw(user_data as *const Mutex<Box<F>>));
+        let callback = weak.upgrade()?;
+        let _call;
+        let mut guard = if wait {
+            let reentrant = CALLING.with(|calling| {
+                let mut calling = calling.borrow_mut();
+                let reentrant = calling.contains(&(user_data as usize));
+                if !reentrant {
+                    calling.push(user_data as usize);
+                }
+                reentrant
+            });
+            if reentrant {
+                return None;
+            }
+            _call = CallGuard;
+            callback.lock().unwrap_or_else(|e| e.into_inner())
+        } else {
+            match callback.try_lock() {
+                Ok(guard) => guard,
+                Err(TryLockError::Poisoned(e)) => e.into_inner(),
+                Err(TryLockError::WouldBlock) => return None,
+            }
+        };
+        Some(f(&mut **guard))
+    }
+}
+
+#[test]
+fn test_shared_callback() {
+    let calls = Arc::new(Mutex::new(0));
+    let counter = calls.clone();
+    let shared: SharedCallback<dyn FnMut(u32) + Send> =
+        SharedCallback::new(Box::new(move |n| *counter.lock().unwrap() += n));
+    let user_data = shared.user_data();
+    let call =
+        |n| unsafe { SharedCallback::<dyn FnMut(u32) + Send>::with(user_data, true, |f| f(n)) };
+    assert_eq!(call(2), Some(()));
+    assert_eq!(call(3), Some(()));
+    assert_eq!(*calls.lock().unwrap(), 5);
+
+    // Calls arriving after the owner has dropped the closure are ignored.
+    drop(shared);
+    assert_eq!(call(7), None);
+    assert_eq!(*calls.lock().unwrap(), 5);
+    assert_eq!(Arc::strong_count(&calls), 1);
+    unsafe { SharedCallback::<dyn FnMut(u32) + Send>::release(user_data) };
+}
+
+#[test]
+fn test_shared_callback_reentrant() {
+    // The closure calls itself through the user data it is given, returning whether it ran.
+    type Reentrant = dyn FnMut(usize) -> bool + Send;
+    let shared: SharedCallback<Reentrant> = SharedCallback::new(Box::new(|user_data| {
+        assert!(in_call());
+        let nested =
+            unsafe { SharedCallback::<Reentrant>::with(user_data as *mut c_void, true, |_| ()) };
+        nested.is_some()
+    }));
+    let user_data = shared.user_data();
+    let call =
+        || unsafe { SharedCallback::<Reentrant>::with(user_data, true, |f| f(user_data as usize)) };
+    assert_eq!(call(), Some(false));
+    assert!(!in_call());
+    assert_eq!(call(), Some(false));
+    drop(shared);
+    unsafe { SharedCallback::<Reentrant>::release(user_data) };
+}